@@ -0,0 +1,63 @@
+// src/apidoc.rs
+//! API documentation generation with the SDK's `cjdoc` for `/cangjie-doc`.
+//!
+//! The documentation of the package's sources is written below the target directory, next
+//! to the build profiles, so `/cangjie-clean` removes it together with the build output.
+
+use crate::cjpm;
+
+/// Name of the documentation generator shipped with the SDK
+pub const DOC_TOOL: &str = "cjdoc";
+
+/// Directory below the target directory the documentation is written to
+pub const DOC_DIR: &str = "doc";
+
+/// Entry page of the generated documentation
+pub const INDEX_FILE: &str = "index.html";
+
+/// Returns the directory the documentation of a package is written to, relative to the
+/// package root unless `target-dir` is absolute.
+///
+/// # Arguments
+/// * `manifest` - The package manifest
+pub fn output_dir(manifest: &cjpm::Manifest) -> String {
+    let target_dir = manifest
+        .target_dir
+        .as_deref()
+        .unwrap_or(cjpm::DEFAULT_TARGET_DIR);
+    format!("{}/{}", target_dir.trim_end_matches(['/', '\\']), DOC_DIR)
+}
+
+/// Returns the cjdoc arguments documenting a source directory.
+///
+/// # Arguments
+/// * `source_dir` - The package's source directory
+/// * `output_dir` - The directory to write the documentation to
+///
+/// # Returns
+/// * `Vec<String>` - The arguments after `cjdoc`
+pub fn doc_args(source_dir: &str, output_dir: &str) -> Vec<String> {
+    vec![
+        "-p".to_string(),
+        source_dir.to_string(),
+        "-o".to_string(),
+        output_dir.to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doc_output() {
+        // 测试文档输出目录跟随 target-dir
+        let manifest = cjpm::parse_manifest("[package]\ntarget-dir = \"/tmp/out/\"\n");
+        assert_eq!(output_dir(&manifest), "/tmp/out/doc");
+        assert_eq!(output_dir(&cjpm::Manifest::default()), "target/doc");
+        assert_eq!(
+            doc_args("src", "target/doc"),
+            vec!["-p", "src", "-o", "target/doc"]
+        );
+    }
+}
@@ -0,0 +1,605 @@
+// src/assets.rs
+//! Verification of language server release assets against the platform matrix.
+
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use zed_extension_api::{Architecture, DownloadedFileType, Os};
+
+use crate::paths::{encoded_powershell, powershell_literal};
+use crate::version::Version;
+
+/// Archive suffixes of release assets, in order of preference
+const ARCHIVE_SUFFIXES: &[&str] = &[".tar.gz", ".tgz", ".zip", ".gz"];
+
+/// How deep [`find_binary`] looks into an extracted archive
+const MAX_ARCHIVE_DEPTH: usize = 4;
+
+/// Prefix of GitHub release download URLs
+const GITHUB_URL: &str = "https://github.com";
+
+/// Every platform Zed can run the extension on
+pub const PLATFORMS: &[(Os, Architecture)] = &[
+    (Os::Linux, Architecture::X8664),
+    (Os::Linux, Architecture::Aarch64),
+    (Os::Linux, Architecture::X86),
+    (Os::Mac, Architecture::X8664),
+    (Os::Mac, Architecture::Aarch64),
+    (Os::Windows, Architecture::X8664),
+    (Os::Windows, Architecture::Aarch64),
+    (Os::Windows, Architecture::X86),
+];
+
+/// Result of checking one platform
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetCheck {
+    /// Human-readable platform, e.g. `linux-x86_64`
+    pub platform: String,
+    /// The asset name the extension would download, or why the platform is unsupported
+    pub expected: Result<String, String>,
+    /// The release asset matching the expected name, possibly archived
+    pub asset: Option<String>,
+}
+
+/// Returns a short label for a platform, e.g. `macos-aarch64`
+pub fn platform_label(os: Os, arch: Architecture) -> String {
+    let os = match os {
+        Os::Mac => "macos",
+        Os::Linux => "linux",
+        Os::Windows => "windows",
+    };
+    let arch = match arch {
+        Architecture::Aarch64 => "aarch64",
+        Architecture::X86 => "x86",
+        Architecture::X8664 => "x86_64",
+    };
+    format!("{}-{}", os, arch)
+}
+
+/// Returns the file name of a binary on the given platform
+pub fn binary_file_name(os: Os, base_name: &str) -> String {
+    match os {
+        Os::Windows => format!("{}.exe", base_name),
+        _ => base_name.to_string(),
+    }
+}
+
+/// Returns how an asset must be downloaded, based on its file name
+pub fn archive_type(asset_name: &str) -> DownloadedFileType {
+    let name = asset_name.to_ascii_lowercase();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        DownloadedFileType::GzipTar
+    } else if name.ends_with(".zip") {
+        DownloadedFileType::Zip
+    } else if name.ends_with(".gz") {
+        DownloadedFileType::Gzip
+    } else {
+        DownloadedFileType::Uncompressed
+    }
+}
+
+/// Returns the command extracting an archive that was downloaded as is into `dest`.
+///
+/// Zed's download API only extracts archives while downloading them, so an archive whose
+/// checksum is verified first has to be extracted with the platform's tools: `tar`, which
+/// Windows 10 and later also ship with zip support, `unzip`, and `gzip` through `sh`. On
+/// Windows a gzipped binary is decompressed with PowerShell's `GZipStream`.
+///
+/// # Arguments
+/// * `os` - The current platform
+/// * `file_type` - The archive type from [`archive_type`]
+/// * `archive` - The downloaded archive
+/// * `dest` - The directory to extract into
+/// * `binary_name` - The file name a gzipped binary is decompressed to
+///
+/// # Returns
+/// * `Option<(String, Vec<String>)>` - The program and its arguments, or `None` for an
+///   uncompressed asset
+pub fn extract_command(
+    os: Os,
+    file_type: DownloadedFileType,
+    archive: &Path,
+    dest: &Path,
+    binary_name: &str,
+) -> Option<(String, Vec<String>)> {
+    let archive = archive.to_string_lossy().to_string();
+    let dest_dir = dest.to_string_lossy().to_string();
+    let output = dest.join(binary_name).to_string_lossy().to_string();
+    let tar = || {
+        let args = ["-xf", &archive, "-C", &dest_dir];
+        ("tar".to_string(), args.map(str::to_string).to_vec())
+    };
+    match (file_type, os) {
+        (DownloadedFileType::Uncompressed, _) => None,
+        (DownloadedFileType::GzipTar, _) | (DownloadedFileType::Zip, Os::Windows) => Some(tar()),
+        (DownloadedFileType::Zip, _) => {
+            let args = ["-q", "-o", &archive, "-d", &dest_dir];
+            Some(("unzip".to_string(), args.map(str::to_string).to_vec()))
+        }
+        (DownloadedFileType::Gzip, Os::Windows) => {
+            let script = format!(
+                "$ErrorActionPreference = 'Stop'; \
+                 $in = [IO.File]::OpenRead({}); $out = [IO.File]::Create({}); \
+                 $gzip = New-Object IO.Compression.GZipStream($in, \
+                 [IO.Compression.CompressionMode]::Decompress); \
+                 $gzip.CopyTo($out); $out.Dispose(); $gzip.Dispose()",
+                powershell_literal(&archive),
+                powershell_literal(&output)
+            );
+            Some(("powershell".to_string(), encoded_powershell(&script)))
+        }
+        (DownloadedFileType::Gzip, _) => {
+            let args = ["-c", r#"gzip -dc -- "$1" > "$2""#, "sh", &archive, &output];
+            Some(("sh".to_string(), args.map(str::to_string).to_vec()))
+        }
+    }
+}
+
+/// Operating systems Zed runs on
+const ALL_OS: [Os; 3] = [Os::Linux, Os::Mac, Os::Windows];
+
+/// Architectures Zed runs on
+const ALL_ARCH: [Architecture; 3] = [
+    Architecture::X8664,
+    Architecture::Aarch64,
+    Architecture::X86,
+];
+
+/// Names an architecture goes by in release assets, after `x86_64` is normalized to `amd64`
+fn arch_aliases(arch: Architecture) -> &'static [&'static str] {
+    match arch {
+        Architecture::X8664 => &["amd64", "x64"],
+        Architecture::Aarch64 => &["aarch64", "arm64"],
+        Architecture::X86 => &["i686", "i386", "x86"],
+    }
+}
+
+/// Names an operating system goes by in release assets
+fn os_aliases(os: Os) -> &'static [&'static str] {
+    match os {
+        Os::Linux => &["linux"],
+        Os::Mac => &["darwin", "macos", "osx", "mac"],
+        Os::Windows => &["windows", "win", "win64", "msvc"],
+    }
+}
+
+/// Names of macOS binaries that run on every architecture
+const UNIVERSAL_ALIASES: &[&str] = &["universal", "universal2"];
+
+/// Architectures Zed does not run on; assets for them are never picked
+const FOREIGN_ARCH_ALIASES: &[&str] = &["riscv64", "riscv64gc", "loongarch64", "armv7", "s390x"];
+
+/// Vendor and C library names of target triples
+const TRIPLE_TOKENS: &[&str] = &["unknown", "pc", "apple", "gnu", "musl", "msvc", "mingw"];
+
+/// Splits an asset name into lowercase tokens, keeping `x86_64` together as `amd64`
+fn asset_tokens(name: &str) -> Vec<String> {
+    name.to_ascii_lowercase()
+        .replace("x86_64", "amd64")
+        .replace("x86-64", "amd64")
+        .split(['-', '_', '.'])
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Returns true if a token names a platform or is a version component such as `v1` or `0`
+fn is_platform_token(token: &str) -> bool {
+    let digits = token.strip_prefix('v').unwrap_or(token);
+    (!digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+        || TRIPLE_TOKENS.contains(&token)
+        || UNIVERSAL_ALIASES.contains(&token)
+        || FOREIGN_ARCH_ALIASES.contains(&token)
+        || ALL_OS.iter().any(|&os| os_aliases(os).contains(&token))
+        || ALL_ARCH
+            .iter()
+            .any(|&arch| arch_aliases(arch).contains(&token))
+}
+
+/// Ranks a release asset for a platform; lower ranks are better.
+///
+/// Returns `None` if the asset is not a build of `base_name` for the platform. Every part of
+/// the name after the base name must describe a platform or a version, so checksum files and
+/// other binaries sharing the prefix (`cangjie-lsp-server-…`) are never picked.
+fn asset_rank(base_name: &str, os: Os, arch: Architecture, name: &str) -> Option<[usize; 3]> {
+    let lower = name.to_ascii_lowercase();
+    let rest = lower.strip_prefix(&base_name.to_ascii_lowercase())?;
+    if !rest.starts_with(['-', '_']) {
+        return None;
+    }
+    let (stem, format_rank) = match ARCHIVE_SUFFIXES
+        .iter()
+        .position(|suffix| rest.ends_with(suffix))
+    {
+        Some(index) => (
+            &rest[..rest.len() - ARCHIVE_SUFFIXES[index].len()],
+            index + 1,
+        ),
+        None => (rest, 0),
+    };
+    let tokens = asset_tokens(stem.strip_suffix(".exe").unwrap_or(stem));
+    if !tokens.iter().all(|token| is_platform_token(token)) {
+        return None;
+    }
+    let has = |aliases: &[&str]| tokens.iter().any(|token| aliases.contains(&token.as_str()));
+
+    let other_os = ALL_OS
+        .iter()
+        .any(|&other| other != os && has(os_aliases(other)));
+    if !has(os_aliases(os)) || other_os {
+        return None;
+    }
+    let other_arch = ALL_ARCH
+        .iter()
+        .any(|&other| other != arch && has(arch_aliases(other)));
+    if other_arch || has(FOREIGN_ARCH_ALIASES) {
+        return None;
+    }
+    let arch_rank = if has(arch_aliases(arch)) {
+        0
+    } else if os == Os::Mac && has(UNIVERSAL_ALIASES) {
+        1
+    } else {
+        return None;
+    };
+
+    // glibc and MSVC builds match what Zed itself links against; musl and MinGW builds
+    // are fallbacks
+    let libc_rank = match os {
+        Os::Linux if has(&["gnu"]) => 0,
+        Os::Linux if has(&["musl"]) => 2,
+        Os::Windows if has(&["msvc"]) => 0,
+        Os::Windows if has(&["gnu", "mingw"]) => 2,
+        _ => 1,
+    };
+    Some([arch_rank, libc_rank, format_rank])
+}
+
+/// Finds the best release asset of a binary for a platform.
+///
+/// Besides the canonical `<base>-<arch>-<vendor>-<os>[-<env>]` names, assets using other
+/// common naming schemes are accepted, e.g. `cangjie-lsp-linux-amd64.tar.gz`,
+/// `cangjie-lsp-darwin-arm64.zip` or `cangjie-lsp-universal-apple-darwin`. Candidates are
+/// ranked by how well they fit the platform:
+///
+/// 1. An exact architecture match, then a universal macOS binary
+/// 2. A glibc or MSVC build, then an unspecified C library, then a musl or MinGW build
+/// 3. A plain binary, then archives in the order of [`ARCHIVE_SUFFIXES`]
+///
+/// # Arguments
+/// * `base_name` - The binary's base name, e.g. `cangjie-lsp`
+/// * `os` - The platform's operating system
+/// * `arch` - The platform's architecture
+/// * `asset_names` - The assets of the release
+///
+/// # Returns
+/// * `Option<&str>` - The best matching asset, if any
+pub fn match_asset<'a>(
+    base_name: &str,
+    os: Os,
+    arch: Architecture,
+    asset_names: &'a [String],
+) -> Option<&'a str> {
+    asset_names
+        .iter()
+        .filter_map(|name| Some((asset_rank(base_name, os, arch, name)?, name)))
+        .min()
+        .map(|(_, name)| name.as_str())
+}
+
+/// Finds a binary inside an extracted archive.
+///
+/// Archives usually wrap their contents in a top-level directory and keep binaries in `bin`,
+/// so the search descends a few levels, preferring shallower matches.
+///
+/// # Arguments
+/// * `dir` - The extraction directory
+/// * `file_name` - The binary's file name
+/// * `is_file` - Returns true if a path is an existing file
+/// * `subdirectories` - Lists the subdirectories of a directory
+///
+/// # Returns
+/// * `Option<PathBuf>` - The binary path, if found
+pub fn find_binary(
+    dir: &Path,
+    file_name: &str,
+    is_file: impl Fn(&Path) -> bool,
+    subdirectories: impl Fn(&Path) -> Vec<PathBuf>,
+) -> Option<PathBuf> {
+    let mut level = vec![dir.to_path_buf()];
+    for _ in 0..=MAX_ARCHIVE_DEPTH {
+        let candidate = level
+            .iter()
+            .map(|dir| dir.join(file_name))
+            .find(|path| is_file(path));
+        if candidate.is_some() {
+            return candidate;
+        }
+        level = level.iter().flat_map(|dir| subdirectories(dir)).collect();
+    }
+    None
+}
+
+/// Rewrites a GitHub download URL to go through a mirror.
+///
+/// A mirror containing `{url}` receives the whole GitHub URL (for proxies such as
+/// `https://proxy.example.com/{url}`); any other mirror replaces the `https://github.com`
+/// prefix, keeping the `<owner>/<repo>/releases/download/<tag>/<asset>` path.
+///
+/// # Arguments
+/// * `url` - The GitHub download URL
+/// * `mirror` - The configured mirror
+///
+/// # Returns
+/// * `Option<String>` - The mirrored URL, or `None` if the mirror does not apply
+pub fn mirror_url(url: &str, mirror: &str) -> Option<String> {
+    let mirror = mirror.trim();
+    if mirror.is_empty() {
+        return None;
+    }
+    if mirror.contains("{url}") {
+        return Some(mirror.replace("{url}", url));
+    }
+    let path = url.strip_prefix(GITHUB_URL)?;
+    Some(format!("{}{}", mirror.trim_end_matches('/'), path))
+}
+
+/// Selects the versioned install directories that can be deleted after an install.
+///
+/// Keeps the current install and the newest other version, so switching back after a bad
+/// release needs no download. Directories whose suffix is not a version are left alone.
+///
+/// # Arguments
+/// * `installs` - The directories next to the current install
+/// * `prefix` - The install directory prefix, e.g. `cangjie-lsp-`
+/// * `current` - The current install directory
+///
+/// # Returns
+/// * `Vec<PathBuf>` - The directories to delete
+pub fn stale_installs(installs: &[PathBuf], prefix: &str, current: &Path) -> Vec<PathBuf> {
+    let mut versioned: Vec<(Version, &PathBuf)> = installs
+        .iter()
+        .filter(|dir| dir.file_name() != current.file_name())
+        .filter_map(|dir| {
+            let name = dir.file_name()?.to_str()?;
+            Some((Version::parse(name.strip_prefix(prefix)?)?, dir))
+        })
+        .collect();
+    versioned.sort_by(|a, b| b.0.cmp(&a.0));
+    versioned
+        .into_iter()
+        .skip(1)
+        .map(|(_, dir)| dir.clone())
+        .collect()
+}
+
+/// Reads asset names from a recorded release listing.
+///
+/// Accepts a GitHub release object (`{"assets": [{"name": ...}]}`), an array of asset
+/// objects, or an array of asset names.
+///
+/// # Arguments
+/// * `json` - The recorded listing
+///
+/// # Returns
+/// * `Result<Vec<String>, String>` - The asset names or an error message
+pub fn parse_asset_listing(json: &str) -> Result<Vec<String>, String> {
+    let value: Value =
+        serde_json::from_str(json).map_err(|e| format!("Invalid asset listing: {}", e))?;
+    let assets = match &value {
+        Value::Object(release) => release.get("assets").unwrap_or(&Value::Null),
+        other => other,
+    };
+    let assets = assets
+        .as_array()
+        .ok_or("Asset listing must be an array or a release object with `assets`")?;
+    assets
+        .iter()
+        .map(|asset| match asset {
+            Value::String(name) => Ok(name.clone()),
+            Value::Object(object) => object
+                .get("name")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| "Asset entry without a `name`".to_string()),
+            other => Err(format!("Unexpected asset entry: {}", other)),
+        })
+        .collect()
+}
+
+/// Checks every platform against a release's assets.
+///
+/// # Arguments
+/// * `asset_names` - The assets of the release
+/// * `base_name` - The binary's base name, e.g. `cangjie-lsp`
+/// * `expected_for` - Returns the canonical asset name of a platform
+///
+/// # Returns
+/// * `Vec<AssetCheck>` - One entry per platform in [`PLATFORMS`]
+pub fn verify_assets(
+    asset_names: &[String],
+    base_name: &str,
+    expected_for: impl Fn(Os, Architecture) -> Result<String, String>,
+) -> Vec<AssetCheck> {
+    PLATFORMS
+        .iter()
+        .map(|&(os, arch)| {
+            let expected = expected_for(os, arch);
+            let asset = expected
+                .is_ok()
+                .then(|| match_asset(base_name, os, arch, asset_names))
+                .flatten()
+                .map(str::to_string);
+            AssetCheck {
+                platform: platform_label(os, arch),
+                expected,
+                asset,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_asset_listing() {
+        // 测试解析多种格式的发布资源列表
+        let release = r#"{"tag_name": "v1.0.0", "assets": [{"name": "a"}, {"name": "b"}]}"#;
+        assert_eq!(parse_asset_listing(release).unwrap(), vec!["a", "b"]);
+        assert_eq!(parse_asset_listing(r#"["a"]"#).unwrap(), vec!["a"]);
+        assert!(parse_asset_listing(r#"{"tag_name": "v1"}"#).is_err());
+
+        let checks = verify_assets(&["x-linux-amd64".to_string()], "x", |os, _| match os {
+            Os::Linux => Ok("x-x86_64-unknown-linux-gnu".to_string()),
+            _ => Err("unsupported".to_string()),
+        });
+        assert_eq!(checks.len(), PLATFORMS.len());
+        assert_eq!(checks[0].asset.as_deref(), Some("x-linux-amd64"));
+        assert_eq!(checks[0].platform, "linux-x86_64");
+        assert!(checks[3].asset.is_none());
+    }
+
+    #[test]
+    fn test_archived_assets() {
+        // 测试压缩包形式的发布资源匹配与类型识别
+        let names = vec![
+            "cangjie-lsp-x86_64-unknown-linux-gnu.tar.gz".to_string(),
+            "cangjie-lsp-x86_64-pc-windows-msvc.zip".to_string(),
+        ];
+        assert_eq!(
+            match_asset("cangjie-lsp", Os::Windows, Architecture::X8664, &names),
+            Some("cangjie-lsp-x86_64-pc-windows-msvc.zip")
+        );
+        let linux = match_asset("cangjie-lsp", Os::Linux, Architecture::X8664, &names).unwrap();
+        assert!(matches!(archive_type(linux), DownloadedFileType::GzipTar));
+        assert!(matches!(archive_type("a.zip"), DownloadedFileType::Zip));
+        assert!(matches!(
+            archive_type("a"),
+            DownloadedFileType::Uncompressed
+        ));
+        assert_eq!(binary_file_name(Os::Windows, "cjdb"), "cjdb.exe");
+    }
+
+    #[test]
+    fn test_extract_command() {
+        // 测试按平台与归档类型选择解压命令
+        let archive = Path::new("/dl/lsp.tar.gz.partial");
+        let dest = Path::new("/dl/cangjie-lsp-1.0.0.partial");
+        let command = |os, file_type| extract_command(os, file_type, archive, dest, "cangjie-lsp");
+        assert_eq!(command(Os::Linux, DownloadedFileType::Uncompressed), None);
+        assert_eq!(
+            command(Os::Linux, DownloadedFileType::GzipTar),
+            Some((
+                "tar".to_string(),
+                vec![
+                    "-xf".to_string(),
+                    "/dl/lsp.tar.gz.partial".to_string(),
+                    "-C".to_string(),
+                    "/dl/cangjie-lsp-1.0.0.partial".to_string()
+                ]
+            ))
+        );
+        assert_eq!(
+            command(Os::Windows, DownloadedFileType::Zip).unwrap().0,
+            "tar"
+        );
+        assert_eq!(
+            command(Os::Mac, DownloadedFileType::Zip).unwrap().0,
+            "unzip"
+        );
+        let (program, args) = command(Os::Linux, DownloadedFileType::Gzip).unwrap();
+        assert_eq!(program, "sh");
+        assert_eq!(args[4], "/dl/cangjie-lsp-1.0.0.partial/cangjie-lsp");
+        let (program, args) = command(Os::Windows, DownloadedFileType::Gzip).unwrap();
+        assert_eq!(program, "powershell");
+        assert_eq!(args[2], "-EncodedCommand");
+    }
+
+    #[test]
+    fn test_match_asset() {
+        // 测试按平台对不同命名方式的发布资源排序并选出最合适的一个
+        let names: Vec<String> = [
+            "cangjie-lsp-x86_64-unknown-linux-musl.tar.gz",
+            "cangjie-lsp-linux-amd64.tar.gz",
+            "cangjie-lsp-x86_64-unknown-linux-gnu.tar.gz",
+            "cangjie-lsp-x86_64-unknown-linux-gnu.tar.gz.sha256",
+            "cangjie-lsp-linux-riscv64.tar.gz",
+            "cangjie-lsp-aarch64-unknown-linux-musl",
+            "cangjie-lsp-universal-apple-darwin.zip",
+            "cangjie-lsp-darwin-arm64.zip",
+            "cangjie-lsp-windows-x64.exe",
+            "cangjie-lsp-x86_64-pc-windows-gnu.exe",
+            "cangjie-lsp-i686-pc-windows-msvc.zip",
+            "cangjie-lsp-server-x86_64-unknown-linux-gnu",
+        ]
+        .map(String::from)
+        .to_vec();
+        let best = |os, arch| match_asset("cangjie-lsp", os, arch, &names);
+        assert_eq!(
+            best(Os::Linux, Architecture::X8664),
+            Some("cangjie-lsp-x86_64-unknown-linux-gnu.tar.gz")
+        );
+        assert_eq!(
+            best(Os::Linux, Architecture::Aarch64),
+            Some("cangjie-lsp-aarch64-unknown-linux-musl")
+        );
+        assert_eq!(
+            best(Os::Mac, Architecture::Aarch64),
+            Some("cangjie-lsp-darwin-arm64.zip")
+        );
+        assert_eq!(
+            best(Os::Mac, Architecture::X8664),
+            Some("cangjie-lsp-universal-apple-darwin.zip")
+        );
+        assert_eq!(
+            best(Os::Windows, Architecture::X8664),
+            Some("cangjie-lsp-windows-x64.exe")
+        );
+        assert_eq!(
+            best(Os::Windows, Architecture::X86),
+            Some("cangjie-lsp-i686-pc-windows-msvc.zip")
+        );
+        assert_eq!(best(Os::Windows, Architecture::Aarch64), None);
+        assert_eq!(best(Os::Linux, Architecture::X86), None);
+    }
+
+    #[test]
+    fn test_stale_installs() {
+        // 测试只保留当前版本与上一个版本的安装目录
+        let dir = |name: &str| PathBuf::from("/ext").join(name);
+        let installs = vec![
+            dir("cangjie-lsp-v0.9.0"),
+            dir("cangjie-lsp-v1.1.0"),
+            dir("cangjie-lsp-v1.0.0"),
+            dir("cangjie-lsp-custom"),
+            dir("cjdb-v0.1.0"),
+        ];
+        let stale = stale_installs(&installs, "cangjie-lsp-", &dir("cangjie-lsp-v1.1.0"));
+        assert_eq!(stale, vec![dir("cangjie-lsp-v0.9.0")]);
+        assert!(stale_installs(&installs[..2], "cangjie-lsp-", &installs[1]).is_empty());
+    }
+
+    #[test]
+    fn test_mirror_url() {
+        // 测试将 GitHub 下载地址改写为镜像地址
+        let url = "https://github.com/cangjie-lang/cangjie/releases/download/v1.0.0/lsp.tar.gz";
+        assert_eq!(
+            mirror_url(url, "https://mirror.example.com/github/").as_deref(),
+            Some(
+                "https://mirror.example.com/github/cangjie-lang/cangjie/releases/download/v1.0.0/lsp.tar.gz"
+            )
+        );
+        assert_eq!(
+            mirror_url(url, "https://proxy.example.com/{url}").as_deref(),
+            Some(
+                "https://proxy.example.com/https://github.com/cangjie-lang/cangjie/releases/download/v1.0.0/lsp.tar.gz"
+            )
+        );
+        assert_eq!(
+            mirror_url("https://example.com/lsp", "https://mirror.example.com"),
+            None
+        );
+        assert_eq!(mirror_url(url, " "), None);
+    }
+}
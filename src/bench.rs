@@ -0,0 +1,267 @@
+// src/bench.rs
+//! Running benchmarks for `/cangjie-bench` and comparing them with the previous run.
+//!
+//! Benchmarks are run with `cjpm bench`, or without cjpm by running a binary compiled with
+//! `cjc --test` with `--bench`. Both print one table per test class:
+//!
+//! ```text
+//!     TCS: CalcBench, time elapsed: 812 ns, RESULT:
+//!     | Case | Args |   Median |       Err | Err% |     Mean |
+//!     |:-----|:-----|---------:|----------:|-----:|---------:|
+//!     | add  | -    | 12.34 ns | ±0.05 ns  | ±0.4% | 12.40 ns |
+//! ```
+
+use std::collections::BTreeMap;
+
+use serde_json::{Value, json};
+
+use crate::humanize::{NumberFormat, format_nanos};
+
+/// Option making a test binary run its benchmarks instead of its tests
+pub const BENCH_FLAG: &str = "--bench";
+
+/// Relative change of the median below which a benchmark counts as unchanged, in percent
+const NOISE_PERCENT: f64 = 2.0;
+
+/// Arguments accepted by `/cangjie-bench`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BenchArgs {
+    /// Benchmark to run, forwarded as `--filter`
+    pub filter: Option<String>,
+    /// Remaining options, forwarded unchanged
+    pub passthrough: Vec<String>,
+}
+
+impl BenchArgs {
+    /// Parses the slash command arguments: a bare argument selects the benchmarks to run,
+    /// options are forwarded
+    pub fn parse(args: &[String]) -> Self {
+        let mut bench_args = BenchArgs::default();
+        for arg in args {
+            if arg.starts_with('-') || bench_args.filter.is_some() {
+                bench_args.passthrough.push(arg.clone());
+            } else {
+                bench_args.filter = Some(arg.clone());
+            }
+        }
+        bench_args
+    }
+
+    /// Returns the arguments of `cjpm bench`
+    pub fn cjpm_args(&self) -> Vec<String> {
+        let mut args = vec!["bench".to_string()];
+        args.extend(self.passthrough.iter().cloned());
+        args.extend(
+            self.filter
+                .iter()
+                .map(|filter| format!("--filter={}", filter)),
+        );
+        args
+    }
+
+    /// Returns the arguments of a test binary compiled with `cjc --test`
+    pub fn binary_args(&self) -> Vec<String> {
+        let mut args = vec![BENCH_FLAG.to_string()];
+        args.extend(self.passthrough.iter().cloned());
+        args.extend(
+            self.filter
+                .iter()
+                .map(|filter| format!("--filter={}", filter)),
+        );
+        args
+    }
+}
+
+/// The timing of one benchmark case
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    /// `Class.case`, followed by the arguments in parentheses for parameterized cases
+    pub name: String,
+    pub median_ns: f64,
+    /// Relative error of the median, in percent
+    pub error_percent: Option<f64>,
+}
+
+/// Parses a time such as `12.34 ns`, `1.5 us` or `2 ms` into nanoseconds
+fn parse_nanos(value: &str) -> Option<f64> {
+    let value = value.trim().trim_start_matches('±');
+    let split = value.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+    let (number, unit) = value.split_at(split);
+    let factor = match unit.trim() {
+        "ns" => 1.0,
+        "us" | "µs" | "μs" => 1e3,
+        "ms" => 1e6,
+        "s" => 1e9,
+        _ => return None,
+    };
+    Some(number.parse::<f64>().ok()? * factor)
+}
+
+/// Parses the benchmark tables of a run.
+///
+/// # Arguments
+/// * `output` - The standard output of the run
+///
+/// # Returns
+/// * `Vec<BenchResult>` - The cases in output order
+pub fn parse_results(output: &str) -> Vec<BenchResult> {
+    let mut results = Vec::new();
+    let mut class = String::new();
+    for line in output.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("TCS:") {
+            class = rest
+                .split(',')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            continue;
+        }
+        let Some(row) = line.strip_prefix('|') else {
+            continue;
+        };
+        let cells: Vec<&str> = row.split('|').map(str::trim).collect();
+        let [case, args, median, _error, error_percent, ..] = cells.as_slice() else {
+            continue;
+        };
+        let Some(median_ns) = parse_nanos(median) else {
+            continue;
+        };
+        let mut name = if class.is_empty() {
+            case.to_string()
+        } else {
+            format!("{}.{}", class, case)
+        };
+        if !args.is_empty() && *args != "-" {
+            name.push_str(&format!("({})", args));
+        }
+        results.push(BenchResult {
+            name,
+            median_ns,
+            error_percent: error_percent
+                .trim_start_matches('±')
+                .trim_end_matches('%')
+                .parse()
+                .ok(),
+        });
+    }
+    results
+}
+
+/// Serializes the medians of a run, merged into the medians of earlier runs
+pub fn to_state(previous: &BTreeMap<String, f64>, results: &[BenchResult]) -> String {
+    let mut medians = previous.clone();
+    medians.extend(
+        results
+            .iter()
+            .map(|result| (result.name.clone(), result.median_ns)),
+    );
+    json!(medians).to_string()
+}
+
+/// Reads the medians saved by [`to_state`]
+pub fn from_state(state: &str) -> BTreeMap<String, f64> {
+    let Ok(Value::Object(medians)) = serde_json::from_str(state) else {
+        return BTreeMap::new();
+    };
+    medians
+        .into_iter()
+        .filter_map(|(name, median)| Some((name, median.as_f64()?)))
+        .collect()
+}
+
+/// Describes a benchmark result and its change since the previous run.
+///
+/// Changes within the benchmark's error, or below 2 %, count as unchanged.
+///
+/// # Arguments
+/// * `result` - The result of this run
+/// * `previous` - The median of the previous run, in nanoseconds
+/// * `format` - The number format of the output
+///
+/// # Returns
+/// * `String` - A line such as `⚠️ Calc.add: 13.0 ns (was 12.0 ns, +8.3%)`
+pub fn describe(result: &BenchResult, previous: Option<f64>, format: &NumberFormat) -> String {
+    let median = format_nanos(result.median_ns, format);
+    let Some(previous) = previous.filter(|previous| *previous > 0.0) else {
+        return format!("{}: {}", result.name, median);
+    };
+    let change = (result.median_ns - previous) / previous * 100.0;
+    let noise = result.error_percent.unwrap_or(0.0).max(NOISE_PERCENT);
+    let marker = if change.abs() <= noise {
+        "➖"
+    } else if change > 0.0 {
+        "⚠️"
+    } else {
+        "✅"
+    };
+    format!(
+        "{} {}: {} (was {}, {:+.1}%)",
+        marker,
+        result.name,
+        median,
+        format_nanos(previous, format),
+        change
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_results() {
+        // 测试解析基准测试表格并与上次运行比较
+        let output = "\
+TP: default, time elapsed: 9000 ns, RESULT:
+    TCS: CalcBench, time elapsed: 812 ns, RESULT:
+    | Case   | Args   |   Median |        Err |   Err% |     Mean |
+    |:-------|:-------|---------:|-----------:|-------:|---------:|
+    | add    | -      | 12.00 ns |  ±0.05 ns  |  ±0.4% | 12.40 ns |
+    | sum    | 1000   |  1.50 us |  ±0.30 us  | ±20.0% |  1.60 us |
+Summary: TOTAL: 2
+";
+        let results = parse_results(output);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name, "CalcBench.add");
+        assert_eq!(results[0].median_ns, 12.0);
+        assert_eq!(results[0].error_percent, Some(0.4));
+        assert_eq!(results[1].name, "CalcBench.sum(1000)");
+        assert_eq!(results[1].median_ns, 1500.0);
+
+        let state = to_state(&BTreeMap::from([("Old.case".to_string(), 1.0)]), &results);
+        let previous = from_state(&state);
+        assert_eq!(previous.len(), 3);
+        assert_eq!(previous["CalcBench.add"], 12.0);
+
+        let format = NumberFormat::default();
+        let slower = BenchResult {
+            median_ns: 13.0,
+            ..results[0].clone()
+        };
+        assert_eq!(
+            describe(&slower, previous.get("CalcBench.add").copied(), &format),
+            "⚠️ CalcBench.add: 13.0 ns (was 12.0 ns, +8.3%)"
+        );
+        // 误差范围内的变化视为不变
+        let noisy = BenchResult {
+            median_ns: 1200.0,
+            ..results[1].clone()
+        };
+        assert!(describe(&noisy, Some(1500.0), &format).starts_with("➖"));
+        assert_eq!(
+            describe(&results[0], None, &format),
+            "CalcBench.add: 12.0 ns"
+        );
+
+        let args = BenchArgs::parse(&["CalcBench.*".to_string(), "--no-color".to_string()]);
+        assert_eq!(
+            args.cjpm_args(),
+            vec!["bench", "--no-color", "--filter=CalcBench.*"]
+        );
+        assert_eq!(
+            args.binary_args(),
+            vec!["--bench", "--no-color", "--filter=CalcBench.*"]
+        );
+    }
+}
@@ -0,0 +1,248 @@
+// src/build.rs
+//! Choosing how `/cangjie-build` builds a worktree.
+//!
+//! Package projects, recognized by their `cjpm.toml`, are built with `cjpm build`. A worktree
+//! without a manifest holding a single source file is compiled with cjc directly, into an
+//! executable named after the file next to it. `cangjie.build.defaultProfile` selects
+//! whether builds are optimized, and `cangjie.build.targets` lists the cross-compilation
+//! targets that get build tasks of their own.
+
+use serde_json::Value;
+use zed_extension_api::Os;
+
+use crate::cjpm::{DEBUG_PROFILE, RELEASE_PROFILE, profile_flags};
+use crate::debug::executable_path;
+use crate::formatter::SOURCE_EXTENSION;
+
+/// A build profile: an unoptimized build with debug info, or an optimized one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BuildProfile {
+    #[default]
+    Debug,
+    Release,
+}
+
+impl BuildProfile {
+    /// Both profiles, in the order their task variants are listed
+    pub const ALL: [BuildProfile; 2] = [BuildProfile::Debug, BuildProfile::Release];
+
+    /// Parses a profile name, `debug` or `release`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            DEBUG_PROFILE => Some(BuildProfile::Debug),
+            RELEASE_PROFILE => Some(BuildProfile::Release),
+            _ => None,
+        }
+    }
+
+    /// Returns the profile's name, which is also its output directory below the target
+    /// directory
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuildProfile::Debug => DEBUG_PROFILE,
+            BuildProfile::Release => RELEASE_PROFILE,
+        }
+    }
+
+    /// Returns the flags building with the profile
+    pub fn flags(&self) -> Vec<String> {
+        match self {
+            BuildProfile::Debug => vec!["-g".to_string()],
+            BuildProfile::Release => vec!["-O2".to_string()],
+        }
+    }
+}
+
+/// Applies the default profile to the arguments from `cangjie.build.extraArgs`.
+///
+/// Unoptimized builds are what cjpm and cjc do by default, so only the release profile adds
+/// flags, and only when the arguments do not select a profile themselves.
+///
+/// # Arguments
+/// * `profile` - The profile from `cangjie.build.defaultProfile`
+/// * `extra_args` - The arguments from `cangjie.build.extraArgs`
+///
+/// # Returns
+/// * `Vec<String>` - The arguments to append to the build command
+pub fn default_profile_args(profile: BuildProfile, extra_args: &[String]) -> Vec<String> {
+    if profile == BuildProfile::Release && profile_flags(extra_args).is_empty() {
+        [profile.flags(), extra_args.to_vec()].concat()
+    } else {
+        extra_args.to_vec()
+    }
+}
+
+/// A cross-compilation target from `cangjie.build.targets`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrossTarget {
+    /// Target triple, e.g. `aarch64-linux-ohos`
+    pub triple: String,
+    /// Sysroot passed to cjc; cjpm reads it from the `[target.<triple>]` table of
+    /// `cjpm.toml` instead
+    pub sysroot: Option<String>,
+}
+
+impl CrossTarget {
+    /// Returns the arguments selecting the target for `cjpm build`
+    pub fn cjpm_args(&self) -> Vec<String> {
+        vec!["--target".to_string(), self.triple.clone()]
+    }
+
+    /// Returns the arguments selecting the target and its sysroot for cjc
+    pub fn cjc_args(&self) -> Vec<String> {
+        let mut args = self.cjpm_args();
+        if let Some(sysroot) = &self.sysroot {
+            args.extend(["--sysroot".to_string(), sysroot.clone()]);
+        }
+        args
+    }
+}
+
+/// Parses `cangjie.build.targets`: target triples, or objects with a `triple` and a
+/// `sysroot`.
+///
+/// # Arguments
+/// * `value` - The setting value
+///
+/// # Returns
+/// * `Result<Vec<CrossTarget>, String>` - The targets, or an error naming the malformed entry
+pub fn parse_targets(value: &Value) -> Result<Vec<CrossTarget>, String> {
+    let entries = value
+        .as_array()
+        .ok_or("'cangjie.build.targets' must be a list of target triples")?;
+    entries
+        .iter()
+        .map(|entry| {
+            let (triple, sysroot) = match entry {
+                Value::String(triple) => (triple.as_str(), None),
+                Value::Object(target) => (
+                    target["triple"].as_str().unwrap_or_default(),
+                    target
+                        .get("sysroot")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                ),
+                _ => ("", None),
+            };
+            // A triple has at least an architecture, a system and an environment or vendor
+            if triple.split('-').filter(|part| !part.is_empty()).count() < 3 {
+                return Err(format!(
+                    "Invalid target in 'cangjie.build.targets': {}",
+                    entry
+                ));
+            }
+            Ok(CrossTarget {
+                triple: triple.to_string(),
+                sysroot,
+            })
+        })
+        .collect()
+}
+
+/// Returns the arguments of `cjpm build`.
+///
+/// # Arguments
+/// * `extra_args` - The arguments from `cangjie.build.extraArgs`
+pub fn package_args(extra_args: &[String]) -> Vec<String> {
+    let mut args = vec!["build".to_string()];
+    args.extend(extra_args.iter().cloned());
+    args
+}
+
+/// Returns the source file of a worktree without a manifest.
+///
+/// # Arguments
+/// * `sources` - The source files of the worktree
+///
+/// # Returns
+/// * `Result<&str, String>` - The only source file, or an error when there is none or several
+pub fn single_source(sources: &[String]) -> Result<&str, String> {
+    match sources {
+        [source] => Ok(source),
+        [] => Err(format!(
+            "No cjpm.toml and no {} source file found in the worktree",
+            SOURCE_EXTENSION
+        )),
+        _ => Err(format!(
+            "No cjpm.toml found and the worktree has {} source files; run `cjpm init` to \
+             build them as a package",
+            sources.len()
+        )),
+    }
+}
+
+/// Returns the cjc arguments compiling a single source file into an executable named after it.
+///
+/// # Arguments
+/// * `os` - The current platform
+/// * `source` - The source file, relative to the worktree root
+/// * `extra_args` - The arguments from `cangjie.build.extraArgs`
+///
+/// # Returns
+/// * `Vec<String>` - The arguments after `cjc`
+pub fn single_file_args(os: Os, source: &str, extra_args: &[String]) -> Vec<String> {
+    let output = source.strip_suffix(SOURCE_EXTENSION).unwrap_or(source);
+    let mut args = vec![
+        source.to_string(),
+        "-o".to_string(),
+        executable_path(os, None, output),
+    ];
+    args.extend(extra_args.iter().cloned());
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_args() {
+        // 测试包项目与单文件工作区的构建参数
+        let extra = vec!["-O2".to_string()];
+        assert_eq!(package_args(&extra), vec!["build", "-O2"]);
+        assert_eq!(
+            single_file_args(Os::Linux, "hello.cj", &extra),
+            vec!["hello.cj", "-o", "hello", "-O2"]
+        );
+        assert_eq!(
+            single_file_args(Os::Windows, "demo/hello.cj", &[]),
+            vec!["demo/hello.cj", "-o", r"demo\hello.exe"]
+        );
+        assert_eq!(single_source(&["main.cj".to_string()]), Ok("main.cj"));
+        assert!(single_source(&[]).is_err());
+        assert!(single_source(&["a.cj".to_string(), "b.cj".to_string()]).is_err());
+
+        // 测试默认构建配置：release 追加优化参数，除非额外参数已指定
+        assert_eq!(BuildProfile::parse("release"), Some(BuildProfile::Release));
+        assert_eq!(BuildProfile::parse("fast"), None);
+        assert_eq!(
+            default_profile_args(BuildProfile::Release, &["-V".to_string()]),
+            vec!["-O2", "-V"]
+        );
+        assert_eq!(default_profile_args(BuildProfile::Release, &extra), extra);
+        assert!(default_profile_args(BuildProfile::Debug, &[]).is_empty());
+
+        // 测试交叉编译目标：三元组或带 sysroot 的对象
+        let targets = parse_targets(&serde_json::json!([
+            "aarch64-linux-ohos",
+            { "triple": "arm-linux-ohos", "sysroot": "/opt/ohos/sysroot" }
+        ]))
+        .unwrap();
+        assert_eq!(
+            targets[0].cjpm_args(),
+            vec!["--target", "aarch64-linux-ohos"]
+        );
+        assert_eq!(targets[0].cjc_args(), targets[0].cjpm_args());
+        assert_eq!(
+            targets[1].cjc_args(),
+            vec![
+                "--target",
+                "arm-linux-ohos",
+                "--sysroot",
+                "/opt/ohos/sysroot"
+            ]
+        );
+        assert!(parse_targets(&serde_json::json!(["ohos"])).is_err());
+        assert!(parse_targets(&serde_json::json!("aarch64-linux-ohos")).is_err());
+    }
+}
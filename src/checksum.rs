@@ -0,0 +1,133 @@
+// src/checksum.rs
+//! SHA-256 verification of downloaded release assets.
+//!
+//! Releases publish checksums either per asset (`<asset>.sha256`) or in a combined file
+//! (`SHA256SUMS`, `sha256sums.txt`, `checksums.txt`) in `sha256sum` format.
+
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Names of combined checksum files, in order of preference
+const COMBINED_CHECKSUM_FILES: &[&str] = &["SHA256SUMS", "sha256sums.txt", "checksums.txt"];
+
+/// Suffix of per-asset checksum files
+const ASSET_CHECKSUM_SUFFIX: &str = ".sha256";
+
+/// Finds the release asset holding the checksum of `asset_name`.
+///
+/// # Arguments
+/// * `asset_name` - The asset to verify
+/// * `asset_names` - All assets of the release
+///
+/// # Returns
+/// * `Option<&str>` - The checksum asset, if the release publishes one
+pub fn find_checksum_asset<'a>(asset_name: &str, asset_names: &'a [String]) -> Option<&'a str> {
+    let per_asset = format!("{}{}", asset_name, ASSET_CHECKSUM_SUFFIX);
+    std::iter::once(per_asset.as_str())
+        .chain(COMBINED_CHECKSUM_FILES.iter().copied())
+        .find_map(|wanted| {
+            asset_names
+                .iter()
+                .find(|name| name.eq_ignore_ascii_case(wanted))
+        })
+        .map(String::as_str)
+}
+
+/// Extracts the expected checksum of `asset_name` from a checksum file.
+///
+/// Accepts `sha256sum` output (`<hash>  <name>` or `<hash> *<name>`) and files containing
+/// only a bare hash.
+///
+/// # Arguments
+/// * `contents` - The checksum file contents
+/// * `asset_name` - The asset to look up
+///
+/// # Returns
+/// * `Option<String>` - The lowercase hex digest, if listed
+pub fn parse_checksum(contents: &str, asset_name: &str) -> Option<String> {
+    let is_digest = |s: &str| s.len() == 64 && s.chars().all(|c| c.is_ascii_hexdigit());
+    let entries: Vec<(&str, Option<&str>)> = contents
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let hash = fields.next().filter(|hash| is_digest(hash))?;
+            let name = fields.next().map(|name| {
+                let name = name.trim_start_matches('*');
+                name.rsplit(['/', '\\']).next().unwrap_or(name)
+            });
+            Some((hash, name))
+        })
+        .collect();
+
+    entries
+        .iter()
+        .find(|(_, name)| *name == Some(asset_name))
+        .or(match entries.as_slice() {
+            [entry @ (_, None)] => Some(entry),
+            _ => None,
+        })
+        .map(|(hash, _)| hash.to_ascii_lowercase())
+}
+
+/// Computes the lowercase hex SHA-256 digest of `bytes`
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Compares the digest of a downloaded file with the expected one.
+///
+/// # Arguments
+/// * `path` - The downloaded file, for the error message
+/// * `expected` - The expected hex digest
+/// * `actual` - The digest of the downloaded file
+///
+/// # Returns
+/// * `Result<(), String>` - An error describing the mismatch
+pub fn check_digest(path: &Path, expected: &str, actual: &str) -> Result<(), String> {
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            path.display(),
+            expected,
+            actual
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum() {
+        // 测试校验文件的查找、解析与 SHA-256 计算
+        let assets = vec![
+            "cangjie-lsp-x86_64-unknown-linux-gnu".to_string(),
+            "SHA256SUMS".to_string(),
+        ];
+        assert_eq!(
+            find_checksum_asset("cangjie-lsp-x86_64-unknown-linux-gnu", &assets),
+            Some("SHA256SUMS")
+        );
+        assert_eq!(find_checksum_asset("a", &["b".to_string()]), None);
+
+        let digest = sha256_hex(b"abc");
+        assert_eq!(
+            digest,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let sums = format!(
+            "{}  dist/other\n{} *dist/cangjie-lsp\n",
+            "0".repeat(64),
+            digest
+        );
+        assert_eq!(parse_checksum(&sums, "cangjie-lsp"), Some(digest.clone()));
+        assert_eq!(parse_checksum(&digest.to_uppercase(), "x"), Some(digest));
+        assert_eq!(parse_checksum(&sums, "missing"), None);
+    }
+}
@@ -0,0 +1,324 @@
+// src/cjpm.rs
+//! Reading `cjpm.toml` package manifests, for locating the artifacts cjpm builds and
+//! offering the package's scripts as tasks.
+//!
+//! Only the keys the extension needs are read, with a line-based reader that understands
+//! `[section]` headers and `key = "value"` pairs; everything else is ignored.
+
+/// Name of the package manifest in the package root
+pub const MANIFEST_FILE: &str = "cjpm.toml";
+
+/// Directory cjpm writes build output to when the manifest sets no `target-dir`
+pub const DEFAULT_TARGET_DIR: &str = "target";
+
+/// Directory holding the package's sources when the manifest sets no `src-dir`
+pub const DEFAULT_SRC_DIR: &str = "src";
+
+/// Output directory below the target directory of unoptimized builds
+pub const DEBUG_PROFILE: &str = "debug";
+
+/// Output directory below the target directory of optimized builds
+pub const RELEASE_PROFILE: &str = "release";
+
+/// Compiler flags that turn on optimization
+const OPTIMIZATION_FLAGS: &[&str] = &["-O1", "-O2", "-O3", "-Os", "-Oz"];
+
+/// The parts of a `cjpm.toml` manifest the extension uses
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    /// `[package] name`
+    pub name: Option<String>,
+    /// `[package] output-type`: `executable`, `static` or `dynamic`
+    pub output_type: Option<String>,
+    /// `[package] target-dir`, or `output-dir`: where build output goes, relative to the
+    /// package root unless absolute
+    pub target_dir: Option<String>,
+    /// `[package] src-dir`: where the package's sources are, relative to the package root
+    pub src_dir: Option<String>,
+    /// `[scripts]`: script names and the shell commands they run, in manifest order
+    pub scripts: Vec<(String, String)>,
+}
+
+impl Manifest {
+    /// Returns true if the package builds a program rather than a library
+    pub fn is_executable(&self) -> bool {
+        self.output_type
+            .as_deref()
+            .is_none_or(|output_type| output_type == "executable")
+    }
+
+    /// Returns the path of the executable a build writes, relative to the package root
+    /// unless `target-dir` is absolute.
+    ///
+    /// # Arguments
+    /// * `profile` - The build's output directory, see [`build_profile`]
+    /// * `fallback_name` - The program name to use when the manifest has no package name
+    ///
+    /// # Returns
+    /// * `String` - The executable path
+    pub fn executable(&self, profile: &str, fallback_name: &str) -> String {
+        let target_dir = self.target_dir.as_deref().unwrap_or(DEFAULT_TARGET_DIR);
+        let name = self.name.as_deref().unwrap_or(fallback_name);
+        format!(
+            "{}/{}/bin/{}",
+            target_dir.trim_end_matches(['/', '\\']),
+            profile,
+            name
+        )
+    }
+}
+
+/// Parses the manifest.
+///
+/// # Arguments
+/// * `contents` - The contents of `cjpm.toml`
+///
+/// # Returns
+/// * `Manifest` - The keys found; missing keys are `None`
+pub fn parse_manifest(contents: &str) -> Manifest {
+    let mut manifest = Manifest::default();
+    let mut section = String::new();
+    for line in contents.lines() {
+        let line = strip_comment(line).trim();
+        if let Some(header) = line.strip_prefix('[') {
+            section = header.trim_end_matches(']').trim().to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = unquote(value.trim());
+        if section == "scripts" {
+            manifest.scripts.push((unquote(key.trim()), value));
+            continue;
+        }
+        if section != "package" {
+            continue;
+        }
+        match key.trim() {
+            "name" => manifest.name = Some(value),
+            "output-type" => manifest.output_type = Some(value),
+            "target-dir" => manifest.target_dir = Some(value),
+            "src-dir" => manifest.src_dir = Some(value),
+            "output-dir" if manifest.target_dir.is_none() => manifest.target_dir = Some(value),
+            _ => {}
+        }
+    }
+    manifest
+}
+
+/// Returns the flags of a build command that select its build profile: optimization levels
+/// and `--profile`.
+///
+/// # Arguments
+/// * `args` - The command arguments
+///
+/// # Returns
+/// * `Vec<String>` - The flags, in the order given
+pub fn profile_flags(args: &[String]) -> Vec<String> {
+    let mut flags = Vec::new();
+    let mut args_it = args.iter();
+    while let Some(arg) = args_it.next() {
+        if OPTIMIZATION_FLAGS.contains(&arg.as_str()) || arg.starts_with("--profile=") {
+            flags.push(arg.clone());
+        } else if arg == "--profile" {
+            flags.push(arg.clone());
+            flags.extend(args_it.next().cloned());
+        }
+    }
+    flags
+}
+
+/// Returns the output directory below the target directory that a build writes to: the
+/// `--profile` name, [`RELEASE_PROFILE`] for optimized builds, or [`DEBUG_PROFILE`].
+///
+/// # Arguments
+/// * `args` - The build command arguments
+///
+/// # Returns
+/// * `String` - The directory name
+pub fn build_profile(args: &[String]) -> String {
+    if let Some(profile) = option_values(args, "--profile").pop() {
+        return profile.to_string();
+    }
+    let optimized = args
+        .iter()
+        .any(|arg| OPTIMIZATION_FLAGS.contains(&arg.as_str()));
+    if optimized {
+        RELEASE_PROFILE
+    } else {
+        DEBUG_PROFILE
+    }
+    .to_string()
+}
+
+/// Returns the values of an option of a cjpm or cjc command line, given as `name value` or
+/// `name=value`.
+///
+/// # Arguments
+/// * `args` - The command arguments
+/// * `name` - The option, e.g. `--run-args`
+///
+/// # Returns
+/// * `Vec<&str>` - The values, in the order given
+pub fn option_values<'a>(args: &'a [String], name: &str) -> Vec<&'a str> {
+    let mut args_it = args.iter();
+    let mut values = Vec::new();
+    while let Some(arg) = args_it.next() {
+        let value = match arg.strip_prefix(name) {
+            Some("") => args_it.next().map(String::as_str),
+            Some(value) => value.strip_prefix('='),
+            None => None,
+        };
+        values.extend(value);
+    }
+    values
+}
+
+/// Returns the program arguments of a `cjpm run` command, given with `--run-args`.
+///
+/// # Arguments
+/// * `args` - The arguments after `cjpm`
+///
+/// # Returns
+/// * `Vec<String>` - The program arguments, split at whitespace
+pub fn run_args(args: &[String]) -> Vec<String> {
+    option_values(args, "--run-args")
+        .into_iter()
+        .flat_map(str::split_whitespace)
+        .map(str::to_string)
+        .collect()
+}
+
+/// Removes a `#` comment that is not inside a quoted string
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Removes the quotes around a string value
+fn unquote(value: &str) -> String {
+    let stripped = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .or_else(|| {
+            value
+                .strip_prefix('\'')
+                .and_then(|value| value.strip_suffix('\''))
+        });
+    stripped.unwrap_or(value).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_manifest() {
+        // 测试读取 [package] 中的包名与输出类型，忽略其他节与注释
+        let manifest = parse_manifest(
+            r#"
+[dependencies]
+name = "not-the-package"
+
+[package] # 包信息
+  cjc-version = "0.59.6"
+  name = "hello#world" # 注释
+  output-type = 'executable'
+"#,
+        );
+        assert_eq!(manifest.name.as_deref(), Some("hello#world"));
+        assert_eq!(manifest.output_type.as_deref(), Some("executable"));
+        assert_eq!(parse_manifest(""), Manifest::default());
+        assert!(manifest.is_executable());
+        assert_eq!(
+            manifest.executable(DEBUG_PROFILE, "dir"),
+            "target/debug/bin/hello#world"
+        );
+        assert_eq!(
+            Manifest::default().executable(DEBUG_PROFILE, "dir"),
+            "target/debug/bin/dir"
+        );
+
+        // 测试自定义输出目录与库类型的包
+        let manifest = parse_manifest(
+            "[package]\nname = \"core\"\noutput-type = \"static\"\ntarget-dir = \"/tmp/out/\"\n",
+        );
+        assert!(!manifest.is_executable());
+        assert_eq!(manifest.src_dir, None);
+        assert!(manifest.scripts.is_empty());
+        assert_eq!(
+            manifest.executable(RELEASE_PROFILE, "dir"),
+            "/tmp/out/release/bin/core"
+        );
+
+        // 测试读取 [scripts] 中的脚本，键可带引号
+        let manifest = parse_manifest(
+            "[scripts]\nlint = \"cjlint -f src\" # 检查\n\"gen:docs\" = 'cjpm build && ./doc.sh'\n",
+        );
+        assert_eq!(
+            manifest.scripts,
+            vec![
+                ("lint".to_string(), "cjlint -f src".to_string()),
+                ("gen:docs".to_string(), "cjpm build && ./doc.sh".to_string()),
+            ]
+        );
+
+        // 测试 cjpm run 的 --run-args 参数
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            run_args(&args(&[
+                "run",
+                "--run-args",
+                "--port 8080",
+                "--run-args=-v"
+            ])),
+            vec!["--port", "8080", "-v"]
+        );
+        assert!(run_args(&args(&["run", "-g"])).is_empty());
+        assert_eq!(
+            option_values(
+                &args(&["test", "--filter", "Calc.*", "--filter-x"]),
+                "--filter"
+            ),
+            vec!["Calc.*"]
+        );
+    }
+
+    #[test]
+    fn test_build_profile() {
+        // 测试根据优化选项与 --profile 确定构建输出目录
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(build_profile(&args(&["build", "-g"])), DEBUG_PROFILE);
+        assert_eq!(build_profile(&args(&["build", "-O0", "-g"])), DEBUG_PROFILE);
+        assert_eq!(
+            build_profile(&args(&["build", "-O2", "-g"])),
+            RELEASE_PROFILE
+        );
+        assert_eq!(
+            build_profile(&args(&["build", "-O2", "--profile", "relwithdebinfo"])),
+            "relwithdebinfo"
+        );
+        assert_eq!(
+            profile_flags(&args(&[
+                "run",
+                "-O2",
+                "-g",
+                "--profile",
+                "bench",
+                "--run-args",
+                "-v"
+            ])),
+            vec!["-O2", "--profile", "bench"]
+        );
+        assert!(profile_flags(&args(&["run", "-g"])).is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+// src/clean.rs
+//! Removing a project's build output for `/cangjie-clean`.
+//!
+//! `cjpm clean` is used when cjpm is available; otherwise the target directory is deleted
+//! directly, but only if it lies inside the worktree. The directory is measured before and
+//! after, so the reclaimed space can be shown.
+
+use zed_extension_api::Os;
+
+use crate::paths::{encoded_powershell, has_traversal, powershell_literal};
+use crate::run_config::is_absolute;
+
+/// Returns the command printing the disk usage of a directory. It fails if the directory
+/// does not exist.
+///
+/// # Arguments
+/// * `os` - The current platform
+/// * `dir` - The directory to measure
+///
+/// # Returns
+/// * `(String, Vec<String>)` - The program and its arguments
+pub fn size_command(os: Os, dir: &str) -> (String, Vec<String>) {
+    match os {
+        Os::Windows => {
+            let dir = dir.replace('\'', "''");
+            (
+                "powershell".to_string(),
+                vec![
+                    "-NoProfile".to_string(),
+                    "-Command".to_string(),
+                    format!(
+                        "if (Test-Path -LiteralPath '{0}') {{ \
+                         (Get-ChildItem -LiteralPath '{0}' -Recurse -File -Force | \
+                         Measure-Object -Property Length -Sum).Sum + 0 }} else {{ exit 1 }}",
+                        dir
+                    ),
+                ],
+            )
+        }
+        _ => ("du".to_string(), vec!["-sk".to_string(), dir.to_string()]),
+    }
+}
+
+/// Parses the output of [`size_command`].
+///
+/// # Arguments
+/// * `os` - The platform the command ran on
+/// * `output` - The command's stdout
+///
+/// # Returns
+/// * `Option<u64>` - The size in bytes; `du` reports whole kibibytes
+pub fn parse_size(os: Os, output: &str) -> Option<u64> {
+    let value = output.split_whitespace().next()?.parse::<u64>().ok()?;
+    match os {
+        Os::Windows => Some(value),
+        _ => Some(value * 1024),
+    }
+}
+
+/// Resolves the target directory the delete fallback may remove.
+///
+/// The `target-dir` of a manifest comes with the repository, so without cjpm only a directory
+/// strictly inside the worktree is deleted: no `..` components, and an absolute path must lie
+/// under the root.
+///
+/// # Arguments
+/// * `root` - The worktree root
+/// * `target_dir` - The manifest's target directory, absolute or relative to the root
+///
+/// # Returns
+/// * `Result<String, String>` - The directory to delete, or why it must not be deleted
+pub fn removable_dir(root: &str, target_dir: &str) -> Result<String, String> {
+    if has_traversal(target_dir) {
+        return Err(format!("`{}` contains '..' components", target_dir));
+    }
+    let root = root.replace('\\', "/").trim_end_matches('/').to_string();
+    let target = target_dir.replace('\\', "/");
+    let relative = if is_absolute(target_dir) {
+        match target
+            .strip_prefix(&root)
+            .and_then(|rest| rest.strip_prefix('/'))
+        {
+            Some(relative) => relative.to_string(),
+            None => return Err(format!("`{}` is outside the worktree", target_dir)),
+        }
+    } else {
+        target
+    };
+    let components: Vec<&str> = relative
+        .split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect();
+    if components.is_empty() {
+        return Err(format!("`{}` is the worktree root", target_dir));
+    }
+    Ok(format!("{}/{}", root, components.join("/")))
+}
+
+/// Returns the command deleting a directory, for when cjpm is not available.
+///
+/// # Arguments
+/// * `os` - The current platform
+/// * `dir` - The directory to delete
+///
+/// # Returns
+/// * `(String, Vec<String>)` - The program and its arguments
+pub fn remove_command(os: Os, dir: &str) -> (String, Vec<String>) {
+    match os {
+        Os::Windows => (
+            "powershell".to_string(),
+            encoded_powershell(&format!(
+                "Remove-Item -LiteralPath {} -Recurse -Force",
+                powershell_literal(&dir.replace('/', "\\"))
+            )),
+        ),
+        _ => (
+            "rm".to_string(),
+            vec!["-rf".to_string(), "--".to_string(), dir.to_string()],
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_commands() {
+        // 测试目录大小的统计命令与输出解析
+        let (program, args) = size_command(Os::Linux, "/work/my app/target");
+        assert_eq!(program, "du");
+        assert_eq!(args, vec!["-sk", "/work/my app/target"]);
+        assert_eq!(
+            parse_size(Os::Mac, "1536\t/work/target\n"),
+            Some(1536 * 1024)
+        );
+        assert_eq!(parse_size(Os::Windows, "20480\r\n"), Some(20480));
+        assert_eq!(parse_size(Os::Linux, ""), None);
+        let (_, args) = size_command(Os::Windows, r"C:\it's\target");
+        assert!(args[2].contains(r"Test-Path -LiteralPath 'C:\it''s\target'"));
+
+        // 测试没有 cjpm 时只删除工作区内的输出目录
+        assert_eq!(
+            removable_dir("/work/app/", "target"),
+            Ok("/work/app/target".to_string())
+        );
+        assert_eq!(
+            removable_dir("/work/app", "/work/app/./build/out"),
+            Ok("/work/app/build/out".to_string())
+        );
+        assert_eq!(
+            removable_dir(r"C:\work\app", r"C:\work\app\target"),
+            Ok("C:/work/app/target".to_string())
+        );
+        assert!(removable_dir("/work/app", "/home/me").is_err());
+        assert!(removable_dir("/work/app", "/work/application").is_err());
+        assert!(removable_dir("/work/app", "../target").is_err());
+        assert!(removable_dir("/work/app", "target/../..").is_err());
+        assert!(removable_dir("/work/app", "/work/app").is_err());
+        assert!(removable_dir("/work/app", "./").is_err());
+
+        // 测试没有 cjpm 时删除输出目录的命令
+        assert_eq!(
+            remove_command(Os::Linux, "-target").1,
+            vec!["-rf", "--", "-target"]
+        );
+        let (program, args) = remove_command(Os::Windows, "C:/work/target");
+        assert_eq!(program, "powershell");
+        assert_eq!(
+            args,
+            encoded_powershell(r"Remove-Item -LiteralPath 'C:\work\target' -Recurse -Force")
+        );
+    }
+}
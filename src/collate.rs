@@ -0,0 +1,137 @@
+// src/collate.rs
+//! Ordering of names shown in reports, such as test cases, snapshot files and run
+//! configurations.
+//!
+//! Cangjie identifiers and file names may contain CJK characters, for which plain string
+//! comparison puts full-width letters, katakana and ideographs in surprising places. The
+//! `cangjie.output.collation` setting selects between that codepoint order and a natural
+//! order that folds those variants together.
+
+use std::cmp::Ordering;
+
+/// A collation used when sorting names for display
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Collation {
+    /// Unicode codepoint order; stable and locale independent
+    #[default]
+    Codepoint,
+    /// Case-insensitive, numbers by value, full-width forms folded to ASCII, katakana
+    /// folded to hiragana, scripts grouped (digits, Latin, kana, Han), and ideographs in
+    /// radical-stroke order
+    Natural,
+}
+
+impl Collation {
+    /// Parses the value of `cangjie.output.collation`
+    pub fn from_setting(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "codepoint" => Some(Collation::Codepoint),
+            "natural" => Some(Collation::Natural),
+            _ => None,
+        }
+    }
+
+    /// Compares two names
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            Collation::Codepoint => a.cmp(b),
+            Collation::Natural => natural_key(a).cmp(&natural_key(b)).then_with(|| a.cmp(b)),
+        }
+    }
+
+    /// Sorts items by the name `key` returns
+    pub fn sort_by_name<T>(&self, items: &mut [T], key: impl Fn(&T) -> &str) {
+        items.sort_by(|a, b| self.compare(key(a), key(b)));
+    }
+}
+
+/// A unit of the natural sort key
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Token {
+    /// A run of digits, compared by value
+    Number(u128),
+    /// A folded character with its script group
+    Char(u8, char),
+}
+
+/// Folds full-width ASCII, case and katakana
+fn fold(c: char) -> char {
+    let c = match c as u32 {
+        0xFF01..=0xFF5E => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        0x3000 => ' ',
+        0x30A1..=0x30F6 => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    };
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+/// Returns the script group a folded character sorts in
+fn script_group(c: char) -> u8 {
+    match c as u32 {
+        _ if c.is_ascii_punctuation() || c.is_whitespace() => 0,
+        _ if c.is_ascii_alphabetic() => 1,
+        0x00C0..=0x024F => 1,
+        0x3040..=0x30FF => 2,
+        0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FA1F => 3,
+        _ => 4,
+    }
+}
+
+/// Builds the natural sort key of a name
+fn natural_key(name: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = name.chars().map(fold).peekable();
+    while let Some(c) = chars.next() {
+        if let Some(digit) = c.to_digit(10) {
+            let mut value = digit as u128;
+            while let Some(next) = chars.peek().and_then(|c| c.to_digit(10)) {
+                value = value.saturating_mul(10).saturating_add(next as u128);
+                chars.next();
+            }
+            tokens.push(Token::Number(value));
+        } else {
+            tokens.push(Token::Char(script_group(c), c));
+        }
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collation() {
+        // 测试包含中日文字符的名称排序
+        let mut names = vec![
+            "测试10",
+            "テスト",
+            "Beta",
+            "测试2",
+            "ａｌｐｈａ",
+            "てすと2",
+            "中文",
+        ];
+        Collation::Natural.sort_by_name(&mut names, |name| name);
+        assert_eq!(
+            names,
+            vec![
+                "ａｌｐｈａ",
+                "Beta",
+                "テスト",
+                "てすと2",
+                "中文",
+                "测试2",
+                "测试10"
+            ]
+        );
+
+        let mut names = vec!["b", "B", "a"];
+        Collation::Codepoint.sort_by_name(&mut names, |name| name);
+        assert_eq!(names, vec!["B", "a", "b"]);
+        Collation::Natural.sort_by_name(&mut names, |name| name);
+        assert_eq!(names, vec!["a", "B", "b"]);
+        assert_eq!(Collation::from_setting("Natural"), Some(Collation::Natural));
+        assert_eq!(Collation::from_setting("pinyin"), None);
+    }
+}
@@ -0,0 +1,210 @@
+// src/compat.rs
+//! Compatibility gate between downloaded components and the installed extension.
+//!
+//! Components such as the language server may start relying on host features that only newer
+//! `zed_extension_api` versions expose. A compatibility manifest (shipped with the extension in
+//! `compatibility.json` and optionally published as a release asset of the same name) lists
+//! which component versions need which extension API, so the extension can disable the
+//! affected features with an explanation instead of failing in obscure ways.
+
+use serde_json::Value;
+
+use crate::version::Version;
+
+/// The `zed_extension_api` version the extension is built with
+pub const EXTENSION_API_VERSION: &str = "0.7.0";
+
+/// The version of the installed extension
+pub const EXTENSION_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The manifest shipped with the extension
+pub const SHIPPED_MANIFEST: &str = include_str!("../compatibility.json");
+
+/// Name of the manifest asset published with component releases
+pub const MANIFEST_ASSET: &str = "compatibility.json";
+
+/// Feature name of the language server
+pub const FEATURE_LANGUAGE_SERVER: &str = "language-server";
+
+/// Feature name of the debug adapter
+pub const FEATURE_DEBUGGER: &str = "debugger";
+
+/// A manifest entry: component versions from `min_version` on need `extension_api`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompatRule {
+    pub component: String,
+    pub min_version: Version,
+    pub extension_api: Version,
+    /// The first extension release built with `extension_api`
+    pub extension_version: String,
+    /// The extension features that stop working with older extensions
+    pub features: Vec<String>,
+}
+
+/// A component version the installed extension cannot support
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Incompatibility {
+    pub component: String,
+    pub component_version: String,
+    pub required_api: String,
+    pub required_extension: String,
+    pub features: Vec<String>,
+}
+
+impl Incompatibility {
+    /// Returns the explanation shown in installation status and `/cangjie-info`
+    pub fn message(&self) -> String {
+        format!(
+            "{} {} requires zed_extension_api {} but this extension ({}) was built with {}. \
+             Update the Cangjie extension to {} or later; disabled: {}.",
+            self.component,
+            self.component_version,
+            self.required_api,
+            EXTENSION_VERSION,
+            EXTENSION_API_VERSION,
+            self.required_extension,
+            self.features.join(", ")
+        )
+    }
+
+    /// Returns true if `feature` is disabled by this incompatibility
+    pub fn disables(&self, feature: &str) -> bool {
+        self.features.iter().any(|f| f == feature)
+    }
+
+    /// Serializes the incompatibility for the extension state
+    pub fn to_state(&self) -> String {
+        [
+            self.component.as_str(),
+            &self.component_version,
+            &self.required_api,
+            &self.required_extension,
+            &self.features.join(","),
+        ]
+        .join("\t")
+    }
+
+    /// Restores an incompatibility written by [`Incompatibility::to_state`]
+    pub fn from_state(state: &str) -> Option<Self> {
+        let mut fields = state.split('\t').map(str::to_string);
+        Some(Self {
+            component: fields.next()?,
+            component_version: fields.next()?,
+            required_api: fields.next()?,
+            required_extension: fields.next()?,
+            features: fields
+                .next()?
+                .split(',')
+                .filter(|f| !f.is_empty())
+                .map(str::to_string)
+                .collect(),
+        })
+    }
+}
+
+/// Parses a compatibility manifest.
+///
+/// # Arguments
+/// * `json` - The manifest, `{"rules": [{"component", "minVersion", "extensionApi",
+///   "extensionVersion", "features"}]}`
+///
+/// # Returns
+/// * `Result<Vec<CompatRule>, String>` - The rules or an error message
+pub fn parse_manifest(json: &str) -> Result<Vec<CompatRule>, String> {
+    let value: Value =
+        serde_json::from_str(json).map_err(|e| format!("Invalid compatibility manifest: {}", e))?;
+    let rules = value
+        .get("rules")
+        .and_then(Value::as_array)
+        .ok_or("Compatibility manifest has no `rules` array")?;
+    rules
+        .iter()
+        .map(|rule| {
+            let field = |name: &str| {
+                rule.get(name)
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| format!("Compatibility rule without `{}`", name))
+            };
+            let version = |name: &str| {
+                let text = field(name)?;
+                Version::parse(text)
+                    .ok_or_else(|| format!("Invalid version in `{}`: {}", name, text))
+            };
+            Ok(CompatRule {
+                component: field("component")?.to_string(),
+                min_version: version("minVersion")?,
+                extension_api: version("extensionApi")?,
+                extension_version: field("extensionVersion")?.to_string(),
+                features: rule
+                    .get("features")
+                    .and_then(Value::as_array)
+                    .map(|features| {
+                        features
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .map(str::to_string)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Checks a component version against the manifest rules.
+///
+/// # Arguments
+/// * `rules` - The manifest rules
+/// * `component` - The component name, e.g. `cangjie-lsp`
+/// * `component_version` - The component version, e.g. a release tag
+///
+/// # Returns
+/// * `Option<Incompatibility>` - The strictest unmet requirement, if any
+pub fn check(
+    rules: &[CompatRule],
+    component: &str,
+    component_version: &str,
+) -> Option<Incompatibility> {
+    let version = Version::parse(component_version)?;
+    let built = Version::parse(EXTENSION_API_VERSION)?;
+    rules
+        .iter()
+        .filter(|rule| rule.component == component && version >= rule.min_version)
+        .filter(|rule| rule.extension_api > built)
+        .max_by(|a, b| a.extension_api.cmp(&b.extension_api))
+        .map(|rule| Incompatibility {
+            component: component.to_string(),
+            component_version: component_version.to_string(),
+            required_api: rule.extension_api.to_string(),
+            required_extension: rule.extension_version.clone(),
+            features: rule.features.clone(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compatibility_check() {
+        // 测试根据兼容性清单检测扩展 API 版本不匹配
+        assert!(parse_manifest(SHIPPED_MANIFEST).is_ok());
+        let rules = parse_manifest(
+            r#"{"rules": [
+                {"component": "cangjie-lsp", "minVersion": "1.2.0", "extensionApi": "0.7.0",
+                 "extensionVersion": "0.1.0", "features": ["language-server"]},
+                {"component": "cangjie-lsp", "minVersion": "2.0.0", "extensionApi": "0.9.0",
+                 "extensionVersion": "0.3.0", "features": ["language-server", "debugger"]}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(check(&rules, "cangjie-lsp", "v1.5.0"), None);
+        assert_eq!(check(&rules, "cjdb", "v2.1.0"), None);
+
+        let gate = check(&rules, "cangjie-lsp", "v2.1.0").unwrap();
+        assert_eq!(gate.required_extension, "0.3.0");
+        assert!(gate.disables(FEATURE_DEBUGGER));
+        assert!(gate.message().contains("zed_extension_api 0.9.0"));
+        assert_eq!(Incompatibility::from_state(&gate.to_state()), Some(gate));
+    }
+}
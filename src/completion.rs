@@ -0,0 +1,255 @@
+// src/completion.rs
+//! Argument completion for the slash commands.
+//!
+//! Zed asks for completions without a worktree, so the extension remembers the worktree seen
+//! last and, when completions are requested, indexes its sources, test items, executable
+//! targets and run configurations into a [`CompletionIndex`], which is reused for a short
+//! while.
+
+use serde_json::{Value, json};
+use zed_extension_api::{Os, SlashCommandArgumentCompletion};
+
+use crate::cjpm;
+use crate::formatter::SOURCE_EXTENSION;
+
+/// Most source files read when indexing test items
+pub const MAX_INDEXED_FILES: usize = 500;
+
+/// Most completions offered at once
+const MAX_COMPLETIONS: usize = 50;
+
+/// `/cangjie-run` options
+const RUN_OPTIONS: &[&str] = &["--config", "--name", "--tasks"];
+
+/// What the slash command arguments are completed from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompletionIndex {
+    /// Source files, relative to the worktree root
+    pub files: Vec<String>,
+    /// `--filter` values of the test classes and cases
+    pub tests: Vec<String>,
+    /// Executables `cjpm run --name` can select
+    pub targets: Vec<String>,
+    /// Names of the run configurations
+    pub configs: Vec<String>,
+}
+
+impl CompletionIndex {
+    pub fn to_json(&self) -> String {
+        json!({
+            "files": self.files,
+            "tests": self.tests,
+            "targets": self.targets,
+            "configs": self.configs,
+        })
+        .to_string()
+    }
+
+    pub fn from_json(json: &str) -> Option<Self> {
+        let value: Value = serde_json::from_str(json).ok()?;
+        let strings = |key: &str| -> Vec<String> {
+            value[key]
+                .as_array()
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(|item| item.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        Some(CompletionIndex {
+            files: strings("files"),
+            tests: strings("tests"),
+            targets: strings("targets"),
+            configs: strings("configs"),
+        })
+    }
+
+    /// Returns the directories holding the indexed files, sorted
+    pub fn directories(&self) -> Vec<String> {
+        let mut dirs: Vec<String> = self
+            .files
+            .iter()
+            .filter_map(|file| file.rsplit_once('/').map(|(dir, _)| dir.to_string()))
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        dirs
+    }
+}
+
+/// Returns the executables `cjpm run` can build from a manifest
+pub fn targets(manifest: &cjpm::Manifest) -> Vec<String> {
+    match &manifest.name {
+        Some(name) if manifest.is_executable() => vec![name.clone()],
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the command listing the source files below the worktree root, run in the root.
+///
+/// # Arguments
+/// * `os` - The current platform
+///
+/// # Returns
+/// * `(String, Vec<String>)` - The program and its arguments
+pub fn list_command(os: Os) -> (String, Vec<String>) {
+    match os {
+        Os::Windows => (
+            "cmd".to_string(),
+            vec![
+                "/S".to_string(),
+                "/C".to_string(),
+                format!("dir /s /b /a-d *{}", SOURCE_EXTENSION),
+            ],
+        ),
+        _ => (
+            "find".to_string(),
+            vec![
+                ".".to_string(),
+                "-type".to_string(),
+                "f".to_string(),
+                "-name".to_string(),
+                format!("*{}", SOURCE_EXTENSION),
+            ],
+        ),
+    }
+}
+
+/// Parses the output of [`list_command`], skipping hidden directories and build output.
+///
+/// # Arguments
+/// * `listing` - The command output, one path per line
+/// * `root` - The worktree root, stripped from absolute paths
+///
+/// # Returns
+/// * `Vec<String>` - The files relative to the root, sorted
+pub fn parse_listing(listing: &str, root: &str) -> Vec<String> {
+    let root = format!("{}/", root.replace('\\', "/").trim_end_matches('/'));
+    let mut files: Vec<String> = listing
+        .lines()
+        .map(|line| line.trim().replace('\\', "/"))
+        .filter(|line| line.ends_with(SOURCE_EXTENSION))
+        .map(|line| {
+            let relative = line.strip_prefix(&root).unwrap_or(&line);
+            relative.trim_start_matches("./").to_string()
+        })
+        .filter(|file| {
+            file.split('/')
+                .all(|part| !part.starts_with('.') && part != cjpm::DEFAULT_TARGET_DIR)
+        })
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Completes the argument being typed.
+///
+/// # Arguments
+/// * `index` - What to complete from
+/// * `command` - The slash command name
+/// * `args` - The arguments so far, the last one being typed
+///
+/// # Returns
+/// * `Vec<SlashCommandArgumentCompletion>` - The candidates starting with the typed text
+pub fn complete(
+    index: &CompletionIndex,
+    command: &str,
+    args: &[String],
+) -> Vec<SlashCommandArgumentCompletion> {
+    let typed = args.last().map(String::as_str).unwrap_or_default();
+    let previous = args
+        .len()
+        .checked_sub(2)
+        .and_then(|i| args.get(i))
+        .map(String::as_str);
+    let candidates: Vec<String> = match (command, previous) {
+        ("cangjie-run", Some("--config")) => index.configs.clone(),
+        ("cangjie-run", Some("--name")) => index.targets.clone(),
+        ("cangjie-run", _) if typed.starts_with('-') => RUN_OPTIONS
+            .iter()
+            .map(|option| option.to_string())
+            .collect(),
+        ("cangjie-test" | "cangjie-bench", _) if !typed.starts_with('-') => index.tests.clone(),
+        ("cangjie-fmt" | "cangjie-doc" | "cangjie-lint", _) => {
+            let mut paths = index.directories();
+            if command == "cangjie-fmt" {
+                paths.extend(index.files.iter().cloned());
+            }
+            paths
+        }
+        ("cangjie-test-here", None) => index.files.clone(),
+        _ => Vec::new(),
+    };
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(typed))
+        .take(MAX_COMPLETIONS)
+        .map(|candidate| SlashCommandArgumentCompletion {
+            label: candidate.clone(),
+            new_text: candidate,
+            run_command: false,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(completions: Vec<SlashCommandArgumentCompletion>) -> Vec<String> {
+        completions.into_iter().map(|c| c.label).collect()
+    }
+
+    #[test]
+    fn test_complete() {
+        // 测试按命令与前一个参数补全测试名、运行目标与路径
+        let index = CompletionIndex {
+            files: parse_listing(
+                "./src/main.cj\n./src/util/io.cj\n./target/gen.cj\n./.git/x.cj\n",
+                "/work",
+            ),
+            tests: vec!["CalcTest.*".to_string(), "CalcTest.add".to_string()],
+            targets: targets(&cjpm::parse_manifest("[package]\nname = \"hello\"\n")),
+            configs: vec!["smoke".to_string()],
+        };
+        assert_eq!(index.files, vec!["src/main.cj", "src/util/io.cj"]);
+        assert_eq!(
+            CompletionIndex::from_json(&index.to_json()),
+            Some(index.clone())
+        );
+
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            labels(complete(&index, "cangjie-test", &args(&["CalcTest.a"]))),
+            vec!["CalcTest.add"]
+        );
+        assert!(complete(&index, "cangjie-test", &args(&["--repeat"])).is_empty());
+        assert_eq!(
+            labels(complete(&index, "cangjie-run", &args(&["--name", ""]))),
+            vec!["hello"]
+        );
+        assert_eq!(
+            labels(complete(&index, "cangjie-run", &args(&["--c"]))),
+            vec!["--config"]
+        );
+        assert_eq!(
+            labels(complete(&index, "cangjie-run", &args(&["--config", "s"]))),
+            vec!["smoke"]
+        );
+        assert_eq!(
+            labels(complete(&index, "cangjie-fmt", &args(&["src/u"]))),
+            vec!["src/util", "src/util/io.cj"]
+        );
+        assert_eq!(
+            labels(complete(
+                &CompletionIndex::default(),
+                "cangjie-fmt",
+                &args(&[])
+            )),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -0,0 +1,706 @@
+// src/debug.rs
+//! Translation between debug scenario configurations (`debug.json`) and the requests Zed
+//! starts debug sessions with.
+//!
+//! A configuration either launches a program (`"request": "launch"`, the default) or attaches
+//! to a running process (`"request": "attach"`) given by `processId`. An attach configuration
+//! with only `processName` leaves the process to Zed's process picker; the name is forwarded
+//! to the adapter, which attaches to the process of that name.
+//!
+//! A configuration with a `coreFile` opens a core dump of `program` for post-mortem
+//! debugging instead; the session attaches to the dump rather than to a live process.
+//!
+//! Either request can go to a debug server that is already running on another machine or in
+//! a container, reached over TCP instead of starting the adapter locally.
+//!
+//! Programs built elsewhere, e.g. inside a container, embed source paths that do not exist
+//! locally. A `sourceMap` from those paths to local ones is forwarded to the adapter, so
+//! breakpoints bind, and is applied to `program` and `cwd`.
+//!
+//! Without cjdb, native-backend programs are debugged with LLVM's `lldb-dap`; the
+//! configuration is translated to its launch and attach arguments and the SDK's Cangjie data
+//! formatters are loaded with `initCommands`.
+
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+
+use serde_json::{Map, Value, json};
+use zed_extension_api::{
+    AttachRequest, DebugRequest, LaunchRequest, Os, StartDebuggingRequestArgumentsRequest,
+    TcpArgumentsTemplate,
+};
+
+use crate::run_config::is_absolute;
+
+/// Names of the LLDB debug adapter, newest first; `lldb-vscode` is its name before LLVM 18
+pub const LLDB_DAP_NAMES: &[&str] = &["lldb-dap", "lldb-vscode"];
+
+/// Parses the request a debug configuration makes.
+///
+/// # Arguments
+/// * `config` - The configuration object from the debug scenario
+///
+/// # Returns
+/// * `Result<DebugRequest, String>` - The launch or attach request, or an error naming the
+///   missing or invalid field
+pub fn debug_request(config: &Value) -> Result<DebugRequest, String> {
+    if core_file(config).is_some() {
+        config
+            .get("program")
+            .and_then(Value::as_str)
+            .filter(|program| !program.is_empty())
+            .ok_or("A core dump configuration needs \"program\", the executable that crashed")?;
+        return Ok(DebugRequest::Attach(AttachRequest { process_id: None }));
+    }
+    match config.get("request").and_then(Value::as_str) {
+        None | Some("launch") => {
+            let program = config
+                .get("program")
+                .and_then(Value::as_str)
+                .filter(|program| !program.is_empty())
+                .ok_or("A launch configuration needs \"program\"")?;
+            Ok(DebugRequest::Launch(LaunchRequest {
+                program: program.to_string(),
+                cwd: config
+                    .get("cwd")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                args: string_list(config.get("args")),
+                envs: string_map(config.get("env")),
+            }))
+        }
+        Some("attach") => {
+            let process_id = match config.get("processId") {
+                None | Some(Value::Null) => None,
+                Some(value) => Some(parse_process_id(value)?),
+            };
+            let has_name = config
+                .get("processName")
+                .and_then(Value::as_str)
+                .is_some_and(|name| !name.trim().is_empty());
+            if process_id.is_none() && !has_name {
+                return Err(
+                    "An attach configuration needs \"processId\" or \"processName\"".to_string(),
+                );
+            }
+            Ok(DebugRequest::Attach(AttachRequest { process_id }))
+        }
+        Some(other) => Err(format!(
+            "Unsupported debug request \"{}\": expected \"launch\" or \"attach\"",
+            other
+        )),
+    }
+}
+
+/// Returns the core dump a post-mortem debug configuration opens, from `coreFile`.
+pub fn core_file(config: &Value) -> Option<&str> {
+    config
+        .get("coreFile")
+        .and_then(Value::as_str)
+        .filter(|core| !core.trim().is_empty())
+}
+
+/// Returns whether a debug request launches a program or attaches to a process.
+pub fn request_kind(request: &DebugRequest) -> StartDebuggingRequestArgumentsRequest {
+    match request {
+        DebugRequest::Launch(_) => StartDebuggingRequestArgumentsRequest::Launch,
+        DebugRequest::Attach(_) => StartDebuggingRequestArgumentsRequest::Attach,
+    }
+}
+
+/// Builds the scenario configuration for a request from Zed's new session UI.
+///
+/// # Arguments
+/// * `request` - The launch or attach request
+/// * `stop_on_entry` - Whether to stop at the program's entry point
+///
+/// # Returns
+/// * `Value` - The configuration object, readable again with [`debug_request`]
+pub fn scenario_config(request: &DebugRequest, stop_on_entry: Option<bool>) -> Value {
+    let mut config = match request {
+        DebugRequest::Launch(launch) => {
+            let env: Map<String, Value> = launch
+                .envs
+                .iter()
+                .map(|(key, value)| (key.clone(), json!(value)))
+                .collect();
+            let mut config = json!({
+                "request": "launch",
+                "program": launch.program,
+                "args": launch.args,
+                "env": env,
+            });
+            if let Some(cwd) = &launch.cwd {
+                config["cwd"] = json!(cwd);
+            }
+            config
+        }
+        DebugRequest::Attach(attach) => {
+            let mut config = json!({ "request": "attach" });
+            if let Some(pid) = attach.process_id {
+                config["processId"] = json!(pid);
+            }
+            config
+        }
+    };
+    if let Some(stop_on_entry) = stop_on_entry {
+        config["stopOnEntry"] = json!(stop_on_entry);
+    }
+    config
+}
+
+/// Applies the `args`, `env` and `cwd` of a launch configuration to the request a locator
+/// found for the built program.
+///
+/// `args` and `cwd` replace the located values when present; `env` entries are added to the
+/// build environment, replacing variables of the same name. The `sourceMap`, if valid, maps
+/// the program and working directory to local paths.
+///
+/// # Arguments
+/// * `config` - The launch configuration
+/// * `launch` - The located launch request
+///
+/// # Returns
+/// * `LaunchRequest` - The request with the configuration applied
+pub fn apply_launch_config(config: &Value, mut launch: LaunchRequest) -> LaunchRequest {
+    if config.get("args").is_some_and(Value::is_array) {
+        launch.args = string_list(config.get("args"));
+    }
+    for (key, value) in string_map(config.get("env")) {
+        launch.envs.retain(|(existing, _)| *existing != key);
+        launch.envs.push((key, value));
+    }
+    if let Some(cwd) = config.get("cwd").and_then(Value::as_str) {
+        launch.cwd = Some(cwd.to_string());
+    }
+    let map = source_map(config).unwrap_or_default();
+    launch.program = map_source_path(&launch.program, &map);
+    launch.cwd = launch.cwd.map(|cwd| map_source_path(&cwd, &map));
+    launch
+}
+
+/// Builds the path of a program to debug, with the path separators and `.exe` extension of
+/// the platform.
+///
+/// # Arguments
+/// * `os` - The platform the program runs on
+/// * `root` - The directory a relative `program` is in, if any
+/// * `program` - The program path, with `/` separators
+///
+/// # Returns
+/// * `String` - The program path for the platform
+pub fn executable_path(os: Os, root: Option<&str>, program: &str) -> String {
+    let mut path = match root {
+        Some(root) if !is_absolute(program) => {
+            format!("{}/{}", root.trim_end_matches(['/', '\\']), program)
+        }
+        _ => program.to_string(),
+    };
+    if os == Os::Windows {
+        path = path.replace('/', "\\");
+        if !path.to_ascii_lowercase().ends_with(".exe") {
+            path.push_str(".exe");
+        }
+    }
+    path
+}
+
+/// Sets an environment variable in the `env` of an adapter configuration.
+///
+/// # Arguments
+/// * `configuration` - The adapter configuration
+/// * `key` - The variable name
+/// * `value` - The value
+pub fn set_env(configuration: &mut Value, key: &str, value: &str) {
+    if !configuration.get("env").is_some_and(Value::is_object) {
+        configuration["env"] = json!({});
+    }
+    configuration["env"][key] = json!(value);
+}
+
+/// Parses the `sourceMap` of a debug configuration.
+///
+/// The map is an object from build paths to local paths, or a list of `[build, local]`
+/// pairs as lldb takes it.
+///
+/// # Arguments
+/// * `config` - The configuration object from the debug scenario
+///
+/// # Returns
+/// * `Result<Vec<(String, String)>, String>` - The mappings in the order given, or an error
+///   describing the malformed entry
+pub fn source_map(config: &Value) -> Result<Vec<(String, String)>, String> {
+    let invalid = |entry: &Value| {
+        format!(
+            "\"sourceMap\" entries must map a build path to a local path, got {}",
+            entry
+        )
+    };
+    match config.get("sourceMap") {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::Object(map)) => map
+            .iter()
+            .map(|(from, to)| match to.as_str() {
+                Some(to) => Ok((from.clone(), to.to_string())),
+                None => Err(invalid(to)),
+            })
+            .collect(),
+        Some(Value::Array(pairs)) => pairs
+            .iter()
+            .map(|pair| match pair.as_array().map(Vec::as_slice) {
+                Some([Value::String(from), Value::String(to)]) => Ok((from.clone(), to.clone())),
+                _ => Err(invalid(pair)),
+            })
+            .collect(),
+        Some(other) => Err(invalid(other)),
+    }
+}
+
+/// Maps a build path to the local path through a source map.
+///
+/// The longest build path that is the path itself or one of its parent directories wins.
+///
+/// # Arguments
+/// * `path` - The path, as the build saw it
+/// * `map` - The source map, see [`source_map`]
+///
+/// # Returns
+/// * `String` - The local path, or `path` when no mapping applies
+pub fn map_source_path(path: &str, map: &[(String, String)]) -> String {
+    map.iter()
+        .filter_map(|(from, to)| {
+            let from = from.trim_end_matches(['/', '\\']);
+            let rest = path.strip_prefix(from)?;
+            (rest.is_empty() || rest.starts_with(['/', '\\'])).then_some((from.len(), to, rest))
+        })
+        .max_by_key(|(len, _, _)| *len)
+        .map(|(_, to, rest)| format!("{}{}", to.trim_end_matches(['/', '\\']), rest))
+        .unwrap_or_else(|| path.to_string())
+}
+
+/// Builds the configuration the adapter receives for a debug configuration.
+///
+/// Every field is forwarded, so options such as `stopOnEntry`, `console`, `terminal` or
+/// breakpoint settings reach the adapter; `program` and `cwd` are mapped to local paths and
+/// the `sourceMap` is sent as `[build, local]` pairs. Without a `name`, the session is named
+/// after the scenario's label.
+///
+/// # Arguments
+/// * `label` - The label of the debug scenario
+/// * `config` - The configuration object from the debug scenario
+///
+/// # Returns
+/// * `Result<Value, String>` - The adapter configuration, or an error from [`source_map`]
+pub fn adapter_configuration(label: &str, config: &Value) -> Result<Value, String> {
+    let map = source_map(config)?;
+    let mut configuration = config.as_object().cloned().unwrap_or_default();
+    if !label.is_empty() && !configuration.get("name").is_some_and(Value::is_string) {
+        configuration.insert("name".to_string(), json!(label));
+    }
+    for key in ["program", "cwd"] {
+        if let Some(path) = config.get(key).and_then(Value::as_str) {
+            configuration.insert(key.to_string(), json!(map_source_path(path, &map)));
+        }
+    }
+    if map.is_empty() {
+        configuration.remove("sourceMap");
+    } else {
+        let pairs: Vec<[&String; 2]> = map.iter().map(|(from, to)| [from, to]).collect();
+        configuration.insert("sourceMap".to_string(), json!(pairs));
+    }
+    Ok(Value::Object(configuration))
+}
+
+/// Translates an adapter configuration (see [`adapter_configuration`]) for `lldb-dap`.
+///
+/// `env` becomes a list of `KEY=VALUE` strings, `processId` becomes `pid` and
+/// `processName` becomes the `program` to attach to. The formatter scripts are imported
+/// before any `initCommands` of the configuration.
+///
+/// # Arguments
+/// * `configuration` - The adapter configuration
+/// * `formatter_scripts` - The SDK's LLDB scripts with the Cangjie data formatters
+///
+/// # Returns
+/// * `Value` - The configuration for `lldb-dap`
+pub fn lldb_configuration(configuration: &Value, formatter_scripts: &[PathBuf]) -> Value {
+    let mut lldb = configuration.as_object().cloned().unwrap_or_default();
+    if let Some(env) = lldb.get("env").and_then(Value::as_object) {
+        let env: Vec<String> = env
+            .iter()
+            .filter_map(|(key, value)| value.as_str().map(|value| format!("{}={}", key, value)))
+            .collect();
+        lldb.insert("env".to_string(), json!(env));
+    }
+    if let Some(pid) = lldb.remove("processId") {
+        let pid = parse_process_id(&pid).map_or(pid, |pid| json!(pid));
+        lldb.insert("pid".to_string(), pid);
+    }
+    if let Some(name) = lldb.remove("processName")
+        && !lldb.contains_key("pid")
+    {
+        lldb.insert("program".to_string(), name);
+    }
+    let mut init_commands: Vec<Value> = formatter_scripts
+        .iter()
+        .map(|script| json!(format!("command script import \"{}\"", script.display())))
+        .collect();
+    if let Some(Value::Array(commands)) = lldb.get("initCommands") {
+        init_commands.extend(commands.iter().cloned());
+    }
+    if !init_commands.is_empty() {
+        lldb.insert("initCommands".to_string(), json!(init_commands));
+    }
+    Value::Object(lldb)
+}
+
+/// Parses the host of a debug server: an IPv4 address or `localhost`.
+///
+/// # Arguments
+/// * `host` - The configured host
+///
+/// # Returns
+/// * `Result<u32, String>` - The address in host byte order, as Zed expects it
+pub fn parse_tcp_host(host: &str) -> Result<u32, String> {
+    let host = host.trim();
+    if host.eq_ignore_ascii_case("localhost") {
+        return Ok(u32::from(Ipv4Addr::LOCALHOST));
+    }
+    host.parse::<Ipv4Addr>()
+        .map(u32::from)
+        .map_err(|_| format!("Debug server host must be an IPv4 address: {}", host))
+}
+
+/// Combines the TCP connection of a debug scenario with the connection settings.
+///
+/// Fields given in the scenario's `tcp_connection` take precedence over the settings.
+///
+/// # Arguments
+/// * `scenario` - The scenario's `tcp_connection`, if any
+/// * `host` - The configured host, see [`parse_tcp_host`]
+/// * `port` - The configured port
+/// * `timeout` - The configured connection timeout in milliseconds
+///
+/// # Returns
+/// * `Result<Option<TcpArgumentsTemplate>, String>` - The connection to use, `None` to start
+///   the adapter over stdio, or an error naming the invalid setting
+pub fn tcp_template(
+    scenario: Option<TcpArgumentsTemplate>,
+    host: Option<&str>,
+    port: Option<u64>,
+    timeout: Option<u64>,
+) -> Result<Option<TcpArgumentsTemplate>, String> {
+    let host = host.map(parse_tcp_host).transpose()?;
+    let port = port
+        .map(|port| {
+            u16::try_from(port)
+                .ok()
+                .filter(|port| *port > 0)
+                .ok_or_else(|| format!("Debug server port must be between 1 and 65535: {}", port))
+        })
+        .transpose()?;
+    let template = match scenario {
+        Some(scenario) => TcpArgumentsTemplate {
+            host: scenario.host.or(host),
+            port: scenario.port.or(port),
+            timeout: scenario.timeout.or(timeout),
+        },
+        None if host.is_some() || port.is_some() => TcpArgumentsTemplate {
+            host,
+            port,
+            timeout,
+        },
+        None => return Ok(None),
+    };
+    Ok(Some(template))
+}
+
+/// Parses `processId`, given as a number or a numeric string
+fn parse_process_id(value: &Value) -> Result<u32, String> {
+    let pid = match value {
+        Value::Number(number) => number.as_u64().and_then(|pid| u32::try_from(pid).ok()),
+        Value::String(pid) => pid.trim().parse().ok(),
+        _ => None,
+    };
+    pid.filter(|pid| *pid > 0)
+        .ok_or_else(|| format!("\"processId\" must be a process ID, got {}", value))
+}
+
+/// Reads a list of strings, skipping other entries
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Reads an object of string values as environment variables, skipping other entries
+fn string_map(value: Option<&Value>) -> Vec<(String, String)> {
+    value
+        .and_then(Value::as_object)
+        .map(|env| {
+            env.iter()
+                .filter_map(|(key, value)| {
+                    value.as_str().map(|value| (key.clone(), value.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_debug_request() {
+        // 测试 launch 与 attach 配置的解析以及与场景配置的往返转换
+        let launch = json!({
+            "program": "target/debug/main",
+            "args": ["--verbose", 1],
+            "env": { "LOG": "debug" },
+        });
+        let Ok(DebugRequest::Launch(request)) = debug_request(&launch) else {
+            panic!("expected a launch request");
+        };
+        assert_eq!(request.args, vec!["--verbose"]);
+        assert_eq!(request.envs, vec![("LOG".to_string(), "debug".to_string())]);
+        let request = DebugRequest::Launch(request);
+        let config = scenario_config(&request, Some(true));
+        assert_eq!(config["request"], "launch");
+        assert_eq!(config["stopOnEntry"], true);
+        assert!(matches!(
+            debug_request(&config),
+            Ok(DebugRequest::Launch(_))
+        ));
+        assert!(debug_request(&json!({ "request": "launch" })).is_err());
+
+        let attach = json!({ "request": "attach", "processId": "4242" });
+        let request = debug_request(&attach).unwrap();
+        assert!(matches!(
+            request,
+            DebugRequest::Attach(AttachRequest {
+                process_id: Some(4242)
+            })
+        ));
+        assert!(matches!(
+            request_kind(&request),
+            StartDebuggingRequestArgumentsRequest::Attach
+        ));
+        assert_eq!(
+            scenario_config(&request, None),
+            json!({ "request": "attach", "processId": 4242 })
+        );
+        let by_name = json!({ "request": "attach", "processName": "server" });
+        assert!(matches!(
+            debug_request(&by_name),
+            Ok(DebugRequest::Attach(AttachRequest { process_id: None }))
+        ));
+        assert!(debug_request(&json!({ "request": "attach" })).is_err());
+        assert!(debug_request(&json!({ "request": "attach", "processId": -1 })).is_err());
+        assert!(debug_request(&json!({ "request": "restart" })).is_err());
+    }
+
+    #[test]
+    fn test_tcp_template() {
+        // 测试远程调试的 TCP 连接：场景中的字段优先于设置
+        assert_eq!(parse_tcp_host("localhost"), Ok(0x7f00_0001));
+        assert_eq!(parse_tcp_host("192.168.1.20"), Ok(0xc0a8_0114));
+        assert!(parse_tcp_host("devbox.local").is_err());
+
+        assert!(
+            tcp_template(None, None, None, Some(5000))
+                .unwrap()
+                .is_none()
+        );
+        let scenario = TcpArgumentsTemplate {
+            host: None,
+            port: Some(4711),
+            timeout: None,
+        };
+        let template = tcp_template(Some(scenario), Some("10.0.0.2"), Some(9000), Some(5000))
+            .unwrap()
+            .unwrap();
+        assert_eq!(template.host, Some(0x0a00_0002));
+        assert_eq!(template.port, Some(4711));
+        assert_eq!(template.timeout, Some(5000));
+        let template = tcp_template(None, None, Some(9000), None).unwrap().unwrap();
+        assert_eq!((template.host, template.port), (None, Some(9000)));
+        assert!(tcp_template(None, None, Some(70000), None).is_err());
+    }
+
+    #[test]
+    fn test_apply_launch_config() {
+        // 测试调试配置中的参数、环境变量与工作目录覆盖定位到的启动请求
+        let located = || LaunchRequest {
+            program: "/work/target/debug/bin/main".to_string(),
+            cwd: Some("/work".to_string()),
+            args: vec![],
+            envs: vec![
+                ("LOG".to_string(), "info".to_string()),
+                ("PATH".to_string(), "/bin".to_string()),
+            ],
+        };
+        let config = json!({
+            "args": ["--port", "8080"],
+            "env": { "LOG": "debug" },
+            "cwd": "/work/data",
+        });
+        let launch = apply_launch_config(&config, located());
+        assert_eq!(launch.args, vec!["--port", "8080"]);
+        assert_eq!(
+            launch.envs,
+            vec![
+                ("PATH".to_string(), "/bin".to_string()),
+                ("LOG".to_string(), "debug".to_string()),
+            ]
+        );
+        assert_eq!(launch.cwd.as_deref(), Some("/work/data"));
+        let launch = apply_launch_config(&json!({}), located());
+        assert_eq!(launch.cwd.as_deref(), Some("/work"));
+        assert_eq!(launch.envs.len(), 2);
+    }
+
+    #[test]
+    fn test_source_map() {
+        // 测试容器内构建路径映射到本地路径，取最长匹配的前缀
+        let config = json!({
+            "program": "/workspace/app/target/debug/bin/app",
+            "cwd": "/workspace",
+            "sourceMap": {
+                "/workspace/": "/home/dev/app-src",
+                "/workspace/app": "/home/dev/app",
+            },
+        });
+        let map = source_map(&config).unwrap();
+        assert_eq!(map_source_path("/workspace", &map), "/home/dev/app-src");
+        assert_eq!(map_source_path("/workspaces/x", &map), "/workspaces/x");
+        assert_eq!(
+            adapter_configuration("", &config).unwrap(),
+            json!({
+                "program": "/home/dev/app/target/debug/bin/app",
+                "cwd": "/home/dev/app-src",
+                "sourceMap": [
+                    ["/workspace/", "/home/dev/app-src"],
+                    ["/workspace/app", "/home/dev/app"],
+                ],
+            })
+        );
+
+        // 测试 lldb 形式的路径对列表与无效条目
+        let pairs = json!({ "sourceMap": [["/src", "C:\\src"]] });
+        assert_eq!(
+            map_source_path("/src/main.cj", &source_map(&pairs).unwrap()),
+            "C:\\src/main.cj"
+        );
+        assert!(source_map(&json!({ "sourceMap": [["/src"]] })).is_err());
+        assert!(source_map(&json!({ "sourceMap": "/src" })).is_err());
+        assert_eq!(adapter_configuration("", &json!({})).unwrap(), json!({}));
+    }
+
+    #[test]
+    fn test_adapter_configuration() {
+        // 测试调试配置中的启动选项原样转发给调试适配器
+        let config = json!({
+            "request": "launch",
+            "program": "target/debug/bin/app",
+            "args": ["--verbose"],
+            "stopOnEntry": true,
+            "console": "integratedTerminal",
+            "terminal": "external",
+        });
+        assert_eq!(adapter_configuration("", &config).unwrap(), config);
+        let named = adapter_configuration("Debug server", &config).unwrap();
+        assert_eq!(named["name"], "Debug server");
+        assert_eq!(named["stopOnEntry"], true);
+        let config = json!({ "name": "Server", "program": "/app" });
+        assert_eq!(
+            adapter_configuration("Debug server", &config).unwrap(),
+            config
+        );
+        let config = json!({ "program": "/app", "sourceMap": null });
+        assert_eq!(
+            adapter_configuration("", &config).unwrap(),
+            json!({ "program": "/app" })
+        );
+    }
+
+    #[test]
+    fn test_lldb_configuration() {
+        // 测试 lldb-dap 回退时的配置转换与格式化脚本导入
+        let scripts = vec![PathBuf::from("/sdk/tools/lldb/cangjie_formatters.py")];
+        let launch = json!({
+            "request": "launch",
+            "program": "/work/main",
+            "env": { "LOG": "debug" },
+            "initCommands": ["settings set target.x86-disassembly-flavor intel"],
+        });
+        assert_eq!(
+            lldb_configuration(&launch, &scripts),
+            json!({
+                "request": "launch",
+                "program": "/work/main",
+                "env": ["LOG=debug"],
+                "initCommands": [
+                    "command script import \"/sdk/tools/lldb/cangjie_formatters.py\"",
+                    "settings set target.x86-disassembly-flavor intel",
+                ],
+            })
+        );
+        let attach = json!({ "request": "attach", "processId": "4242", "processName": "server" });
+        assert_eq!(
+            lldb_configuration(&attach, &[]),
+            json!({ "request": "attach", "pid": 4242 })
+        );
+        let by_name = json!({ "request": "attach", "processName": "server" });
+        assert_eq!(lldb_configuration(&by_name, &[])["program"], "server");
+    }
+
+    #[test]
+    fn test_executable_path() {
+        // 测试 Windows 下的路径分隔符与 .exe 扩展名
+        assert_eq!(
+            executable_path(Os::Linux, Some("/work/"), "target/debug/bin/app"),
+            "/work/target/debug/bin/app"
+        );
+        assert_eq!(
+            executable_path(Os::Windows, Some("C:\\work"), "target/debug/bin/app"),
+            "C:\\work\\target\\debug\\bin\\app.exe"
+        );
+        assert_eq!(
+            executable_path(Os::Windows, Some("C:\\work"), "D:/out/app.EXE"),
+            "D:\\out\\app.EXE"
+        );
+        assert_eq!(executable_path(Os::Mac, None, "main"), "main");
+
+        // 测试向调试配置添加环境变量
+        let mut configuration = json!({ "program": "app.exe", "env": { "LOG": "debug" } });
+        set_env(&mut configuration, "PATH", "C:\\sdk\\runtime\\lib");
+        assert_eq!(configuration["env"]["LOG"], "debug");
+        assert_eq!(configuration["env"]["PATH"], "C:\\sdk\\runtime\\lib");
+        let mut configuration = json!({});
+        set_env(&mut configuration, "PATH", "/sdk/lib");
+        assert_eq!(configuration, json!({ "env": { "PATH": "/sdk/lib" } }));
+    }
+
+    #[test]
+    fn test_core_file() {
+        // 测试打开核心转储的事后调试配置
+        let config = json!({ "program": "bin/server", "coreFile": "/var/crash/core.4242" });
+        assert_eq!(core_file(&config), Some("/var/crash/core.4242"));
+        assert!(matches!(
+            debug_request(&config),
+            Ok(DebugRequest::Attach(AttachRequest { process_id: None }))
+        ));
+        assert!(debug_request(&json!({ "request": "attach", "coreFile": "core" })).is_err());
+        assert_eq!(
+            core_file(&json!({ "program": "bin/server", "coreFile": " " })),
+            None
+        );
+    }
+}
@@ -0,0 +1,505 @@
+// src/deveco.rs
+//! Translation of DevEco Studio Cangjie projects into Zed tasks, debug scenarios and settings.
+
+use serde_json::{Map, Value, json};
+use zed_extension_api::Os;
+
+use crate::cjpm::{self, Manifest};
+use crate::debug;
+
+/// Build profile of a DevEco Studio project or module
+pub const BUILD_PROFILE_FILE: &str = "build-profile.json5";
+/// Module manifest, relative to the module directory
+pub const MODULE_MANIFEST_FILE: &str = "src/main/module.json5";
+/// Local machine configuration written by DevEco Studio
+pub const LOCAL_PROPERTIES_FILE: &str = "local.properties";
+
+/// A product (build flavor) declared in the project build profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DevEcoProduct {
+    pub name: String,
+    pub signing_config: Option<String>,
+    pub compile_sdk_version: Option<String>,
+}
+
+/// A module declared in the project build profile
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DevEcoModule {
+    pub name: String,
+    pub src_path: String,
+    /// Module type from the module manifest (`entry`, `feature`, `har`, ...)
+    pub module_type: Option<String>,
+    /// Path of the Cangjie package manifest relative to the module directory
+    pub cangjie_manifest: Option<String>,
+    /// Names of the module's build option sets (e.g. `debug`, `release`)
+    pub build_option_sets: Vec<String>,
+    /// The Cangjie package manifest, if it could be read
+    pub package: Option<Manifest>,
+}
+
+/// Parsed DevEco Studio project configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DevEcoProject {
+    pub products: Vec<DevEcoProduct>,
+    pub modules: Vec<DevEcoModule>,
+    pub signing_configs: Vec<String>,
+}
+
+/// Result of translating a DevEco project into Zed configuration
+#[derive(Debug, Clone, Default)]
+pub struct DevEcoImport {
+    /// Entries for `.zed/tasks.json`
+    pub tasks: Vec<Value>,
+    /// Entries for `.zed/debug.json`
+    pub debug_scenarios: Vec<Value>,
+    /// Extension settings for the `cangjie` LSP settings blob
+    pub settings: Map<String, Value>,
+    /// Human-readable notes about configuration that could not be translated
+    pub untranslated: Vec<String>,
+}
+
+/// Converts JSON5 text (comments, trailing commas, unquoted keys, single-quoted strings)
+/// into a JSON value.
+///
+/// # Arguments
+/// * `source` - The JSON5 document
+///
+/// # Returns
+/// * `Result<Value, String>` - The parsed value or an error message
+pub fn parse_json5(source: &str) -> Result<Value, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::with_capacity(source.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' | '\'' => {
+                // Re-emit strings with double quotes
+                out.push('"');
+                i += 1;
+                while i < chars.len() && chars[i] != c {
+                    if chars[i] == '\\' && i + 1 < chars.len() {
+                        // A backslash before a line break continues the string on the next
+                        // line without a line break
+                        if let Some(len) = line_break_len(&chars[i + 1..]) {
+                            i += 1 + len;
+                            continue;
+                        }
+                        if chars[i + 1] == '\'' {
+                            out.push('\'');
+                        } else {
+                            out.push('\\');
+                            out.push(chars[i + 1]);
+                        }
+                        i += 2;
+                        continue;
+                    }
+                    if chars[i] == '"' {
+                        out.push('\\');
+                    }
+                    out.push(chars[i]);
+                    i += 1;
+                }
+                out.push('"');
+                i += 1;
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+            }
+            ',' => {
+                // Drop trailing commas before a closing bracket
+                let mut j = i + 1;
+                while j < chars.len() && chars[j].is_whitespace() {
+                    j += 1;
+                }
+                if !matches!(chars.get(j), Some('}') | Some(']')) {
+                    out.push(',');
+                }
+                i += 1;
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                let mut j = i;
+                while j < chars.len() && chars[j].is_whitespace() {
+                    j += 1;
+                }
+                if chars.get(j) == Some(&':') {
+                    out.push('"');
+                    out.push_str(&word);
+                    out.push('"');
+                } else {
+                    out.push_str(&word);
+                }
+            }
+            _ => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    serde_json::from_str(&out).map_err(|e| format!("Failed to parse JSON5: {}", e))
+}
+
+/// Returns the length of the line break at the start of `chars`, if there is one
+fn line_break_len(chars: &[char]) -> Option<usize> {
+    match chars {
+        ['\r', '\n', ..] => Some(2),
+        ['\n' | '\r' | '\u{2028}' | '\u{2029}', ..] => Some(1),
+        _ => None,
+    }
+}
+
+/// Extracts products, modules and signing configs from the project build profile.
+///
+/// # Arguments
+/// * `profile` - The parsed project-level `build-profile.json5`
+///
+/// # Returns
+/// * `Result<DevEcoProject, String>` - The project description or an error message
+pub fn parse_project(profile: &Value) -> Result<DevEcoProject, String> {
+    let modules = profile
+        .get("modules")
+        .and_then(Value::as_array)
+        .ok_or("build-profile.json5 does not declare any modules")?;
+
+    let mut project = DevEcoProject::default();
+    for module in modules {
+        let Some(name) = module.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        let src_path = module
+            .get("srcPath")
+            .and_then(Value::as_str)
+            .unwrap_or(name)
+            .trim_start_matches("./")
+            .to_string();
+        project.modules.push(DevEcoModule {
+            name: name.to_string(),
+            src_path,
+            module_type: None,
+            cangjie_manifest: None,
+            build_option_sets: Vec::new(),
+            package: None,
+        });
+    }
+
+    let app = profile.get("app");
+    if let Some(products) = app
+        .and_then(|a| a.get("products"))
+        .and_then(Value::as_array)
+    {
+        for product in products {
+            let Some(name) = product.get("name").and_then(Value::as_str) else {
+                continue;
+            };
+            let compile_sdk_version = product
+                .get("compileSdkVersion")
+                .or_else(|| product.get("compatibleSdkVersion"))
+                .map(|v| {
+                    v.as_str()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| v.to_string())
+                });
+            project.products.push(DevEcoProduct {
+                name: name.to_string(),
+                signing_config: product
+                    .get("signingConfig")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                compile_sdk_version,
+            });
+        }
+    }
+    if let Some(configs) = app
+        .and_then(|a| a.get("signingConfigs"))
+        .and_then(Value::as_array)
+    {
+        project.signing_configs = configs
+            .iter()
+            .filter_map(|c| c.get("name").and_then(Value::as_str).map(str::to_string))
+            .collect();
+    }
+
+    Ok(project)
+}
+
+/// Merges a module-level `build-profile.json5` into the module description.
+///
+/// # Arguments
+/// * `module` - The module to update
+/// * `profile` - The parsed module build profile
+pub fn apply_module_profile(module: &mut DevEcoModule, profile: &Value) {
+    module.cangjie_manifest = profile
+        .pointer("/buildOption/cangjieOptions/path")
+        .and_then(Value::as_str)
+        .map(|p| p.trim_start_matches("./").to_string());
+
+    if let Some(sets) = profile.get("buildOptionSet").and_then(Value::as_array) {
+        module.build_option_sets = sets
+            .iter()
+            .filter_map(|s| s.get("name").and_then(Value::as_str).map(str::to_string))
+            .collect();
+    }
+}
+
+/// Merges the module manifest (`src/main/module.json5`) into the module description.
+///
+/// # Arguments
+/// * `module` - The module to update
+/// * `manifest` - The parsed module manifest
+pub fn apply_module_manifest(module: &mut DevEcoModule, manifest: &Value) {
+    module.module_type = manifest
+        .pointer("/module/type")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+}
+
+/// Merges the module's Cangjie package manifest (`cjpm.toml`) into the module description.
+///
+/// # Arguments
+/// * `module` - The module to update
+/// * `manifest` - Contents of the package manifest
+pub fn apply_package_manifest(module: &mut DevEcoModule, manifest: &str) {
+    module.package = Some(cjpm::parse_manifest(manifest));
+}
+
+/// Reads the SDK directory from DevEco's `local.properties`, if one is recorded.
+///
+/// # Arguments
+/// * `properties` - Contents of `local.properties`
+///
+/// # Returns
+/// * `Option<String>` - The Cangjie SDK directory
+pub fn sdk_dir_from_local_properties(properties: &str) -> Option<String> {
+    properties
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("cangjie.sdk.dir"))
+        .map(|(_, value)| value.trim().replace("\\\\", "\\").replace("\\:", ":"))
+        .filter(|value| !value.is_empty())
+}
+
+/// Translates a DevEco project into Zed task templates, debug scenarios and settings.
+///
+/// # Arguments
+/// * `project` - The parsed project, with module profiles applied
+/// * `sdk_dir` - SDK directory from `local.properties`, if any
+/// * `debug_adapter` - Name of the debug adapter to reference in scenarios
+/// * `os` - The platform the debugged programs run on
+///
+/// # Returns
+/// * `DevEcoImport` - The generated configuration and untranslated items
+pub fn translate(
+    project: &DevEcoProject,
+    sdk_dir: Option<&str>,
+    debug_adapter: &str,
+    os: Os,
+) -> DevEcoImport {
+    let mut import = DevEcoImport::default();
+
+    for module in &project.modules {
+        let Some(manifest) = &module.cangjie_manifest else {
+            import.untranslated.push(format!(
+                "Module `{}` has no `cangjieOptions.path`; only Cangjie modules are imported",
+                module.name
+            ));
+            continue;
+        };
+
+        let package_dir = match manifest.rsplit_once('/') {
+            Some((dir, _)) => format!("{}/{}", module.src_path, dir),
+            None => module.src_path.clone(),
+        };
+        let cwd = format!("$ZED_WORKTREE_ROOT/{}", package_dir);
+
+        import.tasks.push(json!({
+            "label": format!("DevEco: build {}", module.name),
+            "command": "cjpm",
+            "args": ["build"],
+            "cwd": cwd,
+        }));
+        for set in module
+            .build_option_sets
+            .iter()
+            .filter(|s| s.as_str() != "debug")
+        {
+            if set == "release" {
+                import.tasks.push(json!({
+                    "label": format!("DevEco: build {} (release)", module.name),
+                    "command": "cjpm",
+                    "args": ["build", "--release"],
+                    "cwd": cwd,
+                }));
+            } else {
+                import.untranslated.push(format!(
+                    "Build option set `{}` of module `{}` has no cjpm equivalent",
+                    set, module.name
+                ));
+            }
+        }
+
+        if module.module_type.as_deref().is_none_or(|t| t == "entry") {
+            import.tasks.push(json!({
+                "label": format!("DevEco: run {}", module.name),
+                "command": "cjpm",
+                "args": ["run"],
+                "cwd": cwd,
+            }));
+            // The debug build is `cjpm build`, which writes to the debug profile's directory
+            let package = module.package.clone().unwrap_or_default();
+            if package.is_executable() {
+                let fallback_name = package_dir.rsplit('/').next().unwrap_or(&module.name);
+                let program = package.executable(cjpm::DEBUG_PROFILE, fallback_name);
+                import.debug_scenarios.push(json!({
+                    "label": format!("DevEco: debug {}", module.name),
+                    "adapter": debug_adapter,
+                    "request": "launch",
+                    "program": debug::executable_path(os, Some(&cwd), &program),
+                    "cwd": cwd,
+                    "build": format!("DevEco: build {}", module.name),
+                }));
+            } else {
+                import.untranslated.push(format!(
+                    "Module `{}` builds a {} library, which cannot be debugged",
+                    module.name,
+                    package.output_type.as_deref().unwrap_or_default()
+                ));
+            }
+        }
+    }
+
+    if let Some(sdk_dir) = sdk_dir {
+        import.settings.insert(
+            "cangjie.sdkPath".to_string(),
+            Value::String(sdk_dir.to_string()),
+        );
+    }
+
+    for product in &project.products {
+        if let Some(version) = &product.compile_sdk_version {
+            import.untranslated.push(format!(
+                "Product `{}` targets SDK version {}; select a matching SDK via `cangjie.sdkPath`",
+                product.name, version
+            ));
+        }
+    }
+    if !project.signing_configs.is_empty() {
+        import.untranslated.push(format!(
+            "Signing configs ({}) are not supported; sign HAP packages in DevEco Studio",
+            project.signing_configs.join(", ")
+        ));
+    }
+
+    import
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json5() {
+        // 测试 JSON5 注释、尾随逗号、未加引号的键和单引号字符串
+        let source = r#"{
+            // comment
+            app: { name: 'demo', },
+            /* block */ "modules": [ { "name": "entry", srcPath: "./entry" }, ],
+        }"#;
+        let value = parse_json5(source).unwrap();
+        assert_eq!(value["app"]["name"], "demo");
+        assert_eq!(value["modules"][0]["srcPath"], "./entry");
+
+        // 测试字符串中反斜杠加换行的续行
+        let source = "{ description: 'first \\\n second', path: \"a\\\r\nb\" }";
+        let value = parse_json5(source).unwrap();
+        assert_eq!(value["description"], "first  second");
+        assert_eq!(value["path"], "ab");
+    }
+
+    #[test]
+    fn test_translate_deveco_project() {
+        // 测试将 DevEco 项目转换为任务和调试场景
+        let profile = parse_json5(
+            r#"{
+                app: {
+                    signingConfigs: [{ name: "default" }],
+                    products: [{ name: "default", signingConfig: "default", compileSdkVersion: "5.0.0(12)" }],
+                },
+                modules: [
+                    { name: "entry", srcPath: "./entry" },
+                    { name: "arkui", srcPath: "./arkui" },
+                ],
+            }"#,
+        )
+        .unwrap();
+        let mut project = parse_project(&profile).unwrap();
+        let module_profile = parse_json5(
+            r#"{
+                buildOption: { cangjieOptions: { path: "./src/main/cangjie/cjpm.toml" } },
+                buildOptionSet: [{ name: "release" }, { name: "obfuscated" }],
+            }"#,
+        )
+        .unwrap();
+        apply_module_profile(&mut project.modules[0], &module_profile);
+        apply_module_manifest(
+            &mut project.modules[0],
+            &parse_json5(r#"{ module: { name: "entry", type: "entry" } }"#).unwrap(),
+        );
+
+        apply_package_manifest(
+            &mut project.modules[0],
+            "[package]\nname = \"demo\"\ntarget-dir = \"out\"\n",
+        );
+
+        let import = translate(&project, Some("/opt/cangjie"), "Cangjie", Os::Linux);
+        assert_eq!(import.tasks.len(), 3);
+        assert_eq!(
+            import.tasks[0]["cwd"],
+            "$ZED_WORKTREE_ROOT/entry/src/main/cangjie"
+        );
+        assert_eq!(import.debug_scenarios.len(), 1);
+        // 测试调试程序路径遵循 cjpm.toml 的包名与 target-dir
+        assert_eq!(
+            import.debug_scenarios[0]["program"],
+            "$ZED_WORKTREE_ROOT/entry/src/main/cangjie/out/debug/bin/demo"
+        );
+        let windows = translate(&project, None, "Cangjie", Os::Windows);
+        assert_eq!(
+            windows.debug_scenarios[0]["program"],
+            "$ZED_WORKTREE_ROOT\\entry\\src\\main\\cangjie\\out\\debug\\bin\\demo.exe"
+        );
+        assert_eq!(import.settings["cangjie.sdkPath"], "/opt/cangjie");
+        // arkui 模块、obfuscated 选项集、SDK 版本和签名配置均无法转换
+        assert_eq!(import.untranslated.len(), 4);
+    }
+
+    #[test]
+    fn test_sdk_dir_from_local_properties() {
+        // 测试从 local.properties 读取 SDK 目录
+        let properties = "# generated\nsdk.dir=/opt/ohos\ncangjie.sdk.dir=C\\:\\\\cangjie\n";
+        assert_eq!(
+            sdk_dir_from_local_properties(properties),
+            Some("C:\\cangjie".to_string())
+        );
+        assert_eq!(sdk_dir_from_local_properties("sdk.dir=/opt/ohos"), None);
+    }
+}
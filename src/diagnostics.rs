@@ -0,0 +1,165 @@
+// src/diagnostics.rs
+//! Splitting compiler output into diagnostics for `/cangjie-build`.
+//!
+//! cjc reports each diagnostic as a `error: message` (or `warning[code]: message`) header,
+//! followed by an ` ==> path:line:col:` location and the annotated source lines. Every header
+//! starts a new diagnostic; lines before the first one and the closing summary are not part
+//! of any.
+
+use crate::output::{SourceLocation, parse_location};
+
+/// How severe a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+impl Level {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "error" => Some(Level::Error),
+            "warning" => Some(Level::Warning),
+            _ => None,
+        }
+    }
+}
+
+/// A compiler diagnostic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub level: Level,
+    /// Diagnostic code, e.g. `E0012` from `error[E0012]: ...`
+    pub code: Option<String>,
+    pub message: String,
+    /// Primary location, from the first location line of the diagnostic
+    pub location: Option<SourceLocation>,
+    /// The diagnostic as printed, header included
+    pub text: String,
+}
+
+impl Diagnostic {
+    /// Returns the section label: status marker, level, code, location and message
+    pub fn label(&self) -> String {
+        let (marker, level) = match self.level {
+            Level::Error => ("❌", "error"),
+            Level::Warning => ("⚠️", "warning"),
+        };
+        let mut label = format!("{} {}", marker, level);
+        if let Some(code) = &self.code {
+            label.push_str(&format!("[{}]", code));
+        }
+        if let Some(location) = &self.location {
+            label.push_str(&format!(" {}", location));
+        }
+        format!("{}: {}", label, self.message)
+    }
+}
+
+/// Parses a diagnostic header: `error: message` or `warning[code]: message`
+fn parse_header(line: &str) -> Option<(Level, Option<String>, String)> {
+    let (head, message) = line.split_once(':')?;
+    let (level, code) = match head.split_once('[') {
+        Some((level, code)) => (level, Some(code.strip_suffix(']')?.to_string())),
+        None => (head, None),
+    };
+    Some((Level::parse(level)?, code, message.trim().to_string()))
+}
+
+/// Splits compiler output into its diagnostics.
+///
+/// # Arguments
+/// * `output` - The compiler's stdout and stderr
+///
+/// # Returns
+/// * `Vec<Diagnostic>` - The diagnostics in output order
+pub fn parse_diagnostics(output: &str) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let mut current: Option<Diagnostic> = None;
+    for line in output.lines() {
+        if let Some((level, code, message)) = parse_header(line) {
+            diagnostics.extend(current.take());
+            current = Some(Diagnostic {
+                level,
+                code,
+                message,
+                location: None,
+                text: format!("{}\n", line),
+            });
+            continue;
+        }
+        let Some(diagnostic) = current.as_mut() else {
+            continue;
+        };
+        // The summary ("1 error generated, 2 warnings generated.") ends the last diagnostic
+        if line.contains(" generated") && line.starts_with(|c: char| c.is_ascii_digit()) {
+            diagnostics.extend(current.take());
+            continue;
+        }
+        if diagnostic.location.is_none() {
+            diagnostic.location = line.split_whitespace().find_map(parse_location);
+        }
+        diagnostic.text.push_str(line);
+        diagnostic.text.push('\n');
+    }
+    diagnostics.extend(current);
+    diagnostics
+}
+
+/// Counts the errors and warnings, e.g. `2 errors, 1 warning`
+pub fn summary(diagnostics: &[Diagnostic]) -> String {
+    let count = |level: Level| diagnostics.iter().filter(|d| d.level == level).count();
+    let plural = |count: usize, noun: &str| {
+        format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+    };
+    format!(
+        "{}, {}",
+        plural(count(Level::Error), "error"),
+        plural(count(Level::Warning), "warning")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_diagnostics() {
+        // 测试将 cjc 输出拆分为带位置与错误码的诊断
+        let output = "\
+compiling package main
+error: undeclared identifier 'x'
+ ==> src/main.cj:3:5:
+  |
+3 |     x
+  |     ^
+  |
+warning[W0301]: unused variable: 'a'
+ ==> src/util.cj:10:9:
+  |
+1 error generated, 1 warning generated.
+";
+        let diagnostics = parse_diagnostics(output);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].level, Level::Error);
+        assert_eq!(diagnostics[0].code, None);
+        assert_eq!(
+            diagnostics[0].location,
+            Some(SourceLocation::new("src/main.cj", 3, 5))
+        );
+        assert!(
+            diagnostics[0]
+                .text
+                .starts_with("error: undeclared identifier 'x'\n")
+        );
+        assert!(diagnostics[0].text.ends_with("  |\n"));
+        assert_eq!(
+            diagnostics[1].label(),
+            "⚠️ warning[W0301] src/util.cj:10:9: unused variable: 'a'"
+        );
+        assert!(!diagnostics[1].text.contains("generated"));
+        assert_eq!(summary(&diagnostics), "1 error, 1 warning");
+        assert!(parse_diagnostics("error while loading shared libraries").is_empty());
+        assert_eq!(summary(&[]), "0 errors, 0 warnings");
+    }
+}
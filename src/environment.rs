@@ -0,0 +1,124 @@
+// src/environment.rs
+//! Describing the process environments for `/cangjie-env`.
+//!
+//! The extension starts processes with the worktree's shell environment plus a few variables
+//! of its own. The report lists those variables and, for search paths, the entries added in
+//! front of the shell's value, so it can be compared with a terminal where things work.
+
+use zed_extension_api::Os;
+
+use crate::sdk_layout::library_path_var;
+
+/// Returns the separator of search path lists on a platform
+pub fn path_separator(os: Os) -> char {
+    if os == Os::Windows { ';' } else { ':' }
+}
+
+/// Returns true if a variable holds a search path list the extension prepends to
+pub fn is_path_list(os: Os, name: &str) -> bool {
+    if os == Os::Windows {
+        name.eq_ignore_ascii_case("PATH")
+    } else {
+        name == "PATH" || name == library_path_var(os)
+    }
+}
+
+/// Returns the entries of a search path list that are not in the shell's value.
+///
+/// # Arguments
+/// * `os` - The current platform
+/// * `value` - The value the process is started with
+/// * `shell` - The value in the worktree's shell environment, if set
+///
+/// # Returns
+/// * `Vec<String>` - The added entries, in order
+pub fn added_entries(os: Os, value: &str, shell: Option<&str>) -> Vec<String> {
+    let separator = path_separator(os);
+    let existing: Vec<&str> = shell.unwrap_or_default().split(separator).collect();
+    value
+        .split(separator)
+        .filter(|entry| !entry.is_empty() && !existing.contains(entry))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Describes the variables a process is started with on top of the shell environment.
+///
+/// # Arguments
+/// * `os` - The current platform
+/// * `env` - The variables the extension sets
+/// * `shell` - Looks up a variable in the worktree's shell environment
+///
+/// # Returns
+/// * `String` - One line per variable, search path additions indented below it
+pub fn describe(
+    os: Os,
+    env: &[(String, String)],
+    shell: impl Fn(&str) -> Option<String>,
+) -> String {
+    if env.is_empty() {
+        return "No variables set; the shell environment is used as is".to_string();
+    }
+    let mut lines = Vec::new();
+    for (name, value) in env {
+        let shell_value = shell(name);
+        if is_path_list(os, name) {
+            let added = added_entries(os, value, shell_value.as_deref());
+            if added.is_empty() {
+                lines.push(format!("{}: unchanged", name));
+            } else {
+                lines.push(format!("{}: prepended", name));
+                lines.extend(added.iter().map(|entry| format!("  + {}", entry)));
+            }
+            continue;
+        }
+        lines.push(format!("{}={}", name, value));
+        match shell_value {
+            Some(shell_value) if shell_value != *value => {
+                lines.push(format!("  (shell: {})", shell_value))
+            }
+            None => lines.push("  (not set in the shell)".to_string()),
+            _ => {}
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_env() {
+        // 测试列出设置的变量与追加到搜索路径前的条目
+        let env = vec![
+            ("CANGJIE_HOME".to_string(), "/opt/cj".to_string()),
+            (
+                "LD_LIBRARY_PATH".to_string(),
+                "/opt/cj/lib:/opt/cj/runtime/lib:/usr/lib".to_string(),
+            ),
+        ];
+        let shell = |name: &str| match name {
+            "CANGJIE_HOME" => Some("/opt/old".to_string()),
+            "LD_LIBRARY_PATH" => Some("/usr/lib:/opt/cj/runtime/lib".to_string()),
+            _ => None,
+        };
+        assert_eq!(
+            describe(Os::Linux, &env, shell),
+            "CANGJIE_HOME=/opt/cj\n  (shell: /opt/old)\nLD_LIBRARY_PATH: prepended\n  + /opt/cj/lib"
+        );
+        assert_eq!(
+            describe(Os::Linux, &env[..1], |_| None),
+            "CANGJIE_HOME=/opt/cj\n  (not set in the shell)"
+        );
+        assert!(describe(Os::Mac, &[], |_| None).starts_with("No variables set"));
+
+        assert!(is_path_list(Os::Windows, "Path"));
+        assert!(is_path_list(Os::Mac, "DYLD_LIBRARY_PATH"));
+        assert!(!is_path_list(Os::Linux, "DYLD_LIBRARY_PATH"));
+        assert_eq!(
+            added_entries(Os::Windows, r"C:\cj\bin;C:\Windows", Some(r"C:\Windows")),
+            vec![r"C:\cj\bin"]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+// src/fingerprint.rs
+//! Fingerprints of the last successful language server resolution, kept across restarts.
+//!
+//! Resolving the language server probes the SDK layout, the downloads and runs `--version` on
+//! two binaries. When a project is reopened and neither the settings nor the files involved
+//! have changed, the previous result is reused instead. A fingerprint combines a hash of the
+//! settings that influence resolution with the modification times of the files and
+//! directories it depended on.
+
+use serde_json::{Value, json};
+
+/// File in the extension's working directory holding the resolutions of all worktrees
+pub const STATE_FILE: &str = "resolution-state.json";
+
+/// Modification time of a path, `None` if it does not exist
+pub type Stamp = (String, Option<u64>);
+
+/// What a resolution depended on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint {
+    /// Hash of the settings and environment that influence resolution
+    pub settings: u64,
+    /// Modification times of the files and directories that were probed
+    pub stamps: Vec<Stamp>,
+}
+
+/// A successful resolution and its fingerprint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolution {
+    pub fingerprint: Fingerprint,
+    /// Path of the language server binary
+    pub server_path: String,
+    /// Where the language server came from, as shown by `/cangjie-info`
+    pub channel: Option<String>,
+    /// The LSP / SDK version mismatch warning, if any
+    pub version_warning: Option<String>,
+}
+
+/// Hashes settings with 64-bit FNV-1a, which is stable across builds
+pub fn hash_settings(parts: &[String]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for part in parts {
+        for byte in part.bytes().chain(std::iter::once(0)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// Reads the resolution stored for a worktree.
+///
+/// # Arguments
+/// * `state` - Contents of [`STATE_FILE`]
+/// * `worktree` - The worktree root the resolution was stored for
+///
+/// # Returns
+/// * `Option<Resolution>` - The stored resolution, if there is a readable one
+pub fn load(state: &str, worktree: &str) -> Option<Resolution> {
+    let value: Value = serde_json::from_str(state).ok()?;
+    let entry = value.get("worktrees")?.get(worktree)?;
+    let text = |key: &str| entry.get(key).and_then(Value::as_str).map(str::to_string);
+    let stamps = entry
+        .get("stamps")?
+        .as_array()?
+        .iter()
+        .map(|stamp| Some((stamp.get(0)?.as_str()?.to_string(), stamp.get(1)?.as_u64())))
+        .collect::<Option<Vec<Stamp>>>()?;
+    Some(Resolution {
+        fingerprint: Fingerprint {
+            settings: u64::from_str_radix(&text("settings")?, 16).ok()?,
+            stamps,
+        },
+        server_path: text("server_path")?,
+        channel: text("channel"),
+        version_warning: text("version_warning"),
+    })
+}
+
+/// Stores the resolution of a worktree, keeping those of other worktrees.
+///
+/// # Arguments
+/// * `state` - Current contents of [`STATE_FILE`], if any
+/// * `worktree` - The worktree root
+/// * `resolution` - The resolution to store
+///
+/// # Returns
+/// * `String` - The new contents of [`STATE_FILE`]
+pub fn store(state: Option<&str>, worktree: &str, resolution: &Resolution) -> String {
+    let mut worktrees = state
+        .and_then(|state| serde_json::from_str::<Value>(state).ok())
+        .and_then(|value| value.get("worktrees")?.as_object().cloned())
+        .unwrap_or_default();
+    let stamps: Vec<Value> = resolution
+        .fingerprint
+        .stamps
+        .iter()
+        .map(|(path, time)| json!([path, time]))
+        .collect();
+    worktrees.insert(
+        worktree.to_string(),
+        json!({
+            "settings": format!("{:016x}", resolution.fingerprint.settings),
+            "stamps": stamps,
+            "server_path": resolution.server_path,
+            "channel": resolution.channel,
+            "version_warning": resolution.version_warning,
+        }),
+    );
+    json!({ "worktrees": worktrees }).to_string()
+}
+
+/// Removes the resolution of a worktree, e.g. after `/cangjie-refresh-sdk`.
+///
+/// # Arguments
+/// * `state` - Current contents of [`STATE_FILE`]
+/// * `worktree` - The worktree root
+///
+/// # Returns
+/// * `Option<String>` - The new contents, or `None` if nothing was stored for the worktree
+pub fn forget(state: &str, worktree: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(state).ok()?;
+    let mut worktrees = value.get("worktrees")?.as_object()?.clone();
+    worktrees.remove(worktree)?;
+    Some(json!({ "worktrees": worktrees }).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolution_state() {
+        // 测试解析结果的指纹在持久状态中保存与读取
+        let resolution = Resolution {
+            fingerprint: Fingerprint {
+                settings: hash_settings(&["a".to_string(), "b".to_string()]),
+                stamps: vec![
+                    ("/opt/cj".to_string(), Some(100)),
+                    ("/missing".to_string(), None),
+                ],
+            },
+            server_path: "/opt/cj/tools/bin/cangjie-lsp".to_string(),
+            channel: Some("SDK".to_string()),
+            version_warning: None,
+        };
+        let state = store(None, "/work/a", &resolution);
+        let state = store(Some(&state), "/work/b", &resolution);
+        assert_eq!(load(&state, "/work/a"), Some(resolution.clone()));
+        assert_eq!(load(&state, "/work/b"), Some(resolution));
+        assert_eq!(load(&state, "/work/c"), None);
+        assert_eq!(load("not json", "/work/a"), None);
+        let state = forget(&state, "/work/a").unwrap();
+        assert_eq!(load(&state, "/work/a"), None);
+        assert!(load(&state, "/work/b").is_some());
+        assert_eq!(forget(&state, "/work/a"), None);
+
+        // 设置的拼接方式不同时哈希也不同
+        assert_ne!(
+            hash_settings(&["ab".to_string(), String::new()]),
+            hash_settings(&["a".to_string(), "b".to_string()])
+        );
+    }
+}
@@ -0,0 +1,163 @@
+// src/formatter.rs
+//! Source formatting with `cjfmt` for `/cangjie-fmt`.
+//!
+//! cjfmt rewrites files in place without saying which ones it changed, so the Cangjie sources
+//! are checksummed before and after the run and the files whose checksum differs are reported.
+
+use std::collections::BTreeMap;
+
+use zed_extension_api::Os;
+
+/// Extension of Cangjie source files
+pub const SOURCE_EXTENSION: &str = ".cj";
+
+/// Returns the cjfmt arguments formatting a path in place: `-f` for a source file, `-d` for a
+/// directory.
+///
+/// # Arguments
+/// * `path` - The file or directory to format
+///
+/// # Returns
+/// * `Vec<String>` - The arguments after `cjfmt`
+pub fn format_args(path: &str) -> Vec<String> {
+    let flag = if path.ends_with(SOURCE_EXTENSION) {
+        "-f"
+    } else {
+        "-d"
+    };
+    vec![flag.to_string(), path.to_string()]
+}
+
+/// Returns the command printing a checksum for every Cangjie source file below a path, one
+/// `<checksum> <path>` line per file.
+///
+/// # Arguments
+/// * `os` - The current platform
+/// * `path` - The file or directory to list
+///
+/// # Returns
+/// * `(String, Vec<String>)` - The program and its arguments
+pub fn checksum_command(os: Os, path: &str) -> (String, Vec<String>) {
+    match os {
+        Os::Windows => (
+            "powershell".to_string(),
+            vec![
+                "-NoProfile".to_string(),
+                "-Command".to_string(),
+                format!(
+                    "Get-ChildItem -LiteralPath '{}' -Recurse -File -Filter '*{}' | \
+                     Get-FileHash -Algorithm MD5 | \
+                     ForEach-Object {{ $_.Hash + ' ' + $_.Path }}",
+                    path.replace('\'', "''"),
+                    SOURCE_EXTENSION
+                ),
+            ],
+        ),
+        _ => (
+            "find".to_string(),
+            vec![
+                path.to_string(),
+                "-type".to_string(),
+                "f".to_string(),
+                "-name".to_string(),
+                format!("*{}", SOURCE_EXTENSION),
+                "-exec".to_string(),
+                "cksum".to_string(),
+                "{}".to_string(),
+                "+".to_string(),
+            ],
+        ),
+    }
+}
+
+/// Parses the output of [`checksum_command`].
+///
+/// # Arguments
+/// * `os` - The platform the command ran on
+/// * `listing` - The command output
+/// * `root` - The worktree root, stripped from absolute paths
+///
+/// # Returns
+/// * `BTreeMap<String, String>` - The checksum of every file, keyed by its path relative to
+///   the worktree root
+pub fn parse_checksums(os: Os, listing: &str, root: &str) -> BTreeMap<String, String> {
+    // `cksum` prints the CRC and the size before the path, Get-FileHash only the hash
+    let fields = if matches!(os, Os::Windows) { 2 } else { 3 };
+    let root = format!("{}/", root.replace('\\', "/").trim_end_matches('/'));
+    listing
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.trim().splitn(fields, ' ').collect();
+            let (path, checksum) = parts.split_last()?;
+            if parts.len() < fields {
+                return None;
+            }
+            let path = path.replace('\\', "/");
+            let path = path.strip_prefix(&root).unwrap_or(&path);
+            Some((
+                path.trim_start_matches("./").to_string(),
+                checksum.join(" "),
+            ))
+        })
+        .collect()
+}
+
+/// Returns the files whose checksum differs between two listings, sorted by path.
+///
+/// # Arguments
+/// * `before` - The checksums before formatting
+/// * `after` - The checksums after formatting
+pub fn changed_files(
+    before: &BTreeMap<String, String>,
+    after: &BTreeMap<String, String>,
+) -> Vec<String> {
+    after
+        .iter()
+        .filter(|(path, checksum)| before.get(*path) != Some(*checksum))
+        .map(|(path, _)| path.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_args() {
+        // 测试按文件或目录选择 cjfmt 参数
+        assert_eq!(format_args("src/main.cj"), vec!["-f", "src/main.cj"]);
+        assert_eq!(format_args("/work/app"), vec!["-d", "/work/app"]);
+        let (program, args) = checksum_command(Os::Linux, "/work/my app");
+        assert_eq!(program, "find");
+        assert_eq!(args[0], "/work/my app");
+        let (_, args) = checksum_command(Os::Windows, r"C:\it's");
+        assert!(args[2].starts_with(r"Get-ChildItem -LiteralPath 'C:\it''s'"));
+    }
+
+    #[test]
+    fn test_changed_files() {
+        // 测试解析 cksum 与 Get-FileHash 输出并找出格式化改动的文件
+        let before = parse_checksums(
+            Os::Linux,
+            "1 10 /work/src/main.cj\n2 20 /work/src/my util.cj\n\n",
+            "/work/",
+        );
+        assert_eq!(
+            before.get("src/my util.cj").map(String::as_str),
+            Some("2 20")
+        );
+        let after = parse_checksums(
+            Os::Linux,
+            "1 10 /work/src/main.cj\n3 21 /work/src/my util.cj\n4 5 ./new.cj\n",
+            "/work",
+        );
+        assert_eq!(
+            changed_files(&before, &after),
+            vec!["new.cj", "src/my util.cj"]
+        );
+        assert!(changed_files(&after, &after).is_empty());
+
+        let windows = parse_checksums(Os::Windows, "ABCD C:\\work\\src\\main.cj\r\n", "C:\\work");
+        assert_eq!(windows.get("src/main.cj").map(String::as_str), Some("ABCD"));
+    }
+}
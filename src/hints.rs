@@ -0,0 +1,144 @@
+// src/hints.rs
+//! Knowledge base mapping well-known toolchain exit codes and stderr patterns to hints.
+//!
+//! To add a hint, append an entry to [`HINTS`]. An entry matches when every condition it
+//! specifies holds: the tool name, the exit code, and (case-insensitively) any one of its
+//! output patterns.
+
+/// A known failure mode of a toolchain command
+#[derive(Debug, Clone, Copy)]
+pub struct Hint {
+    /// Tool the entry applies to (`cjc`, `cjpm`, ...), or `None` for any tool
+    pub tool: Option<&'static str>,
+    /// Exit code the entry applies to, or `None` for any non-zero exit code
+    pub exit_code: Option<i32>,
+    /// Lower-case substrings of stdout/stderr; at least one must occur (empty matches all)
+    pub patterns: &'static [&'static str],
+    /// Human-readable explanation and suggested fix
+    pub message: &'static str,
+}
+
+/// The hint table, in display order
+pub const HINTS: &[Hint] = &[
+    Hint {
+        tool: None,
+        exit_code: None,
+        patterns: &[
+            "license check failed",
+            "license verification",
+            "invalid license",
+        ],
+        message: "The SDK license check failed. Make sure the SDK was installed from an official \
+                  package and that its license file has not been removed or modified.",
+    },
+    Hint {
+        tool: None,
+        exit_code: None,
+        patterns: &["libc++.so", "libc++abi", "libc++.1.dylib", "libunwind.so"],
+        message: "The libc++ runtime required by the Cangjie toolchain could not be loaded. \
+                  Install libc++ (e.g. `apt install libc++1 libc++abi1`) or add the SDK's \
+                  `runtime/lib` directory to the library search path.",
+    },
+    Hint {
+        tool: None,
+        exit_code: None,
+        patterns: &[
+            "error while loading shared libraries",
+            "library not loaded",
+            "dll was not found",
+        ],
+        message: "A shared library of the SDK could not be found. Source the SDK's `envsetup` \
+                  script or add its `runtime/lib` directory to LD_LIBRARY_PATH / \
+                  DYLD_LIBRARY_PATH / PATH.",
+    },
+    Hint {
+        tool: None,
+        exit_code: None,
+        patterns: &[
+            ".cjo' version",
+            ".cjo version",
+            "incompatible cjo",
+            "cjo file version",
+        ],
+        message: "A compiled package (.cjo) was produced by a different compiler version. \
+                  Run `/cangjie-build` after `cjpm clean` so dependencies are rebuilt with the \
+                  current SDK.",
+    },
+    Hint {
+        tool: Some("cjpm"),
+        exit_code: None,
+        patterns: &["cjpm.toml"],
+        message: "cjpm could not read `cjpm.toml`. Run cjpm from the package root or create a \
+                  package with `cjpm init`.",
+    },
+    Hint {
+        tool: None,
+        exit_code: Some(126),
+        patterns: &[],
+        message: "The command exists but could not be executed. Check that the binary has \
+                  execute permissions and matches this platform's architecture.",
+    },
+    Hint {
+        tool: None,
+        exit_code: Some(127),
+        patterns: &[],
+        message: "The command was not found. Check `cangjie.sdkPath` / CANGJIE_HOME or the \
+                  tool path overrides.",
+    },
+    Hint {
+        tool: None,
+        exit_code: Some(137),
+        patterns: &[],
+        message: "The process was killed (SIGKILL), most likely by the out-of-memory killer.",
+    },
+];
+
+/// Returns the hints matching a failed command.
+///
+/// # Arguments
+/// * `tool` - Name of the tool that was run (`cjc`, `cjpm`, ...)
+/// * `exit_code` - The exit code, `None` if the process was terminated by a signal
+/// * `output` - The command's stdout and stderr
+///
+/// # Returns
+/// * `Vec<&'static str>` - The matching hint messages, in table order
+pub fn hints_for(tool: &str, exit_code: Option<i32>, output: &str) -> Vec<&'static str> {
+    if exit_code == Some(0) {
+        return Vec::new();
+    }
+    let output = output.to_lowercase();
+    HINTS
+        .iter()
+        .filter(|hint| hint.tool.is_none_or(|t| t == tool))
+        .filter(|hint| hint.exit_code.is_none_or(|code| exit_code == Some(code)))
+        .filter(|hint| hint.patterns.is_empty() || hint.patterns.iter().any(|p| output.contains(p)))
+        .map(|hint| hint.message)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hints_for() {
+        // 测试根据输出内容匹配提示
+        let hints = hints_for(
+            "cjc",
+            Some(1),
+            "cjc: error while loading shared libraries: libc++.so.1: cannot open shared object file",
+        );
+        assert_eq!(hints.len(), 2);
+
+        // 测试根据退出码匹配提示
+        assert_eq!(hints_for("cjpm", Some(127), "").len(), 1);
+
+        // 测试工具限定的提示和成功退出
+        assert!(hints_for("cjc", Some(1), "could not read cjpm.toml").is_empty());
+        assert_eq!(
+            hints_for("cjpm", Some(1), "could not read cjpm.toml").len(),
+            1
+        );
+        assert!(hints_for("cjc", Some(0), "invalid license").is_empty());
+    }
+}
@@ -0,0 +1,977 @@
+// src/host.rs
+//! Host services used by SDK resolution, language server installation and slash commands.
+//!
+//! Everything the extension asks of Zed, the operating system or the network goes through
+//! [`HostServices`], so the same logic can run against a live worktree ([`ZedHost`]), record
+//! the answers it gets ([`Recorder`]) and replay recorded answers offline ([`Replay`]).
+//!
+//! Recordings are JSON: `{"interactions": [{"call": "is_dir /opt/cangjie/bin", "response":
+//! true}, ...]}`. Fallible calls answer `{"ok": ...}` or `{"err": "..."}`.
+
+use std::cell::RefCell;
+#[cfg(test)]
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use zed_extension_api::http_client::{HttpMethod, HttpRequest, RedirectPolicy};
+use zed_extension_api::{self as zed, DownloadedFileType, process::Command, settings::LspSettings};
+
+/// Selects a GitHub release
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseSelector<'a> {
+    /// The latest release, optionally including pre-releases
+    Latest { pre_release: bool },
+    /// The release with the given tag
+    Tag(&'a str),
+}
+
+/// Output of a finished process
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProcessOutput {
+    pub status: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// The services the extension needs from its host
+pub trait HostServices {
+    /// Root path of the worktree, which also keys the per-worktree caches
+    fn worktree_root(&self) -> String;
+    /// Looks up a flat `cangjie.*` setting of the worktree
+    fn setting(&self, key: &str) -> Option<Value>;
+    /// The `lsp.cangjie-lsp.binary.path` override, if configured
+    fn lsp_binary_override(&self) -> Option<String>;
+    /// The `lsp.cangjie.binary.path` override of the SDK tools, if configured
+    fn tool_binary_override(&self) -> Option<String>;
+    /// Reads a file relative to the worktree root
+    fn read_worktree_file(&self, path: &str) -> Result<String, String>;
+    /// Reads an environment variable of the extension process
+    fn env_var(&self, name: &str) -> Option<String>;
+    /// Looks up a binary on the `PATH` of the worktree's shell environment
+    fn which(&self, name: &str) -> Option<String>;
+    /// Seconds since the Unix epoch
+    fn unix_time(&self) -> u64;
+    /// Path of the running executable
+    fn current_exe(&self) -> Result<PathBuf, String>;
+    /// Current directory of the extension process
+    fn current_dir(&self) -> Result<PathBuf, String>;
+    /// Returns true if `path` is an existing directory
+    fn is_dir(&self, path: &Path) -> bool;
+    /// Returns true if `path` is an existing file (or symlink to one)
+    fn is_file(&self, path: &Path) -> bool;
+    /// Lists the subdirectories of `path`, sorted
+    fn subdirectories(&self, path: &Path) -> Vec<PathBuf>;
+    /// Resolves symbolic links in `path`, e.g. an SDK managed by a `current -> 1.0.3` link
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, String>;
+    /// Reads a text file outside the worktree, e.g. a downloaded asset
+    fn read_text_file(&self, path: &Path) -> Result<String, String>;
+    /// Computes the lowercase hex SHA-256 digest of a file
+    fn sha256_file(&self, path: &Path) -> Result<String, String>;
+    /// Modification time of a file or directory in seconds since the Unix epoch
+    fn modified_time(&self, path: &Path) -> Option<u64>;
+    /// Writes a text file outside the worktree, e.g. persistent extension state
+    fn write_text_file(&self, path: &Path, contents: &str) -> Result<(), String>;
+    /// Creates a directory and its parents
+    fn create_dir_all(&self, path: &Path) -> Result<(), String>;
+    /// Removes a file or directory tree, ignoring errors
+    fn remove_path(&self, path: &Path);
+    /// Moves a file or directory into place, replacing nothing
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), String>;
+    /// Waits before retrying a failed operation
+    fn sleep(&self, duration: Duration);
+    /// Marks a file as executable
+    fn make_executable(&self, path: &Path) -> Result<(), String>;
+    /// Runs a program to completion
+    fn run(&self, program: &str, args: &[String]) -> Result<ProcessOutput, String> {
+        self.run_with_env(program, args, &[])
+    }
+    /// Runs a program to completion with additional environment variables
+    fn run_with_env(
+        &self,
+        program: &str,
+        args: &[String],
+        env: &[(String, String)],
+    ) -> Result<ProcessOutput, String>;
+    /// Fetches release metadata from GitHub
+    fn github_release(
+        &self,
+        repo: &str,
+        selector: ReleaseSelector,
+    ) -> Result<zed::GithubRelease, String>;
+    /// Downloads a file, extracting archives according to `file_type`
+    fn download(&self, url: &str, path: &Path, file_type: DownloadedFileType)
+    -> Result<(), String>;
+    /// Fetches a URL and returns the response body as text
+    fn http_get(&self, url: &str) -> Result<String, String>;
+}
+
+/// The live host: a Zed worktree, the local file system and the network
+pub struct ZedHost<'a> {
+    worktree: Option<&'a zed::Worktree>,
+}
+
+impl<'a> ZedHost<'a> {
+    pub fn new(worktree: &'a zed::Worktree) -> Self {
+        Self {
+            worktree: Some(worktree),
+        }
+    }
+
+    /// The host outside of a worktree, for calls Zed makes without one such as slash
+    /// command completion: settings and worktree files are absent, the root is empty
+    pub fn without_worktree() -> Self {
+        Self { worktree: None }
+    }
+}
+
+impl HostServices for ZedHost<'_> {
+    fn worktree_root(&self) -> String {
+        self.worktree
+            .map(|worktree| worktree.root_path())
+            .unwrap_or_default()
+    }
+
+    fn setting(&self, key: &str) -> Option<Value> {
+        let lsp_settings = LspSettings::for_worktree("cangjie", self.worktree?).ok()?;
+        match lsp_settings.settings? {
+            Value::Object(mut settings) => settings.remove(key),
+            _ => None,
+        }
+    }
+
+    fn lsp_binary_override(&self) -> Option<String> {
+        LspSettings::for_worktree("cangjie-lsp", self.worktree?)
+            .ok()?
+            .binary?
+            .path
+    }
+
+    fn tool_binary_override(&self) -> Option<String> {
+        LspSettings::for_worktree("cangjie", self.worktree?)
+            .ok()?
+            .binary?
+            .path
+    }
+
+    fn read_worktree_file(&self, path: &str) -> Result<String, String> {
+        self.worktree
+            .ok_or_else(|| format!("{}: no worktree", path))?
+            .read_text_file(path)
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+
+    fn which(&self, name: &str) -> Option<String> {
+        self.worktree?.which(name)
+    }
+
+    fn unix_time(&self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs())
+    }
+
+    fn current_exe(&self) -> Result<PathBuf, String> {
+        std::env::current_exe().map_err(|e| e.to_string())
+    }
+
+    fn current_dir(&self) -> Result<PathBuf, String> {
+        std::env::current_dir().map_err(|e| e.to_string())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file() || path.is_symlink()
+    }
+
+    fn subdirectories(&self, path: &Path) -> Vec<PathBuf> {
+        // An empty path is the working directory; entries keep the form of `path`
+        let dir = if path.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            path
+        };
+        let mut dirs: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| path.join(entry.file_name()))
+                    .filter(|path| path.is_dir())
+                    .collect()
+            })
+            .unwrap_or_default();
+        dirs.sort();
+        dirs
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, String> {
+        let target =
+            std::fs::canonicalize(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        // Windows returns verbatim `\\?\C:\...` paths, which read poorly in reports
+        let text = target.to_string_lossy();
+        match text.strip_prefix(r"\\?\") {
+            Some(rest) if !rest.starts_with("UNC") => Ok(PathBuf::from(rest)),
+            _ => Ok(target),
+        }
+    }
+
+    fn read_text_file(&self, path: &Path) -> Result<String, String> {
+        std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn sha256_file(&self, path: &Path) -> Result<String, String> {
+        let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(crate::checksum::sha256_hex(&bytes))
+    }
+
+    fn modified_time(&self, path: &Path) -> Option<u64> {
+        let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+        let elapsed = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+        Some(elapsed.as_secs())
+    }
+
+    fn write_text_file(&self, path: &Path, contents: &str) -> Result<(), String> {
+        std::fs::write(path, contents).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<(), String> {
+        std::fs::create_dir_all(path).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    fn remove_path(&self, path: &Path) {
+        let _ = if path.is_dir() {
+            std::fs::remove_dir_all(path)
+        } else {
+            std::fs::remove_file(path)
+        };
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), String> {
+        std::fs::rename(from, to).map_err(|e| format!("{}: {}", to.display(), e))
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+
+    fn make_executable(&self, path: &Path) -> Result<(), String> {
+        zed::make_file_executable(path.to_string_lossy().as_ref())
+    }
+
+    fn run_with_env(
+        &self,
+        program: &str,
+        args: &[String],
+        env: &[(String, String)],
+    ) -> Result<ProcessOutput, String> {
+        let output = Command::new(program)
+            .args(args.to_vec())
+            .envs(env.to_vec())
+            .output()?;
+        Ok(ProcessOutput {
+            status: output.status,
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        })
+    }
+
+    fn github_release(
+        &self,
+        repo: &str,
+        selector: ReleaseSelector,
+    ) -> Result<zed::GithubRelease, String> {
+        match selector {
+            ReleaseSelector::Latest { pre_release } => zed::latest_github_release(
+                repo,
+                zed::GithubReleaseOptions {
+                    require_assets: true,
+                    pre_release,
+                },
+            ),
+            ReleaseSelector::Tag(tag) => zed::github_release_by_tag_name(repo, tag),
+        }
+    }
+
+    fn download(
+        &self,
+        url: &str,
+        path: &Path,
+        file_type: DownloadedFileType,
+    ) -> Result<(), String> {
+        zed::download_file(url, path.to_string_lossy().as_ref(), file_type)
+    }
+
+    fn http_get(&self, url: &str) -> Result<String, String> {
+        let response = HttpRequest::builder()
+            .method(HttpMethod::Get)
+            .url(url)
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "zed-cangjie-extension")
+            .redirect_policy(RedirectPolicy::FollowAll)
+            .build()?
+            .fetch()?;
+        String::from_utf8(response.body).map_err(|e| format!("{}: {}", url, e))
+    }
+}
+
+/// Builds the key identifying a call in a recording
+fn call_key(kind: &str, arg: impl Display) -> String {
+    let arg = arg.to_string();
+    if arg.is_empty() {
+        kind.to_string()
+    } else {
+        format!("{} {}", kind, arg)
+    }
+}
+
+fn selector_key(repo: &str, selector: ReleaseSelector) -> String {
+    match selector {
+        ReleaseSelector::Latest { pre_release } => call_key(
+            "release",
+            format!("{} latest pre_release={}", repo, pre_release),
+        ),
+        ReleaseSelector::Tag(tag) => call_key("release", format!("{} tag={}", repo, tag)),
+    }
+}
+
+fn file_type_name(file_type: &DownloadedFileType) -> &'static str {
+    match file_type {
+        DownloadedFileType::Gzip => "gzip",
+        DownloadedFileType::GzipTar => "tar.gz",
+        DownloadedFileType::Zip => "zip",
+        DownloadedFileType::Uncompressed => "uncompressed",
+    }
+}
+
+fn encode_result<T>(result: &Result<T, String>, encode: impl Fn(&T) -> Value) -> Value {
+    match result {
+        Ok(value) => json!({ "ok": encode(value) }),
+        Err(e) => json!({ "err": e }),
+    }
+}
+
+#[cfg(test)]
+fn decode_result(value: &Value) -> Result<Value, String> {
+    match (value.get("ok"), value.get("err")) {
+        (Some(ok), _) => Ok(ok.clone()),
+        (None, Some(err)) => Err(err.as_str().unwrap_or_default().to_string()),
+        _ => Err(format!("Malformed recorded result: {}", value)),
+    }
+}
+
+/// Serializes release metadata as `{"version", "assets": [{"name", "download_url"}]}`
+pub fn encode_release(release: &zed::GithubRelease) -> Value {
+    json!({
+        "version": release.version,
+        "assets": release
+            .assets
+            .iter()
+            .map(|asset| json!({ "name": asset.name, "download_url": asset.download_url }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// Restores release metadata written by [`encode_release`]
+pub fn decode_release(value: &Value) -> Result<zed::GithubRelease, String> {
+    let text = |value: &Value, key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| format!("Release metadata without `{}`", key))
+    };
+    Ok(zed::GithubRelease {
+        version: text(value, "version")?,
+        assets: value
+            .get("assets")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .map(|asset| {
+                Ok(zed::GithubReleaseAsset {
+                    name: text(asset, "name")?,
+                    download_url: text(asset, "download_url")?,
+                })
+            })
+            .collect::<Result<_, String>>()?,
+    })
+}
+
+fn encode_output(output: &ProcessOutput) -> Value {
+    json!({ "status": output.status, "stdout": output.stdout, "stderr": output.stderr })
+}
+
+#[cfg(test)]
+fn decode_output(value: &Value) -> ProcessOutput {
+    let text = |key: &str| value.get(key).and_then(Value::as_str).unwrap_or_default();
+    ProcessOutput {
+        status: value
+            .get("status")
+            .and_then(Value::as_i64)
+            .map(|status| status as i32),
+        stdout: text("stdout").to_string(),
+        stderr: text("stderr").to_string(),
+    }
+}
+
+fn path_list(paths: &[PathBuf]) -> Value {
+    paths
+        .iter()
+        .map(|path| Value::String(path.to_string_lossy().to_string()))
+        .collect()
+}
+
+/// Records every answer of the wrapped host
+pub struct Recorder<H> {
+    inner: H,
+    interactions: RefCell<Vec<(String, Value)>>,
+}
+
+impl<H: HostServices> Recorder<H> {
+    pub fn new(inner: H) -> Self {
+        Self {
+            inner,
+            interactions: RefCell::new(Vec::new()),
+        }
+    }
+
+    fn record<T>(&self, call: String, value: T, encode: impl Fn(&T) -> Value) -> T {
+        self.interactions.borrow_mut().push((call, encode(&value)));
+        value
+    }
+
+    /// Returns the recording, loadable with [`Replay::from_json`]
+    pub fn to_json(&self) -> Value {
+        let interactions: Vec<Value> = self
+            .interactions
+            .borrow()
+            .iter()
+            .map(|(call, response)| json!({ "call": call, "response": response }))
+            .collect();
+        json!({ "interactions": interactions })
+    }
+}
+
+impl<H: HostServices> HostServices for Recorder<H> {
+    fn worktree_root(&self) -> String {
+        self.record(
+            call_key("worktree_root", ""),
+            self.inner.worktree_root(),
+            |root| json!(root),
+        )
+    }
+
+    fn setting(&self, key: &str) -> Option<Value> {
+        self.record(call_key("setting", key), self.inner.setting(key), |value| {
+            value.clone().unwrap_or(Value::Null)
+        })
+    }
+
+    fn lsp_binary_override(&self) -> Option<String> {
+        let path = self.inner.lsp_binary_override();
+        self.record(call_key("lsp_binary_override", ""), path, |path| {
+            json!(path)
+        })
+    }
+
+    fn tool_binary_override(&self) -> Option<String> {
+        let path = self.inner.tool_binary_override();
+        self.record(call_key("tool_binary_override", ""), path, |path| {
+            json!(path)
+        })
+    }
+
+    fn read_worktree_file(&self, path: &str) -> Result<String, String> {
+        let contents = self.inner.read_worktree_file(path);
+        self.record(call_key("read_worktree_file", path), contents, |r| {
+            encode_result(r, |text| json!(text))
+        })
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        self.record(call_key("env", name), self.inner.env_var(name), |value| {
+            json!(value)
+        })
+    }
+
+    fn which(&self, name: &str) -> Option<String> {
+        self.record(call_key("which", name), self.inner.which(name), |path| {
+            json!(path)
+        })
+    }
+
+    fn unix_time(&self) -> u64 {
+        self.record(call_key("unix_time", ""), self.inner.unix_time(), |time| {
+            json!(time)
+        })
+    }
+
+    fn current_exe(&self) -> Result<PathBuf, String> {
+        self.record(call_key("current_exe", ""), self.inner.current_exe(), |r| {
+            encode_result(r, |path| json!(path.to_string_lossy()))
+        })
+    }
+
+    fn current_dir(&self) -> Result<PathBuf, String> {
+        self.record(call_key("current_dir", ""), self.inner.current_dir(), |r| {
+            encode_result(r, |path| json!(path.to_string_lossy()))
+        })
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        let key = call_key("is_dir", path.display());
+        self.record(key, self.inner.is_dir(path), |value| json!(value))
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        let key = call_key("is_file", path.display());
+        self.record(key, self.inner.is_file(path), |value| json!(value))
+    }
+
+    fn subdirectories(&self, path: &Path) -> Vec<PathBuf> {
+        let key = call_key("subdirectories", path.display());
+        self.record(key, self.inner.subdirectories(path), |dirs| path_list(dirs))
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, String> {
+        let key = call_key("canonicalize", path.display());
+        self.record(key, self.inner.canonicalize(path), |r| {
+            encode_result(r, |path| json!(path.to_string_lossy()))
+        })
+    }
+
+    fn read_text_file(&self, path: &Path) -> Result<String, String> {
+        let key = call_key("read_text_file", path.display());
+        self.record(key, self.inner.read_text_file(path), |r| {
+            encode_result(r, |text| json!(text))
+        })
+    }
+
+    fn sha256_file(&self, path: &Path) -> Result<String, String> {
+        let key = call_key("sha256_file", path.display());
+        self.record(key, self.inner.sha256_file(path), |r| {
+            encode_result(r, |digest| json!(digest))
+        })
+    }
+
+    fn modified_time(&self, path: &Path) -> Option<u64> {
+        let key = call_key("modified_time", path.display());
+        self.record(key, self.inner.modified_time(path), |time| json!(time))
+    }
+
+    fn write_text_file(&self, path: &Path, contents: &str) -> Result<(), String> {
+        let key = call_key("write_text_file", path.display());
+        self.record(key, self.inner.write_text_file(path, contents), |r| {
+            encode_result(r, |_| Value::Null)
+        })
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<(), String> {
+        let key = call_key("create_dir_all", path.display());
+        self.record(key, self.inner.create_dir_all(path), |r| {
+            encode_result(r, |_| Value::Null)
+        })
+    }
+
+    fn remove_path(&self, path: &Path) {
+        self.inner.remove_path(path);
+        self.record(call_key("remove_path", path.display()), (), |_| Value::Null)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), String> {
+        let key = call_key("rename", format!("{} -> {}", from.display(), to.display()));
+        self.record(key, self.inner.rename(from, to), |r| {
+            encode_result(r, |_| Value::Null)
+        })
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.inner.sleep(duration);
+        let key = call_key("sleep", format!("{}ms", duration.as_millis()));
+        self.record(key, (), |_| Value::Null)
+    }
+
+    fn make_executable(&self, path: &Path) -> Result<(), String> {
+        let key = call_key("make_executable", path.display());
+        self.record(key, self.inner.make_executable(path), |r| {
+            encode_result(r, |_| Value::Null)
+        })
+    }
+
+    fn run_with_env(
+        &self,
+        program: &str,
+        args: &[String],
+        env: &[(String, String)],
+    ) -> Result<ProcessOutput, String> {
+        // The environment is not part of the key, so recordings stay readable
+        let key = call_key("run", format!("{} {}", program, args.join(" ")).trim_end());
+        self.record(key, self.inner.run_with_env(program, args, env), |r| {
+            encode_result(r, encode_output)
+        })
+    }
+
+    fn github_release(
+        &self,
+        repo: &str,
+        selector: ReleaseSelector,
+    ) -> Result<zed::GithubRelease, String> {
+        let key = selector_key(repo, selector);
+        self.record(key, self.inner.github_release(repo, selector), |r| {
+            encode_result(r, encode_release)
+        })
+    }
+
+    fn download(
+        &self,
+        url: &str,
+        path: &Path,
+        file_type: DownloadedFileType,
+    ) -> Result<(), String> {
+        let key = call_key(
+            "download",
+            format!(
+                "{} {} -> {}",
+                file_type_name(&file_type),
+                url,
+                path.display()
+            ),
+        );
+        self.record(key, self.inner.download(url, path, file_type), |r| {
+            encode_result(r, |_| Value::Null)
+        })
+    }
+
+    fn http_get(&self, url: &str) -> Result<String, String> {
+        let key = call_key("http_get", url);
+        self.record(key, self.inner.http_get(url), |r| {
+            encode_result(r, |body| json!(body))
+        })
+    }
+}
+
+/// Answers calls from a recording.
+///
+/// Repeated calls consume recorded answers in order; the last answer is kept for further
+/// calls. Unrecorded queries get the "absent" answer (no setting, no file, no directory) and
+/// unrecorded actions fail (except creating directories, writing files, renaming and marking
+/// files executable), so a recording only needs to list what exists. Written files can be
+/// read back. Every unrecorded call is remembered in [`Replay::misses`].
+#[cfg(test)]
+#[derive(Default)]
+pub struct Replay {
+    responses: RefCell<HashMap<String, VecDeque<Value>>>,
+    misses: RefCell<Vec<String>>,
+}
+
+#[cfg(test)]
+impl Replay {
+    /// Loads a recording written by [`Recorder::to_json`]
+    pub fn from_json(recording: &Value) -> Result<Self, String> {
+        let interactions = recording
+            .get("interactions")
+            .and_then(Value::as_array)
+            .ok_or("Recording has no `interactions` array")?;
+        let mut replay = Self::default();
+        for interaction in interactions {
+            let call = interaction
+                .get("call")
+                .and_then(Value::as_str)
+                .ok_or("Recorded interaction without `call`")?;
+            let response = interaction.get("response").cloned().unwrap_or(Value::Null);
+            replay = replay.answer(call, response);
+        }
+        Ok(replay)
+    }
+
+    /// Adds a recorded answer
+    pub fn answer(self, call: &str, response: Value) -> Self {
+        self.responses
+            .borrow_mut()
+            .entry(call.to_string())
+            .or_default()
+            .push_back(response);
+        self
+    }
+
+    /// Returns the calls that had no recorded answer
+    pub fn misses(&self) -> Vec<String> {
+        self.misses.borrow().clone()
+    }
+
+    fn lookup(&self, call: String) -> Option<Value> {
+        let mut responses = self.responses.borrow_mut();
+        match responses.get_mut(&call) {
+            Some(queue) if queue.len() > 1 => queue.pop_front(),
+            Some(queue) => queue.front().cloned(),
+            None => {
+                self.misses.borrow_mut().push(call);
+                None
+            }
+        }
+    }
+
+    fn lookup_result(&self, call: String) -> Result<Value, String> {
+        match self.lookup(call.clone()) {
+            Some(response) => decode_result(&response),
+            None => Err(format!("No recorded answer for `{}`", call)),
+        }
+    }
+
+    fn lookup_str(&self, call: String) -> Option<String> {
+        self.lookup(call)
+            .and_then(|value| value.as_str().map(str::to_string))
+    }
+}
+
+#[cfg(test)]
+impl HostServices for Replay {
+    fn worktree_root(&self) -> String {
+        self.lookup_str(call_key("worktree_root", ""))
+            .unwrap_or_default()
+    }
+
+    fn setting(&self, key: &str) -> Option<Value> {
+        self.lookup(call_key("setting", key))
+            .filter(|value| !value.is_null())
+    }
+
+    fn lsp_binary_override(&self) -> Option<String> {
+        self.lookup_str(call_key("lsp_binary_override", ""))
+    }
+
+    fn tool_binary_override(&self) -> Option<String> {
+        self.lookup_str(call_key("tool_binary_override", ""))
+    }
+
+    fn read_worktree_file(&self, path: &str) -> Result<String, String> {
+        self.lookup_result(call_key("read_worktree_file", path))
+            .map(|text| text.as_str().unwrap_or_default().to_string())
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        self.lookup_str(call_key("env", name))
+    }
+
+    fn which(&self, name: &str) -> Option<String> {
+        self.lookup_str(call_key("which", name))
+    }
+
+    fn unix_time(&self) -> u64 {
+        self.lookup(call_key("unix_time", ""))
+            .and_then(|time| time.as_u64())
+            .unwrap_or_default()
+    }
+
+    fn current_exe(&self) -> Result<PathBuf, String> {
+        self.lookup_result(call_key("current_exe", ""))
+            .map(|path| PathBuf::from(path.as_str().unwrap_or_default()))
+    }
+
+    fn current_dir(&self) -> Result<PathBuf, String> {
+        self.lookup_result(call_key("current_dir", ""))
+            .map(|path| PathBuf::from(path.as_str().unwrap_or_default()))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.lookup(call_key("is_dir", path.display()))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false)
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.lookup(call_key("is_file", path.display()))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false)
+    }
+
+    fn subdirectories(&self, path: &Path) -> Vec<PathBuf> {
+        self.lookup(call_key("subdirectories", path.display()))
+            .and_then(|value| value.as_array().cloned())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(Value::as_str)
+            .map(PathBuf::from)
+            .collect()
+    }
+
+    fn canonicalize(&self, path: &Path) -> Result<PathBuf, String> {
+        // Paths are taken as canonical unless a link target was recorded
+        match self.lookup(call_key("canonicalize", path.display())) {
+            Some(response) => decode_result(&response)
+                .map(|target| PathBuf::from(target.as_str().unwrap_or_default())),
+            None => Ok(path.to_path_buf()),
+        }
+    }
+
+    fn read_text_file(&self, path: &Path) -> Result<String, String> {
+        self.lookup_result(call_key("read_text_file", path.display()))
+            .map(|text| text.as_str().unwrap_or_default().to_string())
+    }
+
+    fn sha256_file(&self, path: &Path) -> Result<String, String> {
+        self.lookup_result(call_key("sha256_file", path.display()))
+            .map(|digest| digest.as_str().unwrap_or_default().to_string())
+    }
+
+    fn modified_time(&self, path: &Path) -> Option<u64> {
+        self.lookup(call_key("modified_time", path.display()))
+            .and_then(|time| time.as_u64())
+    }
+
+    fn write_text_file(&self, path: &Path, contents: &str) -> Result<(), String> {
+        if let Some(response) = self.lookup(call_key("write_text_file", path.display())) {
+            decode_result(&response)?;
+        }
+        // Later reads see the written contents
+        let read = call_key("read_text_file", path.display());
+        let mut responses = self.responses.borrow_mut();
+        let queue = responses.entry(read).or_default();
+        queue.clear();
+        queue.push_back(json!({ "ok": contents }));
+        Ok(())
+    }
+
+    fn create_dir_all(&self, path: &Path) -> Result<(), String> {
+        // Creating directories always succeeds unless a failure was recorded
+        match self.lookup(call_key("create_dir_all", path.display())) {
+            Some(response) => decode_result(&response).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    fn remove_path(&self, path: &Path) {
+        self.lookup(call_key("remove_path", path.display()));
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<(), String> {
+        let key = call_key("rename", format!("{} -> {}", from.display(), to.display()));
+        match self.lookup(key) {
+            Some(response) => decode_result(&response).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    fn sleep(&self, _duration: Duration) {
+        // Replays never wait
+    }
+
+    fn make_executable(&self, path: &Path) -> Result<(), String> {
+        match self.lookup(call_key("make_executable", path.display())) {
+            Some(response) => decode_result(&response).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    fn run_with_env(
+        &self,
+        program: &str,
+        args: &[String],
+        _env: &[(String, String)],
+    ) -> Result<ProcessOutput, String> {
+        let key = call_key("run", format!("{} {}", program, args.join(" ")).trim_end());
+        self.lookup_result(key).map(|output| decode_output(&output))
+    }
+
+    fn github_release(
+        &self,
+        repo: &str,
+        selector: ReleaseSelector,
+    ) -> Result<zed::GithubRelease, String> {
+        self.lookup_result(selector_key(repo, selector))
+            .and_then(|release| decode_release(&release))
+    }
+
+    fn download(
+        &self,
+        url: &str,
+        path: &Path,
+        file_type: DownloadedFileType,
+    ) -> Result<(), String> {
+        let key = call_key(
+            "download",
+            format!(
+                "{} {} -> {}",
+                file_type_name(&file_type),
+                url,
+                path.display()
+            ),
+        );
+        self.lookup_result(key).map(|_| ())
+    }
+
+    fn http_get(&self, url: &str) -> Result<String, String> {
+        self.lookup_result(call_key("http_get", url))
+            .map(|body| body.as_str().unwrap_or_default().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_replay() {
+        // 测试录制的宿主应答可以离线回放
+        let source = Replay::default()
+            .answer("is_dir /opt/cangjie/bin", json!(true))
+            .answer("env CANGJIE_HOME", json!("/opt/cangjie"))
+            .answer(
+                "run cjc --version",
+                json!({ "ok": { "status": 0, "stdout": "cjc 1.0.0", "stderr": "" } }),
+            )
+            .answer(
+                "release cangjie-lang/cangjie tag=v1.0.0",
+                json!({ "ok": { "version": "v1.0.0", "assets": [
+                    { "name": "cangjie-lsp", "download_url": "https://example.com/lsp" }
+                ] } }),
+            );
+        let recorder = Recorder::new(source);
+        assert!(recorder.is_dir(Path::new("/opt/cangjie/bin")));
+        assert!(!recorder.is_file(Path::new("/opt/cangjie/bin/cjc")));
+        assert_eq!(
+            recorder.env_var("CANGJIE_HOME").as_deref(),
+            Some("/opt/cangjie")
+        );
+        let output = recorder.run("cjc", &["--version".to_string()]).unwrap();
+        assert_eq!(output.stdout, "cjc 1.0.0");
+        let release = recorder
+            .github_release("cangjie-lang/cangjie", ReleaseSelector::Tag("v1.0.0"))
+            .unwrap();
+        assert_eq!(release.assets[0].name, "cangjie-lsp");
+
+        let replay = Replay::from_json(&recorder.to_json()).unwrap();
+        assert!(replay.is_dir(Path::new("/opt/cangjie/bin")));
+        assert!(!replay.is_file(Path::new("/opt/cangjie/bin/cjc")));
+        assert_eq!(
+            replay
+                .run("cjc", &["--version".to_string()])
+                .unwrap()
+                .status,
+            Some(0)
+        );
+        assert_eq!(
+            replay
+                .github_release("cangjie-lang/cangjie", ReleaseSelector::Tag("v1.0.0"))
+                .unwrap()
+                .version,
+            "v1.0.0"
+        );
+        assert!(replay.misses().is_empty());
+        assert!(
+            replay
+                .download("u", Path::new("/x"), DownloadedFileType::Zip)
+                .is_err()
+        );
+        assert_eq!(replay.misses(), vec!["download zip u -> /x"]);
+    }
+}
@@ -0,0 +1,164 @@
+// src/humanize.rs
+//! Human-readable formatting of counts, sizes and durations in slash command output.
+//!
+//! Digit grouping and the decimal mark follow the `cangjie.output.locale` setting; unit
+//! names are not translated.
+
+use std::time::Duration;
+
+/// Units of [`format_size`], in steps of 1024
+const SIZE_UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+
+/// Units of [`format_nanos`], in steps of 1000
+const TIME_UNITS: &[&str] = &["ns", "µs", "ms", "s"];
+
+/// Separators used when formatting numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumberFormat {
+    /// Separator between groups of three digits, or `None` for no grouping
+    pub group_separator: Option<char>,
+    /// Decimal mark
+    pub decimal_separator: char,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            group_separator: Some(','),
+            decimal_separator: '.',
+        }
+    }
+}
+
+impl NumberFormat {
+    /// Returns the separators of a locale tag such as `en-US`, `de` or `zh_CN`.
+    ///
+    /// `C` and `POSIX` disable digit grouping; unknown languages use the English separators.
+    pub fn for_locale(tag: &str) -> Self {
+        let tag = tag.trim().replace('_', "-").to_ascii_lowercase();
+        let (language, region) = tag.split_once('-').unwrap_or((tag.as_str(), ""));
+        let (group, decimal) = match (language, region) {
+            ("c" | "posix", _) => (None, '.'),
+            ("de" | "it", "ch") => (Some('\''), '.'),
+            ("de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da", _) => (Some('.'), ','),
+            ("fr" | "ru" | "uk" | "pl" | "cs" | "sv" | "fi" | "nb" | "no", _) => {
+                (Some('\u{202f}'), ',')
+            }
+            _ => (Some(','), '.'),
+        };
+        Self {
+            group_separator: group,
+            decimal_separator: decimal,
+        }
+    }
+}
+
+/// Formats a count with digit grouping, e.g. `12,345`
+pub fn format_count(value: u64, format: &NumberFormat) -> String {
+    let digits = value.to_string();
+    let Some(separator) = format.group_separator else {
+        return digits;
+    };
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Formats a number with one decimal place, e.g. `1,234.5`
+fn format_decimal(value: f64, format: &NumberFormat) -> String {
+    let tenths = (value * 10.0).round() as u64;
+    format!(
+        "{}{}{}",
+        format_count(tenths / 10, format),
+        format.decimal_separator,
+        tenths % 10
+    )
+}
+
+/// Formats a byte count with binary units, e.g. `512 B` or `1.5 MiB`
+pub fn format_size(bytes: u64, format: &NumberFormat) -> String {
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < SIZE_UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", format_count(bytes, format), SIZE_UNITS[0])
+    } else {
+        format!("{} {}", format_decimal(value, format), SIZE_UNITS[unit])
+    }
+}
+
+/// Formats a duration, e.g. `850 ms`, `12.3 s` or `2 min 05 s`
+pub fn format_duration(duration: Duration, format: &NumberFormat) -> String {
+    let millis = duration.as_millis() as u64;
+    if millis < 1000 {
+        format!("{} ms", millis)
+    } else if millis < 60_000 {
+        format!("{} s", format_decimal(millis as f64 / 1000.0, format))
+    } else {
+        let seconds = duration.as_secs();
+        format!(
+            "{} min {:02} s",
+            format_count(seconds / 60, format),
+            seconds % 60
+        )
+    }
+}
+
+/// Formats a benchmark time given in nanoseconds, e.g. `12.3 ns` or `4.5 ms`
+pub fn format_nanos(nanos: f64, format: &NumberFormat) -> String {
+    let mut value = nanos;
+    let mut unit = 0;
+    while value >= 1000.0 && unit < TIME_UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    format!("{} {}", format_decimal(value, format), TIME_UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_humanize() {
+        // 测试按区域设置格式化数量、大小与时长
+        let en = NumberFormat::default();
+        let de = NumberFormat::for_locale("de_DE");
+        assert_eq!(format_count(1234567, &en), "1,234,567");
+        assert_eq!(format_count(123, &en), "123");
+        assert_eq!(format_count(1234567, &de), "1.234.567");
+        assert_eq!(
+            format_count(12345, &NumberFormat::for_locale("de-CH")),
+            "12'345"
+        );
+        assert_eq!(NumberFormat::for_locale("zh-CN"), en);
+        assert_eq!(format_count(12345, &NumberFormat::for_locale("C")), "12345");
+
+        assert_eq!(format_size(512, &en), "512 B");
+        assert_eq!(format_size(1536, &en), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024 + 1024 * 512, &de), "3,5 MiB");
+
+        assert_eq!(format_duration(Duration::from_millis(850), &en), "850 ms");
+        assert_eq!(
+            format_duration(Duration::from_millis(12_340), &en),
+            "12.3 s"
+        );
+        assert_eq!(
+            format_duration(Duration::from_millis(12_340), &de),
+            "12,3 s"
+        );
+        assert_eq!(format_duration(Duration::from_secs(125), &en), "2 min 05 s");
+
+        assert_eq!(format_nanos(12.34, &en), "12.3 ns");
+        assert_eq!(format_nanos(4_560_000.0, &de), "4,6 ms");
+        assert_eq!(format_nanos(7_200_000_000_000.0, &en), "7,200.0 s");
+    }
+}
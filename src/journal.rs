@@ -0,0 +1,215 @@
+// src/journal.rs
+//! Append-only journal of the changes the extension makes to the machine, for
+//! `/cangjie-journal`.
+//!
+//! Every install, move and removal outside the worktree is recorded as one JSON line in
+//! [`JOURNAL_FILE`] with the time, the path, the reason and, for reversible actions, a hint
+//! on how to undo it. Entries are never rewritten; lines that fail to parse are skipped.
+
+use serde_json::{Value, json};
+
+/// File in the extension's working directory holding the journal
+pub const JOURNAL_FILE: &str = "journal.jsonl";
+
+/// Entries shown by `/cangjie-journal` without a count
+pub const DEFAULT_LIMIT: usize = 20;
+
+/// What the extension did to a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Files were downloaded or written
+    Install,
+    /// A file or directory was moved; the entry's path is the destination
+    Move,
+    /// A file or directory was deleted
+    Delete,
+}
+
+impl Action {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Action::Install => "install",
+            Action::Move => "move",
+            Action::Delete => "delete",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "install" => Some(Action::Install),
+            "move" => Some(Action::Move),
+            "delete" => Some(Action::Delete),
+            _ => None,
+        }
+    }
+
+    /// Past-tense label shown in the journal
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Install => "Installed",
+            Action::Move => "Moved",
+            Action::Delete => "Deleted",
+        }
+    }
+}
+
+/// A journal entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    pub action: Action,
+    pub path: String,
+    /// Why the extension made the change
+    pub reason: String,
+    /// How to revert the change, for reversible actions
+    pub undo: Option<String>,
+}
+
+impl Entry {
+    /// Serializes the entry as one journal line, including the newline
+    pub fn to_line(&self) -> String {
+        let mut value = json!({
+            "time": self.time,
+            "action": self.action.as_str(),
+            "path": self.path,
+            "reason": self.reason,
+        });
+        if let Some(undo) = &self.undo {
+            value["undo"] = json!(undo);
+        }
+        format!("{}\n", value)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        Some(Entry {
+            time: value.get("time")?.as_u64()?,
+            action: Action::parse(value.get("action")?.as_str()?)?,
+            path: value.get("path")?.as_str()?.to_string(),
+            reason: value.get("reason")?.as_str()?.to_string(),
+            undo: value
+                .get("undo")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        })
+    }
+}
+
+/// Appends an entry to the journal.
+///
+/// # Arguments
+/// * `journal` - Current contents of [`JOURNAL_FILE`], if any
+/// * `entry` - The entry to append
+///
+/// # Returns
+/// * `String` - The new contents of [`JOURNAL_FILE`]
+pub fn append(journal: Option<&str>, entry: &Entry) -> String {
+    let mut contents = journal.unwrap_or_default().to_string();
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    contents.push_str(&entry.to_line());
+    contents
+}
+
+/// Parses the journal, oldest entry first, skipping malformed lines
+pub fn parse(journal: &str) -> Vec<Entry> {
+    journal
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter_map(|value| Entry::from_value(&value))
+        .collect()
+}
+
+/// Formats a Unix time as `YYYY-MM-DD HH:MM:SS UTC`
+pub fn format_time(time: u64) -> String {
+    let days = (time / 86_400) as i64;
+    let seconds = time % 86_400;
+    // Civil date from days since 1970-01-01, after Howard Hinnant's `civil_from_days`
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3_600,
+        seconds % 3_600 / 60,
+        seconds % 60
+    )
+}
+
+/// Renders the newest entries, newest first.
+///
+/// # Arguments
+/// * `entries` - The journal entries, oldest first
+/// * `limit` - How many entries to show; `None` shows all
+///
+/// # Returns
+/// * `String` - One Markdown list item per entry, with undo hints
+pub fn render(entries: &[Entry], limit: Option<usize>) -> String {
+    let limit = limit.unwrap_or(entries.len());
+    entries
+        .iter()
+        .rev()
+        .take(limit)
+        .map(|entry| {
+            let mut item = format!(
+                "- {} {} `{}`: {}",
+                format_time(entry.time),
+                entry.action.label(),
+                entry.path,
+                entry.reason
+            );
+            if let Some(undo) = &entry.undo {
+                item.push_str(&format!("\n  💡 Undo: {}", undo));
+            }
+            item
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal() {
+        // 测试日志追加、解析（跳过损坏行）与按时间倒序显示
+        let install = Entry {
+            time: 1_792_108_800,
+            action: Action::Install,
+            path: "cangjie-lsp-v1.0.0".to_string(),
+            reason: "language server release v1.0.0".to_string(),
+            undo: Some("delete `cangjie-lsp-v1.0.0`".to_string()),
+        };
+        let delete = Entry {
+            time: 1_792_112_461,
+            action: Action::Delete,
+            path: "cangjie-lsp-v0.9.0".to_string(),
+            reason: "replaced by v1.0.0".to_string(),
+            undo: None,
+        };
+        let journal = append(Some("not json"), &install);
+        let journal = append(Some(&journal), &delete);
+        let entries = parse(&journal);
+        assert_eq!(entries, vec![install, delete]);
+
+        assert_eq!(format_time(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_time(1_792_112_461), "2026-10-16 01:01:01 UTC");
+        assert_eq!(
+            render(&entries, Some(1)),
+            "- 2026-10-16 01:01:01 UTC Deleted `cangjie-lsp-v0.9.0`: replaced by v1.0.0"
+        );
+        assert!(render(&entries, None).ends_with("💡 Undo: delete `cangjie-lsp-v1.0.0`"));
+    }
+}
@@ -1,34 +1,277 @@
 // src/lib.rs
+mod apidoc;
+mod assets;
+mod bench;
+mod build;
+mod checksum;
+mod clean;
+mod cjpm;
+mod collate;
+mod completion;
+mod compat;
+mod debug;
+mod deveco;
+mod diagnostics;
+mod environment;
+mod fingerprint;
+mod formatter;
+mod host;
+mod hints;
+mod journal;
+mod lint;
+mod lsp_config;
+mod humanize;
+mod output;
+mod paths;
+mod prefetch;
+mod run_config;
+mod sdk_layout;
+mod smoke;
+mod snapshot;
+mod tasks;
+mod testing;
+mod tool_versions;
+mod toolchain;
+mod updates;
+mod version;
+
 use std::collections::HashMap;
-use std::env;
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use host::{HostServices, ZedHost};
+use output::{OutputBuilder, Severity, SourceLocation, push_output_section};
 use zed_extension_api::{self as zed, Architecture, Os, process::Command, settings::LspSettings};
 
 /// Constants for tool names
 const SERVER_NAME: &str = "cangjie-lsp";
 const CJC_NAME: &str = "cjc";
 const CJC_FRONTEND_NAME: &str = "cjc-frontend";
+const CJPM_NAME: &str = "cjpm";
+const CJFMT_NAME: &str = "cjfmt";
+const CJLINT_NAME: &str = "cjlint";
+const CJDB_NAME: &str = "cjdb";
+
+/// Name of the cjlint language server declared in `language_servers`
+const LINT_SERVER_NAME: &str = "cangjie-lint";
+
+/// Arguments cjlint is started with as a language server unless `cangjie.lint.args` is set
+const LINT_SERVER_DEFAULT_ARGS: &[&str] = &["--lsp"];
+
+/// Name of the debug adapter declared in `debug_adapter_schemas`
+const DEBUG_ADAPTER_NAME: &str = "Cangjie";
+
+/// Arguments cjdb is started with as a debug adapter unless `cangjie.debug.adapterArgs` is set
+const CJDB_DAP_ARGS: &[&str] = &["--dap"];
 
 /// Configuration keys for extension settings
 const CONFIG_SDK_PATH_KEY: &str = "cangjie.sdkPath";
 const CONFIG_CJC_PATH_KEY: &str = "cangjie.cjcPathOverride";
 const CONFIG_CJC_FRONTEND_PATH_KEY: &str = "cangjie.cjcFrontendPathOverride";
+const CONFIG_CJPM_PATH_KEY: &str = "cangjie.cjpmPathOverride";
+const CONFIG_SDK_FLAVOR_KEY: &str = "cangjie.sdk.flavor";
+const CONFIG_EXTRA_BIN_DIRS_KEY: &str = "cangjie.extraBinDirs";
+const CONFIG_TOOLCHAIN_CHANNEL_KEY: &str = "cangjie.toolchain.channel";
+const CONFIG_IGNORE_VERSION_MISMATCH_KEY: &str = "cangjie.lsp.ignoreVersionMismatch";
+const CONFIG_LSP_VERSION_KEY: &str = "cangjie.lsp.version";
+const CONFIG_LSP_PRE_RELEASE_KEY: &str = "cangjie.lsp.preRelease";
+const CONFIG_LSP_AUTO_UPDATE_KEY: &str = "cangjie.lsp.autoUpdate";
+const CONFIG_LSP_PREFETCH_KEY: &str = "cangjie.lsp.prefetch";
+const CONFIG_LSP_LOG_LEVEL_KEY: &str = "cangjie.lsp.logLevel";
+const CONFIG_LSP_LOG_FILE_KEY: &str = "cangjie.lsp.logFile";
+const CONFIG_NETWORK_OFFLINE_KEY: &str = "cangjie.network.offline";
+const CONFIG_NETWORK_METERED_KEY: &str = "cangjie.network.metered";
+const CONFIG_SEMANTIC_HIGHLIGHTING_KEY: &str = "cangjie.enableSemanticHighlighting";
+const CONFIG_MAX_FILE_SIZE_KEY: &str = "cangjie.maxFileSize";
+const CONFIG_CHECK_ON_SAVE_KEY: &str = "cangjie.checkOnSave";
+const CONFIG_MODULE_PATH_KEY: &str = "cangjie.modulePath";
+const CONFIG_BUILD_EXTRA_ARGS_KEY: &str = "cangjie.build.extraArgs";
+const CONFIG_BUILD_DEFAULT_PROFILE_KEY: &str = "cangjie.build.defaultProfile";
+const CONFIG_BUILD_TARGETS_KEY: &str = "cangjie.build.targets";
+const CONFIG_OHOS_DEVICE_KEY: &str = "cangjie.ohos.device";
+const CONFIG_LINT_ENABLED_KEY: &str = "cangjie.lint.enabled";
+const CONFIG_LINT_ARGS_KEY: &str = "cangjie.lint.args";
+const CONFIG_LINT_SETTINGS_KEY: &str = "cangjie.lint.settings";
+const CONFIG_STDLIB_PATH_KEY: &str = "cangjie.stdlibPath";
+const CONFIG_WATCH_PATTERNS_KEY: &str = "cangjie.files.watch";
+const CONFIG_DEBUG_ADAPTER_ARGS_KEY: &str = "cangjie.debug.adapterArgs";
+const CONFIG_DEBUG_TCP_HOST_KEY: &str = "cangjie.debug.tcp.host";
+const CONFIG_DEBUG_TCP_PORT_KEY: &str = "cangjie.debug.tcp.port";
+const CONFIG_DEBUG_TCP_TIMEOUT_KEY: &str = "cangjie.debug.tcp.timeout";
+const CONFIG_DOWNLOAD_MIRROR_KEY: &str = "cangjie.downloadMirror";
+const CONFIG_DOWNLOAD_DIR_KEY: &str = "cangjie.download.dir";
+const CONFIG_TEST_PARALLELISM_KEY: &str = "cangjie.test.parallelism";
+const CONFIG_TEST_NETWORK_ISOLATION_KEY: &str = "cangjie.test.networkIsolation";
+const CONFIG_OUTPUT_LOCALE_KEY: &str = "cangjie.output.locale";
+const CONFIG_OUTPUT_MAX_SECTION_LINES_KEY: &str = "cangjie.output.maxSectionLines";
+const CONFIG_OUTPUT_COLLATION_KEY: &str = "cangjie.output.collation";
+const CONFIG_OUTPUT_STYLE_KEY: &str = "cangjie.output.style";
+
+/// Lines shown per output section when `cangjie.output.maxSectionLines` is unset
+const DEFAULT_MAX_SECTION_LINES: usize = 200;
+
+/// Default of `cangjie.maxFileSize`, in bytes
+const DEFAULT_MAX_FILE_SIZE: u64 = 1_000_000;
+
+/// Files the language server watches before the entries of `cangjie.files.watch`
+const DEFAULT_WATCH_PATTERNS: &[&str] = &["**/cjpm.toml", "**/cjpm.lock", "**/*.cj"];
+
+/// In-memory state key holding the last LSP / SDK version mismatch warning
+/// Attempts made for each download before giving up
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// Delay before the first download retry; doubled for every further retry
+const DOWNLOAD_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Transport the language server is started with unless `binary.arguments` choose one
+const LSP_DEFAULT_TRANSPORT: &str = "--stdio";
+
+/// Language server flags that choose a transport
+const LSP_TRANSPORT_FLAGS: &[&str] = &["--stdio", "--pipe", "--socket", "--port"];
+
+/// Values accepted by `cangjie.lsp.logLevel`, passed to the server's `--log-level`
+const LSP_LOG_LEVELS: &[&str] = &["error", "warn", "info", "debug", "trace"];
+
+/// Language server flags that set the log level and log file
+const LSP_LOG_LEVEL_FLAG: &str = "--log-level";
+const LSP_LOG_PATH_FLAG: &str = "--log-path";
+
+/// Suffix of downloads that have not been verified yet
+const PARTIAL_SUFFIX: &str = ".partial";
+
+/// File written and removed again to check that a downloads directory is writable
+const WRITE_PROBE_FILE: &str = ".cangjie-write-probe";
+
+/// Directory below the system temp directory used when no other location is writable
+const TEMP_DOWNLOADS_DIR: &str = "zed-cangjie-downloads";
+
+const STATE_VERSION_WARNING_KEY: &str = "lsp_version_warning";
+const STATE_TEST_SEED_HISTORY_KEY: &str = "test_seed_history";
+const STATE_COMPATIBILITY_KEY: &str = "compatibility_gate";
+
+/// Settings that influence language server resolution, part of its fingerprint
+const RESOLUTION_SETTING_KEYS: &[&str] = &[
+    CONFIG_SDK_PATH_KEY,
+    CONFIG_CJC_PATH_KEY,
+    CONFIG_SDK_FLAVOR_KEY,
+    CONFIG_EXTRA_BIN_DIRS_KEY,
+    CONFIG_TOOLCHAIN_CHANNEL_KEY,
+    CONFIG_IGNORE_VERSION_MISMATCH_KEY,
+    CONFIG_LSP_VERSION_KEY,
+    CONFIG_LSP_PRE_RELEASE_KEY,
+    CONFIG_LSP_AUTO_UPDATE_KEY,
+    CONFIG_LSP_PREFETCH_KEY,
+];
+
+/// Environment variables that influence language server resolution
+const RESOLUTION_ENV_VARS: &[&str] = &[ENV_CANGJIE_HOME, "PATH"];
+
+/// In-memory state keys holding the directory language servers are downloaded to, and a
+/// description of where it came from
+const STATE_DOWNLOADS_DIR_KEY: &str = "downloads_dir";
+const STATE_DOWNLOADS_LOCATION_KEY: &str = "downloads_location";
+
+/// In-memory state key holding where the running language server came from
+const STATE_LSP_CHANNEL_KEY: &str = "lsp_channel";
+
+/// In-memory state key prefix of cached release metadata, followed by the release selection
+const STATE_RELEASE_CACHE_PREFIX: &str = "release_cache:";
+
+/// In-memory state key prefix of the launch configuration of a located debug scenario,
+/// followed by the label of its build task
+const STATE_DEBUG_LAUNCH_PREFIX: &str = "debug_launch:";
+
+/// In-memory state key holding the root and run configuration names of the worktree seen
+/// last, whose slash command arguments are completed
+const STATE_COMPLETION_WORKTREE_KEY: &str = "completion_worktree";
+
+/// In-memory state key holding the slash command completion index, with the time it was
+/// built and the worktree root it indexes
+const STATE_COMPLETION_INDEX_KEY: &str = "completion_index";
+
+/// Seconds a completion index is reused before the worktree's sources are indexed again
+const COMPLETION_INDEX_TTL_SECS: u64 = 30;
+
+/// In-memory state key holding the benchmark medians of the worktree's previous runs
+const STATE_BENCH_RESULTS_KEY: &str = "bench_results";
+
+/// In-memory state key prefix of the `--version` output of a binary, followed by its path and
+/// modification time
+const STATE_TOOL_VERSION_PREFIX: &str = "tool_version:";
+
+/// In-memory state key holding the SDK environment of the worktree, for the debug locator
+const STATE_SDK_ENVIRONMENT_KEY: &str = "sdk_environment";
+
+/// In-memory state key holding the language server release downloaded for the next start
+const STATE_LSP_UPDATE_KEY: &str = "lsp_update";
+
+/// How long cached release metadata is reused, in seconds
+const RELEASE_CACHE_TTL_SECS: u64 = 60 * 60;
+
+/// GitHub repository that publishes language server releases
+const LSP_RELEASE_REPO: &str = "cangjie-lang/cangjie";
+/// Rolling release tag that tracks the current LTS toolchain
+const LTS_RELEASE_TAG: &str = "lts";
 
 /// Environment variable for Cangjie SDK home directory
 const ENV_CANGJIE_HOME: &str = "CANGJIE_HOME";
+/// Project-local SDK directory, relative to the worktree root
+const LOCAL_SDK_DIR: &str = ".cangjie/sdk";
 
 /// Error messages
 #[allow(dead_code)]
 const ERR_SDK_NOT_FOUND: &str = "Cangjie SDK not found. Please set the 'CANGJIE_HOME' environment variable, set 'cangjie.sdkPath' in your project settings, or place this extension within a standard Cangjie SDK structure.";
 const ERR_TOOL_NOT_FOUND_FMT: &str = "Tool '{}' not found in SDK or overridden path.";
 
+/// Release channel of the Cangjie toolchain, selected via `cangjie.toolchain.channel`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToolchainChannel {
+    /// Latest stable release (default)
+    #[default]
+    Release,
+    /// Long-term support release
+    Lts,
+    /// Nightly builds, published as GitHub pre-releases
+    Nightly,
+}
+
+impl ToolchainChannel {
+    /// Parses a channel name from settings (case-insensitive)
+    pub fn from_setting(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "release" | "stable" => Some(Self::Release),
+            "lts" => Some(Self::Lts),
+            "nightly" => Some(Self::Nightly),
+            _ => None,
+        }
+    }
+
+    /// Returns the canonical setting value for this channel
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Release => "release",
+            Self::Lts => "lts",
+            Self::Nightly => "nightly",
+        }
+    }
+
+    /// Suffix of channel-specific SDK install directories (e.g. `/opt/cangjie-nightly`)
+    pub fn sdk_dir_suffix(&self) -> Option<&'static str> {
+        match self {
+            Self::Release => None,
+            Self::Lts | Self::Nightly => Some(self.as_str()),
+        }
+    }
+}
+
 /// Main extension struct for Cangjie language support
 pub struct CangjieExtension {
-    /// Cache for resolved tool paths to improve performance
-    cached_tool_paths: Arc<Mutex<HashMap<String, String>>>,
-    /// In-memory state store for temporary data
+    /// Cache for resolved tool paths, keyed by worktree root and then by tool
+    cached_tool_paths: Arc<Mutex<HashMap<String, HashMap<String, String>>>>,
+    /// Cache for resolved SDK roots, keyed by worktree root
+    cached_sdk_roots: Arc<Mutex<HashMap<String, sdk_layout::SdkRoot>>>,
+    /// In-memory state store for temporary data; per-worktree entries are keyed with
+    /// [`worktree_state_key`]
     in_memory_state: Arc<Mutex<HashMap<String, String>>>,
 }
 
@@ -37,55 +280,574 @@ impl CangjieExtension {
     pub fn new() -> Self {
         Self {
             cached_tool_paths: Arc::new(Mutex::new(HashMap::new())),
+            cached_sdk_roots: Arc::new(Mutex::new(HashMap::new())),
             in_memory_state: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
-    /// Resolves the root path of the Cangjie SDK.
+    /// Reads a single key from the `cangjie` settings blob of the worktree.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `key` - Full setting key, e.g. `cangjie.toolchain.channel`
+    ///
+    /// # Returns
+    /// * `Option<serde_json::Value>` - The setting value if present
+    fn extension_setting(&self, worktree: &zed::Worktree, key: &str) -> Option<serde_json::Value> {
+        self.extension_settings(worktree)?.remove(key)
+    }
+
+    /// Returns the whole `cangjie` settings blob of the worktree.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    ///
+    /// # Returns
+    /// * `Option<serde_json::Map<String, serde_json::Value>>` - The settings object if present
+    fn extension_settings(
+        &self,
+        worktree: &zed::Worktree,
+    ) -> Option<serde_json::Map<String, serde_json::Value>> {
+        let lsp_settings = LspSettings::for_worktree("cangjie", worktree).ok()?;
+        match lsp_settings.settings? {
+            serde_json::Value::Object(settings) => Some(settings),
+            _ => None,
+        }
+    }
+
+    /// Returns the toolchain channel configured for the worktree, defaulting to `release`.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `ToolchainChannel` - The configured channel
+    fn toolchain_channel(&self, host: &dyn HostServices) -> ToolchainChannel {
+        let Some(value) = host.setting(CONFIG_TOOLCHAIN_CHANNEL_KEY) else {
+            return ToolchainChannel::default();
+        };
+        match value.as_str().and_then(ToolchainChannel::from_setting) {
+            Some(channel) => channel,
+            None => {
+                log::warn!(
+                    "Unknown value for '{}': {}, falling back to 'release'",
+                    CONFIG_TOOLCHAIN_CHANNEL_KEY,
+                    value
+                );
+                ToolchainChannel::default()
+            }
+        }
+    }
+
+    /// Returns the number format of the configured output locale.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `humanize::NumberFormat` - The separators used for counts, sizes and durations
+    fn number_format(&self, host: &dyn HostServices) -> humanize::NumberFormat {
+        host.setting(CONFIG_OUTPUT_LOCALE_KEY)
+            .and_then(|value| value.as_str().map(humanize::NumberFormat::for_locale))
+            .unwrap_or_default()
+    }
+
+    /// Returns the collation used to sort names in reports.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `collate::Collation` - The configured collation
+    fn collation(&self, host: &dyn HostServices) -> collate::Collation {
+        let Some(value) = host.setting(CONFIG_OUTPUT_COLLATION_KEY) else {
+            return collate::Collation::default();
+        };
+        match value.as_str().and_then(collate::Collation::from_setting) {
+            Some(collation) => collation,
+            None => {
+                log::warn!(
+                    "Unknown value for '{}': {}, falling back to 'codepoint'",
+                    CONFIG_OUTPUT_COLLATION_KEY,
+                    value
+                );
+                collate::Collation::default()
+            }
+        }
+    }
+
+    /// Returns the output style, `rich` unless `cangjie.output.style` selects `plain`.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `output::OutputStyle` - The configured style
+    fn output_style(&self, host: &dyn HostServices) -> output::OutputStyle {
+        let Some(value) = host.setting(CONFIG_OUTPUT_STYLE_KEY) else {
+            return output::OutputStyle::default();
+        };
+        match value.as_str().and_then(output::OutputStyle::from_setting) {
+            Some(style) => style,
+            None => {
+                log::warn!(
+                    "Unknown value for '{}': {}, falling back to 'rich'",
+                    CONFIG_OUTPUT_STYLE_KEY,
+                    value
+                );
+                output::OutputStyle::default()
+            }
+        }
+    }
+
+    /// Returns an output builder for the worktree, honoring the output settings.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `OutputBuilder` - A builder resolving locations against the worktree root
+    fn output_builder(&self, host: &dyn HostServices) -> OutputBuilder {
+        let max_lines = host
+            .setting(CONFIG_OUTPUT_MAX_SECTION_LINES_KEY)
+            .and_then(|value| value.as_u64())
+            .map_or(DEFAULT_MAX_SECTION_LINES, |lines| lines as usize);
+        OutputBuilder::with_root(host.worktree_root())
+            .max_lines(max_lines)
+            .style(self.output_style(host))
+    }
+
+    /// Returns the language server version pinned for the worktree, if any.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Option<String>` - The pinned version or release tag; `None` when unset or `latest`
+    fn pinned_lsp_version(&self, host: &dyn HostServices) -> Option<String> {
+        let value = host.setting(CONFIG_LSP_VERSION_KEY)?;
+        let Some(pin) = value.as_str().map(str::trim) else {
+            log::warn!("Ignoring non-string value for '{}': {}", CONFIG_LSP_VERSION_KEY, value);
+            return None;
+        };
+        (!pin.is_empty() && !pin.eq_ignore_ascii_case("latest")).then(|| pin.to_string())
+    }
+
+    /// Returns true if `cangjie.lsp.preRelease` opts into pre-release language servers.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `bool` - Whether the release channel also considers GitHub pre-releases
+    fn lsp_pre_release(&self, host: &dyn HostServices) -> bool {
+        host.setting(CONFIG_LSP_PRE_RELEASE_KEY)
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false)
+    }
+
+    /// Records where the language server was installed from, for `/cangjie-info`
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `label` - A short description such as `release v1.0.0` or `SDK`
+    fn record_lsp_channel(&self, host: &dyn HostServices, label: String) {
+        self.set_worktree_state(&host.worktree_root(), STATE_LSP_CHANNEL_KEY, Some(label));
+    }
+
+    /// Reads a per-worktree entry of the in-memory state.
+    ///
+    /// # Arguments
+    /// * `root` - The worktree root
+    /// * `key` - The state key
+    ///
+    /// # Returns
+    /// * `Option<String>` - The entry of this worktree, if set
+    fn worktree_state(&self, root: &str, key: &str) -> Option<String> {
+        self.in_memory_state
+            .lock()
+            .ok()
+            .and_then(|state| state.get(&worktree_state_key(root, key)).cloned())
+    }
+
+    /// Sets or, with `None`, removes a per-worktree entry of the in-memory state.
+    ///
+    /// # Arguments
+    /// * `root` - The worktree root
+    /// * `key` - The state key
+    /// * `value` - The new value
+    fn set_worktree_state(&self, root: &str, key: &str, value: Option<String>) {
+        if let Ok(mut state) = self.in_memory_state.lock() {
+            let key = worktree_state_key(root, key);
+            match value {
+                Some(value) => state.insert(key, value),
+                None => state.remove(&key),
+            };
+        }
+    }
+
+    /// Lists the source files below a worktree root, skipping hidden directories and build
+    /// output.
+    ///
+    /// # Arguments
+    /// * `host` - The host services used to run the listing
+    /// * `os` - The current platform
+    /// * `root` - The worktree root
+    ///
+    /// # Returns
+    /// * `Vec<String>` - The files relative to the worktree root, sorted
+    fn list_sources(&self, host: &dyn HostServices, os: Os, root: &str) -> Vec<String> {
+        let (program, args) = completion::list_command(os);
+        let (program, args) = run_config::wrap_command(os, &program, &args, root, None);
+        match host.run(&program, &args) {
+            Ok(output) => completion::parse_listing(&output.stdout, root),
+            Err(e) => {
+                log::warn!("Could not list the worktree sources: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Lists the worktree's source files, skipping hidden directories and build output.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    ///
+    /// # Returns
+    /// * `Vec<String>` - The files relative to the worktree root, sorted
+    fn worktree_sources(&self, worktree: &zed::Worktree) -> Vec<String> {
+        let (os, _) = zed::current_platform();
+        self.list_sources(&ZedHost::new(worktree), os, &worktree.root_path())
+    }
+
+    /// Remembers the worktree that slash command arguments are completed for, which Zed
+    /// asks for without a worktree.
+    ///
+    /// Only the root and the run configuration names, which come from the worktree's
+    /// settings, are kept; the sources are indexed when completions are requested.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    fn note_completion_worktree(&self, worktree: &zed::Worktree) {
+        let settings = self.extension_settings(worktree).unwrap_or_default();
+        let collation = self.collation(&ZedHost::new(worktree));
+        let configs: Vec<String> = run_config::parse_run_configs(&settings, collation)
+            .map(|configs| configs.into_iter().map(|config| config.name).collect())
+            .unwrap_or_default();
+        let source = serde_json::json!({ "root": worktree.root_path(), "configs": configs });
+        if let Ok(mut state) = self.in_memory_state.lock() {
+            state.insert(STATE_COMPLETION_WORKTREE_KEY.to_string(), source.to_string());
+        }
+    }
+
+    /// Returns the completion index of the worktree seen last, indexing its sources again
+    /// when the index is older than [`COMPLETION_INDEX_TTL_SECS`] or belongs to another
+    /// worktree.
+    ///
+    /// # Arguments
+    /// * `host` - The host services used to list and read the sources
+    /// * `os` - The current platform
+    ///
+    /// # Returns
+    /// * `completion::CompletionIndex` - The index; empty before any worktree was seen
+    fn completion_index(&self, host: &dyn HostServices, os: Os) -> completion::CompletionIndex {
+        let (source, cached) = match self.in_memory_state.lock() {
+            Ok(state) => (
+                state.get(STATE_COMPLETION_WORKTREE_KEY).cloned(),
+                state.get(STATE_COMPLETION_INDEX_KEY).cloned(),
+            ),
+            Err(_) => return completion::CompletionIndex::default(),
+        };
+        let source = source.and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok());
+        let Some(source) = source else {
+            return completion::CompletionIndex::default();
+        };
+        let root = source["root"].as_str().unwrap_or_default().to_string();
+        let now = host.unix_time();
+        let fresh = cached.as_deref().and_then(|cached| {
+            let mut parts = cached.splitn(3, '\t');
+            let built_at: u64 = parts.next()?.parse().ok()?;
+            let fresh = parts.next()? == root
+                && now.saturating_sub(built_at) < COMPLETION_INDEX_TTL_SECS;
+            fresh.then(|| completion::CompletionIndex::from_json(parts.next()?))?
+        });
+        if let Some(index) = fresh {
+            return index;
+        }
+
+        let files = self.list_sources(host, os, &root);
+        let mut tests = Vec::new();
+        for file in files.iter().take(completion::MAX_INDEXED_FILES) {
+            if let Ok(source) = host.read_text_file(&Path::new(&root).join(file)) {
+                tests.extend(testing::find_test_items(&source).iter().map(|t| t.filter()));
+            }
+        }
+        tests.sort();
+        tests.dedup();
+        let manifest = host
+            .read_text_file(&Path::new(&root).join(cjpm::MANIFEST_FILE))
+            .map(|manifest| cjpm::parse_manifest(&manifest))
+            .unwrap_or_default();
+        let configs = source["configs"]
+            .as_array()
+            .map(|configs| {
+                configs
+                    .iter()
+                    .filter_map(|config| config.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let index = completion::CompletionIndex {
+            files,
+            tests,
+            targets: completion::targets(&manifest),
+            configs,
+        };
+        if let Ok(mut state) = self.in_memory_state.lock() {
+            let cached = format!("{}\t{}\t{}", now, root, index.to_json());
+            state.insert(STATE_COMPLETION_INDEX_KEY.to_string(), cached);
+        }
+        index
+    }
+
+    /// Drops the cached tool paths of a worktree.
+    ///
+    /// # Arguments
+    /// * `root` - The worktree root
+    fn clear_tool_paths(&self, root: &str) {
+        if let Ok(mut cache) = self.cached_tool_paths.lock() {
+            cache.remove(root);
+        }
+    }
+
+    /// Resolves the root path of the Cangjie SDK that tools are run from, following
+    /// symbolic links.
+    ///
+    /// Use `/cangjie-refresh-sdk` to clear the cache after installing or moving an SDK.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Result<PathBuf, String>` - The canonical SDK path or an error message
+    fn resolve_sdk_root(&self, host: &dyn HostServices) -> Result<PathBuf, String> {
+        self.resolve_sdk_root_entry(host).map(|root| root.target)
+    }
+
+    /// Resolves the SDK root and its link target, caching both per worktree.
+    ///
+    /// The cached target is checked on every use: when a link such as `current -> 1.0.3`
+    /// is repointed at another SDK, the new target is used and the cached tool paths are
+    /// dropped; when it no longer leads to an SDK, the root is resolved again.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Result<sdk_layout::SdkRoot, String>` - The SDK root or an error message
+    fn resolve_sdk_root_entry(
+        &self,
+        host: &dyn HostServices,
+    ) -> Result<sdk_layout::SdkRoot, String> {
+        let is_sdk_root =
+            |root: &Path| sdk_layout::SdkLayout::is_sdk_root_with(root, |path| host.is_dir(path));
+        let worktree_root = host.worktree_root();
+        let cached = self
+            .cached_sdk_roots
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(&worktree_root).cloned());
+        if let Some(cached) = cached {
+            let target = host.canonicalize(&cached.path);
+            if target.as_ref().is_ok_and(|target| *target == cached.target) {
+                return Ok(cached);
+            }
+            self.clear_tool_paths(&worktree_root);
+            match target {
+                Ok(target) if is_sdk_root(&target) => {
+                    log::info!("SDK {:?} now leads to {:?}", cached.path, target);
+                    let root = sdk_layout::SdkRoot {
+                        path: cached.path,
+                        target,
+                    };
+                    if let Ok(mut cache) = self.cached_sdk_roots.lock() {
+                        cache.insert(worktree_root, root.clone());
+                    }
+                    return Ok(root);
+                }
+                _ => {
+                    log::info!("Cached SDK {:?} is gone, resolving again", cached.path);
+                    if let Ok(mut cache) = self.cached_sdk_roots.lock() {
+                        cache.remove(&worktree_root);
+                    }
+                }
+            }
+        }
+
+        let mut steps = Vec::new();
+        let path = self.resolve_sdk_root_traced(host, &mut steps)?;
+        let target = host.canonicalize(&path).unwrap_or_else(|_| path.clone());
+        let root = sdk_layout::SdkRoot { path, target };
+        if is_sdk_root(&root.target)
+            && let Ok(mut cache) = self.cached_sdk_roots.lock()
+        {
+            cache.insert(worktree_root, root.clone());
+        }
+        Ok(root)
+    }
+
+    /// Resolves the root path of the Cangjie SDK without consulting the cache.
     /// 
     /// The search order is:
     /// 1. Check user configuration for SDK path (project setting via LspSettings).
-    /// 2. Check the `CANGJIE_HOME` environment variable.
-    /// 3. Attempt to infer from the current executable's location.
-    /// 4. Use default SDK path if available.
-    /// 5. Fallback to current directory for development purposes.
+    /// 2. Check for a project-local `.cangjie/sdk` directory (or symlink) in the worktree.
+    /// 3. Check `.tool-versions` (asdf/mise) for a pinned `cangjie` version.
+    /// 4. Check `cangjie.sdk.dir` in DevEco Studio's `local.properties`.
+    /// 5. Check the `CANGJIE_HOME` environment variable (`~`, `$VAR`, `${VAR}` and
+    ///    `%VAR%` are expanded).
+    /// 6. Attempt to infer from the current executable's location.
+    /// 7. Use default SDK path if available, preferring the directory of the
+    ///    configured toolchain channel (e.g. `/opt/cangjie-nightly`).
+    /// 8. Fallback to current directory for development purposes.
     /// 
     /// # Arguments
-    /// * `worktree` - The current worktree context
+    /// * `host` - The host services of the current worktree
+    /// * `steps` - Receives a human-readable description of each step taken
     /// 
     /// # Returns
     /// * `Result<PathBuf, String>` - The resolved SDK path or an error message
-    fn resolve_sdk_root(&self, worktree: &zed::Worktree) -> Result<PathBuf, String> {
+    fn resolve_sdk_root_traced(
+        &self,
+        host: &dyn HostServices,
+        steps: &mut Vec<String>,
+    ) -> Result<PathBuf, String> {
+        let is_dir = |path: &Path| host.is_dir(path);
+
         // 1. Check user configuration for SDK path via LspSettings
-        if let Ok(lsp_settings) = LspSettings::for_worktree("cangjie", worktree)
-            && let Some(cangjie_settings) = &lsp_settings.settings
-            && let Some(sdk_path_val) = cangjie_settings.get(CONFIG_SDK_PATH_KEY)
+        if let Some(sdk_path_val) = host.setting(CONFIG_SDK_PATH_KEY)
             && let Some(sdk_path_str) = sdk_path_val.as_str()
         {
             let sdk_path = PathBuf::from(sdk_path_str);
-            if sdk_path.exists() && sdk_path.is_dir() {
-                log::info!("Using SDK path from LSP settings: {:?}", sdk_path);
+            if let Err(e) = paths::check_setting_path(CONFIG_SDK_PATH_KEY, sdk_path_str) {
+                trace_step(steps, log::Level::Warn, e);
+            } else if is_dir(&sdk_path) {
+                trace_step(
+                    steps,
+                    log::Level::Info,
+                    format!("Using SDK path from LSP settings: {:?}", sdk_path),
+                );
                 return Ok(sdk_path);
             } else {
-                log::warn!("Configured SDK path from settings does not exist: {:?}", sdk_path);
+                trace_step(
+                    steps,
+                    log::Level::Warn,
+                    format!(
+                        "Configured SDK path from settings does not exist: {:?}",
+                        sdk_path
+                    ),
+                );
+            }
+        }
+
+        // 2. Check for a project-local SDK shipped with the worktree
+        let local_sdk = Path::new(&host.worktree_root()).join(LOCAL_SDK_DIR);
+        if is_dir(&local_sdk) {
+            trace_step(
+                steps,
+                log::Level::Info,
+                format!("Using project-local SDK: {:?}", local_sdk),
+            );
+            return Ok(local_sdk);
+        }
+        trace_step(
+            steps,
+            log::Level::Debug,
+            format!("No project-local SDK at {:?}", local_sdk),
+        );
+
+        // 3. Check `.tool-versions` (asdf/mise) for a pinned toolchain version
+        if let Ok(contents) = host.read_worktree_file(tool_versions::TOOL_VERSIONS_FILE) {
+            for version in tool_versions::versions_for(&contents, tool_versions::CANGJIE_PLUGIN) {
+                let dirs =
+                    tool_versions::install_dirs(tool_versions::CANGJIE_PLUGIN, &version, |name| {
+                        host.env_var(name)
+                    });
+                if let Some(sdk_path) = dirs.into_iter().find(|dir| is_dir(dir)) {
+                    trace_step(
+                        steps,
+                        log::Level::Info,
+                        format!("Using SDK {} from .tool-versions: {:?}", version, sdk_path),
+                    );
+                    return Ok(sdk_path);
+                }
+                trace_step(
+                    steps,
+                    log::Level::Warn,
+                    format!("Cangjie {} from .tool-versions is not installed", version),
+                );
+            }
+        }
+
+        // 4. Check the SDK recorded by DevEco Studio for HarmonyOS (OHOS) projects
+        if let Some(sdk_dir) = host
+            .read_worktree_file(deveco::LOCAL_PROPERTIES_FILE)
+            .ok()
+            .and_then(|text| deveco::sdk_dir_from_local_properties(&text))
+        {
+            let sdk_path = PathBuf::from(sdk_dir);
+            if is_dir(&sdk_path) {
+                trace_step(
+                    steps,
+                    log::Level::Info,
+                    format!(
+                        "Using SDK from {}: {:?}",
+                        deveco::LOCAL_PROPERTIES_FILE,
+                        sdk_path
+                    ),
+                );
+                return Ok(sdk_path);
             }
+            trace_step(
+                steps,
+                log::Level::Warn,
+                format!(
+                    "SDK in {} does not exist: {:?}",
+                    deveco::LOCAL_PROPERTIES_FILE,
+                    sdk_path
+                ),
+            );
         }
 
-        // 2. Check the CANGJIE_HOME environment variable
-        if let Ok(cangjie_home) = env::var(ENV_CANGJIE_HOME) {
-            let sdk_path = PathBuf::from(cangjie_home);
-            if sdk_path.is_absolute() && sdk_path.exists() && sdk_path.is_dir() {
-                log::info!("Using SDK path from {}: {:?}", ENV_CANGJIE_HOME, sdk_path);
+        // 5. Check the CANGJIE_HOME environment variable, expanding `~` and variables
+        if let Some(cangjie_home) = host.env_var(ENV_CANGJIE_HOME) {
+            let expanded = expand_path_variables(&cangjie_home, |name| host.env_var(name));
+            let sdk_path = PathBuf::from(expanded);
+            if sdk_path.is_absolute() && is_dir(&sdk_path) {
+                trace_step(
+                    steps,
+                    log::Level::Info,
+                    format!("Using SDK path from {}: {:?}", ENV_CANGJIE_HOME, sdk_path),
+                );
                 return Ok(sdk_path);
             } else {
-                log::warn!("{} points to an invalid path: {:?}", ENV_CANGJIE_HOME, sdk_path);
+                trace_step(
+                    steps,
+                    log::Level::Warn,
+                    format!(
+                        "{} points to an invalid path: {:?}",
+                        ENV_CANGJIE_HOME, sdk_path
+                    ),
+                );
             }
         }
 
-        // 3. Try to infer the SDK root from the current executable's path
-        match env::current_exe() {
+        // 6. Try to infer the SDK root from the current executable's path
+        match host.current_exe() {
             Ok(exe_path) => {
-                log::debug!("Attempting to infer SDK root from executable path: {:?}", exe_path);
+                log::debug!(
+                    "Attempting to infer SDK root from executable path: {:?}",
+                    exe_path
+                );
 
                 const BIN_DIR: &str = "bin";
                 const TOOLS_DIR: &str = "tools";
@@ -94,11 +856,17 @@ impl CangjieExtension {
                 if let Some(parent) = exe_path.parent()
                     && parent.file_name() == Some(OsStr::new(BIN_DIR))
                     && let Some(candidate) = parent.parent()
-                    && candidate.exists()
-                    && candidate.is_dir()
-                    && candidate.join(BIN_DIR).is_dir()
+                    && is_dir(candidate)
+                    && sdk_layout::SdkLayout::is_sdk_root_with(candidate, is_dir)
                 {
-                    log::info!("Inferred SDK root from executable path (in bin): {:?}", candidate);
+                    trace_step(
+                        steps,
+                        log::Level::Info,
+                        format!(
+                            "Inferred SDK root from executable path (in bin): {:?}",
+                            candidate
+                        ),
+                    );
                     return Ok(candidate.to_path_buf());
                 }
 
@@ -108,20 +876,30 @@ impl CangjieExtension {
                     && let Some(tools) = tools_bin.parent()
                     && tools.file_name() == Some(OsStr::new(TOOLS_DIR))
                     && let Some(candidate) = tools.parent()
-                    && candidate.exists()
-                    && candidate.is_dir()
-                    && candidate.join(BIN_DIR).is_dir()
+                    && is_dir(candidate)
+                    && sdk_layout::SdkLayout::is_sdk_root_with(candidate, is_dir)
                 {
-                    log::info!("Inferred SDK root from executable path (in tools/bin): {:?}", candidate);
+                    trace_step(
+                        steps,
+                        log::Level::Info,
+                        format!(
+                            "Inferred SDK root from executable path (in tools/bin): {:?}",
+                            candidate
+                        ),
+                    );
                     return Ok(candidate.to_path_buf());
                 }
             }
             Err(e) => {
-                log::warn!("Failed to get current executable path: {}", e);
+                trace_step(
+                    steps,
+                    log::Level::Warn,
+                    format!("Failed to get current executable path: {}", e),
+                );
             }
         }
 
-        // 4. Try default SDK paths based on OS
+        // 7. Try default SDK paths based on OS
         let default_paths = match std::env::consts::OS {
             "windows" => [
                 PathBuf::from("C:\\Program Files\\Cangjie"),
@@ -137,75 +915,167 @@ impl CangjieExtension {
             ],
         };
 
+        let channel = self.toolchain_channel(host);
+        let mut candidates = Vec::new();
         for default_path in default_paths {
-            if default_path.exists() && default_path.is_dir() && default_path.join("bin").is_dir() {
-                log::info!("Using default SDK path: {:?}", default_path);
+            if let Some(suffix) = channel.sdk_dir_suffix() {
+                let mut channel_dir = default_path.clone().into_os_string();
+                channel_dir.push(format!("-{}", suffix));
+                candidates.push(PathBuf::from(channel_dir));
+            }
+            candidates.push(default_path);
+        }
+
+        for default_path in candidates {
+            trace_step(
+                steps,
+                log::Level::Debug,
+                format!("Checking {:?}", default_path),
+            );
+            if sdk_layout::SdkLayout::is_sdk_root_with(&default_path, is_dir) {
+                trace_step(
+                    steps,
+                    log::Level::Info,
+                    format!("Using default SDK path: {:?}", default_path),
+                );
                 return Ok(default_path);
             }
         }
 
-        // 5. Fallback: Use current directory for development
-        let current_dir = env::current_dir()
+        // 8. Fallback: Use current directory for development
+        let current_dir = host
+            .current_dir()
             .map_err(|e| format!("Failed to get current directory: {}", e))?;
-        
-        log::warn!("Could not find SDK root, using current directory for development: {:?}", current_dir);
-        log::warn!("Please set {} environment variable or configure '{}' in settings for production use", 
-                  ENV_CANGJIE_HOME, CONFIG_SDK_PATH_KEY);
-        
+
+        trace_step(
+            steps,
+            log::Level::Warn,
+            format!(
+                "Could not find SDK root, using current directory for development: {:?}",
+                current_dir
+            ),
+        );
+        log::warn!(
+            "Please set {} environment variable or configure '{}' in settings for production use",
+            ENV_CANGJIE_HOME,
+            CONFIG_SDK_PATH_KEY
+        );
+
         Ok(current_dir)
     }
 
+    /// Probes the layout and flavor of the resolved SDK.
+    ///
+    /// The flavor is detected automatically unless `cangjie.sdk.flavor` selects one.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Result<sdk_layout::SdkLayout, String>` - The SDK layout or an error message
+    fn sdk_layout(&self, host: &dyn HostServices) -> Result<sdk_layout::SdkLayout, String> {
+        let root = self.resolve_sdk_root(host)?;
+        let platform_dirs: Vec<String> = [root.join("runtime").join("lib"), root.join("lib")]
+            .iter()
+            .flat_map(|dir| host.subdirectories(dir))
+            .filter_map(|dir| dir.file_name().map(|name| name.to_string_lossy().to_string()))
+            .collect();
+        let mut layout =
+            sdk_layout::SdkLayout::probe_with(&root, |path| host.is_dir(path), &platform_dirs);
+        if let Some(setting) = host
+            .setting(CONFIG_SDK_FLAVOR_KEY)
+            .and_then(|v| v.as_str().map(str::to_string))
+        {
+            match sdk_layout::SdkFlavor::from_setting(&setting) {
+                Ok(Some(flavor)) => layout.flavor = flavor,
+                Ok(None) => {}
+                Err(e) => log::warn!("Ignoring '{}': {}", CONFIG_SDK_FLAVOR_KEY, e),
+            }
+        }
+        Ok(layout)
+    }
+
     /// Resolves the full path to a specific tool binary within the SDK or via override.
     /// Uses caching for efficiency to avoid repeated path resolution.
     /// 
+    /// The lookup order is:
+    /// 1. The per-tool override setting (e.g. `cangjie.cjcPathOverride`).
+    /// 2. The LSP settings binary override.
+    /// 3. The SDK's binary directories (`bin`, `tools/bin`), see [`sdk_layout::SdkLayout`].
+    /// 4. The directories listed in `cangjie.extraBinDirs`.
+    /// 5. The `PATH` of the worktree's shell environment.
+    /// 
     /// # Arguments
-    /// * `worktree` - The current worktree context
+    /// * `host` - The host services of the current worktree
     /// * `tool_name` - Name of the tool to resolve
-    /// * `_config_override_key` - Configuration key for path override (unused but kept for compatibility)
-    /// * `default_subdir` - Default subdirectory within SDK where tool is located
+    /// * `config_override_key` - Configuration key for a per-tool path override
     /// * `default_filename` - Default filename of the tool binary
     /// 
     /// # Returns
     /// * `Result<String, String>` - The resolved tool path or an error message
     fn resolve_tool_binary_path(
         &self,
-        worktree: &zed::Worktree,
+        host: &dyn HostServices,
         tool_name: &str,
-        _config_override_key: &str, // Unused, but kept for signature compatibility
-        default_subdir: &str,
+        config_override_key: &str,
         default_filename: &str,
     ) -> Result<String, String> {
         let cache_key = format!("tool_path_{}", tool_name);
+        let worktree_root = host.worktree_root();
 
         // Check cache first for performance
         {   
             let cache = self.cached_tool_paths.lock().unwrap();
-            if let Some(cached_path) = cache.get(&cache_key) {
+            if let Some(cached_path) = cache.get(&worktree_root).and_then(|c| c.get(&cache_key)) {
                 return Ok(cached_path.clone());
             }
         }
 
-        // Check override via LSP settings
-        if let Ok(lsp_settings) = LspSettings::for_worktree("cangjie", worktree)
-            && let Some(binary_settings) = &lsp_settings.binary
-            && let Some(override_path_str) = &binary_settings.path
+        let cache_resolved = |path: &Path, source: &str| -> Result<String, String> {
+            let resolved_path = host
+                .canonicalize(path)
+                .map_err(|e| format!("Failed to canonicalize {} path: {}", source, e))?
+                .to_string_lossy()
+                .to_string();
+
+            // Update cache with resolved path
+            {
+                let mut cache = self.cached_tool_paths.lock().unwrap();
+                cache
+                    .entry(worktree_root.clone())
+                    .or_default()
+                    .insert(cache_key.clone(), resolved_path.clone());
+            }
+
+            log::info!("Resolved path for '{}' ({}): {}", tool_name, source, resolved_path);
+            Ok(resolved_path)
+        };
+        let is_binary = |path: &Path| host.is_file(path);
+
+        // Check the per-tool override setting
+        if let Some(override_path_str) = host
+            .setting(config_override_key)
+            .and_then(|v| v.as_str().map(str::to_string))
         {
-            let override_path = PathBuf::from(override_path_str);
-            if override_path.exists() && (override_path.is_file() || override_path.is_symlink()) {
-                let resolved_path = override_path
-                    .canonicalize()
-                    .map_err(|e| format!("Failed to canonicalize override path: {}", e))?
-                    .to_string_lossy()
-                    .to_string();
+            let override_path = PathBuf::from(&override_path_str);
+            if let Err(e) = paths::check_setting_path(config_override_key, &override_path_str) {
+                log::warn!("{}", e);
+            } else if is_binary(&override_path) {
+                return cache_resolved(&override_path, config_override_key);
+            } else {
+                log::warn!(
+                    "Configured '{}' does not exist or is not a file: {:?}",
+                    config_override_key,
+                    override_path
+                );
+            }
+        }
 
-                // Update cache with resolved path
-                {   
-                    let mut cache = self.cached_tool_paths.lock().unwrap();
-                    cache.insert(cache_key, resolved_path.clone());
-                }
-                
-                log::info!("Using override path for '{}': {}", tool_name, resolved_path);
-                return Ok(resolved_path);
+        // Check override via LSP settings
+        if let Some(override_path_str) = host.tool_binary_override() {
+            let override_path = PathBuf::from(override_path_str);
+            if is_binary(&override_path) {
+                return cache_resolved(&override_path, "override");
             } else {
                 log::warn!(
                     "Configured override path for '{}' does not exist or is not a file: {:?}",
@@ -216,45 +1086,92 @@ impl CangjieExtension {
         }
 
         // Resolve tool path from SDK
-        let sdk_root = self.resolve_sdk_root(worktree)?;
-        let tool_path = sdk_root.join(default_subdir).join(default_filename);
-
-        if tool_path.exists() && (tool_path.is_file() || tool_path.is_symlink()) {
-            let resolved_path = tool_path
-                .canonicalize()
-                .map_err(|e| format!("Failed to canonicalize tool path: {}", e))?
-                .to_string_lossy()
-                .to_string();
+        let layout = self.sdk_layout(host)?;
+        if let Some(tool_path) = layout.find_tool(default_filename, is_binary) {
+            return cache_resolved(&tool_path, "SDK");
+        }
 
-            // Update cache with resolved path
-            {   
-                let mut cache = self.cached_tool_paths.lock().unwrap();
-                cache.insert(cache_key, resolved_path.clone());
+        // Resolve tool path from the extra bin directories
+        for dir in self.extra_bin_dirs(host) {
+            if let Err(e) = paths::check_setting_path(CONFIG_EXTRA_BIN_DIRS_KEY, &dir) {
+                log::warn!("{}", e);
+                continue;
             }
-            
-            log::info!("Resolved path for '{}': {}", tool_name, resolved_path);
-            Ok(resolved_path)
-        } else {
-            Err(format!(
-                "{} '{}' not found at expected location: {:?}",
-                ERR_TOOL_NOT_FOUND_FMT, tool_name, tool_path
-            ))
+            let dir = expand_path_variables(&dir, |name| host.env_var(name));
+            let candidate = Path::new(&worktree_root).join(dir).join(default_filename);
+            if is_binary(&candidate) {
+                return cache_resolved(&candidate, CONFIG_EXTRA_BIN_DIRS_KEY);
+            }
+        }
+
+        // Fall back to the shell PATH
+        if let Some(path) = host.which(default_filename) {
+            return cache_resolved(Path::new(&path), "PATH");
         }
+
+        Err(format!(
+            "{} '{}' not found in SDK directories {:?} (also searched '{}' and PATH)",
+            ERR_TOOL_NOT_FOUND_FMT,
+            tool_name,
+            layout.bin_dirs(),
+            CONFIG_EXTRA_BIN_DIRS_KEY
+        ))
     }
 
-    /// Resolves the path to the cjc compiler binary
-    /// 
+    /// Returns the directories configured in `cangjie.extraBinDirs`.
+    ///
     /// # Arguments
-    /// * `worktree` - The current worktree context
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Vec<String>` - The configured directories, possibly relative to the worktree root
+    fn extra_bin_dirs(&self, host: &dyn HostServices) -> Vec<String> {
+        match host.setting(CONFIG_EXTRA_BIN_DIRS_KEY) {
+            Some(serde_json::Value::Array(dirs)) => dirs
+                .iter()
+                .filter_map(|d| d.as_str().map(str::to_string))
+                .collect(),
+            Some(serde_json::Value::String(dir)) => vec![dir],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Resolves an auxiliary tool (`cjfmt`, `cjlint`, `cjdb`, ...) that ships with the SDK
+    /// or may be installed separately.
+    ///
+    /// The per-tool override key is `cangjie.<tool>PathOverride`.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `tool_name` - Name of the tool to resolve
+    ///
+    /// # Returns
+    /// * `Result<String, String>` - The resolved tool path or an error message
+    fn auxiliary_tool_path(
+        &self,
+        host: &dyn HostServices,
+        tool_name: &str,
+    ) -> Result<String, String> {
+        self.resolve_tool_binary_path(
+            host,
+            tool_name,
+            &format!("cangjie.{}PathOverride", tool_name),
+            &get_binary_name(tool_name),
+        )
+    }
+
+    /// Resolves the path to the cjc compiler binary
+    /// 
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
     /// 
     /// # Returns
     /// * `Result<String, String>` - The resolved cjc path or an error message
-    fn cjc_binary_path(&self, worktree: &zed::Worktree) -> Result<String, String> {
+    fn cjc_binary_path(&self, host: &dyn HostServices) -> Result<String, String> {
         self.resolve_tool_binary_path(
-            worktree,
+            host,
             CJC_NAME,
             CONFIG_CJC_PATH_KEY,
-            "bin",
             &get_binary_name(CJC_NAME),
         )
     }
@@ -262,53 +1179,58 @@ impl CangjieExtension {
     /// Resolves the path to the cjc-frontend debugger binary
     /// 
     /// # Arguments
-    /// * `worktree` - The current worktree context
+    /// * `host` - The host services of the current worktree
     /// 
     /// # Returns
     /// * `Result<String, String>` - The resolved cjc-frontend path or an error message
-    fn cjc_frontend_binary_path(&self, worktree: &zed::Worktree) -> Result<String, String> {
+    fn cjc_frontend_binary_path(&self, host: &dyn HostServices) -> Result<String, String> {
         self.resolve_tool_binary_path(
-            worktree,
+            host,
             CJC_FRONTEND_NAME,
             CONFIG_CJC_FRONTEND_PATH_KEY,
-            "bin",
             &get_binary_name(CJC_FRONTEND_NAME),
         )
     }
 
-    /// Ensures the LSP server is installed, downloading it if necessary.
-    /// 
-    /// Checks for the LSP server in the following order:
-    /// 1. LSP settings override path
-    /// 2. SDK bin directory
-    /// 3. Downloads from GitHub releases if not found elsewhere
+    /// Resolves the path to the cjpm package manager binary
     /// 
     /// # Arguments
-    /// * `language_server_id` - ID of the language server being installed
-    /// * `worktree` - The current worktree context
+    /// * `host` - The host services of the current worktree
     /// 
     /// # Returns
-    /// * `Result<String, String>` - The resolved LSP path or an error message
-    fn ensure_language_server_installed(
-        &self,
-        language_server_id: &zed::LanguageServerId,
-        worktree: &zed::Worktree,
-    ) -> Result<String, String> {
+    /// * `Result<String, String>` - The resolved cjpm path or an error message
+    fn cjpm_binary_path(&self, host: &dyn HostServices) -> Result<String, String> {
+        self.resolve_tool_binary_path(
+            host,
+            CJPM_NAME,
+            CONFIG_CJPM_PATH_KEY,
+            &get_binary_name(CJPM_NAME),
+        )
+    }
+
+    /// Locates an already available LSP server binary without downloading anything.
+    ///
+    /// Checks the LSP settings override path first, then the SDK's binary directories.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Result<Option<String>, String>` - The resolved LSP path, `None` if it has to be
+    ///   downloaded, or an error if the configured override path is invalid
+    fn locate_language_server(&self, host: &dyn HostServices) -> Result<Option<String>, String> {
         // 1. Check for override via LSP settings first
-        if let Ok(lsp_settings) = LspSettings::for_worktree("cangjie-lsp", worktree)
-            && let Some(binary_settings) = &lsp_settings.binary
-            && let Some(override_path_str) = &binary_settings.path
-        {
+        if let Some(override_path_str) = host.lsp_binary_override() {
             let override_path = PathBuf::from(override_path_str);
-            if override_path.exists() && (override_path.is_file() || override_path.is_symlink()) {
+            if host.is_file(&override_path) {
                 let resolved_path = override_path
                     .canonicalize()
-                    .map_err(|e| format!("Failed to canonicalize override LSP path: {}", e))?
+                    .unwrap_or(override_path)
                     .to_string_lossy()
                     .to_string();
                 
                 log::info!("Using override LSP path: {}", resolved_path);
-                return Ok(resolved_path);
+                return Ok(Some(resolved_path));
             } else {
                 log::warn!("Configured LSP override path does not exist: {:?}", override_path);
                 return Err(format!("LSP override path does not exist: {}", override_path.display()));
@@ -316,264 +1238,5118 @@ impl CangjieExtension {
         }
 
         // 2. Check if it exists in the SDK path
-        let sdk_root = self.resolve_sdk_root(worktree)?;
-        let lsp_path = sdk_root.join("bin").join(&get_binary_name(SERVER_NAME));
-
-        if lsp_path.exists() && (lsp_path.is_file() || lsp_path.is_symlink()) {
+        let layout = self.sdk_layout(host)?;
+        if let Some(lsp_path) =
+            layout.find_tool(&get_binary_name(SERVER_NAME), |path| host.is_file(path))
+        {
             let resolved_path = lsp_path
                 .canonicalize()
-                .map_err(|e| format!("Failed to canonicalize LSP path: {}", e))?
+                .unwrap_or(lsp_path)
                 .to_string_lossy()
                 .to_string();
                 
             log::info!("Found LSP in SDK: {}", resolved_path);
-            return Ok(resolved_path);
-        }
-
-        // 3. Attempt to download from GitHub
-        log::info!("LSP not found in SDK, attempting to download...");
-        zed::set_language_server_installation_status(
-            language_server_id,
-            &zed::LanguageServerInstallationStatus::Downloading,
-        );
-
-        let (os, arch) = zed::current_platform();
-        let asset_name = self.get_asset_name_for_platform(os, arch, SERVER_NAME)?;
-        log::info!("Downloading LSP asset: {}", asset_name);
-
-        let options = zed::GithubReleaseOptions {
-            require_assets: true,
-            pre_release: false,
-        };
-        
-        let release = zed::latest_github_release("cangjie-lang/cangjie", options)
-            .map_err(|e| format!("Failed to fetch GitHub release: {}", e))?;
-
-        let asset = release
-            .assets
-            .iter()
-            .find(|a| a.name == asset_name)
-            .ok_or_else(|| format!("No asset found matching '{}'", asset_name))?;
-
-        // Use a path relative to the user's home directory for download
-        let home_dir = std::env::var("HOME")
-            .or_else(|_| std::env::var("USERPROFILE"))
-            .map_err(|_| "Could not find HOME or USERPROFILE directory".to_string())?;
-            
-        let download_path = PathBuf::from(home_dir)
-            .join(".zed")
-            .join("extensions")
-            .join(&asset.name);
-
-        zed::download_file(
-            &asset.download_url,
-            download_path.to_string_lossy().as_ref(),
-            zed::DownloadedFileType::Uncompressed,
-        )
-        .map_err(|e| format!("Download failed: {}", e))?;
-
-        if os != Os::Windows {
-            zed::make_file_executable(download_path.to_string_lossy().as_ref())
-                .map_err(|e| format!("Failed to make executable: {}", e))?;
+            return Ok(Some(resolved_path));
         }
 
-        log::info!("LSP downloaded successfully to: {:?}", download_path);
-        Ok(download_path.to_string_lossy().to_string())
+        Ok(None)
     }
 
-    /// Creates the command to start the language server, integrating LSP settings.
+    /// Ensures the LSP server is installed, downloading it if necessary.
     /// 
     /// # Arguments
-    /// * `language_server_id` - ID of the language server to start
+    /// * `language_server_id` - ID of the language server being installed
     /// * `worktree` - The current worktree context
     /// 
     /// # Returns
-    /// * `Result<zed::Command, String>` - The command to start the LSP server or an error message
-    fn create_language_server_command(
-        &mut self,
+    /// * `Result<String, String>` - The resolved LSP path or an error message
+    fn ensure_language_server_installed(
+        &self,
         language_server_id: &zed::LanguageServerId,
         worktree: &zed::Worktree,
-    ) -> Result<zed::Command, String> {
-        let server_path = self.ensure_language_server_installed(language_server_id, worktree)?;
+    ) -> Result<String, String> {
+        self.install_language_server(&ZedHost::new(worktree), zed::current_platform(), &|status| {
+            zed::set_language_server_installation_status(language_server_id, status)
+        })
+    }
 
-        // Determine if LSP settings override the command or args
-        let mut command = zed::Command::new(&server_path);
-        let mut default_args = vec!["--stdio".to_string()]; // Default transport using stdio
+    /// Finds or installs the LSP server through the given host services.
+    /// 
+    /// Checks for the LSP server in the following order:
+    /// 1. LSP settings override path
+    /// 2. SDK bin directory
+    /// 3. A previously downloaded copy of the pinned release
+    /// 4. The newest previously downloaded release, unless a version is pinned; with
+    ///    `cangjie.lsp.autoUpdate` a newer release is downloaded first, before this start
+    ///    returns, and used from the next start
+    /// 5. Downloads from GitHub releases if not found elsewhere
+    /// 
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `platform` - The platform to install for
+    /// * `report_status` - Receives installation status updates
+    /// 
+    /// # Returns
+    /// * `Result<String, String>` - The resolved LSP path or an error message
+    fn install_language_server(
+        &self,
+        host: &dyn HostServices,
+        (os, arch): (Os, Architecture),
+        report_status: &dyn Fn(&zed::LanguageServerInstallationStatus),
+    ) -> Result<String, String> {
+        // 1. + 2. Check the override path and the SDK
+        if let Some(resolved_path) = self.locate_language_server(host)? {
+            let source = if host.lsp_binary_override().is_some() { "override" } else { "SDK" };
+            self.record_lsp_channel(host, source.to_string());
+            return Ok(resolved_path);
+        }
 
-        if let Ok(lsp_settings) = LspSettings::for_worktree("cangjie-lsp", worktree)
-            && let Some(binary_settings) = &lsp_settings.binary
-        {
-            if let Some(path_override) = &binary_settings.path {
-                // If path is overridden, use the override path
-                command = zed::Command::new(path_override);
+        // Downloads live in the extension's working directory unless it is not writable
+        let downloads_dir = self.select_downloads_dir(host)?;
+        self.migrate_legacy_downloads(host, &downloads_dir);
+
+        // 3. A pinned release that is already installed needs no release metadata
+        let pinned = self.pinned_lsp_version(host);
+        if let Some(pin) = &pinned {
+            let installed = version::release_tag_candidates(pin).into_iter().find_map(|tag| {
+                let install_dir = downloads_dir.join(format!("{}-{}", SERVER_NAME, tag));
+                self.find_installed_binary(host, os, &install_dir)
+            });
+            if let Some(existing) = installed {
+                log::info!("Using pinned LSP {}: {:?}", pin, existing);
+                self.record_lsp_channel(host, format!("pinned ({})", pin));
+                return Ok(existing.to_string_lossy().to_string());
             }
-            if let Some(args_override) = &binary_settings.arguments {
-                // If args are overridden, use them instead of defaults
-                default_args = args_override.clone();
+        }
+
+        // 4. Use the newest download. The update check and download below run before it is
+        // returned, so the server starts only once they finish; the new release is used from
+        // the next start
+        if pinned.is_none()
+            && let Some((installed_version, existing)) =
+                self.newest_installed_language_server(host, os, &downloads_dir)
+        {
+            log::info!("Using downloaded LSP {}: {:?}", installed_version, existing);
+            self.record_lsp_channel(host, format!("downloaded ({})", installed_version));
+            if self.lsp_auto_update(host) {
+                self.update_language_server(
+                    host,
+                    (os, arch),
+                    &downloads_dir,
+                    &installed_version,
+                    report_status,
+                );
+            } else if self.lsp_prefetch(host) {
+                self.prefetch_language_server(host, (os, arch), &downloads_dir, &installed_version);
             }
+            return Ok(existing.to_string_lossy().to_string());
         }
 
-        // Apply arguments to the command
-        let final_command = command.args(default_args);
+        // 5. Attempt to download from GitHub
+        log::info!("LSP not found in SDK, attempting to download...");
+        report_status(&zed::LanguageServerInstallationStatus::Downloading);
+        let channel = self.toolchain_channel(host);
+        let release = self.fetch_language_server_release(host, channel, pinned.as_deref())?;
+        let channel_label = match &pinned {
+            Some(_) => "pinned",
+            None if channel == ToolchainChannel::Release && self.lsp_pre_release(host) => {
+                "pre-release"
+            }
+            None => channel.as_str(),
+        };
+        log::info!("Using {} channel release: {}", channel_label, release.version);
+        self.record_lsp_channel(host, format!("{} ({})", channel_label, release.version));
+        self.install_release(host, (os, arch), &release, &downloads_dir, report_status)
+    }
 
-        Ok(final_command)
+    /// Returns true if `cangjie.lsp.autoUpdate` opts into downloading newer releases.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `bool` - Whether newer language server releases are downloaded automatically
+    fn lsp_auto_update(&self, host: &dyn HostServices) -> bool {
+        host.setting(CONFIG_LSP_AUTO_UPDATE_KEY)
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false)
     }
 
-    /// Generates the appropriate asset name for downloading the LSP server based on platform.
-    /// 
+    /// Returns true if `cangjie.lsp.prefetch` opts into prefetching newer releases.
+    ///
     /// # Arguments
-    /// * `os` - The operating system type
-    /// * `arch` - The system architecture
-    /// * `base_name` - The base name of the asset
-    /// 
+    /// * `host` - The host services of the current worktree
+    ///
     /// # Returns
-    /// * `Result<String, String>` - The generated asset name or an error message
-    fn get_asset_name_for_platform(
-        &self,
-        os: Os,
-        arch: Architecture,
-        base_name: &str,
-    ) -> Result<String, String> {
-        let arch_str = match arch {
-            Architecture::X8664 => "x86_64",
-            Architecture::Aarch64 => "aarch64",
-            _ => return Err("Unsupported architecture".into()),
-        };
+    /// * `bool` - Whether newer releases are downloaded ahead of `/cangjie-upgrade-lsp`
+    fn lsp_prefetch(&self, host: &dyn HostServices) -> bool {
+        host.setting(CONFIG_LSP_PREFETCH_KEY)
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false)
+    }
 
-        let (os_str, ext) = match os {
-            Os::Mac => ("apple-darwin", ""),
-            Os::Linux => ("unknown-linux-gnu", ""),
-            Os::Windows => ("pc-windows-msvc", ".exe"),
-            #[allow(unreachable_patterns)]
-            _ => return Err("Unsupported OS".into()),
-        };
+    /// Returns the network use allowed for background update checks and downloads.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `prefetch::NetworkPolicy` - The policy from `cangjie.network.offline` and
+    ///   `cangjie.network.metered`
+    fn network_policy(&self, host: &dyn HostServices) -> prefetch::NetworkPolicy {
+        let flag = |key: &str| host.setting(key).and_then(|v| v.as_bool()).unwrap_or(false);
+        prefetch::NetworkPolicy::from_settings(
+            flag(CONFIG_NETWORK_OFFLINE_KEY),
+            flag(CONFIG_NETWORK_METERED_KEY),
+        )
+    }
 
-        Ok(format!("{}-{}-{}{}", base_name, arch_str, os_str, ext))
+    /// Finds the language server binary inside an install directory.
+    ///
+    /// # Arguments
+    /// * `host` - The host services used for filesystem access
+    /// * `os` - The platform's operating system
+    /// * `install_dir` - The `cangjie-lsp-<version>` directory
+    ///
+    /// # Returns
+    /// * `Option<PathBuf>` - The binary, if the directory holds a complete install
+    fn find_installed_binary(
+        &self,
+        host: &dyn HostServices,
+        os: Os,
+        install_dir: &Path,
+    ) -> Option<PathBuf> {
+        self.find_installed_tool(host, os, SERVER_NAME, install_dir)
     }
 
-    /// Handles the `/cangjie-build` slash command to build the project.
-    /// 
+    /// Finds a downloaded tool's binary inside an install directory.
+    ///
     /// # Arguments
-    /// * `worktree` - The current worktree context
-    /// 
+    /// * `host` - The host services used for filesystem access
+    /// * `os` - The platform's operating system
+    /// * `tool` - The tool, e.g. `cangjie-lsp` or `cjdb`
+    /// * `install_dir` - The `<tool>-<version>` directory
+    ///
     /// # Returns
-    /// * `Result<zed::SlashCommandOutput, String>` - The build output or an error message
-    fn handle_build_command(
+    /// * `Option<PathBuf>` - The binary, if the directory holds a complete install
+    fn find_installed_tool(
         &self,
-        worktree: &zed::Worktree,
-    ) -> Result<zed::SlashCommandOutput, String> {
-        let cjc_path = self.cjc_binary_path(worktree)?;
+        host: &dyn HostServices,
+        os: Os,
+        tool: &str,
+        install_dir: &Path,
+    ) -> Option<PathBuf> {
+        assets::find_binary(
+            install_dir,
+            &assets::binary_file_name(os, tool),
+            |path| host.is_file(path),
+            |path| host.subdirectories(path),
+        )
+    }
 
-        // Execute the build command
-        let output = Command::new(&cjc_path)
-            .args(vec!["build".to_string()])
-            .output()
-            .map_err(|e| format!("Failed to run build command: {}", e))?;
+    /// Finds the newest downloaded language server release.
+    ///
+    /// # Arguments
+    /// * `host` - The host services used for filesystem access
+    /// * `os` - The platform's operating system
+    /// * `downloads_dir` - The directory holding `cangjie-lsp-<version>` installs
+    ///
+    /// # Returns
+    /// * `Option<(version::Version, PathBuf)>` - The newest release's version and binary
+    fn newest_installed_language_server(
+        &self,
+        host: &dyn HostServices,
+        os: Os,
+        downloads_dir: &Path,
+    ) -> Option<(version::Version, PathBuf)> {
+        self.newest_installed_tool(host, os, SERVER_NAME, downloads_dir)
+    }
 
-        let mut sections = Vec::new();
-        let mut full_output_text = String::new();
+    /// Finds the newest downloaded release of a tool.
+    ///
+    /// # Arguments
+    /// * `host` - The host services used for filesystem access
+    /// * `os` - The platform's operating system
+    /// * `tool` - The tool, e.g. `cangjie-lsp` or `cjdb`
+    /// * `downloads_dir` - The directory holding `<tool>-<version>` installs
+    ///
+    /// # Returns
+    /// * `Option<(version::Version, PathBuf)>` - The newest release's version and binary
+    fn newest_installed_tool(
+        &self,
+        host: &dyn HostServices,
+        os: Os,
+        tool: &str,
+        downloads_dir: &Path,
+    ) -> Option<(version::Version, PathBuf)> {
+        let prefix = format!("{}-", tool);
+        let mut installs: Vec<(version::Version, PathBuf)> = host
+            .subdirectories(downloads_dir)
+            .into_iter()
+            .filter_map(|dir| {
+                let name = dir.file_name()?.to_str()?;
+                Some((version::Version::parse(name.strip_prefix(&prefix)?)?, dir))
+            })
+            .collect();
+        installs.sort_by(|a, b| b.0.cmp(&a.0));
+        installs.into_iter().find_map(|(installed_version, dir)| {
+            let binary = self.find_installed_tool(host, os, tool, &dir)?;
+            Some((installed_version, binary))
+        })
+    }
 
-        // Determine build success status
-        let success = output.status.unwrap_or(1) == 0;
-        if success {
-            full_output_text.push_str("✅ Build succeeded!\n\n");
-        } else {
-            full_output_text.push_str("❌ Build failed!\n\n");
+    /// Downloads a newer language server release, if there is one, for the next start.
+    ///
+    /// Runs synchronously while the language server is being started, so the start waits for
+    /// the check and download. Failures are logged and otherwise ignored, since the installed
+    /// server keeps working.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `platform` - The platform to install for
+    /// * `downloads_dir` - The directory holding `cangjie-lsp-<version>` installs
+    /// * `installed` - The version of the newest installed release
+    /// * `report_status` - Receives installation status updates
+    fn update_language_server(
+        &self,
+        host: &dyn HostServices,
+        platform: (Os, Architecture),
+        downloads_dir: &Path,
+        installed: &version::Version,
+        report_status: &dyn Fn(&zed::LanguageServerInstallationStatus),
+    ) {
+        let policy = self.network_policy(host);
+        if !policy.allows_check() {
+            log::info!("Offline; not checking for LSP updates");
+            return;
+        }
+        report_status(&zed::LanguageServerInstallationStatus::CheckingForUpdate);
+        match self.newer_language_server_release(host, installed) {
+            Ok(Some(release)) if !policy.allows_download() => log::info!(
+                "LSP {} is available; run /cangjie-upgrade-lsp to download it on a metered \
+                 connection",
+                release.version
+            ),
+            Ok(Some(release)) => {
+                report_status(&zed::LanguageServerInstallationStatus::Downloading);
+                match self.install_release(host, platform, &release, downloads_dir, report_status)
+                {
+                    Ok(_) => {
+                        log::info!(
+                            "Downloaded LSP {}; it will be used after a restart",
+                            release.version
+                        );
+                        let root = host.worktree_root();
+                        self.set_worktree_state(&root, STATE_LSP_UPDATE_KEY, Some(release.version));
+                    }
+                    Err(e) => log::warn!("Could not update the LSP: {}", e),
+                }
+            }
+            Ok(None) => log::info!("LSP {} is up to date", installed),
+            Err(e) => log::warn!("Could not update the LSP: {}", e),
         }
+        report_status(&zed::LanguageServerInstallationStatus::None);
+    }
 
-        // Append stdout and stderr to output
-        let stdout_str = String::from_utf8_lossy(&output.stdout);
-        let stderr_str = String::from_utf8_lossy(&output.stderr);
+    /// Looks up the latest release of the toolchain channel, if it is newer than `installed`.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `installed` - The version of the newest installed release
+    ///
+    /// # Returns
+    /// * `Result<Option<zed::GithubRelease>, String>` - The newer release, if any
+    fn newer_language_server_release(
+        &self,
+        host: &dyn HostServices,
+        installed: &version::Version,
+    ) -> Result<Option<zed::GithubRelease>, String> {
+        let channel = self.toolchain_channel(host);
+        let release = self.fetch_language_server_release(host, channel, None)?;
+        let latest = version::Version::parse(&release.version);
+        Ok(latest.is_some_and(|latest| latest > *installed).then_some(release))
+    }
 
-        if !stdout_str.is_empty() {
-            full_output_text.push_str("Standard Output:\n");
-            full_output_text.push_str(&stdout_str);
-            full_output_text.push('\n');
+    /// Downloads and verifies a newer language server release into [`prefetch::PREFETCH_DIR`]
+    /// without switching to it.
+    ///
+    /// Runs silently: the installed server keeps working, so failures are only logged.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `platform` - The platform to install for
+    /// * `downloads_dir` - The directory holding `cangjie-lsp-<version>` installs
+    /// * `installed` - The version of the newest installed release
+    fn prefetch_language_server(
+        &self,
+        host: &dyn HostServices,
+        platform: (Os, Architecture),
+        downloads_dir: &Path,
+        installed: &version::Version,
+    ) {
+        let policy = self.network_policy(host);
+        if !policy.allows_check() {
+            return;
+        }
+        let release = match self.newer_language_server_release(host, installed) {
+            Ok(Some(release)) => release,
+            Ok(None) => return,
+            Err(e) => {
+                log::warn!("Could not check for LSP updates: {}", e);
+                return;
+            }
+        };
+        let prefetch_dir = downloads_dir.join(prefetch::PREFETCH_DIR);
+        let state_path = Path::new(prefetch::STATE_FILE);
+        let state = host.read_text_file(state_path).ok();
+        if state.as_deref().is_some_and(|state| prefetch::attempted(state, &release.version)) {
+            return;
+        }
+        if !policy.allows_download() {
+            log::info!("Not prefetching LSP {} on a metered connection", release.version);
+            return;
+        }
+        // Recorded up front, so a failing release is not downloaded again on every start
+        let state = prefetch::record_attempt(state.as_deref(), &release.version);
+        if let Err(e) = host.write_text_file(state_path, &state) {
+            log::warn!("Could not record the LSP prefetch: {}", e);
+        }
+        match self.install_release(host, platform, &release, &prefetch_dir, &|_| {}) {
+            Ok(_) => log::info!(
+                "Prefetched LSP {}; run /cangjie-upgrade-lsp to switch to it",
+                release.version
+            ),
+            Err(e) => log::warn!("Could not prefetch LSP {}: {}", release.version, e),
         }
+    }
 
-        if !stderr_str.is_empty() {
-            full_output_text.push_str("Standard Error:\n");
-            full_output_text.push_str(&stderr_str);
-            full_output_text.push('\n');
+    /// Appends an entry to the action journal shown by `/cangjie-journal`.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `action` - What was done
+    /// * `path` - The path that was changed
+    /// * `reason` - Why the change was made
+    /// * `undo` - How to revert the change, for reversible actions
+    fn record_action(
+        &self,
+        host: &dyn HostServices,
+        action: journal::Action,
+        path: &Path,
+        reason: String,
+        undo: Option<String>,
+    ) {
+        let entry = journal::Entry {
+            time: host.unix_time(),
+            action,
+            path: path.to_string_lossy().to_string(),
+            reason,
+            undo,
+        };
+        let journal_path = Path::new(journal::JOURNAL_FILE);
+        let journal = journal::append(host.read_text_file(journal_path).ok().as_deref(), &entry);
+        if let Err(e) = host.write_text_file(journal_path, &journal) {
+            log::warn!("Could not record {:?} in the journal: {}", path, e);
         }
+    }
 
-        // Create output section
-        sections.push(zed::SlashCommandOutputSection {
-            label: "Build Result".to_string(),
-            range: zed::Range {
-                start: 0,
-                end: full_output_text.len() as u32,
-            },
-        });
+    /// Removes the installs of a downloaded tool superseded by `install_dir`.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `tool` - The tool, e.g. `cangjie-lsp` or `cjdb`
+    /// * `downloads_dir` - The directory holding `<tool>-<version>` installs
+    /// * `install_dir` - The install that is kept
+    fn remove_stale_installs(
+        &self,
+        host: &dyn HostServices,
+        tool: &str,
+        downloads_dir: &Path,
+        install_dir: &Path,
+    ) {
+        let prefix = format!("{}-", tool);
+        let installs = host.subdirectories(downloads_dir);
+        let kept = install_dir.file_name().map(|name| name.to_string_lossy().to_string());
+        for stale in assets::stale_installs(&installs, &prefix, install_dir) {
+            log::info!("Removing old {} download: {:?}", tool, stale);
+            host.remove_path(&stale);
+            let version = stale
+                .file_name()
+                .and_then(|name| name.to_string_lossy().strip_prefix(&prefix).map(str::to_string));
+            self.record_action(
+                host,
+                journal::Action::Delete,
+                &stale,
+                format!("Superseded by `{}`", kept.as_deref().unwrap_or_default()),
+                version.filter(|_| tool == SERVER_NAME).map(|version| {
+                    format!(
+                        "set '{}' to \"{}\" to download it again",
+                        CONFIG_LSP_VERSION_KEY, version
+                    )
+                }),
+            );
+        }
+    }
 
-        Ok(zed::SlashCommandOutput {
-            text: full_output_text,
-            sections,
-        })
+    /// Downloads, verifies and installs a language server release.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `platform` - The platform to install for
+    /// * `release` - The release to install
+    /// * `downloads_dir` - The directory holding `cangjie-lsp-<version>` installs
+    /// * `report_status` - Receives installation status updates
+    ///
+    /// # Returns
+    /// * `Result<String, String>` - The installed LSP path or an error message
+    fn install_release(
+        &self,
+        host: &dyn HostServices,
+        platform: (Os, Architecture),
+        release: &zed::GithubRelease,
+        downloads_dir: &Path,
+        report_status: &dyn Fn(&zed::LanguageServerInstallationStatus),
+    ) -> Result<String, String> {
+        let tool = SERVER_NAME;
+        self.install_tool_release(host, platform, tool, release, downloads_dir, report_status)
     }
 
-    /// Handles the `/cangjie-check-updates` slash command to check for LSP updates.
-    /// 
+    /// Downloads, verifies and installs a tool from a toolchain release into its own
+    /// `<tool>-<version>` directory.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `platform` - The platform to install for
+    /// * `tool` - The tool, e.g. `cangjie-lsp` or `cjdb`
+    /// * `release` - The release to install
+    /// * `downloads_dir` - The directory holding `<tool>-<version>` installs
+    /// * `report_status` - Receives installation status updates
+    ///
     /// # Returns
-    /// * `Result<zed::SlashCommandOutput, String>` - The update check result or an error message
-    fn handle_check_updates_command(&self) -> Result<zed::SlashCommandOutput, String> {
-        const LAST_CHECK_KEY: &str = "last_update_check_cangjie_lsp";
-        const CHECK_INTERVAL_SECONDS: u64 = 3600; // 1 hour
-        
-        // Get current timestamp
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| format!("Time error: {}", e))?
-            .as_secs();
-
-        // Check if we've checked recently
-        if let Ok(state) = self.in_memory_state.lock()
-            && let Some(last_check_str) = state.get(LAST_CHECK_KEY)
-            && let Ok(last_check) = last_check_str.parse::<u64>()
-            && now - last_check < CHECK_INTERVAL_SECONDS
+    /// * `Result<String, String>` - The installed binary's path or an error message
+    fn install_tool_release(
+        &self,
+        host: &dyn HostServices,
+        (os, arch): (Os, Architecture),
+        tool: &str,
+        release: &zed::GithubRelease,
+        downloads_dir: &Path,
+        report_status: &dyn Fn(&zed::LanguageServerInstallationStatus),
+    ) -> Result<String, String> {
+        let expected_asset = self.get_asset_name_for_platform(os, arch, tool)?;
+        let binary_name = assets::binary_file_name(os, tool);
+        let find_binary = |dir: &Path| self.find_installed_tool(host, os, tool, dir);
+        let asset_names: Vec<String> = release.assets.iter().map(|a| a.name.clone()).collect();
+        let no_asset = || {
+            format!(
+                "No {} asset for {} in release {} (expected e.g. '{}')",
+                tool,
+                assets::platform_label(os, arch),
+                release.version,
+                expected_asset
+            )
+        };
+        let asset_name =
+            assets::match_asset(tool, os, arch, &asset_names).ok_or_else(no_asset)?;
+        let asset = release
+            .assets
+            .iter()
+            .find(|a| a.name == asset_name)
+            .ok_or_else(no_asset)?;
+        log::info!("Downloading {} asset: {}", tool, asset.name);
+
+        // Each release is installed into its own directory, so installs are reused across
+        // restarts and archives can bring their runtime libraries along
+        let install_dir = downloads_dir.join(format!("{}-{}", tool, release.version));
+        if let Some(existing) = find_binary(&install_dir) {
+            log::info!("Using previously downloaded {}: {:?}", tool, existing);
+            return Ok(existing.to_string_lossy().to_string());
+        }
+
+        let feature = if tool == SERVER_NAME {
+            compat::FEATURE_LANGUAGE_SERVER
+        } else {
+            compat::FEATURE_DEBUGGER
+        };
+        if let Some(gate) = self
+            .check_release_compatibility(host, release, downloads_dir)
+            .filter(|gate| gate.disables(feature))
         {
-            return Ok(zed::SlashCommandOutput {
-                text: "Update check performed recently. Skipping...".to_string(),
-                sections: vec![zed::SlashCommandOutputSection {
-                    label: "Status".to_string(),
-                    range: zed::Range { start: 0, end: 45 },
-                }],
-            });
+            report_status(&zed::LanguageServerInstallationStatus::Failed(gate.message()));
+            return Err(gate.message());
         }
 
-        // Note: Actual HTTP calls in synchronous context might not be supported by Zed's synchronous trait methods
-        // For demonstration, we'll simulate the response
-        let simulated_response = r#"{"tag_name": "v0.1.0"}"#;
-        let release_info: serde_json::Value = serde_json::from_str(simulated_response)
-            .map_err(|e| format!("Failed to parse simulated JSON: {}", e))?;
+        // Downloads land under a `.partial` name and are moved into place only once they
+        // are complete and verified, so an interrupted download is never mistaken for an
+        // installed server
+        let staging_dir = PathBuf::from(format!("{}{}", install_dir.display(), PARTIAL_SUFFIX));
+        host.remove_path(&staging_dir);
+        let fail = |e: String| {
+            host.remove_path(&staging_dir);
+            host.remove_path(&install_dir);
+            report_status(&zed::LanguageServerInstallationStatus::Failed(e.clone()));
+            format!("Downloaded {} was deleted: {}", tool, e)
+        };
+
+        let binary_path = match assets::archive_type(&asset.name) {
+            zed::DownloadedFileType::Uncompressed => {
+                let binary_path = install_dir.join(&binary_name);
+                let partial_path =
+                    install_dir.join(format!("{}{}", binary_name, PARTIAL_SUFFIX));
+                host.create_dir_all(&install_dir)
+                    .map_err(|e| format!("Failed to create {:?}: {}", install_dir, e))?;
+                self.download_asset(
+                    host,
+                    &asset.download_url,
+                    &partial_path,
+                    zed::DownloadedFileType::Uncompressed,
+                )
+                .map_err(|e| fail(format!("Download failed: {}", e)))?;
+                self.verify_download(host, release, &asset.name, &partial_path)
+                    .map_err(fail)?;
+                host.rename(&partial_path, &binary_path).map_err(fail)?;
+                binary_path
+            }
+            file_type => {
+                // Zed only extracts archives while downloading them, so an archive with a
+                // published checksum is downloaded once as is, verified, and that same file
+                // is extracted locally
+                if checksum::find_checksum_asset(&asset.name, &asset_names).is_some() {
+                    let archive_path =
+                        downloads_dir.join(format!("{}{}", asset.name, PARTIAL_SUFFIX));
+                    let extracted = self
+                        .download_asset(
+                            host,
+                            &asset.download_url,
+                            &archive_path,
+                            zed::DownloadedFileType::Uncompressed,
+                        )
+                        .map_err(|e| format!("Download failed: {}", e))
+                        .and_then(|()| {
+                            self.verify_download(host, release, &asset.name, &archive_path)
+                        })
+                        .and_then(|()| {
+                            let (archive, dest) = (&archive_path, &staging_dir);
+                            self.extract_archive(host, os, file_type, archive, dest, &binary_name)
+                        });
+                    host.remove_path(&archive_path);
+                    extracted.map_err(fail)?;
+                } else {
+                    self.download_asset(host, &asset.download_url, &staging_dir, file_type)
+                        .map_err(|e| fail(format!("Download failed: {}", e)))?;
+                }
+                let staged = find_binary(&staging_dir).ok_or_else(|| {
+                    fail(format!("{} does not contain {}", asset.name, binary_name))
+                })?;
+                host.rename(&staging_dir, &install_dir).map_err(fail)?;
+                let relative = staged.strip_prefix(&staging_dir).unwrap_or(&staged);
+                install_dir.join(relative)
+            }
+        };
 
-        if let Some(tag_name) = release_info.get("tag_name").and_then(|v| v.as_str()) {
-            // Update last check timestamp
-            {
-                let mut state = self.in_memory_state.lock().unwrap();
-                state.insert(LAST_CHECK_KEY.to_string(), now.to_string());
-            }
-
-            let message = format!("Latest Cangjie LSP release: **{}**", tag_name);
-            Ok(zed::SlashCommandOutput {
-                text: message.clone(),
-                sections: vec![zed::SlashCommandOutputSection {
-                    label: "Update Info".to_string(),
-                    range: zed::Range {
-                        start: 0,
-                        end: message.len() as u32,
-                    },
-                }],
-            })
-        } else {
-            Err("Could not find 'tag_name' in simulated release info.".into())
+        if os != Os::Windows {
+            host.make_executable(&binary_path)
+                .map_err(|e| format!("Failed to make executable: {}", e))?;
         }
+
+        log::info!("{} downloaded successfully to: {:?}", tool, binary_path);
+        self.record_action(
+            host,
+            journal::Action::Install,
+            &install_dir,
+            format!("Downloaded {} release {}", tool, release.version),
+            Some(format!("delete `{}`", install_dir.display())),
+        );
+        self.remove_stale_installs(host, tool, downloads_dir, &install_dir);
+        Ok(binary_path.to_string_lossy().to_string())
     }
-}
 
-/// Gets the appropriate binary name with extension based on the current platform.
-/// 
-/// # Arguments
+    /// Extracts a downloaded archive with the platform's tools.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `os` - The current platform
+    /// * `file_type` - The archive type
+    /// * `archive` - The downloaded archive
+    /// * `dest` - The directory to extract into
+    /// * `binary_name` - The file name a gzipped binary is decompressed to
+    ///
+    /// # Returns
+    /// * `Result<(), String>` - An error naming the extraction tool if it failed
+    fn extract_archive(
+        &self,
+        host: &dyn HostServices,
+        os: Os,
+        file_type: zed::DownloadedFileType,
+        archive: &Path,
+        dest: &Path,
+        binary_name: &str,
+    ) -> Result<(), String> {
+        let Some((program, args)) =
+            assets::extract_command(os, file_type, archive, dest, binary_name)
+        else {
+            return Ok(());
+        };
+        host.create_dir_all(dest)?;
+        let output = host
+            .run(&program, &args)
+            .map_err(|e| format!("Could not run {} to extract the archive: {}", program, e))?;
+        if output.status != Some(0) {
+            return Err(format!(
+                "{} could not extract the archive: {}",
+                program,
+                output.stderr.trim()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Selects the directory language servers are downloaded to.
+    ///
+    /// The extension's working directory, which Zed resolves relative paths against, is
+    /// used when a test write succeeds. Otherwise `cangjie.download.dir` is tried, and last
+    /// a directory below the system temp directory, whose downloads may be deleted by the
+    /// system. The choice is logged and shown by `/cangjie-info`.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Result<PathBuf, String>` - The downloads directory, or an error listing every
+    ///   location tried
+    fn select_downloads_dir(&self, host: &dyn HostServices) -> Result<PathBuf, String> {
+        let mut candidates = vec![(PathBuf::new(), "extension working directory".to_string())];
+        if let Some(dir) = host
+            .setting(CONFIG_DOWNLOAD_DIR_KEY)
+            .and_then(|value| value.as_str().map(str::trim).map(str::to_string))
+            .filter(|dir| !dir.is_empty())
+        {
+            if let Err(e) = paths::check_setting_path(CONFIG_DOWNLOAD_DIR_KEY, &dir) {
+                log::warn!("{}", e);
+            } else {
+                let dir = expand_path_variables(&dir, |name| host.env_var(name));
+                let label = format!("'{}'", CONFIG_DOWNLOAD_DIR_KEY);
+                candidates.push((PathBuf::from(dir), label));
+            }
+        }
+        let temp = ["TMPDIR", "TEMP", "TMP"]
+            .iter()
+            .find_map(|name| host.env_var(name))
+            .unwrap_or_else(|| "/tmp".to_string());
+        candidates.push((
+            PathBuf::from(temp).join(TEMP_DOWNLOADS_DIR),
+            "temporary directory; downloads may not persist".to_string(),
+        ));
+
+        let temporary = candidates.len() - 1;
+        let mut failures = Vec::new();
+        for (index, (dir, source)) in candidates.into_iter().enumerate() {
+            let shown = if dir.as_os_str().is_empty() {
+                source.clone()
+            } else {
+                format!("`{}` ({})", dir.display(), source)
+            };
+            if let Err(e) = self.probe_writable(host, &dir) {
+                log::warn!("Cannot download the language server to {}: {}", shown, e);
+                failures.push(format!("{}: {}", shown, e));
+                continue;
+            }
+            if index == temporary {
+                log::warn!("Downloading the language server to {}", shown);
+            } else {
+                log::info!("Downloading the language server to {}", shown);
+            }
+            if let Ok(mut state) = self.in_memory_state.lock() {
+                state.insert(
+                    STATE_DOWNLOADS_DIR_KEY.to_string(),
+                    dir.to_string_lossy().to_string(),
+                );
+                state.insert(STATE_DOWNLOADS_LOCATION_KEY.to_string(), shown);
+            }
+            return Ok(dir);
+        }
+        Err(format!(
+            "No writable location for language server downloads; set '{}' to a writable \
+             directory. Tried:\n{}",
+            CONFIG_DOWNLOAD_DIR_KEY,
+            failures.join("\n")
+        ))
+    }
+
+    /// Checks that a directory can be written to by creating and removing a file in it.
+    ///
+    /// # Arguments
+    /// * `host` - The host services used for filesystem access
+    /// * `dir` - The directory, created if missing; empty for the working directory
+    ///
+    /// # Returns
+    /// * `Result<(), String>` - An error if the directory is not writable
+    fn probe_writable(&self, host: &dyn HostServices, dir: &Path) -> Result<(), String> {
+        if !dir.as_os_str().is_empty() {
+            host.create_dir_all(dir)?;
+        }
+        let probe = dir.join(WRITE_PROBE_FILE);
+        host.write_text_file(&probe, "")?;
+        host.remove_path(&probe);
+        Ok(())
+    }
+
+    /// Moves language servers downloaded by older versions of the extension from
+    /// `$HOME/.zed/extensions` into the downloads directory.
+    ///
+    /// Best effort: without access to the home directory nothing happens, and downloads that
+    /// cannot be moved are left in place.
+    ///
+    /// # Arguments
+    /// * `host` - The host services used for filesystem access
+    /// * `downloads_dir` - The directory holding `cangjie-lsp-<version>` installs
+    fn migrate_legacy_downloads(&self, host: &dyn HostServices, downloads_dir: &Path) {
+        let Some(home) = host.env_var("HOME").or_else(|| host.env_var("USERPROFILE")) else {
+            return;
+        };
+        let legacy_dir = PathBuf::from(home).join(".zed").join("extensions");
+        let prefix = format!("{}-", SERVER_NAME);
+        for legacy in host.subdirectories(&legacy_dir) {
+            let Some(name) = legacy.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if !name.starts_with(&prefix) || name.ends_with(PARTIAL_SUFFIX) {
+                continue;
+            }
+            let target = downloads_dir.join(name);
+            if host.is_dir(&target) {
+                log::info!("Removing legacy LSP download {:?}, already migrated", legacy);
+                host.remove_path(&legacy);
+                self.record_action(
+                    host,
+                    journal::Action::Delete,
+                    &legacy,
+                    format!("Legacy download, already migrated to `{}`", target.display()),
+                    None,
+                );
+                continue;
+            }
+            match host.rename(&legacy, &target) {
+                Ok(()) => {
+                    log::info!("Migrated legacy LSP download {:?}", legacy);
+                    self.record_action(
+                        host,
+                        journal::Action::Move,
+                        &target,
+                        format!("Migrated the legacy download `{}`", legacy.display()),
+                        Some(format!("move it back to `{}`", legacy.display())),
+                    );
+                }
+                Err(e) => log::warn!("Could not migrate legacy LSP download {:?}: {}", legacy, e),
+            }
+        }
+    }
+
+    /// Downloads a release asset, through the configured mirror if there is one.
+    ///
+    /// When the mirror download fails, the asset is downloaded from GitHub instead. Failed
+    /// attempts are removed and retried with exponential backoff.
+    ///
+    /// # Arguments
+    /// * `host` - The host services used for downloads and settings
+    /// * `url` - The GitHub download URL of the asset
+    /// * `path` - Where to download the asset to
+    /// * `file_type` - How to store or extract the download
+    ///
+    /// # Returns
+    /// * `Result<(), String>` - An error if neither the mirror nor GitHub could be used
+    fn download_asset(
+        &self,
+        host: &dyn HostServices,
+        url: &str,
+        path: &Path,
+        file_type: zed::DownloadedFileType,
+    ) -> Result<(), String> {
+        let mirrored = host
+            .setting(CONFIG_DOWNLOAD_MIRROR_KEY)
+            .and_then(|value| value.as_str().and_then(|m| assets::mirror_url(url, m)));
+        let mut delay = DOWNLOAD_RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            if let Some(mirrored) = &mirrored {
+                match host.download(mirrored, path, file_type) {
+                    Ok(()) => return Ok(()),
+                    Err(e) => {
+                        log::warn!("Mirror download {} failed ({}); trying GitHub", mirrored, e);
+                        host.remove_path(path);
+                    }
+                }
+            }
+            match host.download(url, path, file_type) {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt < DOWNLOAD_ATTEMPTS => {
+                    log::warn!(
+                        "Download of {} failed (attempt {} of {}): {}; retrying in {:?}",
+                        url,
+                        attempt,
+                        DOWNLOAD_ATTEMPTS,
+                        e,
+                        delay
+                    );
+                    host.remove_path(path);
+                    host.sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    host.remove_path(path);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Verifies a downloaded release asset against the release's published SHA-256 checksum.
+    ///
+    /// Releases without a checksum file are accepted with a warning.
+    ///
+    /// # Arguments
+    /// * `host` - The host services used for downloads and file access
+    /// * `release` - The release the asset was downloaded from
+    /// * `asset_name` - The name of the downloaded asset
+    /// * `download_path` - Where the asset was downloaded to
+    ///
+    /// # Returns
+    /// * `Result<(), String>` - An error if the checksum is missing for the asset or mismatches
+    fn verify_download(
+        &self,
+        host: &dyn HostServices,
+        release: &zed::GithubRelease,
+        asset_name: &str,
+        download_path: &Path,
+    ) -> Result<(), String> {
+        let asset_names: Vec<String> = release.assets.iter().map(|a| a.name.clone()).collect();
+        let Some(checksum_name) = checksum::find_checksum_asset(asset_name, &asset_names) else {
+            log::warn!(
+                "Release {} publishes no checksum file; skipping verification of {}",
+                release.version,
+                asset_name
+            );
+            return Ok(());
+        };
+        let scratch = download_path.with_file_name(format!("{}.checksum", asset_name));
+        let contents = self.download_text_asset(host, release, checksum_name, &scratch)?;
+
+        let expected = checksum::parse_checksum(&contents, asset_name)
+            .ok_or_else(|| format!("{} lists no checksum for {}", checksum_name, asset_name))?;
+        let actual = host.sha256_file(download_path)?;
+        checksum::check_digest(download_path, &expected, &actual)?;
+        log::info!("Verified SHA-256 of {} against {}", asset_name, checksum_name);
+        Ok(())
+    }
+
+    /// Downloads a small text asset of a release and returns its contents.
+    ///
+    /// # Arguments
+    /// * `host` - The host services used for downloads and file access
+    /// * `release` - The release publishing the asset
+    /// * `asset_name` - The name of the asset
+    /// * `scratch_path` - Temporary download location, removed afterwards
+    ///
+    /// # Returns
+    /// * `Result<String, String>` - The asset contents or an error message
+    fn download_text_asset(
+        &self,
+        host: &dyn HostServices,
+        release: &zed::GithubRelease,
+        asset_name: &str,
+        scratch_path: &Path,
+    ) -> Result<String, String> {
+        let asset = release
+            .assets
+            .iter()
+            .find(|a| a.name == asset_name)
+            .ok_or_else(|| format!("Release {} has no asset '{}'", release.version, asset_name))?;
+        self.download_asset(
+            host,
+            &asset.download_url,
+            scratch_path,
+            zed::DownloadedFileType::Uncompressed,
+        )
+        .map_err(|e| format!("Failed to download {}: {}", asset_name, e))?;
+        let contents = host.read_text_file(scratch_path);
+        host.remove_path(scratch_path);
+        contents.map_err(|e| format!("Failed to read {}: {}", asset_name, e))
+    }
+
+    /// Checks a language server release against the compatibility manifest.
+    ///
+    /// The rules shipped with the extension are combined with the release's own
+    /// `compatibility.json`, if it publishes one. The result is stored so that
+    /// `/cangjie-info` and the debugger can explain which features are disabled.
+    ///
+    /// # Arguments
+    /// * `host` - The host services used for downloads and file access
+    /// * `release` - The release about to be downloaded
+    /// * `scratch_dir` - Directory for temporary downloads
+    ///
+    /// # Returns
+    /// * `Option<compat::Incompatibility>` - The unmet requirement, if any
+    fn check_release_compatibility(
+        &self,
+        host: &dyn HostServices,
+        release: &zed::GithubRelease,
+        scratch_dir: &Path,
+    ) -> Option<compat::Incompatibility> {
+        let mut rules = compat::parse_manifest(compat::SHIPPED_MANIFEST).unwrap_or_else(|e| {
+            log::warn!("Ignoring shipped compatibility manifest: {}", e);
+            Vec::new()
+        });
+        if release.assets.iter().any(|a| a.name == compat::MANIFEST_ASSET) {
+            let scratch = scratch_dir.join(format!("{}.{}", SERVER_NAME, compat::MANIFEST_ASSET));
+            match self
+                .download_text_asset(host, release, compat::MANIFEST_ASSET, &scratch)
+                .and_then(|json| compat::parse_manifest(&json))
+            {
+                Ok(release_rules) => rules.extend(release_rules),
+                Err(e) => log::warn!("Ignoring release compatibility manifest: {}", e),
+            }
+        }
+
+        let gate = compat::check(&rules, SERVER_NAME, &release.version);
+        self.set_worktree_state(
+            &host.worktree_root(),
+            STATE_COMPATIBILITY_KEY,
+            gate.as_ref().map(|gate| gate.to_state()),
+        );
+        gate
+    }
+
+    /// Returns the stored compatibility gate of the worktree if it disables `feature`
+    fn disabled_by_compatibility(
+        &self,
+        root: &str,
+        feature: &str,
+    ) -> Option<compat::Incompatibility> {
+        self.worktree_state(root, STATE_COMPATIBILITY_KEY)
+            .and_then(|state| compat::Incompatibility::from_state(&state))
+            .filter(|gate| gate.disables(feature))
+    }
+
+    /// Fetches the language server release matching the toolchain channel.
+    ///
+    /// A pinned version (`cangjie.lsp.version`) selects that release tag regardless of the
+    /// channel. Otherwise `release` uses the latest stable release (or the latest release
+    /// including pre-releases with `cangjie.lsp.preRelease`), `nightly` the latest
+    /// pre-release and `lts` the rolling LTS tag. Release metadata is cached in the extension
+    /// state for [`RELEASE_CACHE_TTL_SECS`] to stay clear of GitHub's rate limits.
+    ///
+    /// # Arguments
+    /// * `host` - The host services used for network access
+    /// * `channel` - The configured toolchain channel
+    /// * `pinned` - The pinned version or tag, if any
+    ///
+    /// # Returns
+    /// * `Result<zed::GithubRelease, String>` - The release metadata or an error message
+    fn fetch_language_server_release(
+        &self,
+        host: &dyn HostServices,
+        channel: ToolchainChannel,
+        pinned: Option<&str>,
+    ) -> Result<zed::GithubRelease, String> {
+        let pre_release = self.channel_pre_release(host, channel);
+        let cache_key = self.release_cache_key(host, channel, pinned);
+        let now = host.unix_time();
+        if let Some(release) = self.cached_release(&cache_key, now) {
+            log::info!("Using cached release metadata for {}", release.version);
+            return Ok(release);
+        }
+
+        let release = match pinned {
+            Some(pin) => {
+                let mut errors = Vec::new();
+                let found = version::release_tag_candidates(pin).into_iter().find_map(|tag| {
+                    let selector = host::ReleaseSelector::Tag(&tag);
+                    match host.github_release(LSP_RELEASE_REPO, selector) {
+                        Ok(release) => Some(release),
+                        Err(e) => {
+                            errors.push(format!("{}: {}", tag, e));
+                            None
+                        }
+                    }
+                });
+                found.ok_or_else(|| {
+                    format!(
+                        "Failed to fetch pinned language server release '{}' ({}): {}",
+                        pin,
+                        CONFIG_LSP_VERSION_KEY,
+                        errors.join("; ")
+                    )
+                })?
+            }
+            None => {
+                let selector = match channel {
+                    ToolchainChannel::Release | ToolchainChannel::Nightly => {
+                        host::ReleaseSelector::Latest { pre_release }
+                    }
+                    ToolchainChannel::Lts => host::ReleaseSelector::Tag(LTS_RELEASE_TAG),
+                };
+                host.github_release(LSP_RELEASE_REPO, selector).map_err(|e| {
+                    format!(
+                        "Failed to fetch GitHub release ({} channel): {}",
+                        channel.as_str(),
+                        e
+                    )
+                })?
+            }
+        };
+
+        if let Ok(mut state) = self.in_memory_state.lock() {
+            let entry = format!("{}\t{}", now, host::encode_release(&release));
+            state.insert(cache_key, entry);
+        }
+        Ok(release)
+    }
+
+    /// Returns whether the latest release of a channel includes pre-releases.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `channel` - The configured toolchain channel
+    ///
+    /// # Returns
+    /// * `bool` - True for `nightly`, and for `release` with `cangjie.lsp.preRelease`
+    fn channel_pre_release(&self, host: &dyn HostServices, channel: ToolchainChannel) -> bool {
+        match channel {
+            ToolchainChannel::Release => self.lsp_pre_release(host),
+            ToolchainChannel::Nightly => true,
+            ToolchainChannel::Lts => false,
+        }
+    }
+
+    /// Builds the state key release metadata of a channel or pinned version is cached under.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `channel` - The configured toolchain channel
+    /// * `pinned` - The pinned version or tag, if any
+    ///
+    /// # Returns
+    /// * `String` - The in-memory state key
+    fn release_cache_key(
+        &self,
+        host: &dyn HostServices,
+        channel: ToolchainChannel,
+        pinned: Option<&str>,
+    ) -> String {
+        match pinned {
+            Some(pin) => format!("{}tag={}", STATE_RELEASE_CACHE_PREFIX, pin),
+            None => format!(
+                "{}channel={} pre_release={}",
+                STATE_RELEASE_CACHE_PREFIX,
+                channel.as_str(),
+                self.channel_pre_release(host, channel)
+            ),
+        }
+    }
+
+    /// Returns cached release metadata that is younger than [`RELEASE_CACHE_TTL_SECS`].
+    ///
+    /// # Arguments
+    /// * `cache_key` - The state key of the release selection
+    /// * `now` - The current Unix time in seconds
+    ///
+    /// # Returns
+    /// * `Option<zed::GithubRelease>` - The cached release, if still fresh
+    fn cached_release(&self, cache_key: &str, now: u64) -> Option<zed::GithubRelease> {
+        let state = self.in_memory_state.lock().ok()?;
+        let (fetched_at, json) = state.get(cache_key)?.split_once('\t')?;
+        let age = now.checked_sub(fetched_at.parse().ok()?)?;
+        if age >= RELEASE_CACHE_TTL_SECS {
+            return None;
+        }
+        host::decode_release(&serde_json::from_str(json).ok()?).ok()
+    }
+
+    /// Compares the language server version with the SDK's `cjc` and logs a warning when
+    /// they are incompatible, unless `cangjie.lsp.ignoreVersionMismatch` is set.
+    ///
+    /// The warning is kept for `/cangjie-info`; it is not reported as an installation
+    /// status, since the server still starts. Versions that cannot be determined are not
+    /// reported, and the versions of unchanged binaries are not queried again.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `server_path` - Path of the language server binary
+    fn check_language_server_version(&self, worktree: &zed::Worktree, server_path: &str) {
+        let ignore = self
+            .extension_setting(worktree, CONFIG_IGNORE_VERSION_MISMATCH_KEY)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let host = ZedHost::new(worktree);
+        let parse = |path: &str| self.cached_tool_version(&host, path);
+        let warning = match (
+            parse(server_path),
+            self.cjc_binary_path(&host).ok().and_then(|p| parse(&p)),
+        ) {
+            (Some(lsp), Some(cjc)) => version::check_lsp_compatibility(&lsp, &cjc).err(),
+            _ => None,
+        };
+
+        self.set_worktree_state(
+            &worktree.root_path(),
+            STATE_VERSION_WARNING_KEY,
+            warning.clone().filter(|_| !ignore),
+        );
+
+        if let Some(message) = warning {
+            if ignore {
+                log::info!(
+                    "Ignoring version mismatch ({}): {}",
+                    CONFIG_IGNORE_VERSION_MISMATCH_KEY,
+                    message
+                );
+                return;
+            }
+            log_version_warning(&message);
+        }
+    }
+
+    /// Hashes the settings and environment that influence language server resolution.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `u64` - The settings part of a resolution fingerprint
+    fn resolution_settings_hash(&self, host: &dyn HostServices) -> u64 {
+        let mut parts = vec![compat::EXTENSION_VERSION.to_string()];
+        parts.extend(RESOLUTION_SETTING_KEYS.iter().map(|key| {
+            host.setting(key).map_or_else(String::new, |value| value.to_string())
+        }));
+        parts.push(host.lsp_binary_override().unwrap_or_default());
+        parts.extend(RESOLUTION_ENV_VARS.iter().map(|name| host.env_var(name).unwrap_or_default()));
+        fingerprint::hash_settings(&parts)
+    }
+
+    /// Returns the stored resolution of the worktree if nothing it depended on changed.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Option<fingerprint::Resolution>` - The resolution to reuse, if still valid
+    fn cached_resolution(&self, host: &dyn HostServices) -> Option<fingerprint::Resolution> {
+        let state = host.read_text_file(Path::new(fingerprint::STATE_FILE)).ok()?;
+        let resolution = fingerprint::load(&state, &host.worktree_root())?;
+        if resolution.fingerprint.settings != self.resolution_settings_hash(host) {
+            log::debug!("Settings changed since the last language server resolution");
+            return None;
+        }
+        let changed = resolution
+            .fingerprint
+            .stamps
+            .iter()
+            .find(|(path, time)| host.modified_time(Path::new(path)) != *time);
+        if let Some((path, _)) = changed {
+            log::debug!("{} changed since the last language server resolution", path);
+            return None;
+        }
+        // Downloads that look for newer releases do so once the release metadata expired
+        if is_downloaded_server(&resolution.server_path)
+            && self.pinned_lsp_version(host).is_none()
+            && (self.lsp_auto_update(host) || self.lsp_prefetch(host))
+        {
+            let cache_key = self.release_cache_key(host, self.toolchain_channel(host), None);
+            if self.cached_release(&cache_key, host.unix_time()).is_none() {
+                log::debug!("Release metadata expired; checking for a newer language server");
+                return None;
+            }
+        }
+        Some(resolution)
+    }
+
+    /// Stores the fingerprint of a successful resolution for the next start.
+    ///
+    /// Not stored when a newer release was just downloaded, so the next start switches to it.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `server_path` - The resolved language server
+    /// * `cjc_path` - The resolved compiler, if any
+    fn store_resolution(&self, host: &dyn HostServices, server_path: &str, cjc_path: Option<&str>) {
+        let downloaded = is_downloaded_server(server_path);
+        let update = self.worktree_state(&host.worktree_root(), STATE_LSP_UPDATE_KEY);
+        if let Some(update) = update {
+            self.set_worktree_state(&host.worktree_root(), STATE_LSP_UPDATE_KEY, None);
+            log::debug!("Not storing the resolution; LSP {} is used from the next start", update);
+            return;
+        }
+        let root = PathBuf::from(host.worktree_root());
+        let mut paths = vec![
+            root.clone(),
+            root.join(tool_versions::TOOL_VERSIONS_FILE),
+            root.join(deveco::LOCAL_PROPERTIES_FILE),
+            PathBuf::from(server_path),
+        ];
+        // The link users configured changes its modification time when it is repointed
+        paths.extend(self.resolve_sdk_root_entry(host).ok().map(|root| root.path));
+        paths.extend(cjc_path.map(PathBuf::from));
+        if downloaded {
+            // New downloads appear as directories of the downloads directory
+            let downloads_dir = self
+                .in_memory_state
+                .lock()
+                .ok()
+                .and_then(|state| state.get(STATE_DOWNLOADS_DIR_KEY).cloned())
+                .filter(|dir| !dir.is_empty())
+                .unwrap_or_else(|| ".".to_string());
+            paths.push(PathBuf::from(downloads_dir));
+        }
+        let stamps = paths
+            .into_iter()
+            .map(|path| {
+                let time = host.modified_time(&path);
+                (path.to_string_lossy().to_string(), time)
+            })
+            .collect();
+        let channel = self.worktree_state(&host.worktree_root(), STATE_LSP_CHANNEL_KEY);
+        let version_warning =
+            self.worktree_state(&host.worktree_root(), STATE_VERSION_WARNING_KEY);
+        let resolution = fingerprint::Resolution {
+            fingerprint: fingerprint::Fingerprint {
+                settings: self.resolution_settings_hash(host),
+                stamps,
+            },
+            server_path: server_path.to_string(),
+            channel,
+            version_warning,
+        };
+        let path = Path::new(fingerprint::STATE_FILE);
+        let previous = host.read_text_file(path).ok();
+        let state = fingerprint::store(previous.as_deref(), &host.worktree_root(), &resolution);
+        if let Err(e) = host.write_text_file(path, &state) {
+            log::warn!("Could not store the language server resolution: {}", e);
+        }
+    }
+
+    /// Drops the stored resolution of the worktree, so the next start probes again.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    fn forget_resolution(&self, host: &dyn HostServices) {
+        let path = Path::new(fingerprint::STATE_FILE);
+        let Ok(state) = host.read_text_file(path) else {
+            return;
+        };
+        if let Some(state) = fingerprint::forget(&state, &host.worktree_root())
+            && let Err(e) = host.write_text_file(path, &state)
+        {
+            log::warn!("Could not clear the language server resolution: {}", e);
+        }
+    }
+
+    /// Restores the in-memory state of a reused resolution.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `resolution` - The stored resolution
+    ///
+    /// # Returns
+    /// * `Option<String>` - The version mismatch warning to report, if any
+    fn restore_resolution(
+        &self,
+        host: &dyn HostServices,
+        resolution: &fingerprint::Resolution,
+    ) -> Option<String> {
+        if let Some(channel) = &resolution.channel {
+            self.record_lsp_channel(host, channel.clone());
+        }
+        let warning = resolution.version_warning.clone();
+        self.set_worktree_state(&host.worktree_root(), STATE_VERSION_WARNING_KEY, warning.clone());
+        warning
+    }
+
+    /// Resolves `cangjie.modulePath`, a directory or list of directories holding the project's
+    /// modules, against the worktree root.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Vec<String>` - The absolute module directories; entries containing `..` are skipped
+    fn module_paths(&self, host: &dyn HostServices) -> Vec<String> {
+        let entries = match host.setting(CONFIG_MODULE_PATH_KEY) {
+            Some(serde_json::Value::Array(paths)) => paths
+                .iter()
+                .filter_map(|path| path.as_str().map(str::to_string))
+                .collect(),
+            Some(serde_json::Value::String(path)) => vec![path],
+            _ => Vec::new(),
+        };
+        let root = host.worktree_root();
+        entries
+            .into_iter()
+            .filter(|path| !path.trim().is_empty())
+            .filter_map(|path| match paths::check_setting_path(CONFIG_MODULE_PATH_KEY, &path) {
+                Ok(()) => {
+                    let path = expand_path_variables(path.trim(), |name| host.env_var(name));
+                    if run_config::is_absolute(&path) {
+                        Some(path)
+                    } else {
+                        Some(Path::new(&root).join(path).to_string_lossy().to_string())
+                    }
+                }
+                Err(e) => {
+                    log::warn!("{}", e);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Resolves `cangjie.stdlibPath`, the standard library the server loads, against the SDK
+    /// root.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Option<String>` - The absolute path, or `None` when unset, invalid or relative
+    ///   without an SDK
+    fn stdlib_path(&self, host: &dyn HostServices) -> Option<String> {
+        let path = host
+            .setting(CONFIG_STDLIB_PATH_KEY)?
+            .as_str()
+            .map(str::trim)
+            .filter(|path| !path.is_empty())?
+            .to_string();
+        if let Err(e) = paths::check_setting_path(CONFIG_STDLIB_PATH_KEY, &path) {
+            log::warn!("{}", e);
+            return None;
+        }
+        let path = expand_path_variables(&path, |name| host.env_var(name));
+        if run_config::is_absolute(&path) {
+            return Some(path);
+        }
+        match self.resolve_sdk_root(host) {
+            Ok(root) => Some(root.join(path).to_string_lossy().to_string()),
+            Err(_) => {
+                log::warn!(
+                    "Ignoring relative '{}' = \"{}\": no SDK found to resolve it against",
+                    CONFIG_STDLIB_PATH_KEY,
+                    path
+                );
+                None
+            }
+        }
+    }
+
+    /// Returns the glob patterns the language server should watch: the package manifest, the
+    /// lock file and Cangjie sources, followed by the entries of `cangjie.files.watch`.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Vec<String>` - The patterns, without duplicates; entries containing `..` are skipped
+    fn watch_patterns(&self, host: &dyn HostServices) -> Vec<String> {
+        let extra = match host.setting(CONFIG_WATCH_PATTERNS_KEY) {
+            Some(serde_json::Value::Array(patterns)) => patterns
+                .iter()
+                .filter_map(|pattern| pattern.as_str().map(str::to_string))
+                .collect(),
+            Some(serde_json::Value::String(pattern)) => vec![pattern],
+            _ => Vec::new(),
+        };
+        let mut patterns: Vec<String> =
+            DEFAULT_WATCH_PATTERNS.iter().map(|pattern| pattern.to_string()).collect();
+        for pattern in extra {
+            let pattern = pattern.trim().to_string();
+            if pattern.is_empty() || patterns.contains(&pattern) {
+                continue;
+            }
+            match paths::check_setting_path(CONFIG_WATCH_PATTERNS_KEY, &pattern) {
+                Ok(()) => patterns.push(pattern),
+                Err(e) => log::warn!("{}", e),
+            }
+        }
+        patterns
+    }
+
+    /// Replaces `cangjie.modulePath` and `cangjie.stdlibPath` in the settings with the
+    /// resolved paths and sets `cangjie.files.watch` to the full list of watch patterns, so the
+    /// workspace configuration matches the initialization options.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `settings` - The flat `cangjie.*` settings
+    ///
+    /// # Returns
+    /// * `serde_json::Map<String, serde_json::Value>` - The settings with resolved paths
+    fn resolve_path_settings(
+        &self,
+        host: &dyn HostServices,
+        mut settings: serde_json::Map<String, serde_json::Value>,
+    ) -> serde_json::Map<String, serde_json::Value> {
+        if settings.contains_key(CONFIG_MODULE_PATH_KEY) {
+            settings.insert(CONFIG_MODULE_PATH_KEY.to_string(), self.module_paths(host).into());
+        }
+        if settings.contains_key(CONFIG_STDLIB_PATH_KEY) {
+            match self.stdlib_path(host) {
+                Some(path) => settings.insert(CONFIG_STDLIB_PATH_KEY.to_string(), path.into()),
+                None => settings.remove(CONFIG_STDLIB_PATH_KEY),
+            };
+        }
+        settings.insert(CONFIG_WATCH_PATTERNS_KEY.to_string(), self.watch_patterns(host).into());
+        settings
+    }
+
+    /// Builds the options sent to the language server with the `initialize` request.
+    ///
+    /// The server reads the SDK location and feature toggles while initializing, before it
+    /// requests the workspace configuration, so they are passed here as well. The SDK entries
+    /// are left out when no SDK is found; the server then falls back to `CANGJIE_HOME`.
+    /// `cangjie.stdlibPath` replaces the SDK's standard library and `cangjie.modulePath` adds
+    /// module directories.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `serde_json::Value` - The initialization options object
+    fn initialization_options(&self, host: &dyn HostServices) -> serde_json::Value {
+        let mut options = serde_json::Map::new();
+        if let Ok(root) = self.resolve_sdk_root(host)
+            && sdk_layout::SdkLayout::is_sdk_root_with(&root, |path| host.is_dir(path))
+        {
+            let modules = root.join("modules");
+            options.insert(
+                "modulesHomeOption".to_string(),
+                root.to_string_lossy().to_string().into(),
+            );
+            if host.is_dir(&modules) {
+                options.insert(
+                    "stdLibPathOption".to_string(),
+                    modules.to_string_lossy().to_string().into(),
+                );
+            }
+        }
+        if let Some(stdlib) = self.stdlib_path(host) {
+            options.insert("stdLibPathOption".to_string(), stdlib.into());
+        }
+        let module_paths = self.module_paths(host);
+        if !module_paths.is_empty() {
+            options.insert("modulePathOption".to_string(), module_paths.into());
+        }
+        options.insert("watchPatterns".to_string(), self.watch_patterns(host).into());
+        let flag = |key: &str, default: bool| {
+            host.setting(key).and_then(|v| v.as_bool()).unwrap_or(default)
+        };
+        options.insert(
+            "enableSemanticHighlighting".to_string(),
+            flag(CONFIG_SEMANTIC_HIGHLIGHTING_KEY, true).into(),
+        );
+        options.insert("checkOnSave".to_string(), flag(CONFIG_CHECK_ON_SAVE_KEY, true).into());
+        let max_file_size = host
+            .setting(CONFIG_MAX_FILE_SIZE_KEY)
+            .and_then(|v| v.as_u64())
+            .unwrap_or(DEFAULT_MAX_FILE_SIZE);
+        options.insert("maxFileSize".to_string(), max_file_size.into());
+        serde_json::Value::Object(options)
+    }
+
+    /// Builds the environment the language server needs to load the SDK's standard library.
+    ///
+    /// `CANGJIE_HOME` points at the resolved SDK and the runtime library directories are put
+    /// in front of the loader path (`LD_LIBRARY_PATH`, `DYLD_LIBRARY_PATH` or `PATH`). Nothing
+    /// is set when no SDK is found.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `os` - The current platform
+    ///
+    /// # Returns
+    /// * `Vec<(String, String)>` - The environment variables
+    fn language_server_env(&self, host: &dyn HostServices, os: Os) -> Vec<(String, String)> {
+        let Ok(layout) = self.sdk_layout(host) else {
+            return Vec::new();
+        };
+        if !sdk_layout::SdkLayout::is_sdk_root_with(&layout.root, |path| host.is_dir(path)) {
+            return Vec::new();
+        }
+        let existing = host.env_var(sdk_layout::library_path_var(os));
+        vec![
+            (
+                ENV_CANGJIE_HOME.to_string(),
+                layout.root.to_string_lossy().to_string(),
+            ),
+            layout.library_path_env(os, existing.as_deref()),
+        ]
+    }
+
+    /// Builds the environment of tasks and of the processes slash commands start: the
+    /// resolved SDK's `CANGJIE_HOME`, runtime library path and binary directories, see
+    /// [`sdk_layout::SdkLayout::task_env`]. Nothing is set when no SDK is found.
+    ///
+    /// The environment is kept for the worktree, because the debug locator is not told the
+    /// worktree of the task it builds.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `os` - The current platform
+    ///
+    /// # Returns
+    /// * `Vec<(String, String)>` - The environment variables
+    fn sdk_environment(&self, host: &dyn HostServices, os: Os) -> Vec<(String, String)> {
+        let env = match self.sdk_layout(host) {
+            Ok(layout)
+                if sdk_layout::SdkLayout::is_sdk_root_with(&layout.root, |path| {
+                    host.is_dir(path)
+                }) =>
+            {
+                layout.task_env(os, |name| host.env_var(name))
+            }
+            _ => Vec::new(),
+        };
+        let root = host.worktree_root();
+        self.set_worktree_state(&root, STATE_SDK_ENVIRONMENT_KEY, serde_json::to_string(&env).ok());
+        env
+    }
+
+    /// Returns the SDK environment kept by [`CangjieExtension::sdk_environment`] for the
+    /// worktree containing a directory, empty if there is none.
+    fn cached_sdk_environment(&self, dir: &str) -> Vec<(String, String)> {
+        Path::new(dir)
+            .ancestors()
+            .find_map(|dir| {
+                self.worktree_state(&dir.to_string_lossy(), STATE_SDK_ENVIRONMENT_KEY)
+            })
+            .and_then(|env| serde_json::from_str(&env).ok())
+            .unwrap_or_default()
+    }
+
+    /// Builds the logging flags from `cangjie.lsp.logLevel` and `cangjie.lsp.logFile`.
+    ///
+    /// A relative log file is resolved against the worktree root. Flags already present in
+    /// `args`, e.g. from `binary.arguments`, are not added again.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `args` - The arguments the server is started with so far
+    ///
+    /// # Returns
+    /// * `Vec<String>` - The flags to append
+    fn language_server_log_args(&self, host: &dyn HostServices, args: &[String]) -> Vec<String> {
+        let has_flag = |flag: &str| {
+            args.iter()
+                .any(|arg| arg == flag || arg.starts_with(&format!("{}=", flag)))
+        };
+        let setting = |key: &str| {
+            host.setting(key)
+                .and_then(|value| value.as_str().map(str::trim).map(str::to_string))
+                .filter(|value| !value.is_empty())
+        };
+        let mut log_args = Vec::new();
+        if let Some(level) = setting(CONFIG_LSP_LOG_LEVEL_KEY)
+            && !has_flag(LSP_LOG_LEVEL_FLAG)
+        {
+            let level = level.to_lowercase();
+            if LSP_LOG_LEVELS.contains(&level.as_str()) {
+                log_args.extend([LSP_LOG_LEVEL_FLAG.to_string(), level]);
+            } else {
+                log::warn!(
+                    "Ignoring '{}' = \"{}\"; expected one of {}",
+                    CONFIG_LSP_LOG_LEVEL_KEY,
+                    level,
+                    LSP_LOG_LEVELS.join(", ")
+                );
+            }
+        }
+        if let Some(file) = setting(CONFIG_LSP_LOG_FILE_KEY)
+            && !has_flag(LSP_LOG_PATH_FLAG)
+        {
+            match paths::check_setting_path(CONFIG_LSP_LOG_FILE_KEY, &file) {
+                Ok(()) => {
+                    let file = expand_path_variables(&file, |name| host.env_var(name));
+                    let file = if run_config::is_absolute(&file) {
+                        file
+                    } else {
+                        Path::new(&host.worktree_root()).join(file).to_string_lossy().to_string()
+                    };
+                    log_args.extend([LSP_LOG_PATH_FLAG.to_string(), file]);
+                }
+                Err(e) => log::warn!("{}", e),
+            }
+        }
+        log_args
+    }
+
+    /// Creates the command to start the language server, integrating LSP settings.
+    /// 
+    /// # Arguments
+    /// * `language_server_id` - ID of the language server to start
+    /// * `worktree` - The current worktree context
+    /// 
+    /// # Returns
+    /// * `Result<zed::Command, String>` - The command to start the LSP server or an error message
+    fn create_language_server_command(
+        &mut self,
+        language_server_id: &zed::LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> Result<zed::Command, String> {
+        // Reopening a project whose settings and toolchain are unchanged skips probing
+        let host = ZedHost::new(worktree);
+        let server_path = match self.cached_resolution(&host) {
+            Some(resolution) => {
+                log::info!("Reusing language server resolution: {}", resolution.server_path);
+                if let Some(warning) = self.restore_resolution(&host, &resolution) {
+                    log_version_warning(&warning);
+                }
+                resolution.server_path
+            }
+            None => {
+                let server_path =
+                    self.ensure_language_server_installed(language_server_id, worktree)?;
+                self.check_language_server_version(worktree, &server_path);
+                let cjc_path = self.cjc_binary_path(&host).ok();
+                self.store_resolution(&host, &server_path, cjc_path.as_deref());
+                server_path
+            }
+        };
+
+        // Determine if LSP settings override the command or args
+        let mut command = zed::Command::new(&server_path);
+        let mut default_args = vec![LSP_DEFAULT_TRANSPORT.to_string()];
+
+        if let Ok(lsp_settings) = LspSettings::for_worktree("cangjie-lsp", worktree)
+            && let Some(binary_settings) = &lsp_settings.binary
+        {
+            if let Some(path_override) = &binary_settings.path {
+                // If path is overridden, use the override path
+                command = zed::Command::new(path_override);
+            }
+            if let Some(args_override) = &binary_settings.arguments {
+                // Overrides keep the default transport unless they choose one
+                let (args, warning) = merge_language_server_args(args_override);
+                if let Some(warning) = warning {
+                    log::warn!("{}", warning);
+                }
+                default_args = args;
+            }
+        }
+
+        // Apply arguments and the SDK runtime environment to the command
+        let log_args = self.language_server_log_args(&host, &default_args);
+        default_args.extend(log_args);
+        let (os, _) = zed::current_platform();
+        let final_command = command
+            .args(default_args)
+            .envs(self.language_server_env(&host, os));
+
+        Ok(final_command)
+    }
+
+    /// Builds the arguments cjlint is started with as a diagnostics-only language server.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>, String>` - The arguments, or an error when the lint server is
+    ///   disabled or `cangjie.lint.args` is malformed
+    fn lint_server_args(&self, host: &dyn HostServices) -> Result<Vec<String>, String> {
+        let enabled = host
+            .setting(CONFIG_LINT_ENABLED_KEY)
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        if !enabled {
+            return Err(format!(
+                "The {} language server is disabled; set '{}' to true to enable it",
+                CJLINT_NAME, CONFIG_LINT_ENABLED_KEY
+            ));
+        }
+        let malformed = || format!("'{}' must be a list of strings", CONFIG_LINT_ARGS_KEY);
+        match host.setting(CONFIG_LINT_ARGS_KEY) {
+            None => Ok(LINT_SERVER_DEFAULT_ARGS.iter().map(|arg| arg.to_string()).collect()),
+            Some(serde_json::Value::Array(args)) => args
+                .iter()
+                .map(|arg| arg.as_str().map(str::to_string).ok_or_else(malformed))
+                .collect(),
+            Some(_) => Err(malformed()),
+        }
+    }
+
+    /// Creates the command to start cjlint as a second language server next to
+    /// `cangjie-lsp`, reporting lint findings as diagnostics.
+    ///
+    /// cjlint is resolved like the other SDK tools, so `cangjie.cjlintPathOverride` can point
+    /// at a wrapper script for cjlint builds without a language server mode.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    ///
+    /// # Returns
+    /// * `Result<zed::Command, String>` - The command to start the lint server or an error
+    fn create_lint_server_command(&self, worktree: &zed::Worktree) -> Result<zed::Command, String> {
+        let host = ZedHost::new(worktree);
+        let args = self.lint_server_args(&host)?;
+        let cjlint = self.auxiliary_tool_path(&host, CJLINT_NAME)?;
+        let (os, _) = zed::current_platform();
+        Ok(zed::Command::new(cjlint)
+            .args(args)
+            .envs(self.language_server_env(&host, os)))
+    }
+
+    /// Builds the arguments cjdb is started with as a debug adapter.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>, String>` - The arguments, or an error when
+    ///   `cangjie.debug.adapterArgs` is malformed
+    fn debugger_args(&self, host: &dyn HostServices) -> Result<Vec<String>, String> {
+        let malformed = || format!("'{}' must be a list of strings", CONFIG_DEBUG_ADAPTER_ARGS_KEY);
+        match host.setting(CONFIG_DEBUG_ADAPTER_ARGS_KEY) {
+            None => Ok(CJDB_DAP_ARGS.iter().map(|arg| arg.to_string()).collect()),
+            Some(serde_json::Value::Array(args)) => args
+                .iter()
+                .map(|arg| arg.as_str().map(str::to_string).ok_or_else(malformed))
+                .collect(),
+            Some(_) => Err(malformed()),
+        }
+    }
+
+    /// Installs cjdb for an SDK that does not ship it, downloading it from the toolchain
+    /// release the way the language server is downloaded.
+    ///
+    /// The newest cjdb downloaded before is reused without contacting GitHub.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `platform` - The platform to install for
+    ///
+    /// # Returns
+    /// * `Result<String, String>` - The cjdb path or an error message
+    fn install_debugger(
+        &self,
+        host: &dyn HostServices,
+        (os, arch): (Os, Architecture),
+    ) -> Result<String, String> {
+        let downloads_dir = self.select_downloads_dir(host)?;
+        if let Some((installed_version, existing)) =
+            self.newest_installed_tool(host, os, CJDB_NAME, &downloads_dir)
+        {
+            log::info!("Using downloaded {} {}: {:?}", CJDB_NAME, installed_version, existing);
+            return Ok(existing.to_string_lossy().to_string());
+        }
+        log::info!("{} not found in SDK, attempting to download...", CJDB_NAME);
+        let channel = self.toolchain_channel(host);
+        let release = self.fetch_language_server_release(host, channel, None)?;
+        self.install_tool_release(host, (os, arch), CJDB_NAME, &release, &downloads_dir, &|_| {})
+            .map_err(|e| {
+                format!(
+                    "{}\n💡 Install an SDK that ships {} or set 'cangjie.{}PathOverride'",
+                    e, CJDB_NAME, CJDB_NAME
+                )
+            })
+    }
+
+    /// Returns the TCP connection to a debug server that is already running, e.g. on another
+    /// machine or in a container.
+    ///
+    /// The scenario's `tcp_connection` takes precedence over `cangjie.debug.tcp.host`,
+    /// `cangjie.debug.tcp.port` and `cangjie.debug.tcp.timeout` field by field.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `scenario` - The `tcp_connection` of the debug scenario, if any
+    ///
+    /// # Returns
+    /// * `Result<Option<zed::TcpArgumentsTemplate>, String>` - The connection, `None` to start
+    ///   the adapter locally over stdio, or an error for an invalid host or port
+    fn debug_tcp_connection(
+        &self,
+        host: &dyn HostServices,
+        scenario: Option<zed::TcpArgumentsTemplate>,
+    ) -> Result<Option<zed::TcpArgumentsTemplate>, String> {
+        let server_host = host.setting(CONFIG_DEBUG_TCP_HOST_KEY);
+        let server_host = server_host.as_ref().and_then(|value| value.as_str());
+        let number = |key: &str| host.setting(key).and_then(|value| value.as_u64());
+        debug::tcp_template(
+            scenario,
+            server_host,
+            number(CONFIG_DEBUG_TCP_PORT_KEY),
+            number(CONFIG_DEBUG_TCP_TIMEOUT_KEY),
+        )
+    }
+
+    /// Creates a debug scenario based on the build task: `cjc build run` tasks build with
+    /// `cjc build`, and `cjpm run` and `cjpm build` tasks with `cjpm build -g`, before the
+    /// program is launched under the debugger with the task's `--run-args`, environment and
+    /// working directory. Optimization levels and `--profile` are kept, and the program is
+    /// taken from the output directory of that profile. `cjpm test` and `cjc --test` tasks
+    /// build the test binary with debug info and launch it with the task's `--filter`.
+    ///
+    /// # Arguments
+    /// * `os` - The platform the program runs on
+    /// * `locator_name` - Name of the locator that resolves the built program
+    /// * `build_task` - The task to debug
+    /// * `resolved_label` - The label of the scenario
+    /// * `debug_adapter_name` - Name of the debug adapter
+    ///
+    /// # Returns
+    /// * `Option<zed::DebugScenario>` - The scenario, or `None` if the task cannot be debugged
+    fn debug_scenario_for_task(
+        &self,
+        os: Os,
+        locator_name: String,
+        build_task: zed::TaskTemplate,
+        resolved_label: String,
+        debug_adapter_name: String,
+    ) -> Option<zed::DebugScenario> {
+        let mut args_it = build_task.args.iter().map(String::as_str);
+        let root = build_task.cwd.as_deref().unwrap_or("${workspaceFolder}");
+        // Optimization levels and `--profile` carry over to the build, e.g. for debugging a
+        // release build with debug info, and select the directory the program is in
+        let profile_flags = cjpm::profile_flags(&build_task.args);
+        let profile = cjpm::build_profile(&build_task.args);
+        let (build_args, program, program_args) = match (
+            build_task.command.as_str(),
+            args_it.next(),
+        ) {
+            (CJC_NAME, Some("build")) if args_it.next() == Some("run") => (
+                [vec!["build".to_string()], profile_flags].concat(),
+                debug::executable_path(
+                    os,
+                    Some("${workspaceFolder}"),
+                    &format!("target/{}/${{workspaceFolderBasename}}", profile),
+                ),
+                cjpm::run_args(&build_task.args),
+            ),
+            (CJPM_NAME, Some("run" | "build")) => {
+                // Library packages have no program to launch
+                let manifest = get_package_manifest(&build_task);
+                if !manifest.is_executable() {
+                    return None;
+                }
+                let program = manifest.executable(&profile, "${workspaceFolderBasename}");
+                (
+                    [vec!["build".to_string(), "-g".to_string()], profile_flags].concat(),
+                    debug::executable_path(os, Some(root), &program),
+                    cjpm::run_args(&build_task.args),
+                )
+            }
+            (CJPM_NAME, Some("test")) => {
+                let (mut build_args, filter_args) = testing::split_filter_args(&build_task.args);
+                for flag in ["--no-run", "-g"] {
+                    if !build_args.iter().any(|arg| arg == flag) {
+                        build_args.push(flag.to_string());
+                    }
+                }
+                (
+                    build_args,
+                    debug::executable_path(os, Some(root), testing::TEST_BINARY_PATH),
+                    filter_args,
+                )
+            }
+            (CJC_NAME, _) if build_task.args.iter().any(|arg| arg == testing::CJC_TEST_FLAG) => {
+                let (mut build_args, filter_args) = testing::split_filter_args(&build_task.args);
+                if !build_args.iter().any(|arg| arg == "-g") {
+                    build_args.push("-g".to_string());
+                }
+                let output = testing::cjc_test_output(&build_args);
+                let program = debug::executable_path(os, Some(root), &output);
+                (build_args, program, filter_args)
+            }
+            _ => return None,
+        };
+
+        let cwd = build_task.cwd.clone();
+        // The task's own variables take precedence over the SDK environment
+        let mut build_env = self.cached_sdk_environment(cwd.as_deref().unwrap_or_default());
+        build_env.retain(|(key, _)| !build_task.env.iter().any(|(k, _)| k == key));
+        build_env.extend(build_task.env.iter().cloned());
+        let env: HashMap<String, String> = build_env.iter().cloned().collect();
+        let build_label = format!("{} (build)", resolved_label);
+        let build_template = zed::BuildTaskDefinitionTemplatePayload {
+            template: zed::BuildTaskTemplate {
+                label: build_label.clone(),
+                command: build_task.command.clone(),
+                args: build_args,
+                env: build_env,
+                cwd: cwd.clone(),
+            },
+            locator_name: Some(locator_name),
+        };
+
+        // Define the debug configuration
+        let config = serde_json::json!({
+            "name": "Launch Cangjie Program",
+            "type": "cjc-frontend", // The adapter name
+            "request": "launch",
+            "program": program,
+            "cwd": cwd.as_deref().unwrap_or("${workspaceFolder}"),
+            "args": program_args,
+            "env": env,
+            "stopOnEntry": false,
+        });
+
+        let Ok(config_str) = serde_json::to_string(&config) else {
+            return None;
+        };
+        // The locator only sees the build task again, so the launch configuration is kept
+        // for `debug_request_for_task`
+        if let Ok(mut state) = self.in_memory_state.lock() {
+            state.insert(
+                format!("{}{}", STATE_DEBUG_LAUNCH_PREFIX, build_label),
+                config_str.clone(),
+            );
+        }
+
+        Some(zed::DebugScenario {
+            adapter: debug_adapter_name,
+            label: resolved_label,
+            config: config_str,
+            tcp_connection: None, // Use stdio
+            build: Some(zed::BuildTaskDefinition::Template(build_template)),
+        })
+    }
+
+    /// Resolves the program a debug scenario's build task built, applying the `args`, `env`
+    /// and `cwd` of the scenario created for the task.
+    ///
+    /// # Arguments
+    /// * `os` - The platform the program runs on
+    /// * `build_task` - The build task of the scenario
+    ///
+    /// # Returns
+    /// * `Result<zed::DebugRequest, String>` - The launch request, or an error if the task
+    ///   does not build a program
+    fn debug_request_for_task(
+        &self,
+        os: Os,
+        build_task: zed::TaskTemplate,
+    ) -> Result<zed::DebugRequest, String> {
+        let in_package =
+            |program: &str| debug::executable_path(os, build_task.cwd.as_deref(), program);
+        let is_cjc_test = build_task.command == CJC_NAME
+            && build_task.args.iter().any(|arg| arg == testing::CJC_TEST_FLAG);
+        let program_path = match (build_task.command.as_str(), build_task.args.first()) {
+            (CJPM_NAME, Some(arg)) if arg == "build" => {
+                let manifest = get_package_manifest(&build_task);
+                let name = manifest.name.clone().ok_or("Failed to get package name")?;
+                if !manifest.is_executable() {
+                    return Err(format!(
+                        "Package '{}' builds a {} library, which cannot be debugged",
+                        name,
+                        manifest.output_type.as_deref().unwrap_or_default()
+                    ));
+                }
+                let profile = cjpm::build_profile(&build_task.args);
+                Some(in_package(&manifest.executable(&profile, &name)))
+            }
+            (CJPM_NAME, Some(arg)) if arg == "test" => {
+                Some(in_package(testing::TEST_BINARY_PATH))
+            }
+            (CJC_NAME, _) if is_cjc_test => {
+                Some(in_package(&testing::cjc_test_output(&build_task.args)))
+            }
+            (_, Some(arg)) if arg == "build" => {
+                let exec_name =
+                    get_project_name(&build_task).ok_or("Failed to get project name")?;
+                let profile = cjpm::build_profile(&build_task.args);
+                let program = format!("target/{}/{}", profile, exec_name);
+                Some(debug::executable_path(os, None, &program))
+            }
+            _ => None,
+        };
+        let program_path = program_path.ok_or("Unsupported build task for debugging")?;
+
+        let launch_config = self
+            .in_memory_state
+            .lock()
+            .ok()
+            .and_then(|state| {
+                state
+                    .get(&format!("{}{}", STATE_DEBUG_LAUNCH_PREFIX, build_task.label))
+                    .cloned()
+            })
+            .and_then(|config| serde_json::from_str(&config).ok())
+            .unwrap_or_default();
+        let launch = zed::LaunchRequest {
+            program: program_path,
+            cwd: build_task.cwd,
+            args: vec![],
+            envs: build_task.env.into_iter().collect(),
+        };
+        Ok(zed::DebugRequest::Launch(debug::apply_launch_config(
+            &launch_config,
+            launch,
+        )))
+    }
+
+    /// Generates the canonical asset name of a binary for a platform.
+    ///
+    /// Downloads accept other naming schemes as well (see [`assets::match_asset`]); this name
+    /// is what error messages and `/cangjie-verify-assets` report as expected.
+    /// 
+    /// # Arguments
+    /// * `os` - The operating system type
+    /// * `arch` - The system architecture
+    /// * `base_name` - The base name of the asset
+    /// 
+    /// # Returns
+    /// * `Result<String, String>` - The generated asset name or an error message
+    fn get_asset_name_for_platform(
+        &self,
+        os: Os,
+        arch: Architecture,
+        base_name: &str,
+    ) -> Result<String, String> {
+        let arch_str = match (os, arch) {
+            (_, Architecture::X8664) => "x86_64",
+            (_, Architecture::Aarch64) => "aarch64",
+            (Os::Linux | Os::Windows, Architecture::X86) => "i686",
+            _ => return Err("Unsupported architecture".into()),
+        };
+
+        let (os_str, ext) = match os {
+            Os::Mac => ("apple-darwin", ""),
+            Os::Linux => ("unknown-linux-gnu", ""),
+            Os::Windows => ("pc-windows-msvc", ".exe"),
+            #[allow(unreachable_patterns)]
+            _ => return Err("Unsupported OS".into()),
+        };
+
+        Ok(format!("{}-{}-{}{}", base_name, arch_str, os_str, ext))
+    }
+
+    /// Runs `<tool> --version` and parses the reported version.
+    ///
+    /// # Arguments
+    /// * `host` - The host services used to run the tool
+    /// * `tool_path` - Path to the tool binary
+    ///
+    /// # Returns
+    /// * `Result<version::ToolVersion, String>` - The parsed version or an error message
+    fn query_tool_version(
+        &self,
+        host: &dyn HostServices,
+        tool_path: &str,
+    ) -> Result<version::ToolVersion, String> {
+        let output = host
+            .run(tool_path, &["--version".to_string()])
+            .map_err(|e| format!("Failed to run `{} --version`: {}", tool_path, e))?;
+        let combined = format!("{}\n{}", output.stdout, output.stderr);
+        version::parse_tool_version(&combined)
+            .ok_or_else(|| format!("Could not parse version from output: {}", combined.trim()))
+    }
+
+    /// Returns the version of a binary, running `--version` only when the binary is new or
+    /// changed since the last query.
+    ///
+    /// Binaries without a modification time, such as bare names looked up on `PATH`, are
+    /// queried every time.
+    ///
+    /// # Arguments
+    /// * `host` - The host services used to run the tool
+    /// * `tool_path` - Path to the tool binary
+    ///
+    /// # Returns
+    /// * `Option<version::Version>` - The version, or `None` if it cannot be determined
+    fn cached_tool_version(
+        &self,
+        host: &dyn HostServices,
+        tool_path: &str,
+    ) -> Option<version::Version> {
+        let query = || {
+            let tool_version = self.query_tool_version(host, tool_path).ok()?;
+            version::Version::parse(&tool_version.version)
+        };
+        let Some(modified) = host.modified_time(Path::new(tool_path)) else {
+            return query();
+        };
+        let key = format!("{}{}\t{}", STATE_TOOL_VERSION_PREFIX, tool_path, modified);
+        let cached = self.in_memory_state.lock().ok().and_then(|state| state.get(&key).cloned());
+        if let Some(version) = cached.as_deref().and_then(version::Version::parse) {
+            return Some(version);
+        }
+        let version = query()?;
+        if let Ok(mut state) = self.in_memory_state.lock() {
+            state.insert(key, version.to_string());
+        }
+        Some(version)
+    }
+
+    /// Returns the language server the next start would use, without downloading anything.
+    ///
+    /// A downloaded server is looked up in the downloads directory of the last start.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Result<String, String>` - The server path, or an error if none is installed
+    fn installed_language_server_path(&self, host: &dyn HostServices) -> Result<String, String> {
+        match self.locate_language_server(host) {
+            Ok(Some(path)) => Ok(path),
+            Ok(None) => {
+                let downloads_dir = self
+                    .in_memory_state
+                    .lock()
+                    .ok()
+                    .and_then(|state| state.get(STATE_DOWNLOADS_DIR_KEY).cloned())
+                    .unwrap_or_default();
+                let (os, _) = zed::current_platform();
+                self.newest_installed_language_server(host, os, Path::new(&downloads_dir))
+                    .map(|(_, path)| path.to_string_lossy().to_string())
+                    .ok_or_else(|| {
+                        "Not found in SDK; it will be downloaded on first start".to_string()
+                    })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Handles the `/cangjie-info` slash command, reporting the SDK root and the path,
+    /// version and build date of each toolchain binary.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The info output or an error message
+    fn handle_info_command(
+        &self,
+        worktree: &zed::Worktree,
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let host = ZedHost::new(worktree);
+        let mut text = "**Cangjie Extension Information:**\n\n".to_string();
+        let mut sections = Vec::new();
+
+        let sdk_root = match self.sdk_layout(&host) {
+            Ok(layout) => {
+                let mut lines = match self.resolve_sdk_root_entry(&host) {
+                    Ok(root) if root.is_link() => vec![
+                        format!("SDK Root: `{}`", root.path.display()),
+                        format!("Link Target: `{}`", root.target.display()),
+                    ],
+                    _ => vec![format!("SDK Root: `{}`", layout.root.display())],
+                };
+                lines.push(format!("Flavor: {}", layout.flavor.as_str()));
+                let targets = layout.flavor.target_triples();
+                if !targets.is_empty() {
+                    lines.push(format!("Cross Targets: {}", targets.join(", ")));
+                }
+                lines.join("\n")
+            }
+            Err(e) => format!("SDK Root: *Error finding SDK:* {}", e),
+        };
+        push_output_section(&mut text, &mut sections, "SDK", &sdk_root);
+
+        let tools = [
+            (CJC_NAME, self.cjc_binary_path(&host)),
+            (SERVER_NAME, self.installed_language_server_path(&host)),
+            (CJC_FRONTEND_NAME, self.cjc_frontend_binary_path(&host)),
+            (CJFMT_NAME, self.auxiliary_tool_path(&host, CJFMT_NAME)),
+            (CJLINT_NAME, self.auxiliary_tool_path(&host, CJLINT_NAME)),
+            (CJDB_NAME, self.auxiliary_tool_path(&host, CJDB_NAME)),
+        ];
+        for (tool_name, tool_path) in tools {
+            let mut lines = Vec::new();
+            match tool_path {
+                Ok(path) => {
+                    lines.push(format!("{} Path: `{}`", tool_name, path));
+                    if tool_name == SERVER_NAME {
+                        let root = host.worktree_root();
+                        if let Some(channel) = self.worktree_state(&root, STATE_LSP_CHANNEL_KEY) {
+                            lines.push(format!("Channel: {}", channel));
+                        }
+                        if let Some(location) = self
+                            .in_memory_state
+                            .lock()
+                            .ok()
+                            .and_then(|state| state.get(STATE_DOWNLOADS_LOCATION_KEY).cloned())
+                        {
+                            lines.push(format!("Downloads: {}", location));
+                        }
+                    }
+                    match self.query_tool_version(&host, &path) {
+                        Ok(tool_version) => {
+                            lines.push(format!("Version: {}", tool_version.version));
+                            lines.push(format!(
+                                "Build Date: {}",
+                                tool_version.build_date.as_deref().unwrap_or("unknown")
+                            ));
+                        }
+                        Err(e) => lines.push(format!("Version: *Error:* {}", e)),
+                    }
+                }
+                Err(e) => lines.push(format!("{} Path: *Error:* {}", tool_name, e)),
+            }
+            push_output_section(&mut text, &mut sections, tool_name, &lines.join("\n"));
+        }
+
+        let version_warning =
+            self.worktree_state(&host.worktree_root(), STATE_VERSION_WARNING_KEY);
+        if let Some(warning) = version_warning {
+            let warning = format!("⚠️ {}", warning);
+            push_output_section(&mut text, &mut sections, "Version Mismatch", &warning);
+        }
+
+        let mut compatibility = vec![
+            format!("Extension Version: {}", compat::EXTENSION_VERSION),
+            format!("zed_extension_api: {}", compat::EXTENSION_API_VERSION),
+        ];
+        let gate = self
+            .worktree_state(&host.worktree_root(), STATE_COMPATIBILITY_KEY)
+            .and_then(|state| compat::Incompatibility::from_state(&state));
+        if let Some(gate) = gate {
+            compatibility.push(format!("❌ {}", gate.message()));
+            compatibility.push(format!(
+                "💡 Update the Cangjie extension to {} or later from the Extensions panel",
+                gate.required_extension
+            ));
+        }
+        push_output_section(
+            &mut text,
+            &mut sections,
+            "Compatibility",
+            &compatibility.join("\n"),
+        );
+
+        Ok(zed::SlashCommandOutput { text, sections })
+    }
+
+    /// Handles the `/cangjie-version` slash command, reporting the versions of the extension,
+    /// the grammar, the SDK and each toolchain binary, followed by a table of all of them for
+    /// bug reports.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The version matrix or an error message
+    fn handle_version_command(
+        &self,
+        worktree: &zed::Worktree,
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let host = ZedHost::new(worktree);
+        let mut text = "**Cangjie Toolchain Versions:**\n\n".to_string();
+        let mut sections = Vec::new();
+        let mut rows = vec![("Extension".to_string(), compat::EXTENSION_VERSION.to_string())];
+
+        let extension = format!(
+            "Version: {}\nzed_extension_api: {}",
+            compat::EXTENSION_VERSION,
+            compat::EXTENSION_API_VERSION
+        );
+        push_output_section(&mut text, &mut sections, "Extension", &extension);
+
+        let grammar = match toolchain::grammar_source(
+            toolchain::EXTENSION_MANIFEST,
+            toolchain::GRAMMAR_NAME,
+        ) {
+            Some(source) => {
+                rows.push(("Grammar".to_string(), source.rev.clone()));
+                format!("Repository: {}\nRevision: {}", source.repository, source.rev)
+            }
+            None => {
+                rows.push(("Grammar".to_string(), "unknown".to_string()));
+                "*Error:* No grammar source in extension.toml".to_string()
+            }
+        };
+        push_output_section(&mut text, &mut sections, "Grammar", &grammar);
+
+        let mut tools = Vec::new();
+        for (tool_name, tool_path) in [
+            (CJC_NAME, self.cjc_binary_path(&host)),
+            (CJPM_NAME, self.cjpm_binary_path(&host)),
+            (CJFMT_NAME, self.auxiliary_tool_path(&host, CJFMT_NAME)),
+            (CJLINT_NAME, self.auxiliary_tool_path(&host, CJLINT_NAME)),
+            (CJDB_NAME, self.auxiliary_tool_path(&host, CJDB_NAME)),
+            (SERVER_NAME, self.installed_language_server_path(&host)),
+        ] {
+            let version = tool_path
+                .as_ref()
+                .map_err(String::clone)
+                .and_then(|path| self.query_tool_version(&host, path));
+            tools.push((tool_name, tool_path, version));
+        }
+
+        // The SDK is released under the version of its compiler
+        let sdk = match self.sdk_layout(&host) {
+            Ok(layout) => {
+                let version = match &tools[0].2 {
+                    Ok(cjc) => cjc.version.clone(),
+                    Err(_) => "unknown".to_string(),
+                };
+                rows.push(("SDK".to_string(), version.clone()));
+                format!(
+                    "Root: `{}`\nFlavor: {}\nVersion: {}",
+                    layout.root.display(),
+                    layout.flavor.as_str(),
+                    version
+                )
+            }
+            Err(e) => {
+                rows.push(("SDK".to_string(), "not found".to_string()));
+                format!("*Error finding SDK:* {}", e)
+            }
+        };
+        push_output_section(&mut text, &mut sections, "SDK", &sdk);
+
+        for (tool_name, tool_path, version) in tools {
+            let mut lines = Vec::new();
+            match &tool_path {
+                Ok(path) => lines.push(format!("Path: `{}`", path)),
+                Err(e) => lines.push(format!("Path: *Error:* {}", e)),
+            }
+            let row = match version {
+                Ok(tool_version) => {
+                    lines.push(format!("Version: {}", tool_version.version));
+                    if let Some(build_date) = &tool_version.build_date {
+                        lines.push(format!("Build Date: {}", build_date));
+                    }
+                    tool_version.version
+                }
+                Err(_) if tool_path.is_err() => "not found".to_string(),
+                Err(e) => {
+                    lines.push(format!("Version: *Error:* {}", e));
+                    "unknown".to_string()
+                }
+            };
+            rows.push((tool_name.to_string(), row));
+            push_output_section(&mut text, &mut sections, tool_name, &lines.join("\n"));
+        }
+
+        let (os, arch) = zed::current_platform();
+        rows.push(("Platform".to_string(), toolchain::platform_name(os, arch)));
+        let table = toolchain::version_table(&rows);
+        push_output_section(&mut text, &mut sections, "Bug Report", &table);
+
+        Ok(zed::SlashCommandOutput { text, sections })
+    }
+
+    /// Handles the `/cangjie-env` slash command, see [`CangjieExtension::environment_report`].
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The environment report or an error message
+    fn handle_env_command(
+        &self,
+        worktree: &zed::Worktree,
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let (os, _) = zed::current_platform();
+        let shell_env = worktree.shell_env();
+        self.environment_report(&ZedHost::new(worktree), os, &shell_env)
+    }
+
+    /// Reports the working directory and the variables the extension sets for the language
+    /// server, build, run and test commands, and the debug adapter, next to the worktree's
+    /// shell environment they are added to.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `os` - The current platform
+    /// * `shell_env` - The worktree's shell environment
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The environment report or an error message
+    fn environment_report(
+        &self,
+        host: &dyn HostServices,
+        os: Os,
+        shell_env: &[(String, String)],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let shell = |name: &str| {
+            shell_env
+                .iter()
+                .find(|(key, _)| {
+                    key == name || (os == Os::Windows && key.eq_ignore_ascii_case(name))
+                })
+                .map(|(_, value)| value.clone())
+        };
+        let root = host.worktree_root();
+        let mut text = "**Cangjie Process Environment:**\n\n".to_string();
+        let mut sections = Vec::new();
+
+        let layout = self.sdk_layout(host);
+        let sdk = match &layout {
+            Ok(layout) => {
+                let list = |dirs: Vec<PathBuf>| {
+                    dirs.iter()
+                        .map(|dir| format!("  {}", dir.display()))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                };
+                format!(
+                    "{}={}\nFlavor: {}\nBinary Directories:\n{}\nLibrary Directories:\n{}",
+                    ENV_CANGJIE_HOME,
+                    layout.root.display(),
+                    layout.flavor.as_str(),
+                    list(layout.bin_dirs()),
+                    list(layout.lib_dirs())
+                )
+            }
+            Err(e) => format!("*Error finding SDK:* {}", e),
+        };
+        push_output_section(&mut text, &mut sections, "SDK", &sdk);
+
+        let lib_var = sdk_layout::library_path_var(os);
+        let build_env = self.sdk_environment(host, os);
+        let debug_env = layout
+            .as_ref()
+            .map(|layout| vec![layout.library_path_env(os, shell(lib_var).as_deref())])
+            .unwrap_or_default();
+        let processes = [
+            ("Language Server", self.language_server_env(host, os)),
+            ("Build, Run and Test", build_env),
+            ("Debug Adapter", debug_env),
+        ];
+        for (label, env) in processes {
+            let report = format!(
+                "Working Directory: {}\n{}",
+                root,
+                environment::describe(os, &env, shell)
+            );
+            push_output_section(&mut text, &mut sections, label, &report);
+        }
+
+        let mut shell_vars = vec![ENV_CANGJIE_HOME, "PATH"];
+        if lib_var != "PATH" {
+            shell_vars.push(lib_var);
+        }
+        let shell_lines: Vec<String> = shell_vars
+            .iter()
+            .map(|name| match shell(name) {
+                Some(value) => format!("{}={}", name, value),
+                None => format!("{} is not set", name),
+            })
+            .collect();
+        let label = "Shell Environment";
+        push_output_section(&mut text, &mut sections, label, &shell_lines.join("\n"));
+
+        Ok(zed::SlashCommandOutput { text, sections })
+    }
+
+    /// Handles the `/cangjie-build` slash command to build the project.
+    ///
+    /// Packages (worktrees with a `cjpm.toml`) are built with `cjpm build`; a worktree holding
+    /// a single source file is compiled with cjc. `cangjie.build.extraArgs` is appended to
+    /// the build command, after `-O2` when `cangjie.build.defaultProfile` is `release`.
+    /// 
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// 
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The build output or an error message
+    fn handle_build_command(
+        &self,
+        worktree: &zed::Worktree,
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let host = ZedHost::new(worktree);
+        let numbers = self.number_format(&host);
+        let extra_args = build::default_profile_args(
+            self.default_build_profile(&host)?,
+            &self.build_extra_args(&host)?,
+        );
+        let (os, _) = zed::current_platform();
+        let root = worktree.root_path();
+        let has_manifest = worktree.read_text_file(cjpm::MANIFEST_FILE).is_ok();
+        let (tool, tool_path, build_args) = if has_manifest {
+            let args = build::package_args(&extra_args);
+            (CJPM_NAME, self.cjpm_binary_path(&host)?, args)
+        } else {
+            let sources = self.worktree_sources(worktree);
+            let args = build::single_file_args(os, build::single_source(&sources)?, &extra_args);
+            (CJC_NAME, self.cjc_binary_path(&host)?, args)
+        };
+        let sdk_env = self.sdk_environment(&host, os);
+
+        // Execute the build command
+        let started = std::time::Instant::now();
+        let (program, program_args) =
+            run_config::wrap_command(os, &tool_path, &build_args, &root, None);
+        let output = Command::new(&program)
+            .args(program_args)
+            .envs(sdk_env)
+            .output()
+            .map_err(|e| format!("Failed to run build command: {}", e))?;
+        let elapsed = humanize::format_duration(started.elapsed(), &numbers);
+
+        let mut builder = self.output_builder(&host);
+        builder.section("Command", &format!("`{} {}`", tool, build_args.join(" ")));
+        let mut result = String::new();
+        let stdout_str = String::from_utf8_lossy(&output.stdout);
+        let stderr_str = String::from_utf8_lossy(&output.stderr);
+        let combined = format!("{}\n{}", stdout_str, stderr_str);
+        let diagnostics = diagnostics::parse_diagnostics(&combined);
+
+        // Determine build success status
+        let success = output.status.unwrap_or(1) == 0;
+        if success {
+            result.push_str(&format!("✅ Build succeeded in {}!\n", elapsed));
+        } else {
+            result.push_str(&format!("❌ Build failed after {}!\n", elapsed));
+        }
+        if !diagnostics.is_empty() {
+            result.push_str(&diagnostics::summary(&diagnostics));
+            result.push('\n');
+        }
+        builder.section("Build Result", &result);
+
+        // One section per diagnostic, labeled with its location
+        for mut diagnostic in diagnostics.iter().cloned() {
+            diagnostic.location = diagnostic.location.map(|location| builder.link(location));
+            let severity = match diagnostic.level {
+                diagnostics::Level::Error => Severity::Error,
+                diagnostics::Level::Warning => Severity::Warning,
+            };
+            builder.section_with(severity, &diagnostic.label(), &diagnostic.text);
+        }
+
+        // Append stdout and stderr to the full log
+        let mut log = String::new();
+        if !stdout_str.is_empty() {
+            let size = humanize::format_size(output.stdout.len() as u64, &numbers);
+            log.push_str(&format!("Standard Output ({}):\n", size));
+            log.push_str(&stdout_str);
+            log.push('\n');
+        }
+
+        if !stderr_str.is_empty() {
+            let size = humanize::format_size(output.stderr.len() as u64, &numbers);
+            log.push_str(&format!("Standard Error ({}):\n", size));
+            log.push_str(&stderr_str);
+            log.push('\n');
+        }
+        if !log.is_empty() {
+            builder.section_with(Severity::Info, "Build Log", &log);
+        }
+        // Output that is not in cjc's diagnostic format still gets its locations linked
+        if diagnostics.is_empty() {
+            builder.locations(&combined);
+        }
+
+        let mut result = builder.build();
+        if !success {
+            push_hints_section(
+                &mut result.text,
+                &mut result.sections,
+                tool,
+                output.status,
+                &combined,
+            );
+        }
+
+        Ok(result)
+    }
+
+    /// Handles the `/cangjie-clean` slash command, removing the project's build output with
+    /// `cjpm clean`, or by deleting the target directory when cjpm is not available and the
+    /// directory lies inside the worktree.
+    ///
+    /// Deleting a target directory outside the worktree is recorded in the action journal.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The space reclaimed or an error message
+    fn handle_clean_command(
+        &self,
+        worktree: &zed::Worktree,
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let host = ZedHost::new(worktree);
+        let (os, _) = zed::current_platform();
+        let root = worktree.root_path();
+        let manifest = worktree
+            .read_text_file(cjpm::MANIFEST_FILE)
+            .map(|manifest| cjpm::parse_manifest(&manifest))
+            .unwrap_or_default();
+        let configured_dir = manifest.target_dir.as_deref().unwrap_or(cjpm::DEFAULT_TARGET_DIR);
+        let target_dir = if run_config::is_absolute(configured_dir) {
+            configured_dir.to_string()
+        } else {
+            Path::new(&root).join(configured_dir).to_string_lossy().to_string()
+        };
+        let run = |program: &str, args: &[String]| {
+            let (program, args) = run_config::wrap_command(os, program, args, &root, None);
+            Command::new(&program)
+                .args(args)
+                .output()
+                .map_err(|e| format!("Failed to run {}: {}", program, e))
+        };
+        let measure = || {
+            let (program, args) = clean::size_command(os, &target_dir);
+            run(&program, &args)
+                .ok()
+                .filter(|output| output.status == Some(0))
+                .and_then(|output| clean::parse_size(os, &String::from_utf8_lossy(&output.stdout)))
+        };
+
+        let mut text = String::new();
+        let mut sections = Vec::new();
+        let Some(before) = measure() else {
+            let message = format!("✅ Nothing to clean: `{}` does not exist", target_dir);
+            push_output_section(&mut text, &mut sections, "Result", &message);
+            return Ok(zed::SlashCommandOutput { text, sections });
+        };
+
+        let (tool, program, args) = match self.cjpm_binary_path(&host) {
+            Ok(cjpm_path) => (CJPM_NAME, cjpm_path, vec!["clean".to_string()]),
+            Err(_) => match clean::removable_dir(&root, configured_dir) {
+                Ok(dir) => {
+                    let (program, args) = clean::remove_command(os, &dir);
+                    ("rm", program, args)
+                }
+                Err(reason) => {
+                    let message = format!("❌ cjpm is required to clean this project: {}", reason);
+                    push_output_section(&mut text, &mut sections, "Result", &message);
+                    return Ok(zed::SlashCommandOutput { text, sections });
+                }
+            },
+        };
+        let output = run(&program, &args)?;
+        let command = if tool == CJPM_NAME {
+            format!("`{} clean`", CJPM_NAME)
+        } else {
+            format!("⚠️ cjpm was not found; deleted `{}`", target_dir)
+        };
+        push_output_section(&mut text, &mut sections, "Command", &command);
+        let combined = format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        if !combined.trim().is_empty() {
+            push_output_section(&mut text, &mut sections, "Output", &combined);
+        }
+
+        let after = measure().unwrap_or(0);
+        let numbers = self.number_format(&host);
+        let reclaimed = humanize::format_size(before.saturating_sub(after), &numbers);
+        let status = match output.status {
+            Some(0) => format!("✅ Reclaimed {}", reclaimed),
+            Some(code) => {
+                format!("❌ Clean failed with exit code {} (reclaimed {})", code, reclaimed)
+            }
+            None => format!("❌ Clean terminated by signal (reclaimed {})", reclaimed),
+        };
+        push_output_section(&mut text, &mut sections, "Result", &status);
+        if output.status != Some(0) {
+            push_hints_section(&mut text, &mut sections, tool, output.status, &combined);
+        }
+
+        let outside_worktree = !Path::new(&target_dir).starts_with(&root);
+        if after < before && outside_worktree {
+            self.record_action(
+                &host,
+                journal::Action::Delete,
+                Path::new(&target_dir),
+                format!("build output removed by /cangjie-clean ({})", reclaimed),
+                None,
+            );
+        }
+        Ok(zed::SlashCommandOutput { text, sections })
+    }
+
+    /// Handles the `/cangjie-doc` slash command, generating the API documentation of the
+    /// package with cjdoc and linking the generated index page.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `args` - The slash command arguments: an optional source directory, relative to the
+    ///   worktree root, replacing the manifest's `src-dir`
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The output directory or an error message
+    fn handle_doc_command(
+        &self,
+        worktree: &zed::Worktree,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let host = ZedHost::new(worktree);
+        let manifest = worktree
+            .read_text_file(cjpm::MANIFEST_FILE)
+            .map(|manifest| cjpm::parse_manifest(&manifest))
+            .unwrap_or_default();
+        let source_dir = match args {
+            [] => manifest.src_dir.as_deref().unwrap_or(cjpm::DEFAULT_SRC_DIR),
+            [path] => path.as_str(),
+            _ => return Err("Usage: /cangjie-doc [<source directory>]".to_string()),
+        };
+        let cjdoc_path = self.auxiliary_tool_path(&host, apidoc::DOC_TOOL)?;
+        let (os, _) = zed::current_platform();
+        let root = worktree.root_path();
+        let output_dir = apidoc::output_dir(&manifest);
+        let doc_args = apidoc::doc_args(source_dir, &output_dir);
+        let (program, program_args) =
+            run_config::wrap_command(os, &cjdoc_path, &doc_args, &root, None);
+        let sdk_env = self.sdk_environment(&host, os);
+        let output = Command::new(&program)
+            .args(program_args)
+            .envs(sdk_env)
+            .output()
+            .map_err(|e| format!("Failed to run {}: {}", apidoc::DOC_TOOL, e))?;
+
+        let mut builder = self.output_builder(&host);
+        let command = format!("`{} {}`", apidoc::DOC_TOOL, doc_args.join(" "));
+        builder.section("Command", &command);
+        let combined = format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        if !combined.trim().is_empty() {
+            builder.section("Output", &combined);
+        }
+        if output.status != Some(0) {
+            let status = match output.status {
+                Some(code) => format!("❌ cjdoc exited with code {}", code),
+                None => "❌ cjdoc was terminated by signal".to_string(),
+            };
+            builder.section("Result", &status).locations(&combined);
+            let mut result = builder.build();
+            push_hints_section(
+                &mut result.text,
+                &mut result.sections,
+                apidoc::DOC_TOOL,
+                output.status,
+                &combined,
+            );
+            return Ok(result);
+        }
+
+        let index = SourceLocation::file(format!("{}/{}", output_dir, apidoc::INDEX_FILE))
+            .resolve(&root);
+        if Path::new(&index.path).exists() {
+            let content = format!(
+                "✅ Documentation written to `{}`\nOpen `{}` in a browser to preview it",
+                SourceLocation::file(&output_dir).resolve(&root).path,
+                index.path
+            );
+            builder.location(index, &content);
+        } else {
+            let content = format!(
+                "⚠️ cjdoc succeeded but wrote no {} to `{}`",
+                apidoc::INDEX_FILE,
+                output_dir
+            );
+            builder.section("Result", &content);
+        }
+        Ok(builder.build())
+    }
+
+    /// Handles the `/cangjie-lint` slash command, running cjlint over the package's sources,
+    /// or the directory given as argument, and grouping the findings by level and file.
+    ///
+    /// A project rule list in `config/cjlint_rule_list.json` is passed to cjlint with `-c`.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `args` - The slash command arguments: an optional directory, relative to the root
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The lint findings or an error message
+    fn handle_lint_command(
+        &self,
+        worktree: &zed::Worktree,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let host = ZedHost::new(worktree);
+        let manifest = worktree
+            .read_text_file(cjpm::MANIFEST_FILE)
+            .map(|manifest| cjpm::parse_manifest(&manifest))
+            .unwrap_or_default();
+        let source_dir = match args {
+            [] => manifest.src_dir.as_deref().unwrap_or(cjpm::DEFAULT_SRC_DIR),
+            [path] => path.as_str(),
+            _ => return Err("Usage: /cangjie-lint [<source directory>]".to_string()),
+        };
+        let cjlint_path = self.auxiliary_tool_path(&host, CJLINT_NAME)?;
+        let (os, _) = zed::current_platform();
+        let root = worktree.root_path();
+        let config_dir = worktree
+            .read_text_file(lint::RULE_LIST_FILE)
+            .is_ok()
+            .then(|| root.clone());
+        let report_file = Path::new(lint::REPORT_FILE);
+        let report_path = host.current_dir()?.join(report_file);
+        host.remove_path(report_file);
+        let lint_args =
+            lint::lint_args(source_dir, config_dir.as_deref(), &report_path.to_string_lossy());
+        let (program, program_args) =
+            run_config::wrap_command(os, &cjlint_path, &lint_args, &root, None);
+        let sdk_env = self.sdk_environment(&host, os);
+        let output = Command::new(&program)
+            .args(program_args)
+            .envs(sdk_env)
+            .output()
+            .map_err(|e| format!("Failed to run {}: {}", CJLINT_NAME, e))?;
+        let report = host.read_text_file(report_file);
+        host.remove_path(report_file);
+
+        let mut builder = self.output_builder(&host);
+        builder.section("Command", &format!("`{} {}`", CJLINT_NAME, lint_args.join(" ")));
+        let combined = format!(
+            "{}\n{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        let Ok(report) = report else {
+            let status = match output.status {
+                Some(code) => format!("❌ cjlint wrote no report (exit code {})", code),
+                None => "❌ cjlint was terminated by signal".to_string(),
+            };
+            if !combined.trim().is_empty() {
+                builder.section("Output", &combined);
+            }
+            builder.section("Result", &status).locations(&combined);
+            let mut result = builder.build();
+            push_hints_section(
+                &mut result.text,
+                &mut result.sections,
+                CJLINT_NAME,
+                output.status,
+                &combined,
+            );
+            return Ok(result);
+        };
+
+        let findings = lint::parse_report(&report, &root)?;
+        if findings.is_empty() {
+            builder.section("Lint Result", "✅ No findings");
+            return Ok(builder.build());
+        }
+        let groups = lint::group(&findings);
+        let mut files: Vec<&str> = groups.keys().map(|(_, file)| file.as_str()).collect();
+        files.sort();
+        files.dedup();
+        let marker = if findings.iter().any(|f| f.level == lint::LintLevel::Mandatory) {
+            "❌"
+        } else {
+            "⚠️"
+        };
+        let result = format!(
+            "{} {} in {} file{}",
+            marker,
+            lint::summary(&findings),
+            files.len(),
+            if files.len() == 1 { "" } else { "s" }
+        );
+        builder.section("Lint Result", &result);
+        for ((level, file), group) in &groups {
+            let (marker, name) = level.label();
+            let severity = match level {
+                lint::LintLevel::Mandatory => Severity::Error,
+                lint::LintLevel::Suggestion => Severity::Warning,
+                lint::LintLevel::Info => Severity::Info,
+            };
+            let lines: Vec<String> = group
+                .iter()
+                .map(|finding| {
+                    let location = builder.link(finding.location.clone());
+                    format!("{} [{}] {}", location, finding.rule, finding.message)
+                })
+                .collect();
+            let label = format!("{} {} ({} {})", marker, file, group.len(), name);
+            builder.section_with(severity, &label, &lines.join("\n"));
+        }
+        let mut rules: Vec<String> = lint::rule_counts(&findings)
+            .into_iter()
+            .map(|(rule, count)| format!("{}: {}", rule, count))
+            .collect();
+        rules.push(format!(
+            "💡 Configure or disable rules in `{}`; exclude files in `cjlintignore.cfg`",
+            lint::RULE_LIST_FILE
+        ));
+        builder.section_with(Severity::Info, "Rules", &rules.join("\n"));
+        Ok(builder.build())
+    }
+
+    /// Handles the `/cangjie-fmt` slash command, formatting the worktree, or the file or
+    /// directory given as argument, in place with cjfmt and listing the files that changed.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `args` - The slash command arguments: an optional path, relative to the worktree root
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The formatting report or an error message
+    fn handle_fmt_command(
+        &self,
+        worktree: &zed::Worktree,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let host = ZedHost::new(worktree);
+        let target = match args {
+            [] => None,
+            [path] => Some(path.as_str()),
+            _ => return Err("Usage: /cangjie-fmt [<file or directory>]".to_string()),
+        };
+        let cjfmt_path = self.auxiliary_tool_path(&host, CJFMT_NAME)?;
+        let (os, _) = zed::current_platform();
+        let root = worktree.root_path();
+        let path = match target {
+            Some(path) if run_config::is_absolute(path) => path.to_string(),
+            Some(path) => Path::new(&root).join(path).to_string_lossy().to_string(),
+            None => root.clone(),
+        };
+        let run = |program: &str, args: &[String]| {
+            let (program, args) = run_config::wrap_command(os, program, args, &root, None);
+            Command::new(&program)
+                .args(args)
+                .output()
+                .map_err(|e| format!("Failed to run {}: {}", program, e))
+        };
+        let checksums = || -> Result<_, String> {
+            let (program, args) = formatter::checksum_command(os, &path);
+            let output = run(&program, &args)?;
+            let listing = String::from_utf8_lossy(&output.stdout);
+            Ok(formatter::parse_checksums(os, &listing, &root))
+        };
+
+        let before = checksums()?;
+        let fmt_args = formatter::format_args(&path);
+        let output = run(&cjfmt_path, &fmt_args)?;
+        let changed = formatter::changed_files(&before, &checksums()?);
+
+        let mut text = String::new();
+        let mut sections = Vec::new();
+        let command = format!("`{} {}`", CJFMT_NAME, fmt_args.join(" "));
+        push_output_section(&mut text, &mut sections, "Command", &command);
+        let report = if changed.is_empty() {
+            "✅ All files are already formatted".to_string()
+        } else {
+            changed
+                .iter()
+                .map(|file| format!("- `{}`", file))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let label = format!("Changed Files ({})", changed.len());
+        push_output_section(&mut text, &mut sections, &label, &report);
+
+        let stdout_str = String::from_utf8_lossy(&output.stdout);
+        let stderr_str = String::from_utf8_lossy(&output.stderr);
+        let combined = format!("{}\n{}", stdout_str, stderr_str);
+        if !combined.trim().is_empty() {
+            push_output_section(&mut text, &mut sections, "Formatter Output", &combined);
+        }
+        if output.status != Some(0) {
+            let status = match output.status {
+                Some(code) => format!("❌ cjfmt exited with code {}", code),
+                None => "❌ cjfmt was terminated by signal".to_string(),
+            };
+            push_output_section(&mut text, &mut sections, "Result", &status);
+            push_hints_section(&mut text, &mut sections, CJFMT_NAME, output.status, &combined);
+        }
+
+        let mut builder = self.output_builder(&host);
+        builder.append(zed::SlashCommandOutput { text, sections });
+        if output.status != Some(0) {
+            builder.locations(&combined);
+        }
+        Ok(builder.build())
+    }
+
+    /// Reads `cangjie.build.extraArgs`, the arguments appended to the `/cangjie-build` command.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Result<Vec<String>, String>` - The arguments, or an error when the setting is
+    ///   malformed
+    fn build_extra_args(&self, host: &dyn HostServices) -> Result<Vec<String>, String> {
+        let malformed = || format!("'{}' must be a list of strings", CONFIG_BUILD_EXTRA_ARGS_KEY);
+        match host.setting(CONFIG_BUILD_EXTRA_ARGS_KEY) {
+            None => Ok(Vec::new()),
+            Some(serde_json::Value::Array(args)) => args
+                .iter()
+                .map(|arg| arg.as_str().map(str::to_string).ok_or_else(malformed))
+                .collect(),
+            Some(_) => Err(malformed()),
+        }
+    }
+
+    /// Reads `cangjie.build.defaultProfile`, the profile `/cangjie-build` and the built-in
+    /// build, run and test tasks use.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Result<build::BuildProfile, String>` - The profile, debug when unset, or an error
+    ///   when the setting names no profile
+    fn default_build_profile(
+        &self,
+        host: &dyn HostServices,
+    ) -> Result<build::BuildProfile, String> {
+        match host.setting(CONFIG_BUILD_DEFAULT_PROFILE_KEY) {
+            None => Ok(build::BuildProfile::default()),
+            Some(value) => value
+                .as_str()
+                .and_then(build::BuildProfile::parse)
+                .ok_or_else(|| {
+                    format!(
+                        "'{}' must be \"debug\" or \"release\"",
+                        CONFIG_BUILD_DEFAULT_PROFILE_KEY
+                    )
+                }),
+        }
+    }
+
+    /// Handles the `/cangjie-import-deveco` slash command, translating a DevEco Studio
+    /// project's build profiles into Zed tasks, debug scenarios and extension settings.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The generated configuration or an error
+    fn handle_import_deveco_command(
+        &self,
+        worktree: &zed::Worktree,
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let profile_text = worktree.read_text_file(deveco::BUILD_PROFILE_FILE).map_err(|_| {
+            format!(
+                "No {} found in the worktree root; is this a DevEco Studio project?",
+                deveco::BUILD_PROFILE_FILE
+            )
+        })?;
+        let mut project = deveco::parse_project(&deveco::parse_json5(&profile_text)?)?;
+
+        let mut notes = Vec::new();
+        for module in &mut project.modules {
+            let profile_path = format!("{}/{}", module.src_path, deveco::BUILD_PROFILE_FILE);
+            if let Ok(text) = worktree.read_text_file(&profile_path) {
+                match deveco::parse_json5(&text) {
+                    Ok(profile) => deveco::apply_module_profile(module, &profile),
+                    Err(e) => notes.push(format!("Could not read `{}`: {}", profile_path, e)),
+                }
+            }
+            let manifest_path = format!("{}/{}", module.src_path, deveco::MODULE_MANIFEST_FILE);
+            if let Ok(text) = worktree.read_text_file(&manifest_path)
+                && let Ok(manifest) = deveco::parse_json5(&text)
+            {
+                deveco::apply_module_manifest(module, &manifest);
+            }
+            if let Some(package) = &module.cangjie_manifest {
+                let package_path = format!("{}/{}", module.src_path, package);
+                match worktree.read_text_file(&package_path) {
+                    Ok(text) => deveco::apply_package_manifest(module, &text),
+                    Err(e) => notes.push(format!("Could not read `{}`: {}", package_path, e)),
+                }
+            }
+        }
+
+        let sdk_dir = worktree
+            .read_text_file(deveco::LOCAL_PROPERTIES_FILE)
+            .ok()
+            .and_then(|text| deveco::sdk_dir_from_local_properties(&text));
+        let (os, _) = zed::current_platform();
+        let mut import =
+            deveco::translate(&project, sdk_dir.as_deref(), DEBUG_ADAPTER_NAME, os);
+        import.untranslated.extend(notes);
+
+        let to_json = |value: &serde_json::Value| {
+            serde_json::to_string_pretty(value).map_err(|e| format!("Failed to serialize: {}", e))
+        };
+
+        let mut text = String::new();
+        let mut sections = Vec::new();
+        push_output_section(
+            &mut text,
+            &mut sections,
+            "Tasks (.zed/tasks.json)",
+            &format!("```json\n{}\n```", to_json(&import.tasks.into())?),
+        );
+        push_output_section(
+            &mut text,
+            &mut sections,
+            "Debug Scenarios (.zed/debug.json)",
+            &format!("```json\n{}\n```", to_json(&import.debug_scenarios.into())?),
+        );
+        if !import.settings.is_empty() {
+            push_output_section(
+                &mut text,
+                &mut sections,
+                "Settings (lsp.cangjie.settings)",
+                &format!("```json\n{}\n```", to_json(&import.settings.into())?),
+            );
+        }
+        if !import.untranslated.is_empty() {
+            let notes = import
+                .untranslated
+                .iter()
+                .map(|note| format!("- {}", note))
+                .collect::<Vec<_>>()
+                .join("\n");
+            push_output_section(&mut text, &mut sections, "Not Translated", &notes);
+        }
+
+        Ok(zed::SlashCommandOutput { text, sections })
+    }
+
+    /// Handles the `/cangjie-tasks` slash command, printing the built-in task templates and
+    /// the package's scripts with the SDK environment for `.zed/tasks.json`.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The task templates or an error message
+    fn handle_tasks_command(
+        &self,
+        worktree: &zed::Worktree,
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let (os, _) = zed::current_platform();
+        let templates = self.builtin_tasks(&ZedHost::new(worktree), os)?;
+        let json = serde_json::to_string_pretty(&templates)
+            .map_err(|e| format!("Failed to serialize tasks: {}", e))?;
+        let mut text = String::new();
+        let mut sections = Vec::new();
+        push_output_section(
+            &mut text,
+            &mut sections,
+            "Tasks (.zed/tasks.json)",
+            &format!("```json\n{}\n```", json),
+        );
+        Ok(zed::SlashCommandOutput { text, sections })
+    }
+
+    /// Returns the built-in task templates with their debug and release variants, build
+    /// variants per cross-compilation target, the package's scripts and, with the OHOS
+    /// toolchain, tasks deploying the program to a device, with the environment of the
+    /// resolved SDK, see [`CangjieExtension::sdk_environment`].
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `os` - The current platform
+    ///
+    /// # Returns
+    /// * `Result<Vec<serde_json::Value>, String>` - The task templates, or an error when the
+    ///   build settings are malformed
+    fn builtin_tasks(
+        &self,
+        host: &dyn HostServices,
+        os: Os,
+    ) -> Result<Vec<serde_json::Value>, String> {
+        let env = self.sdk_environment(host, os);
+        let targets = match host.setting(CONFIG_BUILD_TARGETS_KEY) {
+            Some(value) => build::parse_targets(&value)?,
+            None => Vec::new(),
+        };
+        let templates = tasks::target_variants(tasks::builtin_templates(), &targets);
+        let profile = self.default_build_profile(host)?;
+        let mut templates = tasks::profile_variants(templates, profile);
+        templates.push(tasks::single_file_run_template(os));
+        let manifest = host
+            .read_worktree_file(cjpm::MANIFEST_FILE)
+            .ok()
+            .map(|manifest| cjpm::parse_manifest(&manifest));
+        if let Some(manifest) = &manifest {
+            templates.extend(tasks::script_templates(&manifest.scripts));
+        }
+        // Programs built with the OHOS toolchain run on a device, deployed with hdc
+        let ohos = self
+            .sdk_layout(host)
+            .is_ok_and(|layout| layout.flavor == sdk_layout::SdkFlavor::Ohos);
+        if ohos && let Some(manifest) = manifest.filter(|manifest| manifest.is_executable()) {
+            let triple = targets
+                .iter()
+                .map(|target| target.triple.as_str())
+                .find(|triple| triple.ends_with("-ohos"))
+                .unwrap_or(sdk_layout::SdkFlavor::Ohos.target_triples()[0]);
+            let root = host.worktree_root();
+            let fallback_name = Path::new(&root)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let output_dir = format!("{}/{}", triple, profile.name());
+            let device = host
+                .setting(CONFIG_OHOS_DEVICE_KEY)
+                .and_then(|device| device.as_str().map(str::to_string));
+            templates.extend(tasks::hdc_templates(
+                &manifest.executable(&output_dir, &fallback_name),
+                triple,
+                &build::default_profile_args(profile, &[]),
+                device.as_deref(),
+                os,
+            ));
+        }
+        let templates = tasks::for_platform(templates, os);
+        Ok(tasks::with_env(templates, &env))
+    }
+
+    /// Handles the `/cangjie-run` slash command, running the project with `cjpm run`.
+    ///
+    /// `--config <name>` applies a named run configuration from `cangjie.run.configs`
+    /// (program arguments, environment, working directory and stdin file); `--tasks`
+    /// prints every configuration as a task template variant for `.zed/tasks.json`.
+    /// `--name <target>` selects the executable to run. Any other arguments, and everything
+    /// after `--`, are passed to the program after the configuration's own arguments.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `args` - The slash command arguments
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The run output or an error message
+    fn handle_run_command(
+        &self,
+        worktree: &zed::Worktree,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let host = ZedHost::new(worktree);
+        let run_args = run_config::parse_run_args(args)?;
+        let settings = self.extension_settings(worktree).unwrap_or_default();
+        let collation = self.collation(&host);
+        let configs = run_config::parse_run_configs(&settings, collation)?;
+
+        let mut text = String::new();
+        let mut sections = Vec::new();
+
+        let (os, _) = zed::current_platform();
+        let sdk_env = self.sdk_environment(&host, os);
+
+        if run_args.list_tasks {
+            let tasks: Vec<serde_json::Value> = configs
+                .iter()
+                .map(|config| {
+                    let mut task = config.task_template(CJPM_NAME, os);
+                    if let Some(env) = task["env"].as_object_mut() {
+                        for (key, value) in &sdk_env {
+                            env.entry(key.clone())
+                                .or_insert_with(|| serde_json::Value::String(value.clone()));
+                        }
+                    }
+                    task
+                })
+                .collect();
+            let json = serde_json::to_string_pretty(&tasks)
+                .map_err(|e| format!("Failed to serialize tasks: {}", e))?;
+            push_output_section(
+                &mut text,
+                &mut sections,
+                "Run Task Variants (.zed/tasks.json)",
+                &format!("```json\n{}\n```", json),
+            );
+            return Ok(zed::SlashCommandOutput { text, sections });
+        }
+
+        let config = match &run_args.config {
+            Some(name) => configs.iter().find(|c| &c.name == name).cloned().ok_or_else(|| {
+                let known = configs.iter().map(|c| c.name.as_str()).collect::<Vec<_>>();
+                format!(
+                    "Unknown run configuration '{}'. Available: {}",
+                    name,
+                    if known.is_empty() { "(none)".to_string() } else { known.join(", ") }
+                )
+            })?,
+            None => run_config::RunConfig::default(),
+        };
+
+        let cjpm_path = self.cjpm_binary_path(&host)?;
+        let mut cjpm_args = vec!["run".to_string()];
+        if let Some(target) = &run_args.target {
+            cjpm_args.push("--name".to_string());
+            cjpm_args.push(target.clone());
+        }
+        let mut program_args = config.args.clone();
+        program_args.extend(run_args.program_args.iter().cloned());
+        if !program_args.is_empty() {
+            cjpm_args.push("--run-args".to_string());
+            cjpm_args.push(run_config::join_run_args(&program_args));
+        }
+
+        let root = worktree.root_path();
+        let resolve = |path: &str| {
+            if run_config::is_absolute(path) {
+                path.to_string()
+            } else {
+                Path::new(&root).join(path).to_string_lossy().to_string()
+            }
+        };
+        let cwd = config.cwd.as_deref().map(resolve).unwrap_or_else(|| root.clone());
+        let stdin = config.stdin.as_deref().map(resolve);
+
+        let (program, program_args) =
+            run_config::wrap_command(os, &cjpm_path, &cjpm_args, &cwd, stdin.as_deref());
+        let output = Command::new(&program)
+            .args(program_args)
+            .envs(sdk_env)
+            .envs(config.env.clone())
+            .output()
+            .map_err(|e| format!("Failed to run cjpm: {}", e))?;
+
+        let mut summary = format!(
+            "`{} {}`\nWorking directory: `{}`",
+            CJPM_NAME,
+            cjpm_args.join(" "),
+            cwd
+        );
+        if let Some(name) = &run_args.config {
+            summary.push_str(&format!("\nConfiguration: `{}`", name));
+        }
+        if let Some(stdin) = &stdin {
+            summary.push_str(&format!("\nStdin: `{}`", stdin));
+        }
+        push_output_section(&mut text, &mut sections, "Command", &summary);
+
+        let stdout_str = String::from_utf8_lossy(&output.stdout);
+        let stderr_str = String::from_utf8_lossy(&output.stderr);
+        if !stdout_str.is_empty() {
+            push_output_section(&mut text, &mut sections, "Standard Output", &stdout_str);
+        }
+        if !stderr_str.is_empty() {
+            push_output_section(&mut text, &mut sections, "Standard Error", &stderr_str);
+        }
+        let status = match output.status {
+            Some(0) => "✅ Exited successfully".to_string(),
+            Some(code) => format!("❌ Exited with code {}", code),
+            None => "❌ Terminated by signal".to_string(),
+        };
+        push_output_section(&mut text, &mut sections, "Exit Status", &status);
+        push_hints_section(
+            &mut text,
+            &mut sections,
+            CJPM_NAME,
+            output.status,
+            &format!("{}\n{}", stdout_str, stderr_str),
+        );
+
+        Ok(zed::SlashCommandOutput { text, sections })
+    }
+
+    /// Handles the `/cangjie-test` slash command, running `cjpm test` in the worktree root.
+    /// The report is split into one section per test suite with its pass and fail counts.
+    /// Without cjpm, the tests are compiled with `cjc --test` and run directly.
+    ///
+    /// A bare argument selects the test to run; `--repeat N [--until-failure]` runs the
+    /// tests repeatedly and reports suspected flaky tests. Other options are forwarded to
+    /// `cjpm test`. When `cangjie.test.networkIsolation` is enabled, the tests run with
+    /// network access blocked.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `args` - The slash command arguments
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The test output or an error message
+    fn handle_test_command(
+        &self,
+        worktree: &zed::Worktree,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let host = ZedHost::new(worktree);
+        let mut test_args = testing::parse_test_args(args)?;
+        if test_args.show_history {
+            return Ok(self.seed_history_output());
+        }
+        if test_args.accept_snapshots {
+            return self.accept_snapshots(worktree);
+        }
+        let cjpm_path = match self.cjpm_binary_path(&host) {
+            Ok(path) => path,
+            Err(e) => return self.run_tests_with_cjc(worktree, &test_args, e),
+        };
+        let numbers = self.number_format(&host);
+        let parallelism = match self.extension_setting(worktree, CONFIG_TEST_PARALLELISM_KEY) {
+            Some(value) => testing::Parallelism::from_setting(&value)?,
+            None => None,
+        };
+        let parallel_flag = parallelism.is_some() && self.cjpm_supports_parallel(&cjpm_path);
+        if let Some(parallelism) = parallelism
+            && parallel_flag
+        {
+            test_args.parallel = Some(parallelism.flag_value());
+        }
+
+        let mut text = String::new();
+        let mut sections = Vec::new();
+
+        let (os, _) = zed::current_platform();
+        let isolate = self
+            .extension_setting(worktree, CONFIG_TEST_NETWORK_ISOLATION_KEY)
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let has_unshare = host.which("unshare").is_some();
+        let has_sandbox_exec = host.which("sandbox-exec").is_some();
+        if isolate {
+            let isolation = testing::isolate_network(
+                os,
+                &cjpm_path,
+                &test_args.cjpm_args(None),
+                has_unshare,
+                has_sandbox_exec,
+            );
+            let status = if isolation.enforced {
+                "🔒 Network isolation enforced"
+            } else {
+                "⚠️ Network isolation is best-effort"
+            };
+            push_output_section(
+                &mut text,
+                &mut sections,
+                "Network Isolation",
+                &format!("{}\n{}", status, isolation.notes.join("\n")),
+            );
+        }
+
+        let root = worktree.root_path();
+        let mut sdk_env = self.sdk_environment(&host, os);
+        if test_args.update_snapshots {
+            sdk_env.extend(snapshot::update_env(&root));
+        }
+        let run_tests = |run_args: &testing::TestArgs, seed: u64| {
+            let cjpm_args = run_args.cjpm_args(Some(seed));
+            let (program, program_args, env) = if isolate {
+                let isolation = testing::isolate_network(
+                    os,
+                    &cjpm_path,
+                    &cjpm_args,
+                    has_unshare,
+                    has_sandbox_exec,
+                );
+                (isolation.program, isolation.args, isolation.env)
+            } else {
+                (cjpm_path.clone(), cjpm_args, Vec::new())
+            };
+            let (program, program_args) =
+                run_config::wrap_command(os, &program, &program_args, &root, None);
+            Command::new(&program)
+                .args(program_args)
+                .envs(sdk_env.clone())
+                .envs(env)
+                .output()
+                .map_err(|e| format!("Failed to run cjpm test: {}", e))
+        };
+
+        let entropy = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or_default();
+        let seed_for_run = |run: u32| {
+            test_args
+                .seed
+                .unwrap_or_else(|| testing::derive_seed(entropy, run))
+        };
+
+        let summary = format!("`{} {}`", CJPM_NAME, test_args.cjpm_args(None).join(" "));
+        push_output_section(&mut text, &mut sections, "Command", &summary);
+
+        if test_args.repeat > 1 {
+            let mut stats = testing::RepeatStats::default();
+            let mut last_failure = None;
+            for run in 1..=test_args.repeat {
+                let seed = seed_for_run(run);
+                let output = run_tests(&test_args, seed)?;
+                let combined = format!(
+                    "{}\n{}",
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                stats.record(Some(seed), output.status, &combined);
+                self.record_test_seed(&test_args, seed, output.status == Some(0));
+                if output.status != Some(0) {
+                    last_failure = Some((seed, output.status, combined));
+                    if test_args.until_failure {
+                        break;
+                    }
+                }
+            }
+            let collation = self.collation(&host);
+            self.push_repeat_report(&mut text, &mut sections, &test_args, &stats, collation);
+            if let Some((seed, status, combined)) = last_failure {
+                push_output_section(&mut text, &mut sections, "Last Failing Run", &combined);
+                push_output_section(
+                    &mut text,
+                    &mut sections,
+                    "Replay",
+                    &format!("`{}`", test_args.replay_command(seed)),
+                );
+                push_hints_section(&mut text, &mut sections, CJPM_NAME, status, &combined);
+                let mut builder = self.output_builder(&host);
+                builder
+                    .append(zed::SlashCommandOutput { text, sections })
+                    .locations(&combined);
+                return Ok(builder.build());
+            }
+            return Ok(zed::SlashCommandOutput { text, sections });
+        }
+
+        let seed = seed_for_run(1);
+
+        if parallelism.is_some() && !parallel_flag {
+            push_output_section(
+                &mut text,
+                &mut sections,
+                "Parallelism",
+                &format!(
+                    "⚠️ cjpm has no `--parallel` flag; `{}` is ignored and the tests run \
+                     serially",
+                    CONFIG_TEST_PARALLELISM_KEY
+                ),
+            );
+        }
+
+        let started = std::time::Instant::now();
+        let output = run_tests(&test_args, seed)?;
+        let elapsed = humanize::format_duration(started.elapsed(), &numbers);
+        let stdout_str = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr_str = String::from_utf8_lossy(&output.stderr).to_string();
+        let status_code = output.status;
+        self.record_test_seed(&test_args, seed, status_code == Some(0));
+
+        push_test_report(&mut text, &mut sections, &stdout_str);
+        if !stderr_str.is_empty() {
+            push_output_section(&mut text, &mut sections, "Standard Error", &stderr_str);
+        }
+        if let Some(parallel) = &test_args.parallel {
+            push_output_section(
+                &mut text,
+                &mut sections,
+                "Parallelism",
+                &format!("Ran with `--parallel={}` in {}", parallel, elapsed),
+            );
+        }
+        let status = match status_code {
+            Some(0) => format!("✅ Tests passed (seed {})", seed),
+            Some(code) => format!("❌ Tests failed (exit code {}, seed {})", code, seed),
+            None => format!("❌ Test run terminated by signal (seed {})", seed),
+        };
+        push_output_section(&mut text, &mut sections, "Result", &status);
+        if status_code != Some(0) {
+            push_output_section(
+                &mut text,
+                &mut sections,
+                "Replay",
+                &format!("`{}`", test_args.replay_command(seed)),
+            );
+        }
+
+        // Re-run cases that failed under parallel execution on their own to spot tests
+        // that share global state
+        if test_args.parallel.is_some() && status_code != Some(0) {
+            const MAX_SERIAL_RERUNS: usize = 10;
+            let mut parallel_only = Vec::new();
+            let failed = testing::parse_test_report(&stdout_str)
+                .into_iter()
+                .filter(|result| result.status == testing::CaseStatus::Failed)
+                .take(MAX_SERIAL_RERUNS);
+            for result in failed {
+                let serial = testing::TestArgs {
+                    filter: Some(result.full_name()),
+                    parallel: None,
+                    ..test_args.clone()
+                };
+                if run_tests(&serial, seed)?.status == Some(0) {
+                    parallel_only.push(format!(
+                        "⚠️ `{}` fails only under parallel execution; it may share global \
+                         state with other tests",
+                        result.full_name()
+                    ));
+                }
+            }
+            if !parallel_only.is_empty() {
+                push_output_section(
+                    &mut text,
+                    &mut sections,
+                    "Parallel-Only Failures",
+                    &parallel_only.join("\n"),
+                );
+            }
+        }
+        push_hints_section(
+            &mut text,
+            &mut sections,
+            CJPM_NAME,
+            status_code,
+            &format!("{}\n{}", stdout_str, stderr_str),
+        );
+        if test_args.update_snapshots {
+            self.push_snapshot_review(worktree, &mut text, &mut sections)?;
+        }
+
+        // Link the source locations of failed assertions and stack traces
+        let mut builder = self.output_builder(&host);
+        builder.append(zed::SlashCommandOutput { text, sections });
+        if status_code != Some(0) {
+            builder.locations(&format!("{}\n{}", stdout_str, stderr_str));
+        }
+        Ok(builder.build())
+    }
+
+    /// Runs the unit tests without cjpm: compiles the package in the source directory with
+    /// `cjc --test` and runs the test binary.
+    ///
+    /// The test filter, the seed and `--update-snapshots` apply. Options only cjpm
+    /// understands, such as repeats and parallel runs, and network isolation are not
+    /// applied; the "Test Runner" section lists the ones that were given.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `test_args` - The parsed `/cangjie-test` arguments
+    /// * `cjpm_error` - Why cjpm could not be resolved, reported if cjc cannot be either
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The test output or an error message
+    fn run_tests_with_cjc(
+        &self,
+        worktree: &zed::Worktree,
+        test_args: &testing::TestArgs,
+        cjpm_error: String,
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let host = ZedHost::new(worktree);
+        let cjc_path = self.cjc_binary_path(&host).map_err(|_| cjpm_error)?;
+        let (os, _) = zed::current_platform();
+        let root = worktree.root_path();
+        let manifest = worktree
+            .read_text_file(cjpm::MANIFEST_FILE)
+            .map(|manifest| cjpm::parse_manifest(&manifest))
+            .unwrap_or_default();
+        let source_dir = manifest.src_dir.as_deref().unwrap_or(cjpm::DEFAULT_SRC_DIR);
+        let binary = debug::executable_path(os, Some(&root), testing::TEST_BINARY_PATH);
+        let mut sdk_env = self.sdk_environment(&host, os);
+        if test_args.update_snapshots {
+            sdk_env.extend(snapshot::update_env(&root));
+        }
+        let run = |program: &str, args: &[String]| {
+            let (program, args) = run_config::wrap_command(os, program, args, &root, None);
+            Command::new(&program)
+                .args(args)
+                .envs(sdk_env.clone())
+                .output()
+                .map_err(|e| format!("Failed to run {}: {}", program, e))
+        };
+
+        let mut ignored: Vec<String> = test_args
+            .cjpm_only_options()
+            .into_iter()
+            .map(|option| format!("`{}`", option))
+            .collect();
+        for key in [CONFIG_TEST_PARALLELISM_KEY, CONFIG_TEST_NETWORK_ISOLATION_KEY] {
+            let value = self.extension_setting(worktree, key);
+            if value.is_some_and(|value| !value.is_null() && value != false) {
+                ignored.push(format!("'{}'", key));
+            }
+        }
+        let mut runner = "⚠️ cjpm was not found; compiling the tests with cjc".to_string();
+        if !ignored.is_empty() {
+            runner.push_str(&format!("\n⚠️ Ignored without cjpm: {}", ignored.join(", ")));
+        }
+        let mut text = String::new();
+        let mut sections = Vec::new();
+        push_output_section(&mut text, &mut sections, "Test Runner", &runner);
+        let build_args = testing::cjc_test_args(source_dir, &binary);
+        push_output_section(
+            &mut text,
+            &mut sections,
+            "Command",
+            &format!("`{} {}`", CJC_NAME, build_args.join(" ")),
+        );
+        let build = run(&cjc_path, &build_args)?;
+        if build.status != Some(0) {
+            let build_output = format!(
+                "{}{}",
+                String::from_utf8_lossy(&build.stdout),
+                String::from_utf8_lossy(&build.stderr)
+            );
+            push_output_section(&mut text, &mut sections, "Build Output", &build_output);
+            push_output_section(&mut text, &mut sections, "Result", "❌ Tests failed to compile");
+            push_hints_section(&mut text, &mut sections, CJC_NAME, build.status, &build_output);
+            let mut builder = self.output_builder(&host);
+            builder
+                .append(zed::SlashCommandOutput { text, sections })
+                .locations(&build_output);
+            return Ok(builder.build());
+        }
+
+        let seed = test_args.seed.unwrap_or_else(|| {
+            let entropy = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or_default();
+            testing::derive_seed(entropy, 1)
+        });
+        let output = run(&binary, &test_args.binary_args(Some(seed)))?;
+        let stdout_str = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr_str = String::from_utf8_lossy(&output.stderr).to_string();
+        self.record_test_seed(test_args, seed, output.status == Some(0));
+        push_test_report(&mut text, &mut sections, &stdout_str);
+        if !stderr_str.is_empty() {
+            push_output_section(&mut text, &mut sections, "Standard Error", &stderr_str);
+        }
+        let status = match output.status {
+            Some(0) => format!("✅ Tests passed (seed {})", seed),
+            Some(code) => format!("❌ Tests failed (exit code {}, seed {})", code, seed),
+            None => format!("❌ Test run terminated by signal (seed {})", seed),
+        };
+        push_output_section(&mut text, &mut sections, "Result", &status);
+
+        let mut builder = self.output_builder(&host);
+        builder.append(zed::SlashCommandOutput { text, sections });
+        if output.status != Some(0) {
+            builder.locations(&format!("{}\n{}", stdout_str, stderr_str));
+        }
+        Ok(builder.build())
+    }
+
+    /// Handles the `/cangjie-bench` slash command, running the benchmarks with `cjpm bench`,
+    /// or without cjpm by compiling them with `cjc --test` and running the binary with
+    /// `--bench`.
+    ///
+    /// The medians are kept per worktree, so each run reports the change of every benchmark
+    /// since its previous run. A bare argument selects the benchmarks to run; options are
+    /// forwarded.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `args` - The slash command arguments
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The benchmark results or an error message
+    fn handle_bench_command(
+        &self,
+        worktree: &zed::Worktree,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let bench_args = bench::BenchArgs::parse(args);
+        let host = ZedHost::new(worktree);
+        let numbers = self.number_format(&host);
+        let (os, _) = zed::current_platform();
+        let root = worktree.root_path();
+        let sdk_env = self.sdk_environment(&host, os);
+        let run = |program: &str, args: &[String]| {
+            let (program, args) = run_config::wrap_command(os, program, args, &root, None);
+            Command::new(&program)
+                .args(args)
+                .envs(sdk_env.clone())
+                .output()
+                .map_err(|e| format!("Failed to run {}: {}", program, e))
+        };
+
+        let mut builder = self.output_builder(&host);
+        let (tool, output) = match self.cjpm_binary_path(&host) {
+            Ok(cjpm_path) => {
+                let cjpm_args = bench_args.cjpm_args();
+                builder.section("Command", &format!("`{} {}`", CJPM_NAME, cjpm_args.join(" ")));
+                (CJPM_NAME, run(&cjpm_path, &cjpm_args)?)
+            }
+            Err(cjpm_error) => {
+                let cjc_path = self.cjc_binary_path(&host).map_err(|_| cjpm_error)?;
+                let manifest = worktree
+                    .read_text_file(cjpm::MANIFEST_FILE)
+                    .map(|manifest| cjpm::parse_manifest(&manifest))
+                    .unwrap_or_default();
+                let source_dir = manifest.src_dir.as_deref().unwrap_or(cjpm::DEFAULT_SRC_DIR);
+                let binary = debug::executable_path(os, Some(&root), testing::TEST_BINARY_PATH);
+                let build_args = testing::cjc_test_args(source_dir, &binary);
+                builder
+                    .section(
+                        "Benchmark Runner",
+                        "⚠️ cjpm was not found; compiling the benchmarks with cjc",
+                    )
+                    .section("Command", &format!("`{} {}`", CJC_NAME, build_args.join(" ")));
+                let build = run(&cjc_path, &build_args)?;
+                if build.status != Some(0) {
+                    let build_output = format!(
+                        "{}{}",
+                        String::from_utf8_lossy(&build.stdout),
+                        String::from_utf8_lossy(&build.stderr)
+                    );
+                    builder
+                        .section("Build Output", &build_output)
+                        .section("Result", "❌ Benchmarks failed to compile")
+                        .locations(&build_output);
+                    let mut result = builder.build();
+                    push_hints_section(
+                        &mut result.text,
+                        &mut result.sections,
+                        CJC_NAME,
+                        build.status,
+                        &build_output,
+                    );
+                    return Ok(result);
+                }
+                (CJC_NAME, run(&binary, &bench_args.binary_args())?)
+            }
+        };
+
+        let stdout_str = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr_str = String::from_utf8_lossy(&output.stderr).to_string();
+        let results = bench::parse_results(&stdout_str);
+        if !results.is_empty() {
+            let previous = self
+                .worktree_state(&root, STATE_BENCH_RESULTS_KEY)
+                .map(|state| bench::from_state(&state))
+                .unwrap_or_default();
+            let lines: Vec<String> = results
+                .iter()
+                .map(|result| {
+                    bench::describe(result, previous.get(&result.name).copied(), &numbers)
+                })
+                .collect();
+            builder.section_with(
+                Severity::Info,
+                &format!("Results ({})", results.len()),
+                &lines.join("\n"),
+            );
+            let state = bench::to_state(&previous, &results);
+            self.set_worktree_state(&root, STATE_BENCH_RESULTS_KEY, Some(state));
+        }
+        if !stdout_str.is_empty() {
+            builder.section_with(Severity::Info, "Standard Output", &stdout_str);
+        }
+        if !stderr_str.is_empty() {
+            builder.section("Standard Error", &stderr_str);
+        }
+        let status = match output.status {
+            Some(0) if results.is_empty() => {
+                "⚠️ No benchmark results found; benchmarks are test cases marked with @Bench"
+                    .to_string()
+            }
+            Some(0) => "✅ Benchmarks finished".to_string(),
+            Some(code) => format!("❌ Benchmarks failed (exit code {})", code),
+            None => "❌ Benchmark run terminated by signal".to_string(),
+        };
+        builder.section("Result", &status);
+
+        let combined = format!("{}\n{}", stdout_str, stderr_str);
+        if output.status != Some(0) {
+            builder.locations(&combined);
+        }
+        let mut result = builder.build();
+        if output.status != Some(0) {
+            push_hints_section(
+                &mut result.text,
+                &mut result.sections,
+                tool,
+                output.status,
+                &combined,
+            );
+        }
+        Ok(result)
+    }
+
+    /// Lists the snapshots written by a test run but not yet accepted.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    ///
+    /// # Returns
+    /// * `Result<Vec<snapshot::PendingSnapshot>, String>` - The pending snapshots
+    fn pending_snapshots(
+        &self,
+        worktree: &zed::Worktree,
+    ) -> Result<Vec<snapshot::PendingSnapshot>, String> {
+        let (os, _) = zed::current_platform();
+        let root = worktree.root_path();
+        let (program, args) = snapshot::list_command(os);
+        let (program, args) = run_config::wrap_command(os, &program, &args, &root, None);
+        let output = Command::new(&program)
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to list snapshots: {}", e))?;
+        let mut pending = snapshot::parse_pending(&String::from_utf8_lossy(&output.stdout), &root);
+        self.collation(&ZedHost::new(worktree))
+            .sort_by_name(&mut pending, |snapshot| &snapshot.target);
+        Ok(pending)
+    }
+
+    /// Appends a diff of every pending snapshot against its accepted version.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `text` - The output text being built
+    /// * `sections` - The output sections being built
+    fn push_snapshot_review(
+        &self,
+        worktree: &zed::Worktree,
+        text: &mut String,
+        sections: &mut Vec<zed::SlashCommandOutputSection>,
+    ) -> Result<(), String> {
+        let pending = self.pending_snapshots(worktree)?;
+        if pending.is_empty() {
+            push_output_section(text, sections, "Snapshots", "✅ All snapshots are up to date");
+            return Ok(());
+        }
+        let root = worktree.root_path();
+        for snapshot in &pending {
+            let location = SourceLocation::file(&snapshot.target).resolve(&root);
+            let new = worktree
+                .read_text_file(&snapshot.pending)
+                .map_err(|e| format!("Failed to read {}: {}", snapshot.pending, e))?;
+            let old = worktree.read_text_file(&snapshot.target).unwrap_or_default();
+            let status = if old.is_empty() { "new" } else { "changed" };
+            push_output_section(
+                text,
+                sections,
+                &format!("Snapshot: {} ({})", location, status),
+                &format!("```diff\n{}\n```", snapshot::line_diff(&old, &new)),
+            );
+        }
+        push_output_section(
+            text,
+            sections,
+            "Snapshots",
+            &format!(
+                "⚠️ {} snapshot(s) changed. Run `/cangjie-test --accept-snapshots` to accept \
+                 them.",
+                pending.len()
+            ),
+        );
+        Ok(())
+    }
+
+    /// Replaces the accepted snapshots with the pending ones (`--accept-snapshots`).
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The accepted snapshots or an error message
+    fn accept_snapshots(
+        &self,
+        worktree: &zed::Worktree,
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let pending = self.pending_snapshots(worktree)?;
+        let mut text = String::new();
+        let mut sections = Vec::new();
+        if pending.is_empty() {
+            push_output_section(&mut text, &mut sections, "Snapshots", "No pending snapshots");
+            return Ok(zed::SlashCommandOutput { text, sections });
+        }
+
+        let (os, _) = zed::current_platform();
+        let root = worktree.root_path();
+        let (program, args) = snapshot::accept_command(os, &pending);
+        let (program, args) = run_config::wrap_command(os, &program, &args, &root, None);
+        let output = Command::new(&program)
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to accept snapshots: {}", e))?;
+        if output.status != Some(0) {
+            return Err(format!(
+                "Failed to accept snapshots: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+
+        let accepted = pending
+            .iter()
+            .map(|s| format!("✅ {}", SourceLocation::file(&s.target).resolve(&root)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        push_output_section(&mut text, &mut sections, "Accepted Snapshots", &accepted);
+        Ok(zed::SlashCommandOutput { text, sections })
+    }
+
+    /// Checks whether `cjpm test` accepts the `--parallel` option.
+    ///
+    /// # Arguments
+    /// * `cjpm_path` - Path to the cjpm binary
+    ///
+    /// # Returns
+    /// * `bool` - True if `cjpm test --help` lists the option
+    fn cjpm_supports_parallel(&self, cjpm_path: &str) -> bool {
+        Command::new(cjpm_path)
+            .args(["test", "--help"])
+            .output()
+            .map(|output| {
+                testing::supports_parallel_flag(&format!(
+                    "{}{}",
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&output.stderr)
+                ))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Handles the `/cangjie-test-here` slash command, running the test enclosing a location.
+    ///
+    /// The first argument is `path[:line]` relative to the worktree root. With `--debug`, a
+    /// debug scenario for the test is produced instead of running it; other arguments are
+    /// passed on to `/cangjie-test`.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `args` - The slash command arguments
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The test output or an error message
+    fn handle_test_here_command(
+        &self,
+        worktree: &zed::Worktree,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let (location, rest) = args
+            .split_first()
+            .ok_or("Usage: /cangjie-test-here <path>[:<line>] [--debug]")?;
+        let (path, line) = testing::parse_location(location)?;
+        let source = worktree
+            .read_text_file(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+        let item = match line {
+            Some(line) => testing::test_at_line(&source, line)
+                .ok_or_else(|| format!("No @Test class or case encloses {}:{}", path, line))?,
+            None => {
+                let suites: Vec<_> = testing::find_test_items(&source)
+                    .into_iter()
+                    .filter(|item| item.case.is_none())
+                    .collect();
+                match suites.as_slice() {
+                    [suite] => suite.clone(),
+                    [] => return Err(format!("No @Test class found in {}", path)),
+                    _ => {
+                        let names: Vec<_> = suites.iter().map(|s| s.suite.as_str()).collect();
+                        return Err(format!(
+                            "{} contains several test classes ({}); add a line number",
+                            path,
+                            names.join(", ")
+                        ));
+                    }
+                }
+            }
+        };
+        let filter = item.filter();
+
+        if rest.iter().any(|arg| arg == "--debug") {
+            let scenario = testing::debug_scenario(&filter, DEBUG_ADAPTER_NAME);
+            let json = serde_json::to_string_pretty(&scenario)
+                .map_err(|e| format!("Failed to serialize debug scenario: {}", e))?;
+            let mut builder = OutputBuilder::new();
+            builder.section(
+                "Debug Scenario (.zed/debug.json)",
+                &format!("```json\n{}\n```", json),
+            );
+            return Ok(builder.build());
+        }
+
+        let mut test_args = vec![filter.clone()];
+        test_args.extend(rest.iter().cloned());
+        let output = self.handle_test_command(worktree, &test_args)?;
+
+        let mut builder = self.output_builder(&ZedHost::new(worktree));
+        let start = builder.link(SourceLocation::new(&path, item.start_line, 1));
+        let follow_up = format!(
+            "`{}` ({} to line {})\nDebug it with `/cangjie-test-here {} --debug`",
+            filter, start, item.end_line, location
+        );
+        builder.location(start, &follow_up).append(output);
+        Ok(builder.build())
+    }
+
+    /// Appends a test run to the seed history.
+    ///
+    /// # Arguments
+    /// * `test_args` - The parsed test arguments
+    /// * `seed` - The shuffle seed of the run
+    /// * `passed` - Whether the run passed
+    fn record_test_seed(&self, test_args: &testing::TestArgs, seed: u64, passed: bool) {
+        if let Ok(mut state) = self.in_memory_state.lock() {
+            let mut history = state
+                .get(STATE_TEST_SEED_HISTORY_KEY)
+                .map(|h| testing::parse_seed_history(h))
+                .unwrap_or_default();
+            history.push(testing::SeedRecord {
+                seed,
+                passed,
+                filter: test_args.filter.clone(),
+            });
+            state.insert(
+                STATE_TEST_SEED_HISTORY_KEY.to_string(),
+                testing::format_seed_history(&history),
+            );
+        }
+    }
+
+    /// Builds the `/cangjie-test --history` output listing recent seeds, newest first.
+    ///
+    /// # Returns
+    /// * `zed::SlashCommandOutput` - The seed history
+    fn seed_history_output(&self) -> zed::SlashCommandOutput {
+        let history = self
+            .in_memory_state
+            .lock()
+            .ok()
+            .and_then(|state| state.get(STATE_TEST_SEED_HISTORY_KEY).cloned())
+            .map(|h| testing::parse_seed_history(&h))
+            .unwrap_or_default();
+        let content = if history.is_empty() {
+            "No test runs recorded yet.".to_string()
+        } else {
+            history
+                .iter()
+                .rev()
+                .map(|record| {
+                    let status = if record.passed { "✅" } else { "❌" };
+                    format!("{} seed {}: `{}`", status, record.seed, record.replay_command())
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let mut text = String::new();
+        let mut sections = Vec::new();
+        push_output_section(&mut text, &mut sections, "Seed History", &content);
+        zed::SlashCommandOutput { text, sections }
+    }
+
+    /// Appends the aggregated results of a `--repeat` test session.
+    ///
+    /// # Arguments
+    /// * `text` - The output text being built
+    /// * `sections` - The output sections being built
+    /// * `test_args` - The parsed test arguments
+    /// * `stats` - The aggregated run statistics
+    /// * `collation` - The order of test names
+    fn push_repeat_report(
+        &self,
+        text: &mut String,
+        sections: &mut Vec<zed::SlashCommandOutputSection>,
+        test_args: &testing::TestArgs,
+        stats: &testing::RepeatStats,
+        collation: collate::Collation,
+    ) {
+        let failed_runs = stats.runs.iter().filter(|run| !run.passed).count();
+        let mut summary = format!(
+            "Ran {} of {} requested times: {} passed, {} failed",
+            stats.runs.len(),
+            test_args.repeat,
+            stats.runs.len() - failed_runs,
+            failed_runs
+        );
+        if test_args.until_failure && failed_runs > 0 {
+            summary.push_str(" (stopped at first failure)");
+        }
+        push_output_section(text, sections, "Repeated Runs", &summary);
+
+        if !stats.cases.is_empty() {
+            let mut table = vec![
+                "| Test | Passed | Failed |".to_string(),
+                "|------|--------|--------|".to_string(),
+            ];
+            let mut cases: Vec<_> = stats.cases.iter().collect();
+            collation.sort_by_name(&mut cases, |(name, _)| name.as_str());
+            for (name, case) in cases {
+                table.push(format!("| {} | {} | {} |", name, case.passed, case.failed));
+            }
+            push_output_section(text, sections, "Per-Test Results", &table.join("\n"));
+        }
+
+        let flaky = stats.flaky_cases();
+        if flaky.is_empty() {
+            return;
+        }
+        let mut lines = Vec::new();
+        for (name, case) in flaky {
+            let runs = case
+                .failed_runs
+                .iter()
+                .map(|index| match stats.seed_of(*index) {
+                    Some(seed) => format!("#{} (seed {})", index, seed),
+                    None => format!("#{}", index),
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            lines.push(format!(
+                "⚠️ `{}` failed {:.0}% of runs: {}",
+                name,
+                case.failure_rate() * 100.0,
+                runs
+            ));
+            let replay = match case.failed_runs.first().and_then(|i| stats.seed_of(*i)) {
+                Some(seed) => format!("/cangjie-test {} --seed {}", name, seed),
+                None => format!(
+                    "/cangjie-test {} --repeat {} --until-failure",
+                    name, test_args.repeat
+                ),
+            };
+            lines.push(format!("   Reproduce: `{}`", replay));
+        }
+        push_output_section(text, sections, "Suspected Flaky Tests", &lines.join("\n"));
+    }
+
+    /// Handles the `/cangjie-refresh-sdk` slash command, clearing the cached SDK root and tool
+    /// paths and resolving the SDK again.
+    ///
+    /// With `--record`, every answer of the host during resolution is appended as a recording
+    /// that can be replayed offline in tests.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `args` - The slash command arguments
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The resolution steps or an error message
+    fn handle_refresh_sdk_command(
+        &self,
+        worktree: &zed::Worktree,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        match args {
+            [] => self.refresh_sdk(&ZedHost::new(worktree)),
+            [flag] if flag == "--record" => {
+                let recorder = host::Recorder::new(ZedHost::new(worktree));
+                let mut output = self.refresh_sdk(&recorder)?;
+                let recording = serde_json::to_string_pretty(&recorder.to_json())
+                    .map_err(|e| format!("Failed to serialize recording: {}", e))?;
+                push_output_section(
+                    &mut output.text,
+                    &mut output.sections,
+                    "Recording",
+                    &format!("```json\n{}\n```", recording),
+                );
+                Ok(output)
+            }
+            _ => Err("Usage: /cangjie-refresh-sdk [--record]".to_string()),
+        }
+    }
+
+    /// Clears the cached SDK root and resolves it again, reporting each step.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The resolution steps or an error message
+    fn refresh_sdk(&self, host: &dyn HostServices) -> Result<zed::SlashCommandOutput, String> {
+        let previous = self
+            .cached_sdk_roots
+            .lock()
+            .ok()
+            .and_then(|mut cache| cache.remove(&host.worktree_root()));
+        self.clear_tool_paths(&host.worktree_root());
+        self.forget_resolution(host);
+
+        let mut steps = Vec::new();
+        let resolved = self.resolve_sdk_root_traced(host, &mut steps).map(|path| {
+            let target = host.canonicalize(&path).unwrap_or_else(|_| path.clone());
+            sdk_layout::SdkRoot { path, target }
+        });
+        let is_sdk_root =
+            |root: &Path| sdk_layout::SdkLayout::is_sdk_root_with(root, |path| host.is_dir(path));
+        if let Ok(root) = &resolved
+            && is_sdk_root(&root.target)
+            && let Ok(mut cache) = self.cached_sdk_roots.lock()
+        {
+            cache.insert(host.worktree_root(), root.clone());
+        }
+
+        let mut text = String::new();
+        let mut sections = Vec::new();
+        let steps = steps
+            .iter()
+            .enumerate()
+            .map(|(i, step)| format!("{}. {}", i + 1, step))
+            .collect::<Vec<_>>()
+            .join("\n");
+        push_output_section(&mut text, &mut sections, "Resolution Steps", &steps);
+
+        let result = match &resolved {
+            Ok(root) => {
+                let mut lines = vec![format!("✅ SDK Root: `{}`", root.path.display())];
+                if root.is_link() {
+                    lines.push(format!("Link Target: `{}`", root.target.display()));
+                }
+                match &previous {
+                    Some(previous) if previous.target != root.target => {
+                        lines.push(format!("Changed from `{}`", previous.target.display()))
+                    }
+                    Some(_) => lines.push("Unchanged".to_string()),
+                    None => {}
+                }
+                if !is_sdk_root(&root.target) {
+                    lines.push("⚠️ No SDK binaries found; the root is not cached".to_string());
+                }
+                lines.join("\n")
+            }
+            Err(e) => format!("❌ {}", e),
+        };
+        push_output_section(&mut text, &mut sections, "Result", &result);
+
+        Ok(zed::SlashCommandOutput { text, sections })
+    }
+
+    /// Handles the `/cangjie-journal` slash command.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `args` - The slash command arguments: a count of entries, or `--all`
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The journal or an error message
+    fn handle_journal_command(
+        &self,
+        worktree: &zed::Worktree,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        self.show_journal(&ZedHost::new(worktree), args)
+    }
+
+    /// Lists the newest changes the extension made to the machine.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `args` - The slash command arguments: a count of entries, or `--all`
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The journal or an error message
+    fn show_journal(
+        &self,
+        host: &dyn HostServices,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let usage = || "Usage: /cangjie-journal [<count>|--all]".to_string();
+        let limit = match args {
+            [] => Some(journal::DEFAULT_LIMIT),
+            [all] if all == "--all" => None,
+            [count] => Some(count.parse::<usize>().map_err(|_| usage())?),
+            _ => return Err(usage()),
+        };
+        let entries = host
+            .read_text_file(Path::new(journal::JOURNAL_FILE))
+            .map(|journal| journal::parse(&journal))
+            .unwrap_or_default();
+
+        let mut builder = self.output_builder(host);
+        if entries.is_empty() {
+            builder.section("Journal", "No changes recorded yet");
+        } else {
+            let shown = limit.map_or(entries.len(), |limit| limit.min(entries.len()));
+            let mut content = journal::render(&entries, limit);
+            if shown < entries.len() {
+                content.push_str(&format!(
+                    "\n\nShowing the newest {} of {} entries; `/cangjie-journal --all` shows all",
+                    shown,
+                    entries.len()
+                ));
+            }
+            builder.section("Journal", &content);
+        }
+        Ok(builder.build())
+    }
+
+    /// Handles the `/cangjie-lsp-health` slash command.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `args` - The slash command arguments
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The health report or an error message
+    fn handle_lsp_health_command(
+        &self,
+        worktree: &zed::Worktree,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        if !args.is_empty() {
+            return Err("Usage: /cangjie-lsp-health".to_string());
+        }
+        let (os, _) = zed::current_platform();
+        Ok(self.lsp_health(&ZedHost::new(worktree), os))
+    }
+
+    /// Checks that the language server is installed, reports its version and starts it on
+    /// the worktree to see whether it answers `initialize`.
+    ///
+    /// Nothing is downloaded: a server that is neither configured, in the SDK nor downloaded
+    /// is reported as missing.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `os` - The current platform
+    ///
+    /// # Returns
+    /// * `zed::SlashCommandOutput` - The server, startup and capabilities sections
+    fn lsp_health(&self, host: &dyn HostServices, os: Os) -> zed::SlashCommandOutput {
+        let mut builder = self.output_builder(host);
+        let located = match self.locate_language_server(host) {
+            Ok(Some(path)) => Ok(Some(path)),
+            Ok(None) => Ok(self
+                .select_downloads_dir(host)
+                .ok()
+                .and_then(|dir| self.newest_installed_language_server(host, os, &dir))
+                .map(|(_, path)| path.to_string_lossy().to_string())),
+            Err(e) => Err(e),
+        };
+        let server = match located {
+            Ok(Some(server)) => server,
+            Ok(None) => {
+                builder.section(
+                    "Server",
+                    &format!(
+                        "❌ {} is missing: not configured, not in the SDK and not downloaded\n\
+                         💡 Open a Cangjie file to download it, or set '{}' or \
+                         `lsp.{}.binary.path`",
+                        SERVER_NAME, CONFIG_SDK_PATH_KEY, SERVER_NAME
+                    ),
+                );
+                return builder.build();
+            }
+            Err(e) => {
+                builder.section("Server", &format!("❌ {}", e));
+                return builder.build();
+            }
+        };
+
+        let mut lines = vec![format!("Path: `{}`", server)];
+        match self.query_tool_version(host, &server) {
+            Ok(version) => lines.push(format!("✅ Version: {}", version.version)),
+            Err(e) => lines.push(format!("❌ `--version` failed: {}", e)),
+        }
+        builder.section("Server", &lines.join("\n"));
+
+        let root = host.worktree_root();
+        let session = host
+            .current_dir()
+            .map(|dir| dir.join(smoke::HEALTH_SESSION_FILE))
+            .unwrap_or_else(|_| PathBuf::from(smoke::HEALTH_SESSION_FILE));
+        let output = host
+            .write_text_file(&session, &smoke::lsp_session(&root))
+            .and_then(|_| {
+                let args = vec![LSP_DEFAULT_TRANSPORT.to_string()];
+                let session = session.to_string_lossy();
+                let (program, args) =
+                    run_config::wrap_command(os, &server, &args, &root, Some(session.as_ref()));
+                host.run_with_env(&program, &args, &self.language_server_env(host, os))
+            });
+        let output = match output {
+            Ok(output) => output,
+            Err(e) => {
+                builder.section("Startup", &format!("❌ Could not start {}: {}", SERVER_NAME, e));
+                return builder.build();
+            }
+        };
+        let status = output.status.map_or("none".to_string(), |code| code.to_string());
+        let stderr = output.stderr.trim();
+        let stderr_tail = stderr.lines().rev().take(20).collect::<Vec<_>>();
+        let stderr_tail = stderr_tail.into_iter().rev().collect::<Vec<_>>().join("\n");
+        match smoke::lsp_capabilities(&output.stdout) {
+            Some(capabilities) => {
+                let mut lines = vec![format!("✅ Answered `initialize` on `{}`", root)];
+                if output.status != Some(0) {
+                    lines.push(format!("⚠️ Exited with status {} after shutdown", status));
+                }
+                if !stderr.is_empty() {
+                    lines.push(format!("Standard error:\n```\n{}\n```", stderr_tail));
+                }
+                builder.section("Startup", &lines.join("\n"));
+                let capabilities = smoke::enabled_capabilities(&capabilities);
+                let content = if capabilities.is_empty() {
+                    "⚠️ The server announced no capabilities".to_string()
+                } else {
+                    let items: Vec<String> =
+                        capabilities.iter().map(|name| format!("- {}", name)).collect();
+                    items.join("\n")
+                };
+                builder.section("Capabilities", &content);
+            }
+            None => {
+                let mut lines = vec![format!(
+                    "❌ {} started but did not answer `initialize` (exit status {})",
+                    SERVER_NAME, status
+                )];
+                if stderr.is_empty() {
+                    lines.push("No output on standard error".to_string());
+                } else {
+                    lines.push(format!("Standard error:\n```\n{}\n```", stderr_tail));
+                }
+                lines.push(
+                    "💡 The server exits or hangs on start; `/cangjie-info` shows the SDK it \
+                     runs with"
+                        .to_string(),
+                );
+                builder.section("Startup", &lines.join("\n"));
+            }
+        }
+        builder.build()
+    }
+
+    /// Handles the `/cangjie-restart-lsp` slash command.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `args` - The slash command arguments
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The resolved server or an error message
+    fn handle_restart_lsp_command(
+        &self,
+        worktree: &zed::Worktree,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        if !args.is_empty() {
+            return Err("Usage: /cangjie-restart-lsp".to_string());
+        }
+        self.reresolve_language_server(&ZedHost::new(worktree), zed::current_platform())
+    }
+
+    /// Forgets the cached language server of the worktree and resolves it again.
+    ///
+    /// Clears the cached SDK root, tool paths and the remembered resolution, then installs the
+    /// server the way a start would. Extensions cannot restart a language server themselves,
+    /// so the output ends with the command that does.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `platform` - The platform to install for
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The resolved server or an error message
+    fn reresolve_language_server(
+        &self,
+        host: &dyn HostServices,
+        platform: (Os, Architecture),
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let root = host.worktree_root();
+        let previous = self.cached_resolution(host).map(|resolution| resolution.server_path);
+        if let Ok(mut cache) = self.cached_sdk_roots.lock() {
+            cache.remove(&root);
+        }
+        self.clear_tool_paths(&root);
+        self.forget_resolution(host);
+
+        let server_path = self.install_language_server(host, platform, &|_| {})?;
+        let mut lines = vec![format!("✅ {}: `{}`", SERVER_NAME, server_path)];
+        if let Some(channel) = self.worktree_state(&root, STATE_LSP_CHANNEL_KEY) {
+            lines.push(format!("Source: {}", channel));
+        }
+        match previous {
+            Some(previous) if previous != server_path => {
+                lines.push(format!("Changed from `{}`", previous))
+            }
+            Some(_) => lines.push("Unchanged".to_string()),
+            None => {}
+        }
+
+        let mut builder = self.output_builder(host);
+        builder.section("Language Server", &lines.join("\n"));
+        builder.section(
+            "Next Step",
+            "💡 Run `editor: restart language server` from the command palette to start it",
+        );
+        Ok(builder.build())
+    }
+
+    /// Handles the `/cangjie-upgrade-lsp` slash command.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `args` - The slash command arguments
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The upgrade report or an error message
+    fn handle_upgrade_lsp_command(
+        &self,
+        worktree: &zed::Worktree,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        if !args.is_empty() {
+            return Err("Usage: /cangjie-upgrade-lsp".to_string());
+        }
+        self.upgrade_language_server(&ZedHost::new(worktree), zed::current_platform())
+    }
+
+    /// Switches to the newest language server release for the next start.
+    ///
+    /// A release prefetched into [`prefetch::PREFETCH_DIR`] is moved into place; otherwise
+    /// the latest release is downloaded now.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `platform` - The platform to install for
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The upgrade report or an error message
+    fn upgrade_language_server(
+        &self,
+        host: &dyn HostServices,
+        (os, arch): (Os, Architecture),
+    ) -> Result<zed::SlashCommandOutput, String> {
+        if let Some(path) = self.locate_language_server(host)? {
+            return Err(format!(
+                "The language server is not downloaded by the extension (`{}`); update the SDK \
+                 or the binary override instead",
+                path
+            ));
+        }
+        if let Some(pin) = self.pinned_lsp_version(host) {
+            return Err(format!(
+                "The language server is pinned to {} by '{}'",
+                pin, CONFIG_LSP_VERSION_KEY
+            ));
+        }
+
+        let downloads_dir = self.select_downloads_dir(host)?;
+        let prefetch_dir = downloads_dir.join(prefetch::PREFETCH_DIR);
+        let installed = self.newest_installed_language_server(host, os, &downloads_dir);
+        let is_newer = |candidate: &version::Version| {
+            installed.as_ref().is_none_or(|(installed, _)| candidate > installed)
+        };
+        let prefetched = self
+            .newest_installed_language_server(host, os, &prefetch_dir)
+            .filter(|(prefetched, _)| is_newer(prefetched))
+            .and_then(|(prefetched, binary)| {
+                let relative = binary.strip_prefix(&prefetch_dir).ok()?;
+                Some((prefetched, relative.components().next()?.as_os_str().to_owned()))
+            });
+
+        let mut lines = Vec::new();
+        let upgraded = match prefetched {
+            Some((prefetched, dir_name)) => {
+                let install_dir = downloads_dir.join(&dir_name);
+                let prefetched_dir = prefetch_dir.join(&dir_name);
+                host.rename(&prefetched_dir, &install_dir)
+                    .map_err(|e| format!("Failed to move the prefetched release: {}", e))?;
+                self.record_action(
+                    host,
+                    journal::Action::Move,
+                    &install_dir,
+                    "Upgraded to the prefetched release".to_string(),
+                    Some(format!("move it back to `{}`", prefetched_dir.display())),
+                );
+                self.remove_stale_installs(host, SERVER_NAME, &downloads_dir, &install_dir);
+                lines.push("Used the prefetched release".to_string());
+                Some(prefetched)
+            }
+            None => {
+                if !self.network_policy(host).allows_check() {
+                    return Err(format!(
+                        "No prefetched release, and '{}' is set",
+                        CONFIG_NETWORK_OFFLINE_KEY
+                    ));
+                }
+                let channel = self.toolchain_channel(host);
+                let release = self.fetch_language_server_release(host, channel, None)?;
+                match version::Version::parse(&release.version) {
+                    Some(latest) if is_newer(&latest) => {
+                        self.install_release(host, (os, arch), &release, &downloads_dir, &|_| {})?;
+                        Some(latest)
+                    }
+                    _ => None,
+                }
+            }
+        };
+
+        let current = installed.map_or("none".to_string(), |(version, _)| version.to_string());
+        match upgraded {
+            Some(latest) => {
+                self.forget_resolution(host);
+                lines.insert(
+                    0,
+                    format!("✅ Upgraded the language server from {} to {}", current, latest),
+                );
+                lines.push(
+                    "💡 Restart the language server or Zed to use the new release".to_string(),
+                );
+            }
+            None => lines.push(format!("✅ Language server {} is up to date", current)),
+        }
+        let mut builder = self.output_builder(host);
+        builder.section("Language Server Upgrade", &lines.join("\n"));
+        Ok(builder.build())
+    }
+
+    /// Handles the `/cangjie-smoke` slash command, an end-to-end check of the toolchain.
+    ///
+    /// With `--lsp`, the language server is also started against the smoke test project.
+    ///
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    /// * `args` - The slash command arguments
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The per-stage report or an error message
+    fn handle_smoke_command(
+        &self,
+        worktree: &zed::Worktree,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let with_lsp = match args {
+            [] => false,
+            [flag] if flag == "--lsp" => true,
+            _ => return Err("Usage: /cangjie-smoke [--lsp]".to_string()),
+        };
+        let host = ZedHost::new(worktree);
+        let platform = zed::current_platform();
+        let cjpm = self.cjpm_binary_path(&host)?;
+        let env = self.sdk_environment(&host, platform.0);
+        let lsp = with_lsp.then(|| self.install_language_server(&host, platform, &|_| {}));
+        Ok(self.run_smoke_test(&host, platform.0, &cjpm, lsp, &env))
+    }
+
+    /// Creates, builds, runs and tests a hello-world project, reporting each stage.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `os` - The platform's operating system
+    /// * `cjpm` - Path of the cjpm binary
+    /// * `lsp` - The language server to exercise, if requested
+    /// * `env` - The SDK runtime environment
+    ///
+    /// # Returns
+    /// * `zed::SlashCommandOutput` - The per-stage report
+    fn run_smoke_test(
+        &self,
+        host: &dyn HostServices,
+        os: Os,
+        cjpm: &str,
+        lsp: Option<Result<String, String>>,
+        env: &[(String, String)],
+    ) -> zed::SlashCommandOutput {
+        use smoke::Stage;
+
+        let numbers = self.number_format(host);
+        let project = host
+            .current_dir()
+            .map(|dir| dir.join(smoke::PROJECT_DIR))
+            .unwrap_or_else(|_| PathBuf::from(smoke::PROJECT_DIR));
+        let project_dir = project.to_string_lossy().to_string();
+        let source_dir = project.join("src");
+
+        // Runs a program in the project, failing with its exit status and output
+        let run = |program: &str, args: &[&str], stdin: Option<&str>| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            let (program, args) =
+                run_config::wrap_command(os, program, &args, &project_dir, stdin);
+            let output = host.run_with_env(&program, &args, env).map_err(|e| (None, e))?;
+            let combined = format!("{}\n{}", output.stdout, output.stderr);
+            match output.status {
+                Some(0) => Ok(output),
+                status => Err((status, combined.trim().to_string())),
+            }
+        };
+        let write = |path: &Path, contents: &str| {
+            host.write_text_file(path, contents).map_err(|e| (None, e))
+        };
+
+        let mut stages = vec![Stage::Create, Stage::Build, Stage::Run, Stage::Test];
+        if lsp.is_some() {
+            stages.push(Stage::LanguageServer);
+        }
+        let mut table = vec![
+            "| Stage | Result | Time |".to_string(),
+            "|-------|--------|------|".to_string(),
+        ];
+        let mut failure = None;
+        for stage in stages {
+            if failure.is_some() {
+                table.push(format!("| {} | ⚠️ skipped | |", stage.label()));
+                continue;
+            }
+            let started = std::time::Instant::now();
+            let outcome = match stage {
+                Stage::Create => {
+                    host.remove_path(&project);
+                    self.record_action(
+                        host,
+                        journal::Action::Install,
+                        &project,
+                        "Created the /cangjie-smoke project, replacing the previous one"
+                            .to_string(),
+                        Some(format!("delete `{}`", project.display())),
+                    );
+                    host.create_dir_all(&project)
+                        .map_err(|e| (None, e))
+                        .and_then(|_| run(cjpm, &["init", "--name", smoke::PACKAGE_NAME], None))
+                        .and_then(|_| host.create_dir_all(&source_dir).map_err(|e| (None, e)))
+                        .and_then(|_| write(&source_dir.join("main.cj"), &smoke::main_source()))
+                        .and_then(|_| {
+                            let test_file = source_dir.join("smoke_test.cj");
+                            write(&test_file, &smoke::test_source())
+                        })
+                }
+                Stage::Build => run(cjpm, &["build"], None).map(|_| ()),
+                Stage::Run => run(cjpm, &["run"], None).and_then(|output| {
+                    if output.stdout.contains(smoke::EXPECTED_OUTPUT) {
+                        Ok(())
+                    } else {
+                        let message = format!(
+                            "Expected the program to print `{}`\n{}",
+                            smoke::EXPECTED_OUTPUT,
+                            output.stdout
+                        );
+                        Err((output.status, message))
+                    }
+                }),
+                Stage::Test => run(cjpm, &["test"], None).map(|_| ()),
+                Stage::LanguageServer => match &lsp {
+                    Some(Ok(server)) => {
+                        let session = project.join(smoke::LSP_SESSION_FILE);
+                        write(&session, &smoke::lsp_session(&project_dir))
+                            .and_then(|_| {
+                                let session = session.to_string_lossy();
+                                run(server, &["--stdio"], Some(session.as_ref()))
+                            })
+                            .and_then(|output| {
+                                if smoke::lsp_initialized(&output.stdout) {
+                                    Ok(())
+                                } else {
+                                    let message = format!(
+                                        "No `initialize` response from {}\n{}\n{}",
+                                        SERVER_NAME, output.stdout, output.stderr
+                                    );
+                                    Err((output.status, message.trim().to_string()))
+                                }
+                            })
+                    }
+                    Some(Err(e)) => Err((None, e.clone())),
+                    None => Ok(()),
+                },
+            };
+            let elapsed = humanize::format_duration(started.elapsed(), &numbers);
+            match outcome {
+                Ok(()) => table.push(format!("| {} | ✅ passed | {} |", stage.label(), elapsed)),
+                Err(error) => {
+                    table.push(format!("| {} | ❌ failed | {} |", stage.label(), elapsed));
+                    failure = Some((stage, error));
+                }
+            }
+        }
+
+        // Tool output refers to files relative to the smoke test project
+        let mut builder = self.output_builder(host).root(project_dir.as_str());
+        let summary = match &failure {
+            None => "✅ Toolchain smoke test passed".to_string(),
+            Some((stage, _)) => format!("❌ Smoke test failed at {}", stage.label()),
+        };
+        builder.section(
+            "Result",
+            &format!("{}\nProject: `{}`", summary, project_dir),
+        );
+        if let Some((stage, (_, output))) = &failure {
+            let label = format!("{} Output", stage.label());
+            builder.section(&label, &format!("❌ {}", output)).locations(output);
+        }
+        builder.section("Stages", &table.join("\n"));
+        let mut result = builder.build();
+        if let Some((stage, (status, output))) = &failure {
+            let tool = if *stage == Stage::LanguageServer { SERVER_NAME } else { CJPM_NAME };
+            push_hints_section(&mut result.text, &mut result.sections, tool, *status, output);
+        }
+        result
+    }
+
+    /// Handles the `/cangjie-verify-assets` slash command, checking that the language server
+    /// release has an asset for every supported platform.
+    ///
+    /// With `--fixture <path>`, a recorded release listing from the worktree is checked
+    /// instead of fetching the release of the configured channel.
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `args` - The slash command arguments
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The verification report or an error message
+    fn handle_verify_assets_command(
+        &self,
+        host: &dyn HostServices,
+        args: &[String],
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let (source, asset_names) = match args {
+            [] => {
+                let channel = self.toolchain_channel(host);
+                let pinned = self.pinned_lsp_version(host);
+                let release =
+                    self.fetch_language_server_release(host, channel, pinned.as_deref())?;
+                let names = release.assets.iter().map(|a| a.name.clone()).collect();
+                let selection = match pinned {
+                    Some(_) => "pinned".to_string(),
+                    None => format!("{} channel", channel.as_str()),
+                };
+                let source = format!("{} {} ({})", LSP_RELEASE_REPO, release.version, selection);
+                (source, names)
+            }
+            [flag, path] if flag == "--fixture" => {
+                let json = host
+                    .read_worktree_file(path)
+                    .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+                (format!("Fixture `{}`", path), assets::parse_asset_listing(&json)?)
+            }
+            _ => return Err("Usage: /cangjie-verify-assets [--fixture <path>]".to_string()),
+        };
+
+        let checks = assets::verify_assets(&asset_names, SERVER_NAME, |os, arch| {
+            self.get_asset_name_for_platform(os, arch, SERVER_NAME)
+        });
+
+        let mut text = String::new();
+        let mut sections = Vec::new();
+        push_output_section(&mut text, &mut sections, "Release", &source);
+
+        let mut table = vec![
+            "| Platform | Expected Asset | Status |".to_string(),
+            "|----------|----------------|--------|".to_string(),
+        ];
+        for check in &checks {
+            let (expected, status) = match (&check.expected, &check.asset) {
+                (Ok(name), Some(asset)) if asset == name => {
+                    (format!("`{}`", name), "✅ found".to_string())
+                }
+                (Ok(name), Some(asset)) => (format!("`{}`", name), format!("✅ `{}`", asset)),
+                (Ok(name), None) => (format!("`{}`", name), "❌ missing".to_string()),
+                (Err(e), _) => (e.clone(), "⚠️ unsupported".to_string()),
+            };
+            table.push(format!("| {} | {} | {} |", check.platform, expected, status));
+        }
+        push_output_section(&mut text, &mut sections, "Platform Matrix", &table.join("\n"));
+
+        let missing: Vec<_> = checks
+            .iter()
+            .filter(|check| check.expected.is_ok() && check.asset.is_none())
+            .map(|check| check.platform.as_str())
+            .collect();
+        let summary = if missing.is_empty() {
+            "✅ Every supported platform has a matching asset".to_string()
+        } else {
+            format!(
+                "❌ Asset naming drift: no matching asset for {}",
+                missing.join(", ")
+            )
+        };
+        push_output_section(&mut text, &mut sections, "Summary", &summary);
+
+        Ok(zed::SlashCommandOutput { text, sections })
+    }
+
+    /// Handles the `/cangjie-check-updates` slash command to check for LSP updates.
+    /// 
+    /// # Arguments
+    /// * `worktree` - The current worktree context
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The update check result or an error message
+    fn handle_check_updates_command(
+        &self,
+        worktree: &zed::Worktree,
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let (os, _) = zed::current_platform();
+        self.check_for_updates(&ZedHost::new(worktree), os)
+    }
+
+    /// Compares the installed language server with the latest release of the toolchain
+    /// channel and shows an excerpt of its release notes.
+    ///
+    /// The result is kept in [`updates::STATE_FILE`] and reused for
+    /// [`updates::CHECK_INTERVAL_SECS`].
+    ///
+    /// # Arguments
+    /// * `host` - The host services of the current worktree
+    /// * `os` - The platform's operating system
+    ///
+    /// # Returns
+    /// * `Result<zed::SlashCommandOutput, String>` - The update check result or an error message
+    fn check_for_updates(
+        &self,
+        host: &dyn HostServices,
+        os: Os,
+    ) -> Result<zed::SlashCommandOutput, String> {
+        let now = host.unix_time();
+        let channel = self.toolchain_channel(host);
+        let pre_release = self.channel_pre_release(host, channel);
+        let state_path = Path::new(updates::STATE_FILE);
+        let previous = host
+            .read_text_file(state_path)
+            .ok()
+            .and_then(|state| updates::UpdateCheck::from_json(&state))
+            .filter(|check| {
+                check.is_fresh(now, LSP_RELEASE_REPO, channel.as_str(), pre_release)
+            });
+        let reused = previous.is_some();
+        let check = match previous {
+            Some(check) => check,
+            None => {
+                if !self.network_policy(host).allows_check() {
+                    return Err(format!(
+                        "Update checks are disabled by '{}'",
+                        CONFIG_NETWORK_OFFLINE_KEY
+                    ));
+                }
+                let release = self.fetch_language_server_release(host, channel, None)?;
+                let url = updates::release_api_url(LSP_RELEASE_REPO, &release.version);
+                let changelog = match host.http_get(&url) {
+                    Ok(json) => updates::changelog_excerpt(&json),
+                    Err(e) => {
+                        let tag = &release.version;
+                        log::warn!("Could not fetch the release notes of {}: {}", tag, e);
+                        None
+                    }
+                };
+                let check = updates::UpdateCheck {
+                    checked_at: now,
+                    repo: LSP_RELEASE_REPO.to_string(),
+                    channel: channel.as_str().to_string(),
+                    pre_release,
+                    latest: release.version,
+                    changelog,
+                };
+                if let Err(e) = host.write_text_file(state_path, &check.to_json()) {
+                    log::warn!("Could not save the update check: {}", e);
+                }
+                check
+            }
+        };
+
+        let installed = match self.locate_language_server(host) {
+            Ok(Some(path)) => self
+                .query_tool_version(host, &path)
+                .ok()
+                .and_then(|tool_version| version::Version::parse(&tool_version.version)),
+            _ => self
+                .select_downloads_dir(host)
+                .ok()
+                .and_then(|dir| self.newest_installed_language_server(host, os, &dir))
+                .map(|(installed, _)| installed),
+        };
+        let latest = version::Version::parse(&check.latest);
+        let status = match (&installed, &latest) {
+            (Some(installed), Some(latest)) if latest > installed => format!(
+                "⚠️ Cangjie LSP {} is available (installed: {})\n💡 Run /cangjie-upgrade-lsp to \
+                 switch to it",
+                latest, installed
+            ),
+            (Some(installed), _) => format!("✅ Cangjie LSP {} is up to date", installed),
+            (None, _) => format!("Latest Cangjie LSP release: **{}**", check.latest),
+        };
+
+        let mut text = String::new();
+        let mut sections = Vec::new();
+        push_output_section(&mut text, &mut sections, "Update Info", &status);
+        if let Some(changelog) = &check.changelog {
+            let label = format!("Changelog ({})", check.latest);
+            push_output_section(&mut text, &mut sections, &label, changelog);
+        }
+        let checked = format!(
+            "Checked {}{}",
+            journal::format_time(check.checked_at),
+            if reused { " (cached)" } else { "" }
+        );
+        push_output_section(&mut text, &mut sections, "Status", &checked);
+        Ok(zed::SlashCommandOutput { text, sections })
+    }
+}
+
+/// Logs a step of the SDK resolution and records it for `/cangjie-refresh-sdk`.
+///
+/// # Arguments
+/// * `steps` - The recorded steps
+/// * `level` - The log level
+/// * `message` - The step description
+fn trace_step(steps: &mut Vec<String>, level: log::Level, message: String) {
+    log::log!(level, "{}", message);
+    steps.push(message);
+}
+
+/// Gets the appropriate binary name with extension based on the current platform.
+/// 
+/// # Arguments
 /// * `base_name` - The base name of the binary without extension
 /// 
 /// # Returns
@@ -613,28 +6389,60 @@ impl zed::Extension for CangjieExtension {
         language_server_id: &zed::LanguageServerId,
         worktree: &zed::Worktree,
     ) -> Result<zed::Command, String> {
-        self.create_language_server_command(language_server_id, worktree)
+        if language_server_id.as_ref() == LINT_SERVER_NAME {
+            return self.create_lint_server_command(worktree);
+        }
+        let command = self.create_language_server_command(language_server_id, worktree);
+        self.note_completion_worktree(worktree);
+        // Kept for the debug locator, which is not told the worktree
+        self.sdk_environment(&ZedHost::new(worktree), zed::current_platform().0);
+        command
     }
 
-    /// Provides workspace configuration for the language server
-    fn language_server_workspace_configuration(
+    /// Provides the options of the language server's `initialize` request.
+    ///
+    /// `initialization_options` of the `cangjie-lsp` settings are merged over the defaults.
+    fn language_server_initialization_options(
         &mut self,
-        _language_server_id: &zed::LanguageServerId,
+        language_server_id: &zed::LanguageServerId,
         worktree: &zed::Worktree,
     ) -> Result<Option<serde_json::Value>, String> {
-        // Attempt to get settings from LspSettings for the 'cangjie' language
-        if let Ok(lsp_settings) = LspSettings::for_worktree("cangjie", worktree)
-            && let Some(cangjie_settings) = &lsp_settings.settings
+        if language_server_id.as_ref() == LINT_SERVER_NAME {
+            return Ok(None);
+        }
+        let mut options = self.initialization_options(&ZedHost::new(worktree));
+        if let Ok(lsp_settings) = LspSettings::for_worktree("cangjie-lsp", worktree)
+            && let Some(serde_json::Value::Object(user)) = lsp_settings.initialization_options
+            && let Some(defaults) = options.as_object_mut()
         {
-            // Return the 'cangjie' specific settings part
-            return Ok(Some(cangjie_settings.clone()));
+            defaults.extend(user);
         }
-        Ok(None)
+        Ok(Some(options))
+    }
+
+    /// Provides workspace configuration for the language server, translated from the flat
+    /// `cangjie.*` settings into the server's schema
+    fn language_server_workspace_configuration(
+        &mut self,
+        language_server_id: &zed::LanguageServerId,
+        worktree: &zed::Worktree,
+    ) -> Result<Option<serde_json::Value>, String> {
+        if language_server_id.as_ref() == LINT_SERVER_NAME {
+            let host = ZedHost::new(worktree);
+            return Ok(host
+                .setting(CONFIG_LINT_SETTINGS_KEY)
+                .map(|settings| serde_json::json!({ CJLINT_NAME: settings })));
+        }
+        // Sent even without settings, so the server learns the default watch patterns
+        let settings = self.extension_settings(worktree).unwrap_or_default();
+        let settings = self.resolve_path_settings(&ZedHost::new(worktree), settings);
+        Ok(Some(lsp_config::translate(&settings)))
     }
 
     // --- DAP Integration ---
 
-    /// Creates a debug scenario based on the build task
+    /// Creates a debug scenario based on the build task, see
+    /// [`CangjieExtension::debug_scenario_for_task`]
     fn dap_locator_create_scenario(
         &mut self,
         locator_name: String,
@@ -642,167 +6450,224 @@ impl zed::Extension for CangjieExtension {
         resolved_label: String,
         debug_adapter_name: String,
     ) -> Option<zed::DebugScenario> {
-        if build_task.command != CJC_NAME {
-            return None;
-        }
-
-        let cwd = build_task.cwd.clone();
-        let env = build_task.env.clone().into_iter().collect();
-
-        let mut args_it = build_task.args.iter();
-        let build_template = if let Some(arg) = args_it.next()
-            && arg == "build"
-            && let Some(arg) = args_it.next()
-            && arg == "run"
-        {
-            zed::BuildTaskDefinitionTemplatePayload {
-                template: zed::BuildTaskTemplate {
-                    label: format!("{} (build)", resolved_label),
-                    command: CJC_NAME.into(),
-                    args: vec!["build".into()],
-                    env,
-                    cwd,
-                },
-                locator_name: Some(locator_name),
-            }
-        } else {
-            return None;
-        };
-
-        // Define the debug configuration
-        let config = serde_json::json!({
-            "name": "Launch Cangjie Program",
-            "type": "cjc-frontend", // The adapter name
-            "request": "launch",
-            "program": "${workspaceFolder}/target/debug/${workspaceFolderBasename}",
-            "cwd": "${workspaceFolder}",
-            "args": [],
-            "stopOnEntry": false,
-        });
-
-        let Ok(config_str) = serde_json::to_string(&config) else {
-            return None;
-        };
-
-        Some(zed::DebugScenario {
-            adapter: debug_adapter_name,
-            label: resolved_label,
-            config: config_str,
-            tcp_connection: None, // Use stdio
-            build: Some(zed::BuildTaskDefinition::Template(build_template)),
-        })
+        let (os, _) = zed::current_platform();
+        self.debug_scenario_for_task(
+            os,
+            locator_name,
+            build_task,
+            resolved_label,
+            debug_adapter_name,
+        )
     }
 
-    /// Runs the DAP locator to get debug request
+    /// Runs the DAP locator to get debug request, see
+    /// [`CangjieExtension::debug_request_for_task`]
     fn run_dap_locator(
         &mut self,
         _locator_name: String,
         build_task: zed::TaskTemplate,
     ) -> Result<zed::DebugRequest, String> {
-        let mut args_it = build_task.args.iter();
-        if let Some(arg) = args_it.next()
-            && arg == "build"
-        {
-            let exec_name = get_project_name(&build_task).ok_or("Failed to get project name")?;
-            let program_path = format!("target/debug/{}", exec_name);
-
-            Ok(zed::DebugRequest::Launch(zed::LaunchRequest {
-                program: program_path,
-                cwd: build_task.cwd,
-                args: vec![],
-                envs: build_task.env.into_iter().collect(),
-            }))
-        } else {
-            Err("Unsupported build task for debugging".into())
-        }
+        let (os, _) = zed::current_platform();
+        self.debug_request_for_task(os, build_task)
     }
 
-    /// Gets the DAP binary path for debugging
+    /// Gets the debug adapter for a scenario: cjdb from the adapter path configured in Zed,
+    /// the SDK or a download, `lldb-dap` when cjdb is unavailable, or a debug server reached
+    /// over TCP. Core dumps are opened with `cjdb --core`, or by lldb-dap from `coreFile`
     fn get_dap_binary(
         &mut self,
         _adapter_name: String,
-        _config: zed::DebugTaskDefinition,
-        _user_provided_debug_adapter_path: Option<String>,
+        config: zed::DebugTaskDefinition,
+        user_provided_debug_adapter_path: Option<String>,
         worktree: &zed::Worktree,
     ) -> Result<zed::DebugAdapterBinary, String> {
-        let binary_path = self.cjc_frontend_binary_path(worktree)?;
+        let host = ZedHost::new(worktree);
+        let root = worktree.root_path();
+        if let Some(gate) = self.disabled_by_compatibility(&root, compat::FEATURE_DEBUGGER) {
+            return Err(gate.message());
+        }
+        let scenario_config: serde_json::Value = serde_json::from_str(&config.config)
+            .map_err(|e| format!("Invalid debug configuration: {}", e))?;
+        let request = debug::debug_request(&scenario_config)?;
+        let mut configuration = debug::adapter_configuration(&config.label, &scenario_config)?;
+        let (os, _) = zed::current_platform();
+        if os == Os::Windows && matches!(request, zed::DebugRequest::Launch(_)) {
+            if let Some(program) = configuration["program"].as_str() {
+                configuration["program"] = debug::executable_path(os, None, program).into();
+            }
+            // Windows programs find the SDK runtime DLLs through PATH
+            if let Ok(layout) = self.sdk_layout(&host) {
+                let existing = match configuration["env"]["PATH"].as_str() {
+                    Some(path) => Some(path.to_string()),
+                    None => worktree
+                        .shell_env()
+                        .into_iter()
+                        .find(|(key, _)| key.eq_ignore_ascii_case("PATH"))
+                        .map(|(_, value)| value),
+                };
+                let (key, value) = layout.library_path_env(os, existing.as_deref());
+                debug::set_env(&mut configuration, &key, &value);
+            }
+        }
+        let request_args = zed::StartDebuggingRequestArguments {
+            request: debug::request_kind(&request),
+            configuration: configuration.to_string(),
+        };
+
+        // A debug server that is already running is connected to instead of started
+        let tcp_connection = self.debug_tcp_connection(&host, config.tcp_connection)?;
+        if let Some(template) = tcp_connection {
+            return Ok(zed::DebugAdapterBinary {
+                command: None,
+                arguments: vec![],
+                envs: vec![],
+                cwd: None,
+                connection: Some(zed::resolve_tcp_template(template)?),
+                request_args,
+            });
+        }
+
+        let cjdb_path = match user_provided_debug_adapter_path {
+            Some(path) => Ok(path),
+            None => self.auxiliary_tool_path(&host, CJDB_NAME).or_else(|e| {
+                log::info!("{}", e);
+                self.install_debugger(&host, zed::current_platform())
+            }),
+        };
+        let (binary_path, arguments, request_args) = match cjdb_path {
+            Ok(path) => {
+                let mut arguments = self.debugger_args(&host)?;
+                if let Some(core) = debug::core_file(&configuration) {
+                    arguments.extend(["--core".to_string(), core.to_string()]);
+                }
+                (path, arguments, request_args)
+            }
+            // Without cjdb, native-backend programs can still be debugged with LLDB
+            Err(e) => {
+                let lldb_dap = debug::LLDB_DAP_NAMES
+                    .iter()
+                    .find_map(|name| host.which(&get_binary_name(name)))
+                    .ok_or_else(|| {
+                        format!("{}\n💡 Or install lldb-dap from LLVM to debug with LLDB", e)
+                    })?;
+                log::info!("{} unavailable, debugging with {}", CJDB_NAME, lldb_dap);
+                let scripts = self
+                    .sdk_layout(&host)
+                    .map(|layout| layout.lldb_formatter_scripts(|path| host.is_file(path)))
+                    .unwrap_or_default();
+                let request_args = zed::StartDebuggingRequestArguments {
+                    request: request_args.request,
+                    configuration: debug::lldb_configuration(&configuration, &scripts)
+                        .to_string(),
+                };
+                (lldb_dap, vec![], request_args)
+            }
+        };
+
+        // Let the adapter find the SDK runtime libraries regardless of the SDK layout
+        let mut envs = Vec::new();
+        if let Ok(layout) = self.sdk_layout(&host) {
+            let shell_env = worktree.shell_env();
+            let (lib_var, _) = layout.library_path_env(os, None);
+            let existing = shell_env
+                .iter()
+                .find(|(key, _)| *key == lib_var)
+                .map(|(_, value)| value.as_str());
+            envs.push(layout.library_path_env(os, existing));
+        }
+
         Ok(zed::DebugAdapterBinary {
             command: Some(binary_path),
-            arguments: vec![],
-            envs: Default::default(),
-            cwd: None,
+            arguments,
+            envs,
+            cwd: Some(root),
             connection: None, // Use stdio
-            request_args: zed::StartDebuggingRequestArguments {
-                request: zed::StartDebuggingRequestArgumentsRequest::Launch,
-                configuration: Default::default(),
-            },
+            request_args,
         })
     }
 
-    /// Gets the DAP request kind for debugging
+    /// Gets the DAP request kind for debugging: `"request": "attach"` attaches to a running
+    /// process, anything else launches the program
     fn dap_request_kind(
         &mut self,
         _adapter_name: String,
-        _config: serde_json::Value,
+        config: serde_json::Value,
     ) -> Result<zed::StartDebuggingRequestArgumentsRequest, String> {
-        Ok(zed::StartDebuggingRequestArgumentsRequest::Launch)
+        debug::debug_request(&config).map(|request| debug::request_kind(&request))
     }
 
-    /// Converts a debug config to a debug scenario
+    /// Converts a launch or attach request from Zed's new session UI to a debug scenario
     fn dap_config_to_scenario(
         &mut self,
-        _config: zed::DebugConfig,
+        config: zed::DebugConfig,
     ) -> Result<zed::DebugScenario, String> {
-        // This specific API version does not provide direct access to config fields
-        Err(
-            "Direct DebugConfig to Scenario conversion is not supported in this API version."
-                .into(),
-        )
+        let scenario_config = debug::scenario_config(&config.request, config.stop_on_entry);
+        Ok(zed::DebugScenario {
+            adapter: config.adapter,
+            label: config.label,
+            config: scenario_config.to_string(),
+            tcp_connection: None, // Use stdio
+            build: None,
+        })
     }
 
     // --- Slash Commands ---
 
+    /// Completes slash command arguments from the index of the worktree seen last: test
+    /// names for `/cangjie-test`, run configurations and executable targets for
+    /// `/cangjie-run`, and source paths for `/cangjie-fmt`, `/cangjie-doc` and
+    /// `/cangjie-test-here`. The index is built here, on the first completion after it
+    /// went stale.
+    fn complete_slash_command_argument(
+        &self,
+        command: zed::SlashCommand,
+        args: Vec<String>,
+    ) -> Result<Vec<zed::SlashCommandArgumentCompletion>, String> {
+        let (os, _) = zed::current_platform();
+        let index = self.completion_index(&ZedHost::without_worktree(), os);
+        Ok(completion::complete(&index, &command.name, &args))
+    }
+
     /// Handles slash commands for the Cangjie extension
     fn run_slash_command(
         &self,
         command: zed::SlashCommand,
-        _args: Vec<String>,
+        args: Vec<String>,
         worktree: Option<&zed::Worktree>,
     ) -> Result<zed::SlashCommandOutput, String> {
         let worktree = worktree.ok_or("Worktree not available for slash command")?;
+        self.note_completion_worktree(worktree);
 
-        match command.name.as_str() {
-            "cangjie-info" => {
-                let mut output_lines = vec!["**Cangjie Extension Information:**\n".to_string()];
-                match self.resolve_sdk_root(worktree) {
-                    Ok(root) => output_lines.push(format!("SDK Root: `{}`", root.display())),
-                    Err(e) => output_lines.push(format!("SDK Root: *Error finding SDK:* {}", e)),
-                }
-                match self.cjc_binary_path(worktree) {
-                    Ok(path) => output_lines.push(format!("cjc Path: `{}`", path)),
-                    Err(e) => output_lines.push(format!("cjc Path: *Error:* {}", e)),
-                }
-                match self.cjc_frontend_binary_path(worktree) {
-                    Ok(path) => output_lines.push(format!("cjc-frontend Path: `{}`", path)),
-                    Err(e) => output_lines.push(format!("cjc-frontend Path: *Error:* {}", e)),
-                }
-
-                let text = output_lines.join("\n");
-                Ok(zed::SlashCommandOutput {
-                    text: text.clone(),
-                    sections: vec![zed::SlashCommandOutputSection {
-                        label: "Info".to_string(),
-                        range: zed::Range {
-                            start: 0,
-                            end: text.len() as u32,
-                        },
-                    }],
-                })
-            }
+        let output = match command.name.as_str() {
+            "cangjie-info" => self.handle_info_command(worktree),
+            "cangjie-bench" => self.handle_bench_command(worktree, &args),
             "cangjie-build" => self.handle_build_command(worktree),
-            "cangjie-check-updates" => self.handle_check_updates_command(),
+            "cangjie-check-updates" => self.handle_check_updates_command(worktree),
+            "cangjie-clean" => self.handle_clean_command(worktree),
+            "cangjie-doc" => self.handle_doc_command(worktree, &args),
+            "cangjie-env" => self.handle_env_command(worktree),
+            "cangjie-fmt" => self.handle_fmt_command(worktree, &args),
+            "cangjie-import-deveco" => self.handle_import_deveco_command(worktree),
+            "cangjie-journal" => self.handle_journal_command(worktree, &args),
+            "cangjie-lint" => self.handle_lint_command(worktree, &args),
+            "cangjie-lsp-health" => self.handle_lsp_health_command(worktree, &args),
+            "cangjie-restart-lsp" => self.handle_restart_lsp_command(worktree, &args),
+            "cangjie-run" => self.handle_run_command(worktree, &args),
+            "cangjie-tasks" => self.handle_tasks_command(worktree),
+            "cangjie-test" => self.handle_test_command(worktree, &args),
+            "cangjie-test-here" => self.handle_test_here_command(worktree, &args),
+            "cangjie-refresh-sdk" => self.handle_refresh_sdk_command(worktree, &args),
+            "cangjie-smoke" => self.handle_smoke_command(worktree, &args),
+            "cangjie-upgrade-lsp" => self.handle_upgrade_lsp_command(worktree, &args),
+            "cangjie-version" => self.handle_version_command(worktree),
+            "cangjie-verify-assets" => {
+                self.handle_verify_assets_command(&ZedHost::new(worktree), &args)
+            }
             _ => Err(format!("Unhandled slash command: {}", command.name)),
-        }
+        }?;
+        // Some commands assemble their output without the builder; restyle those here.
+        // Output the builder already rendered plain is left unchanged.
+        Ok(self.output_style(&ZedHost::new(worktree)).apply(output))
     }
 
     // --- Other Trait Methods ---
@@ -882,6 +6747,59 @@ impl zed::Extension for CangjieExtension {
 
 // --- Utility Functions ---
 
+/// Merges `binary.arguments` of the `cangjie-lsp` settings with the default transport.
+///
+/// Zed talks to the server over stdio unless told otherwise, so arguments that choose no
+/// transport (`--stdio`, `--pipe`, `--socket` or `--port`) get `--stdio` in front; without it
+/// the server would wait for a connection that never comes.
+///
+/// # Arguments
+/// * `overrides` - The configured arguments
+///
+/// # Returns
+/// * `(Vec<String>, Option<String>)` - The arguments to start the server with, and a
+///   warning describing the merge when the transport was added
+pub fn merge_language_server_args(overrides: &[String]) -> (Vec<String>, Option<String>) {
+    let chooses_transport = overrides.iter().any(|arg| {
+        LSP_TRANSPORT_FLAGS.iter().any(|flag| {
+            arg == flag || arg.strip_prefix(flag).is_some_and(|rest| rest.starts_with('='))
+        })
+    });
+    if chooses_transport {
+        return (overrides.to_vec(), None);
+    }
+    let mut args = vec![LSP_DEFAULT_TRANSPORT.to_string()];
+    args.extend(overrides.iter().cloned());
+    let warning = format!(
+        "'binary.arguments' of cangjie-lsp choose no transport, so {} was added in front; \
+         arguments are merged with the default transport unless one of {} is given",
+        LSP_DEFAULT_TRANSPORT,
+        LSP_TRANSPORT_FLAGS.join(", ")
+    );
+    (args, Some(warning))
+}
+
+/// Reads the `cjpm.toml` in a task's working directory, with the directory name as the
+/// package name when the manifest has none
+///
+/// # Arguments
+/// * `task` - The task template whose working directory holds the package
+///
+/// # Returns
+/// * `cjpm::Manifest` - The manifest; empty if the task has no readable `cjpm.toml`
+pub fn get_package_manifest(task: &zed::TaskTemplate) -> cjpm::Manifest {
+    let mut manifest = task
+        .cwd
+        .as_ref()
+        .and_then(|cwd| std::fs::read_to_string(Path::new(cwd).join(cjpm::MANIFEST_FILE)).ok())
+        .map(|manifest| cjpm::parse_manifest(&manifest))
+        .unwrap_or_default();
+    if manifest.name.is_none() {
+        manifest.name = get_project_name(task);
+    }
+    manifest
+}
+
 /// Extracts the project name from a task template
 /// 
 /// # Arguments
@@ -895,6 +6813,164 @@ pub fn get_project_name(task: &zed::TaskTemplate) -> Option<String> {
         .and_then(|cwd| Some(Path::new(&cwd).file_name()?.to_string_lossy().into_owned()))
 }
 
+/// Logs an LSP / SDK version mismatch, with the setting that silences it.
+///
+/// The language server still starts, so the mismatch is not reported as a failed
+/// installation; `/cangjie-info` shows it too.
+///
+/// # Arguments
+/// * `message` - The mismatch warning
+fn log_version_warning(message: &str) {
+    log::warn!(
+        "{} Set '{}' to true to silence this warning.",
+        message, CONFIG_IGNORE_VERSION_MISMATCH_KEY
+    );
+}
+
+/// Returns whether a language server was downloaded by the extension.
+///
+/// # Arguments
+/// * `server_path` - Path of the language server binary
+///
+/// # Returns
+/// * `bool` - True inside a versioned `cangjie-lsp-<version>` directory
+fn is_downloaded_server(server_path: &str) -> bool {
+    let prefix = format!("{}-", SERVER_NAME);
+    Path::new(server_path)
+        .components()
+        .any(|component| component.as_os_str().to_string_lossy().starts_with(&prefix))
+}
+
+/// Returns the in-memory state key of a per-worktree entry.
+///
+/// # Arguments
+/// * `root` - The worktree root
+/// * `key` - The state key, e.g. [`STATE_LSP_CHANNEL_KEY`]
+///
+/// # Returns
+/// * `String` - The key scoped to the worktree
+fn worktree_state_key(root: &str, key: &str) -> String {
+    format!("{}@{}", key, root)
+}
+
+/// Expands a leading `~` and embedded `$VAR`, `${VAR}` and `%VAR%` references in a path.
+///
+/// Variable values are expanded again so that nested references (e.g. a variable
+/// pointing at `$HOME/sdks`) resolve fully; unknown variables are left untouched.
+///
+/// # Arguments
+/// * `value` - The raw path value
+/// * `lookup` - Resolves an environment variable by name
+///
+/// # Returns
+/// * `String` - The expanded path
+pub fn expand_path_variables(value: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    const MAX_DEPTH: usize = 8;
+
+    let home = || lookup("HOME").or_else(|| lookup("USERPROFILE"));
+    let mut current = value.to_string();
+    for _ in 0..MAX_DEPTH {
+        let mut expanded = String::with_capacity(current.len());
+        let mut rest = current.as_str();
+
+        if (rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\"))
+            && let Some(home) = home()
+        {
+            expanded.push_str(&home);
+            rest = &rest[1..];
+        }
+
+        while let Some(pos) = rest.find(['$', '%']) {
+            expanded.push_str(&rest[..pos]);
+            let tail = &rest[pos..];
+            let (name, consumed) = if let Some(braced) = tail.strip_prefix("${") {
+                match braced.find('}') {
+                    Some(end) => (&braced[..end], end + 3),
+                    None => ("", 0),
+                }
+            } else if let Some(percent) = tail.strip_prefix('%') {
+                match percent.find('%') {
+                    Some(end) => (&percent[..end], end + 2),
+                    None => ("", 0),
+                }
+            } else {
+                let len = tail[1..]
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(tail.len() - 1);
+                (&tail[1..1 + len], len + 1)
+            };
+
+            match (consumed, lookup(name)) {
+                (n, Some(var_value)) if n > 0 && !name.is_empty() => {
+                    expanded.push_str(&var_value);
+                    rest = &tail[n..];
+                }
+                _ => {
+                    expanded.push_str(&tail[..1]);
+                    rest = &tail[1..];
+                }
+            }
+        }
+        expanded.push_str(rest);
+
+        if expanded == current {
+            break;
+        }
+        current = expanded;
+    }
+    current
+}
+
+/// Appends a "Hints" section for a failed command, if the hint table knows its failure mode
+///
+/// # Arguments
+/// * `text` - The output text being built
+/// * `sections` - The output sections being built
+/// * `tool` - Name of the tool that was run
+/// * `exit_code` - The command's exit code
+/// * `output` - The command's stdout and stderr
+pub fn push_hints_section(
+    text: &mut String,
+    sections: &mut Vec<zed::SlashCommandOutputSection>,
+    tool: &str,
+    exit_code: Option<i32>,
+    output: &str,
+) {
+    let hints = hints::hints_for(tool, exit_code, output);
+    if hints.is_empty() {
+        return;
+    }
+    let content = hints
+        .iter()
+        .map(|hint| format!("💡 {}", hint))
+        .collect::<Vec<_>>()
+        .join("\n");
+    push_output_section(text, sections, "Hints", &content);
+}
+
+/// Appends the report of a test run, one section per test suite labeled with its pass and
+/// fail counts; lines outside the suites go to a "Standard Output" section.
+///
+/// # Arguments
+/// * `text` - The output text being built
+/// * `sections` - The output sections being built
+/// * `stdout` - The test run's stdout
+pub fn push_test_report(
+    text: &mut String,
+    sections: &mut Vec<zed::SlashCommandOutputSection>,
+    stdout: &str,
+) {
+    let (suites, rest) = testing::split_report(stdout);
+    for suite in &suites {
+        let marker = if suite.failed > 0 { "❌" } else { "✅" };
+        let label = format!("{} {}", marker, suite.label());
+        push_output_section(text, sections, &label, &suite.output);
+    }
+    if !rest.trim().is_empty() {
+        push_output_section(text, sections, "Standard Output", &rest);
+    }
+}
+
 // --- Entry Point ---
 
 zed::register_extension!(CangjieExtension);
@@ -954,7 +7030,145 @@ mod tests {
             env: vec![],
             cwd: None,
         };
-        assert_eq!(get_project_name(&task), None);
+        assert_eq!(get_project_name(&task), None);
+    }
+
+    #[test]
+    fn test_dap_locator_cjpm() {
+        // 测试 cjpm run 任务生成先构建再调试的场景，程序名与输出目录取自 cjpm.toml
+        let package = std::env::temp_dir().join(format!("cangjie-locator-{}", std::process::id()));
+        std::fs::create_dir_all(&package).unwrap();
+        let manifest = "[package]\nname = \"server\"\ntarget-dir = \"out\"\n";
+        std::fs::write(package.join("cjpm.toml"), manifest).unwrap();
+        let cwd = package.to_string_lossy().to_string();
+        let task = |command: &str, args: &[&str], cwd: &str| TaskTemplate {
+            label: "run".to_string(),
+            command: command.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            env: vec![],
+            cwd: Some(cwd.to_string()),
+        };
+        let extension = CangjieExtension::new();
+        let scenario = extension
+            .debug_scenario_for_task(
+                Os::Linux,
+                "cangjie".to_string(),
+                task("cjpm", &["run", "--run-args", "--port 8080"], &cwd),
+                "Run server".to_string(),
+                DEBUG_ADAPTER_NAME.to_string(),
+            )
+            .unwrap();
+        let Some(zed::BuildTaskDefinition::Template(build)) = scenario.build else {
+            panic!("expected a build template");
+        };
+        assert_eq!(build.template.args, vec!["build", "-g"]);
+        let config: serde_json::Value = serde_json::from_str(&scenario.config).unwrap();
+        assert_eq!(config["program"], format!("{}/out/debug/bin/server", cwd));
+        assert_eq!(config["args"], serde_json::json!(["--port", "8080"]));
+        assert_eq!(config["cwd"], cwd);
+
+        let request = extension.debug_request_for_task(Os::Linux, build.template.clone());
+        let Ok(zed::DebugRequest::Launch(launch)) = request else {
+            panic!("expected a launch request");
+        };
+        assert_eq!(launch.program, package.join("out/debug/bin/server").to_string_lossy());
+        assert_eq!(launch.args, vec!["--port", "8080"]);
+        assert_eq!(launch.cwd.as_deref(), Some(cwd.as_str()));
+
+        // 测试带调试信息的优化构建保留 -O2 并使用 release 输出目录
+        let scenario = extension
+            .debug_scenario_for_task(
+                Os::Linux,
+                "cangjie".to_string(),
+                task("cjpm", &["run", "-O2"], &cwd),
+                "Run optimized".to_string(),
+                DEBUG_ADAPTER_NAME.to_string(),
+            )
+            .unwrap();
+        let Some(zed::BuildTaskDefinition::Template(optimized)) = scenario.build else {
+            panic!("expected a build template");
+        };
+        assert_eq!(optimized.template.args, vec!["build", "-g", "-O2"]);
+        let request = extension.debug_request_for_task(Os::Linux, optimized.template);
+        let Ok(zed::DebugRequest::Launch(launch)) = request else {
+            panic!("expected a launch request");
+        };
+        assert_eq!(launch.program, package.join("out/release/bin/server").to_string_lossy());
+
+        // 测试库类型的包无法调试
+        let manifest = "[package]\noutput-type = \"dynamic\"\n";
+        std::fs::write(package.join("cjpm.toml"), manifest).unwrap();
+        let library = extension.debug_scenario_for_task(
+            Os::Linux,
+            "cangjie".to_string(),
+            task("cjpm", &["build"], &cwd),
+            "Build".to_string(),
+            DEBUG_ADAPTER_NAME.to_string(),
+        );
+        assert!(library.is_none());
+        let request = extension.debug_request_for_task(Os::Linux, build.template);
+        std::fs::remove_dir_all(&package).unwrap();
+        assert!(request.unwrap_err().contains("dynamic library"));
+
+        // 测试 cjpm test 任务构建测试程序并以 --filter 启动
+        let scenario = extension
+            .debug_scenario_for_task(
+                Os::Linux,
+                "cangjie".to_string(),
+                task("cjpm", &["test", "--filter", "CalcTest.testAdd"], "/work/hello"),
+                "Test".to_string(),
+                DEBUG_ADAPTER_NAME.to_string(),
+            )
+            .unwrap();
+        let Some(zed::BuildTaskDefinition::Template(build)) = scenario.build else {
+            panic!("expected a build template");
+        };
+        assert_eq!(build.template.args, vec!["test", "--no-run", "-g"]);
+        let request = extension.debug_request_for_task(Os::Linux, build.template);
+        let Ok(zed::DebugRequest::Launch(launch)) = request else {
+            panic!("expected a launch request");
+        };
+        assert!(launch.program.ends_with("target/debug/unittest_bin/main"));
+        assert_eq!(launch.args, vec!["--filter=CalcTest.testAdd"]);
+
+        // 测试 cjc --test 任务使用 -o 指定的测试程序
+        let scenario = extension
+            .debug_scenario_for_task(
+                Os::Linux,
+                "cangjie".to_string(),
+                task("cjc", &["--test", "calc.cj", "-o", "calc_test"], "/work/hello"),
+                "Test calc".to_string(),
+                DEBUG_ADAPTER_NAME.to_string(),
+            )
+            .unwrap();
+        let config: serde_json::Value = serde_json::from_str(&scenario.config).unwrap();
+        assert_eq!(config["program"], "/work/hello/calc_test");
+
+        // 测试 Windows 下的程序路径使用反斜杠与 .exe 扩展名
+        let scenario = extension
+            .debug_scenario_for_task(
+                Os::Windows,
+                "cangjie".to_string(),
+                task("cjpm", &["test"], "C:\\work\\hello"),
+                "Test".to_string(),
+                DEBUG_ADAPTER_NAME.to_string(),
+            )
+            .unwrap();
+        let config: serde_json::Value = serde_json::from_str(&scenario.config).unwrap();
+        assert_eq!(
+            config["program"],
+            "C:\\work\\hello\\target\\debug\\unittest_bin\\main.exe"
+        );
+
+        // 测试其他 cjpm 子命令与无关任务不生成场景
+        let other = extension.debug_scenario_for_task(
+            Os::Linux,
+            "cangjie".to_string(),
+            task("cjpm", &["clean"], "/work/hello"),
+            "Clean".to_string(),
+            DEBUG_ADAPTER_NAME.to_string(),
+        );
+        assert!(other.is_none());
     }
 
     #[test]
@@ -1011,6 +7225,1452 @@ mod tests {
         assert_eq!(windows_aarch64, Ok("cangjie-lsp-aarch64-pc-windows-msvc.exe".to_string()));
     }
 
+    #[test]
+    fn test_verify_assets_against_recorded_release() {
+        // 测试用录制的发布资源列表校验全部平台的资源命名
+        const RECORDED_RELEASE: &str = r#"{
+            "tag_name": "v0.59.6",
+            "assets": [
+                { "name": "cangjie-lsp-x86_64-unknown-linux-gnu" },
+                { "name": "cangjie-lsp-aarch64-unknown-linux-gnu" },
+                { "name": "cangjie-lsp-x86_64-apple-darwin" },
+                { "name": "cangjie-lsp-aarch64-apple-darwin" },
+                { "name": "cangjie-lsp-x86_64-pc-windows-msvc.exe" }
+            ]
+        }"#;
+        let extension = CangjieExtension::new();
+        let names = assets::parse_asset_listing(RECORDED_RELEASE).unwrap();
+        let checks = assets::verify_assets(&names, SERVER_NAME, |os, arch| {
+            extension.get_asset_name_for_platform(os, arch, SERVER_NAME)
+        });
+        let missing: Vec<_> = checks
+            .iter()
+            .filter(|check| check.expected.is_ok() && check.asset.is_none())
+            .map(|check| check.platform.as_str())
+            .collect();
+        assert_eq!(missing, vec!["linux-x86", "windows-aarch64", "windows-x86"]);
+        let unsupported = checks.iter().filter(|check| check.expected.is_err()).count();
+        assert_eq!(unsupported, 0);
+    }
+
+    /// Builds a replay host answering the given calls
+    fn replay(answers: &[(&str, serde_json::Value)]) -> host::Replay {
+        answers
+            .iter()
+            .fold(host::Replay::default(), |replay, (call, response)| {
+                replay.answer(call, response.clone())
+            })
+    }
+
+    #[test]
+    fn test_sdk_resolution_order_replay() {
+        use serde_json::json;
+
+        // 测试配置的 sdkPath 优先于项目本地 SDK
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("worktree_root", json!("/work")),
+            ("setting cangjie.sdkPath", json!("/custom/sdk")),
+            ("is_dir /custom/sdk", json!(true)),
+            ("is_dir /work/.cangjie/sdk", json!(true)),
+        ]);
+        let mut steps = Vec::new();
+        let root = extension.resolve_sdk_root_traced(&host, &mut steps).unwrap();
+        assert_eq!(root, PathBuf::from("/custom/sdk"));
+
+        // 测试无效的 sdkPath 回退到项目本地 SDK
+        let host = replay(&[
+            ("worktree_root", json!("/work")),
+            ("setting cangjie.sdkPath", json!("/missing")),
+            ("is_dir /work/.cangjie/sdk", json!(true)),
+        ]);
+        let mut steps = Vec::new();
+        let root = extension.resolve_sdk_root_traced(&host, &mut steps).unwrap();
+        assert_eq!(root, PathBuf::from("/work/.cangjie/sdk"));
+        assert!(steps[0].contains("does not exist"));
+
+        // 测试 .tool-versions 优先于 CANGJIE_HOME
+        let host = replay(&[
+            ("worktree_root", json!("/work")),
+            ("read_worktree_file .tool-versions", json!({ "ok": "cangjie 1.0.0\n" })),
+            ("env HOME", json!("/home/dev")),
+            ("env CANGJIE_HOME", json!("/opt/cangjie")),
+            ("is_dir /home/dev/.asdf/installs/cangjie/1.0.0", json!(true)),
+            ("is_dir /opt/cangjie", json!(true)),
+        ]);
+        let mut steps = Vec::new();
+        let root = extension.resolve_sdk_root_traced(&host, &mut steps).unwrap();
+        assert_eq!(root, PathBuf::from("/home/dev/.asdf/installs/cangjie/1.0.0"));
+
+        // 测试 CANGJIE_HOME 中的变量展开
+        let host = replay(&[
+            ("env HOME", json!("/home/dev")),
+            ("env CANGJIE_HOME", json!("${HOME}/cangjie")),
+            ("is_dir /home/dev/cangjie", json!(true)),
+        ]);
+        let mut steps = Vec::new();
+        let root = extension.resolve_sdk_root_traced(&host, &mut steps).unwrap();
+        assert_eq!(root, PathBuf::from("/home/dev/cangjie"));
+
+        // 测试默认路径优先使用所选通道的目录，最后回退到当前目录
+        let host = replay(&[
+            ("setting cangjie.toolchain.channel", json!("nightly")),
+            ("is_dir /usr/local/cangjie/bin", json!(true)),
+            ("is_dir /usr/local/cangjie-nightly/tools/bin", json!(true)),
+        ]);
+        let mut steps = Vec::new();
+        let root = extension.resolve_sdk_root_traced(&host, &mut steps).unwrap();
+        assert_eq!(root, PathBuf::from("/usr/local/cangjie-nightly"));
+
+        let host = replay(&[("current_dir", json!({ "ok": "/tmp/dev" }))]);
+        let mut steps = Vec::new();
+        let root = extension.resolve_sdk_root_traced(&host, &mut steps).unwrap();
+        assert_eq!(root, PathBuf::from("/tmp/dev"));
+        assert!(steps.last().unwrap().contains("using current directory"));
+    }
+
+    #[test]
+    fn test_tool_resolution_order_replay() {
+        use serde_json::json;
+
+        // 测试工具路径按 单独覆盖 → LSP 覆盖 → SDK → extraBinDirs → PATH 的顺序解析
+        let answers = [
+            ("setting cangjie.cjcPathOverride", json!("/custom/cjc")),
+            ("tool_binary_override", json!("/lsp/cjc")),
+            ("is_file /opt/cj/bin/cjc", json!(true)),
+            ("is_file /tools/bin/cjc", json!(true)),
+            ("which cjc", json!("/usr/bin/cjc")),
+        ];
+        let resolve = |found: usize| {
+            let mut calls = vec![
+                ("worktree_root", json!("/work")),
+                ("is_file /custom/cjc", json!(true)),
+                ("is_file /lsp/cjc", json!(true)),
+                ("setting cangjie.sdkPath", json!("/opt/cj")),
+                ("is_dir /opt/cj", json!(true)),
+                ("is_dir /opt/cj/bin", json!(true)),
+                ("setting cangjie.extraBinDirs", json!(["${TOOLS}/bin"])),
+                ("env TOOLS", json!("/tools")),
+            ];
+            calls.extend(answers[found..].iter().cloned());
+            let host = replay(&calls);
+            (CangjieExtension::new().cjc_binary_path(&host), host)
+        };
+        let expected =
+            ["/custom/cjc", "/lsp/cjc", "/opt/cj/bin/cjc", "/tools/bin/cjc", "/usr/bin/cjc"];
+        for (found, path) in expected.iter().enumerate() {
+            assert_eq!(resolve(found).0.unwrap(), *path);
+        }
+        let (path, host) = resolve(expected.len());
+        assert!(path.unwrap_err().contains("'cjc' not found"));
+        assert!(host.misses().contains(&"which cjc".to_string()));
+    }
+
+    #[test]
+    fn test_multi_root_worktrees_replay() {
+        use serde_json::json;
+
+        // 测试多根工作区中每个工作树使用各自的 SDK 与状态
+        let extension = CangjieExtension::new();
+        let worktree = |root: &str, sdk: &str| {
+            replay(&[
+                ("worktree_root", json!(root)),
+                ("setting cangjie.sdkPath", json!(sdk)),
+                (&format!("is_dir {}", sdk), json!(true)),
+                (&format!("is_dir {}/bin", sdk), json!(true)),
+            ])
+        };
+        let old = worktree("/work/legacy", "/opt/cangjie-0.59");
+        let new = worktree("/work/app", "/opt/cangjie-1.0");
+        assert_eq!(
+            extension.resolve_sdk_root(&old).unwrap(),
+            PathBuf::from("/opt/cangjie-0.59")
+        );
+        assert_eq!(
+            extension.resolve_sdk_root(&new).unwrap(),
+            PathBuf::from("/opt/cangjie-1.0")
+        );
+        assert_eq!(
+            extension.resolve_sdk_root(&old).unwrap(),
+            PathBuf::from("/opt/cangjie-0.59")
+        );
+        extension.record_lsp_channel(&old, "SDK".to_string());
+        assert_eq!(
+            extension.worktree_state("/work/legacy", STATE_LSP_CHANNEL_KEY).as_deref(),
+            Some("SDK")
+        );
+        assert!(extension.worktree_state("/work/app", STATE_LSP_CHANNEL_KEY).is_none());
+
+        // 测试刷新一个工作树不影响另一个工作树的缓存
+        extension.refresh_sdk(&old).unwrap();
+        let cache = extension.cached_sdk_roots.lock().unwrap();
+        assert_eq!(cache["/work/app"].target, PathBuf::from("/opt/cangjie-1.0"));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_sdk_symlink_farm_replay() {
+        use serde_json::json;
+
+        // 测试符号链接管理的 SDK：报告链接路径，执行目标路径
+        const LINK: &str = "/opt/cangjie/current";
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("setting cangjie.sdkPath", json!(LINK)),
+            ("is_dir /opt/cangjie/current", json!(true)),
+            ("canonicalize /opt/cangjie/current", json!({ "ok": "/opt/cangjie/1.0.3" })),
+            ("canonicalize /opt/cangjie/current", json!({ "ok": "/opt/cangjie/1.0.3" })),
+            ("canonicalize /opt/cangjie/current", json!({ "ok": "/opt/cangjie/1.0.4" })),
+            ("is_dir /opt/cangjie/1.0.3/bin", json!(true)),
+            ("is_dir /opt/cangjie/1.0.4/bin", json!(true)),
+        ]);
+        let root = extension.resolve_sdk_root_entry(&host).unwrap();
+        assert_eq!(root.path, PathBuf::from(LINK));
+        assert_eq!(root.target, PathBuf::from("/opt/cangjie/1.0.3"));
+        assert!(root.is_link());
+        extension
+            .cached_tool_paths
+            .lock()
+            .unwrap()
+            .entry(String::new())
+            .or_default()
+            .insert("tool_path_cjc".to_string(), "/opt/cangjie/1.0.3/bin/cjc".to_string());
+        assert_eq!(
+            extension.resolve_sdk_root(&host).unwrap(),
+            PathBuf::from("/opt/cangjie/1.0.3")
+        );
+        assert!(!extension.cached_tool_paths.lock().unwrap().is_empty());
+
+        // 测试链接被重新指向后使用新目标并清除工具路径缓存
+        assert_eq!(
+            extension.resolve_sdk_root(&host).unwrap(),
+            PathBuf::from("/opt/cangjie/1.0.4")
+        );
+        assert!(extension.cached_tool_paths.lock().unwrap().is_empty());
+        let cached = extension.cached_sdk_roots.lock().unwrap()[""].clone();
+        assert_eq!(cached.path, PathBuf::from(LINK));
+
+        // 测试链接失效后重新解析且不缓存
+        let host = replay(&[
+            ("setting cangjie.sdkPath", json!(LINK)),
+            ("is_dir /opt/cangjie/current", json!(true)),
+            ("canonicalize /opt/cangjie/current", json!({ "err": "No such file" })),
+        ]);
+        let root = extension.resolve_sdk_root_entry(&host).unwrap();
+        assert_eq!(root.target, PathBuf::from(LINK));
+        assert!(extension.cached_sdk_roots.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_install_language_server_replay() {
+        use serde_json::json;
+        use std::cell::RefCell;
+
+        const PLATFORM: (Os, Architecture) = (Os::Linux, Architecture::X8664);
+        const INSTALL_DIR: &str = "cangjie-lsp-v1.0.0";
+        const STAGING_DIR: &str = "cangjie-lsp-v1.0.0.partial";
+        let statuses = RefCell::new(Vec::new());
+        let report = |status: &zed::LanguageServerInstallationStatus| {
+            statuses.borrow_mut().push(format!("{:?}", status))
+        };
+
+        // 测试 LSP 设置中的路径覆盖优先
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("lsp_binary_override", json!("/tools/cangjie-lsp")),
+            ("is_file /tools/cangjie-lsp", json!(true)),
+        ]);
+        let path = extension.install_language_server(&host, PLATFORM, &report);
+        assert_eq!(path.unwrap(), "/tools/cangjie-lsp");
+        let host = replay(&[("lsp_binary_override", json!("/tools/missing"))]);
+        assert!(extension.install_language_server(&host, PLATFORM, &report).is_err());
+
+        // 测试使用 SDK 自带的 LSP
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("env CANGJIE_HOME", json!("/opt/cj")),
+            ("is_dir /opt/cj", json!(true)),
+            ("is_dir /opt/cj/tools/bin", json!(true)),
+            ("is_file /opt/cj/tools/bin/cangjie-lsp", json!(true)),
+        ]);
+        let path = extension.install_language_server(&host, PLATFORM, &report);
+        assert_eq!(path.unwrap(), "/opt/cj/tools/bin/cangjie-lsp");
+        assert!(statuses.borrow().is_empty());
+
+        // 测试已下载的固定版本无需访问网络
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("current_dir", json!({ "ok": "/work" })),
+            ("env HOME", json!("/home/dev")),
+            (
+                "subdirectories /home/dev/.zed/extensions",
+                json!(["/home/dev/.zed/extensions/cangjie-lsp-v1.0.0"]),
+            ),
+            ("setting cangjie.lsp.version", json!("1.0.0")),
+            ("is_file cangjie-lsp-v1.0.0/cangjie-lsp", json!(true)),
+        ]);
+        let path = extension.install_language_server(&host, PLATFORM, &report);
+        assert_eq!(path.unwrap(), format!("{}/cangjie-lsp", INSTALL_DIR));
+        assert!(!host.misses().iter().any(|call| call.starts_with("release")));
+        // 测试旧版本下载到 $HOME/.zed/extensions 的服务器被迁移
+        assert!(host.misses().contains(
+            &"rename /home/dev/.zed/extensions/cangjie-lsp-v1.0.0 -> cangjie-lsp-v1.0.0".to_string()
+        ));
+
+        // 测试下载并解压压缩包形式的发布资源
+        statuses.borrow_mut().clear();
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("current_dir", json!({ "ok": "/work" })),
+            (
+                "release cangjie-lang/cangjie latest pre_release=false",
+                json!({ "ok": { "version": "v1.0.0", "assets": [{
+                    "name": "cangjie-lsp-x86_64-unknown-linux-gnu.tar.gz",
+                    "download_url": "https://example.com/lsp.tar.gz"
+                }] } }),
+            ),
+            (
+                &format!("download tar.gz https://example.com/lsp.tar.gz -> {}", STAGING_DIR),
+                json!({ "err": "connection reset" }),
+            ),
+            (
+                &format!("download tar.gz https://example.com/lsp.tar.gz -> {}", STAGING_DIR),
+                json!({ "ok": null }),
+            ),
+            (
+                "subdirectories",
+                json!(["cangjie-lsp-v0.8.0", "cangjie-lsp-v0.9.0", INSTALL_DIR]),
+            ),
+            (
+                &format!("subdirectories {}", STAGING_DIR),
+                json!([format!("{}/cangjie-lsp-linux", STAGING_DIR)]),
+            ),
+            (
+                &format!("is_file {}/cangjie-lsp-linux/cangjie-lsp", STAGING_DIR),
+                json!(true),
+            ),
+        ]);
+        let path = extension.install_language_server(&host, PLATFORM, &report);
+        assert_eq!(
+            path.unwrap(),
+            format!("{}/cangjie-lsp-linux/cangjie-lsp", INSTALL_DIR)
+        );
+        assert_eq!(
+            *statuses.borrow(),
+            vec!["LanguageServerInstallationStatus::Downloading"]
+        );
+        // 测试下载失败后重试，并在校验完成后移动到安装目录
+        let misses = host.misses();
+        assert!(misses.contains(&format!("remove_path {}", STAGING_DIR)));
+        assert!(misses.contains(&format!("rename {} -> {}", STAGING_DIR, INSTALL_DIR)));
+        // 测试安装成功后清理旧版本
+        assert!(host.misses().contains(&"remove_path cangjie-lsp-v0.8.0".to_string()));
+        assert!(!host.misses().contains(&"remove_path cangjie-lsp-v0.9.0".to_string()));
+        // 测试记录安装来源的通道，供 /cangjie-info 显示
+        let channel = extension
+            .worktree_state(&host.worktree_root(), STATE_LSP_CHANNEL_KEY)
+            .unwrap();
+        assert_eq!(channel, "release (v1.0.0)");
+
+        // 测试校验和不匹配时删除下载并报告失败
+        statuses.borrow_mut().clear();
+        let extension = CangjieExtension::new();
+        let binary = format!("{}/cangjie-lsp", INSTALL_DIR);
+        let partial = format!("{}/cangjie-lsp.partial", INSTALL_DIR);
+        let host = replay(&[
+            ("current_dir", json!({ "ok": "/work" })),
+            (
+                "release cangjie-lang/cangjie latest pre_release=false",
+                json!({ "ok": { "version": "v1.0.0", "assets": [
+                    { "name": "cangjie-lsp-x86_64-unknown-linux-gnu", "download_url": "u1" },
+                    { "name": "SHA256SUMS", "download_url": "u2" }
+                ] } }),
+            ),
+            (
+                &format!("download uncompressed u1 -> {}", partial),
+                json!({ "ok": null }),
+            ),
+            (
+                &format!(
+                    "download uncompressed u2 -> {}/cangjie-lsp-x86_64-unknown-linux-gnu.checksum",
+                    INSTALL_DIR
+                ),
+                json!({ "ok": null }),
+            ),
+            (
+                &format!(
+                    "read_text_file {}/cangjie-lsp-x86_64-unknown-linux-gnu.checksum",
+                    INSTALL_DIR
+                ),
+                json!({
+                    "ok": format!("{}  cangjie-lsp-x86_64-unknown-linux-gnu", "0".repeat(64))
+                }),
+            ),
+            (
+                &format!("sha256_file {}", partial),
+                json!({ "ok": "1".repeat(64) }),
+            ),
+        ]);
+        let error = extension
+            .install_language_server(&host, PLATFORM, &report)
+            .unwrap_err();
+        assert!(error.contains("Checksum mismatch"));
+        assert!(statuses.borrow()[1].contains("Failed"));
+
+        // 测试镜像下载失败时回退到 GitHub
+        let extension = CangjieExtension::new();
+        let github = "https://github.com/cangjie-lang/cangjie/releases/download/v1.0.0/lsp";
+        let mirror = "https://mirror.example.com/cangjie-lang/cangjie/releases/download/v1.0.0/lsp";
+        let host = replay(&[
+            ("current_dir", json!({ "ok": "/work" })),
+            ("setting cangjie.downloadMirror", json!("https://mirror.example.com/")),
+            (
+                "release cangjie-lang/cangjie latest pre_release=false",
+                json!({ "ok": { "version": "v1.0.0", "assets": [
+                    { "name": "cangjie-lsp-x86_64-unknown-linux-gnu", "download_url": github }
+                ] } }),
+            ),
+            (
+                &format!("download uncompressed {} -> {}", mirror, partial),
+                json!({ "err": "connection reset" }),
+            ),
+            (
+                &format!("download uncompressed {} -> {}", github, partial),
+                json!({ "ok": null }),
+            ),
+        ]);
+        let path = extension.install_language_server(&host, PLATFORM, &report);
+        assert_eq!(path.unwrap(), binary);
+        assert!(host.misses().contains(&format!("remove_path {}", partial)));
+        assert!(host.misses().contains(&format!("rename {} -> {}", partial, binary)));
+
+        // 测试带校验和的压缩包只下载一次，校验通过后解压同一文件
+        let extension = CangjieExtension::new();
+        let archive = "cangjie-lsp-x86_64-unknown-linux-gnu.tar.gz.partial";
+        let host = replay(&[
+            ("current_dir", json!({ "ok": "/work" })),
+            (
+                "release cangjie-lang/cangjie latest pre_release=false",
+                json!({ "ok": { "version": "v1.0.0", "assets": [
+                    { "name": "cangjie-lsp-x86_64-unknown-linux-gnu.tar.gz", "download_url": "u1" },
+                    { "name": "SHA256SUMS", "download_url": "u2" }
+                ] } }),
+            ),
+            (&format!("download uncompressed u1 -> {}", archive), json!({ "ok": null })),
+            (
+                "download uncompressed u2 -> cangjie-lsp-x86_64-unknown-linux-gnu.tar.gz.checksum",
+                json!({ "ok": null }),
+            ),
+            (
+                "read_text_file cangjie-lsp-x86_64-unknown-linux-gnu.tar.gz.checksum",
+                json!({ "ok": format!(
+                    "{}  cangjie-lsp-x86_64-unknown-linux-gnu.tar.gz",
+                    "0".repeat(64)
+                ) }),
+            ),
+            (&format!("sha256_file {}", archive), json!({ "ok": "0".repeat(64) })),
+            (
+                &format!("run tar -xf {} -C {}", archive, STAGING_DIR),
+                json!({ "ok": { "status": 0, "stdout": "", "stderr": "" } }),
+            ),
+            (&format!("is_file {}/cangjie-lsp", STAGING_DIR), json!(true)),
+        ]);
+        let path = extension.install_language_server(&host, PLATFORM, &report);
+        assert_eq!(path.unwrap(), binary);
+        let misses = host.misses();
+        assert!(!misses.iter().any(|call| call.starts_with("download tar.gz")));
+        assert!(misses.contains(&format!("remove_path {}", archive)));
+
+        // 测试默认直接使用已下载的版本，不访问网络
+        let extension = CangjieExtension::new();
+        let installed = ("subdirectories", json!([INSTALL_DIR]));
+        let host = replay(&[
+            ("current_dir", json!({ "ok": "/work" })),
+            installed.clone(),
+            (&format!("is_file {}", binary), json!(true)),
+        ]);
+        let path = extension.install_language_server(&host, PLATFORM, &report);
+        assert_eq!(path.unwrap(), binary);
+        assert!(!host.misses().iter().any(|call| call.starts_with("release")));
+
+        // 测试开启 cangjie.lsp.autoUpdate 后，启动前检查并下载新版本供下次启动使用
+        statuses.borrow_mut().clear();
+        let host = replay(&[
+            ("current_dir", json!({ "ok": "/work" })),
+            ("setting cangjie.lsp.autoUpdate", json!(true)),
+            installed,
+            (&format!("is_file {}", binary), json!(true)),
+            (
+                "release cangjie-lang/cangjie latest pre_release=false",
+                json!({ "ok": { "version": "v1.1.0", "assets": [
+                    { "name": "cangjie-lsp-x86_64-unknown-linux-gnu", "download_url": "u1" }
+                ] } }),
+            ),
+            (
+                "download uncompressed u1 -> cangjie-lsp-v1.1.0/cangjie-lsp.partial",
+                json!({ "ok": null }),
+            ),
+        ]);
+        let path = extension.install_language_server(&host, PLATFORM, &report);
+        assert_eq!(path.unwrap(), binary);
+        assert!(host.misses().contains(
+            &"rename cangjie-lsp-v1.1.0/cangjie-lsp.partial -> cangjie-lsp-v1.1.0/cangjie-lsp"
+                .to_string()
+        ));
+        assert_eq!(
+            *statuses.borrow(),
+            vec![
+                "LanguageServerInstallationStatus::CheckingForUpdate",
+                "LanguageServerInstallationStatus::Downloading",
+                "LanguageServerInstallationStatus::None"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_install_debugger_replay() {
+        use serde_json::json;
+
+        const PLATFORM: (Os, Architecture) = (Os::Linux, Architecture::X8664);
+
+        // 测试复用已下载的 cjdb，无需访问网络
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("current_dir", json!({ "ok": "/work" })),
+            ("subdirectories", json!(["cangjie-lsp-v1.1.0", "cjdb-v1.0.0"])),
+            ("is_file cjdb-v1.0.0/cjdb", json!(true)),
+        ]);
+        assert_eq!(extension.install_debugger(&host, PLATFORM).unwrap(), "cjdb-v1.0.0/cjdb");
+        assert!(!host.misses().iter().any(|call| call.starts_with("release")));
+
+        // 测试 SDK 未附带 cjdb 时从工具链发布下载
+        let host = replay(&[
+            ("current_dir", json!({ "ok": "/work" })),
+            (
+                "release cangjie-lang/cangjie latest pre_release=false",
+                json!({ "ok": { "version": "v1.1.0", "assets": [
+                    { "name": "cangjie-lsp-x86_64-unknown-linux-gnu", "download_url": "u1" },
+                    { "name": "cjdb-x86_64-unknown-linux-gnu", "download_url": "u2" }
+                ] } }),
+            ),
+            ("download uncompressed u2 -> cjdb-v1.1.0/cjdb.partial", json!({ "ok": null })),
+        ]);
+        let path = extension.install_debugger(&host, PLATFORM);
+        assert_eq!(path.unwrap(), "cjdb-v1.1.0/cjdb");
+        let journal = host.read_text_file(Path::new(journal::JOURNAL_FILE)).unwrap();
+        assert!(journal.contains("Downloaded cjdb release v1.1.0"));
+
+        // 测试发布中没有 cjdb 时提示配置路径
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("current_dir", json!({ "ok": "/work" })),
+            (
+                "release cangjie-lang/cangjie latest pre_release=false",
+                json!({ "ok": { "version": "v1.1.0", "assets": [] } }),
+            ),
+        ]);
+        let error = extension.install_debugger(&host, PLATFORM).unwrap_err();
+        assert!(error.contains("No cjdb asset"));
+        assert!(error.contains("cangjie.cjdbPathOverride"));
+
+        // 测试调试适配器参数的默认值与 cangjie.debug.adapterArgs 覆盖
+        let host = replay(&[]);
+        assert_eq!(extension.debugger_args(&host).unwrap(), vec!["--dap"]);
+        let host = replay(&[("setting cangjie.debug.adapterArgs", json!(["--vscode"]))]);
+        assert_eq!(extension.debugger_args(&host).unwrap(), vec!["--vscode"]);
+        let host = replay(&[("setting cangjie.debug.adapterArgs", json!("--vscode"))]);
+        assert!(extension.debugger_args(&host).is_err());
+    }
+
+    #[test]
+    fn test_downloads_dir_fallback_replay() {
+        use serde_json::json;
+
+        let read_only = json!({ "err": "Read-only file system" });
+
+        // 测试工作目录可写时直接使用
+        let extension = CangjieExtension::new();
+        let host = replay(&[]);
+        assert_eq!(extension.select_downloads_dir(&host).unwrap(), PathBuf::new());
+        assert!(host.misses().contains(&"remove_path .cangjie-write-probe".to_string()));
+
+        // 测试工作目录只读时使用 cangjie.download.dir
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("write_text_file .cangjie-write-probe", read_only.clone()),
+            ("setting cangjie.download.dir", json!("$HOME/cangjie-downloads")),
+            ("env HOME", json!("/home/dev")),
+        ]);
+        let dir = extension.select_downloads_dir(&host).unwrap();
+        assert_eq!(dir, PathBuf::from("/home/dev/cangjie-downloads"));
+        let location = extension.in_memory_state.lock().unwrap()[STATE_DOWNLOADS_LOCATION_KEY]
+            .clone();
+        assert_eq!(location, "`/home/dev/cangjie-downloads` ('cangjie.download.dir')");
+
+        // 测试最后回退到临时目录，全部不可写时报告每个位置
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("write_text_file .cangjie-write-probe", read_only.clone()),
+            ("env TMPDIR", json!("/var/tmp")),
+        ]);
+        let dir = extension.select_downloads_dir(&host).unwrap();
+        assert_eq!(dir, PathBuf::from("/var/tmp/zed-cangjie-downloads"));
+        let host = replay(&[
+            ("write_text_file .cangjie-write-probe", read_only.clone()),
+            ("create_dir_all /tmp/zed-cangjie-downloads", read_only),
+        ]);
+        let error = extension.select_downloads_dir(&host).unwrap_err();
+        assert!(error.contains("extension working directory: Read-only file system"));
+        assert!(error.contains("`/tmp/zed-cangjie-downloads`"));
+    }
+
+    #[test]
+    fn test_prefetch_and_upgrade_replay() {
+        use serde_json::json;
+
+        const PLATFORM: (Os, Architecture) = (Os::Linux, Architecture::X8664);
+        const PREFETCH_DIR: &str = "prefetch/cangjie-lsp-v1.1.0";
+        let release = json!({ "ok": { "version": "v1.1.0", "assets": [
+            { "name": "cangjie-lsp-x86_64-unknown-linux-gnu", "download_url": "u1" }
+        ] } });
+        let installed = [
+            ("current_dir", json!({ "ok": "/work" })),
+            ("setting cangjie.lsp.autoUpdate", json!(false)),
+            ("setting cangjie.lsp.prefetch", json!(true)),
+            ("subdirectories", json!(["cangjie-lsp-v0.9.0", "cangjie-lsp-v1.0.0", "prefetch"])),
+            ("is_file cangjie-lsp-v1.0.0/cangjie-lsp", json!(true)),
+        ];
+
+        // 测试启动前预取新版本但不切换
+        let extension = CangjieExtension::new();
+        let mut answers = installed.to_vec();
+        answers.push(("release cangjie-lang/cangjie latest pre_release=false", release.clone()));
+        let download = format!("download uncompressed u1 -> {}/cangjie-lsp.partial", PREFETCH_DIR);
+        answers.push((&download, json!({ "ok": null })));
+        let host = replay(&answers);
+        let path = extension.install_language_server(&host, PLATFORM, &|_| {});
+        assert_eq!(path.unwrap(), "cangjie-lsp-v1.0.0/cangjie-lsp");
+        let misses = host.misses();
+        assert!(misses.contains(&format!(
+            "rename {0}/cangjie-lsp.partial -> {0}/cangjie-lsp",
+            PREFETCH_DIR
+        )));
+        let state = host.read_text_file(Path::new(prefetch::STATE_FILE)).unwrap();
+        assert!(prefetch::attempted(&state, "v1.1.0"));
+
+        // 测试同一版本只预取一次，按流量计费的网络不预取
+        for answer in [
+            ("read_text_file prefetch-state.json", json!({ "ok": state })),
+            ("setting cangjie.network.metered", json!(true)),
+        ] {
+            let extension = CangjieExtension::new();
+            let mut answers = installed.to_vec();
+            answers.push((
+                "release cangjie-lang/cangjie latest pre_release=false",
+                release.clone(),
+            ));
+            answers.push(answer);
+            let host = replay(&answers);
+            extension.install_language_server(&host, PLATFORM, &|_| {}).unwrap();
+            assert!(!host.misses().iter().any(|call| call.starts_with("download")));
+        }
+
+        // 测试升级时移动预取的版本并清理旧版本
+        let extension = CangjieExtension::new();
+        let mut answers = installed.to_vec();
+        answers.push(("subdirectories prefetch", json!([PREFETCH_DIR])));
+        answers.push((
+            "is_file prefetch/cangjie-lsp-v1.1.0/cangjie-lsp",
+            json!(true),
+        ));
+        let host = replay(&answers);
+        let output = extension.upgrade_language_server(&host, PLATFORM).unwrap();
+        assert!(output.text.contains("from 1.0.0 to 1.1.0"));
+        let misses = host.misses();
+        assert!(misses.contains(&format!("rename {} -> cangjie-lsp-v1.1.0", PREFETCH_DIR)));
+        assert!(misses.contains(&"remove_path cangjie-lsp-v0.9.0".to_string()));
+        assert!(!misses.iter().any(|call| call.starts_with("release")));
+
+        // 测试升级与清理记录在操作日志中，并附带撤销提示
+        let output = extension.show_journal(&host, &[]).unwrap();
+        assert!(output.text.contains("Moved `cangjie-lsp-v1.1.0`: Upgraded to the prefetched"));
+        assert!(output.text.contains(
+            "💡 Undo: set 'cangjie.lsp.version' to \"v0.9.0\" to download it again"
+        ));
+        assert!(extension.show_journal(&host, &["many".to_string()]).is_err());
+
+        // 测试没有更新时报告已是最新，固定版本时拒绝升级
+        let extension = CangjieExtension::new();
+        let mut answers = installed.to_vec();
+        answers.push((
+            "release cangjie-lang/cangjie latest pre_release=false",
+            json!({ "ok": { "version": "v1.0.0", "assets": [] } }),
+        ));
+        let host = replay(&answers);
+        let output = extension.upgrade_language_server(&host, PLATFORM).unwrap();
+        assert!(output.text.contains("1.0.0 is up to date"));
+        let mut answers = installed.to_vec();
+        answers.push(("setting cangjie.lsp.version", json!("1.0.0")));
+        let host = replay(&answers);
+        assert!(extension.upgrade_language_server(&host, PLATFORM).is_err());
+    }
+
+    #[test]
+    fn test_check_for_updates_replay() {
+        use serde_json::json;
+
+        // 测试检查更新时比较已安装版本并显示发布说明
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("current_dir", json!({ "ok": "/work" })),
+            ("unix_time", json!(1_792_108_800)),
+            ("subdirectories", json!(["cangjie-lsp-v1.0.0"])),
+            ("is_file cangjie-lsp-v1.0.0/cangjie-lsp", json!(true)),
+            (
+                "release cangjie-lang/cangjie latest pre_release=false",
+                json!({ "ok": { "version": "v1.1.0", "assets": [] } }),
+            ),
+            (
+                "http_get https://api.github.com/repos/cangjie-lang/cangjie/releases/tags/v1.1.0",
+                json!({ "ok": r#"{"body": "- Faster completion"}"# }),
+            ),
+        ]);
+        let output = extension.check_for_updates(&host, Os::Linux).unwrap();
+        assert!(output.text.contains("Cangjie LSP 1.1.0 is available (installed: 1.0.0)"));
+        assert!(output.text.contains("- Faster completion"));
+        let state = host.read_text_file(Path::new(updates::STATE_FILE)).unwrap();
+        assert!(state.contains("v1.1.0"));
+
+        // 测试一小时内复用保存的检查结果，不再访问网络
+        let host = replay(&[
+            ("unix_time", json!(1_792_108_900)),
+            ("read_text_file update-check.json", json!({ "ok": state })),
+            ("setting cangjie.network.offline", json!(true)),
+        ]);
+        let output = extension.check_for_updates(&host, Os::Linux).unwrap();
+        assert!(output.text.contains("(cached)"));
+        assert!(!host.misses().iter().any(|call| call.starts_with("release")));
+
+        // 测试切换到其他通道后不复用保存的检查结果
+        let host = replay(&[
+            ("unix_time", json!(1_792_108_900)),
+            ("read_text_file update-check.json", json!({ "ok": state })),
+            ("setting cangjie.toolchain.channel", json!("nightly")),
+            ("setting cangjie.network.offline", json!(true)),
+        ]);
+        assert!(extension.check_for_updates(&host, Os::Linux).is_err());
+    }
+
+    #[test]
+    fn test_resolution_fingerprint_replay() {
+        use serde_json::json;
+
+        // 测试设置与文件均未变化时复用上次的语言服务器解析结果
+        const SERVER: &str = "/opt/cj/tools/bin/cangjie-lsp";
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("worktree_root", json!("/work")),
+            ("env CANGJIE_HOME", json!("/opt/cj")),
+            ("is_dir /opt/cj", json!(true)),
+            ("is_dir /opt/cj/tools/bin", json!(true)),
+            ("modified_time /work", json!(100)),
+            ("modified_time /opt/cj", json!(200)),
+            (&format!("modified_time {}", SERVER), json!(300)),
+        ]);
+        assert!(extension.cached_resolution(&host).is_none());
+        extension.record_lsp_channel(&host, "SDK".to_string());
+        extension.store_resolution(&host, SERVER, None);
+        let resolution = extension.cached_resolution(&host).unwrap();
+        assert_eq!(resolution.server_path, SERVER);
+        assert_eq!(resolution.channel.as_deref(), Some("SDK"));
+        assert!(!host.misses().iter().any(|call| call.starts_with("run")));
+
+        // 测试文件修改时间或设置变化后重新解析
+        let state = host.read_text_file(Path::new(fingerprint::STATE_FILE)).unwrap();
+        let reopened = |server_time: u64, channel: serde_json::Value| {
+            replay(&[
+                ("worktree_root", json!("/work")),
+                ("env CANGJIE_HOME", json!("/opt/cj")),
+                ("setting cangjie.toolchain.channel", channel),
+                ("read_text_file resolution-state.json", json!({ "ok": state })),
+                ("modified_time /work", json!(100)),
+                ("modified_time /opt/cj", json!(200)),
+                (&format!("modified_time {}", SERVER), json!(server_time)),
+            ])
+        };
+        assert!(extension.cached_resolution(&reopened(300, json!(null))).is_some());
+        assert!(extension.cached_resolution(&reopened(301, json!(null))).is_none());
+        assert!(extension.cached_resolution(&reopened(300, json!("nightly"))).is_none());
+
+        // 测试开启自动更新时，下载的语言服务器在发布信息缓存过期前复用
+        const DOWNLOADED: &str = "cangjie-lsp-v1.0.0/cangjie-lsp";
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("worktree_root", json!("/work")),
+            ("setting cangjie.lsp.autoUpdate", json!(true)),
+            ("unix_time", json!(1_000)),
+            (
+                "release cangjie-lang/cangjie latest pre_release=false",
+                json!({ "ok": { "version": "v1.0.0", "assets": [] } }),
+            ),
+        ]);
+        extension.store_resolution(&host, DOWNLOADED, None);
+        assert!(extension.cached_resolution(&host).is_none());
+        extension.fetch_language_server_release(&host, ToolchainChannel::Release, None).unwrap();
+        assert_eq!(extension.cached_resolution(&host).unwrap().server_path, DOWNLOADED);
+        let state = host.read_text_file(Path::new(fingerprint::STATE_FILE)).unwrap();
+        let expired = replay(&[
+            ("worktree_root", json!("/work")),
+            ("setting cangjie.lsp.autoUpdate", json!(true)),
+            ("read_text_file resolution-state.json", json!({ "ok": state })),
+            ("unix_time", json!(1_000 + RELEASE_CACHE_TTL_SECS)),
+        ]);
+        assert!(extension.cached_resolution(&expired).is_none());
+
+        // 测试下载新版本后不保存解析结果，下次启动切换到新版本
+        let extension = CangjieExtension::new();
+        let host = replay(&[("worktree_root", json!("/work"))]);
+        extension.set_worktree_state("/work", STATE_LSP_UPDATE_KEY, Some("v1.1.0".to_string()));
+        extension.store_resolution(&host, DOWNLOADED, None);
+        assert!(host.read_text_file(Path::new(fingerprint::STATE_FILE)).is_err());
+        extension.store_resolution(&host, DOWNLOADED, None);
+        assert!(extension.cached_resolution(&host).is_some());
+    }
+
+    #[test]
+    fn test_restart_lsp_replay() {
+        use serde_json::json;
+
+        // 测试 /cangjie-restart-lsp 清除缓存的解析结果与 SDK 路径后重新解析
+        const SERVER: &str = "/opt/cj/tools/bin/cangjie-lsp";
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("worktree_root", json!("/work")),
+            ("env CANGJIE_HOME", json!("/opt/cj")),
+            ("is_dir /opt/cj", json!(true)),
+            ("is_dir /opt/cj/tools/bin", json!(true)),
+            (&format!("is_file {}", SERVER), json!(true)),
+            ("modified_time /work", json!(100)),
+            ("modified_time /opt/cj", json!(200)),
+            (&format!("modified_time {}", SERVER), json!(300)),
+        ]);
+        extension.record_lsp_channel(&host, "SDK".to_string());
+        extension.store_resolution(&host, SERVER, None);
+        extension
+            .cached_tool_paths
+            .lock()
+            .unwrap()
+            .entry("/work".to_string())
+            .or_default()
+            .insert("tool_path_cjc".to_string(), "/old/cjc".to_string());
+
+        let platform = (Os::Linux, Architecture::X8664);
+        let output = extension.reresolve_language_server(&host, platform).unwrap();
+        assert!(output.text.contains(&format!("✅ cangjie-lsp: `{}`", SERVER)));
+        assert!(output.text.contains("Source: SDK\nUnchanged"));
+        assert!(output.text.contains("editor: restart language server"));
+        assert!(extension.cached_resolution(&host).is_none());
+        assert!(!extension.cached_tool_paths.lock().unwrap().contains_key("/work"));
+    }
+
+    #[test]
+    fn test_initialization_options_replay() {
+        use serde_json::json;
+
+        // 测试 initialize 选项包含 SDK 路径与设置
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("setting cangjie.sdkPath", json!("/opt/cj")),
+            ("is_dir /opt/cj", json!(true)),
+            ("is_dir /opt/cj/bin", json!(true)),
+            ("is_dir /opt/cj/modules", json!(true)),
+            ("setting cangjie.enableSemanticHighlighting", json!(false)),
+            ("setting cangjie.maxFileSize", json!(2000000)),
+        ]);
+        let options = extension.initialization_options(&host);
+        assert_eq!(
+            options,
+            json!({
+                "modulesHomeOption": "/opt/cj",
+                "stdLibPathOption": "/opt/cj/modules",
+                "enableSemanticHighlighting": false,
+                "checkOnSave": true,
+                "maxFileSize": 2000000,
+                "watchPatterns": DEFAULT_WATCH_PATTERNS,
+            })
+        );
+
+        // 测试找不到 SDK 时只发送设置
+        let extension = CangjieExtension::new();
+        let host = replay(&[("setting cangjie.sdkPath", json!("/missing"))]);
+        let options = extension.initialization_options(&host);
+        assert!(options.get("modulesHomeOption").is_none());
+        assert_eq!(options["maxFileSize"], json!(DEFAULT_MAX_FILE_SIZE));
+
+        // 测试 modulePath 基于工作树、stdlibPath 基于 SDK 解析，并同步到工作区配置
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("worktree_root", json!("/work")),
+            ("setting cangjie.sdkPath", json!("/opt/cj")),
+            ("is_dir /opt/cj", json!(true)),
+            ("is_dir /opt/cj/bin", json!(true)),
+            ("setting cangjie.modulePath", json!(["src/core", "/shared/modules", "../up"])),
+            ("setting cangjie.stdlibPath", json!("lib/std")),
+        ]);
+        let options = extension.initialization_options(&host);
+        assert_eq!(options["stdLibPathOption"], json!("/opt/cj/lib/std"));
+        assert_eq!(options["modulePathOption"], json!(["/work/src/core", "/shared/modules"]));
+        let settings = json!({ "cangjie.modulePath": "src/core", "cangjie.stdlibPath": "lib/std" });
+        let settings =
+            extension.resolve_path_settings(&host, settings.as_object().unwrap().clone());
+        assert_eq!(
+            lsp_config::translate(&settings)["cangjie"]["paths"],
+            json!({ "modules": ["/work/src/core", "/shared/modules"], "stdlib": "/opt/cj/lib/std" })
+        );
+
+        // 测试 files.watch 追加到默认监视模式之后，去重并跳过含 .. 的模式
+        let host = replay(&[(
+            "setting cangjie.files.watch",
+            json!(["gen/**/*.cj", "**/cjpm.toml", "../shared/*.cj"]),
+        )]);
+        let patterns = ["**/cjpm.toml", "**/cjpm.lock", "**/*.cj", "gen/**/*.cj"];
+        assert_eq!(extension.initialization_options(&host)["watchPatterns"], json!(patterns));
+        let settings = extension.resolve_path_settings(&host, serde_json::Map::new());
+        assert_eq!(lsp_config::translate(&settings)["cangjie"]["files"]["watch"], json!(patterns));
+    }
+
+    #[test]
+    fn test_language_server_env_replay() {
+        use serde_json::json;
+
+        // 测试语言服务器进程的 SDK 运行时环境变量
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("setting cangjie.sdkPath", json!("/opt/cj")),
+            ("is_dir /opt/cj", json!(true)),
+            ("is_dir /opt/cj/bin", json!(true)),
+            ("env LD_LIBRARY_PATH", json!("/usr/local/lib")),
+        ]);
+        let env = extension.language_server_env(&host, Os::Linux);
+        assert_eq!(
+            env,
+            vec![
+                ("CANGJIE_HOME".to_string(), "/opt/cj".to_string()),
+                (
+                    "LD_LIBRARY_PATH".to_string(),
+                    "/opt/cj/lib:/usr/local/lib".to_string()
+                ),
+            ]
+        );
+
+        // 测试找不到 SDK 时不设置环境变量
+        let extension = CangjieExtension::new();
+        let host = replay(&[("setting cangjie.sdkPath", json!("/missing"))]);
+        assert!(extension.language_server_env(&host, Os::Mac).is_empty());
+    }
+
+    #[test]
+    fn test_environment_report_replay() {
+        use serde_json::json;
+
+        // 测试报告各进程的工作目录与在 shell 环境之上设置的变量
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("worktree_root", json!("/work")),
+            ("setting cangjie.sdkPath", json!("/opt/cj")),
+            ("is_dir /opt/cj", json!(true)),
+            ("is_dir /opt/cj/bin", json!(true)),
+        ]);
+        let shell_env = vec![
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            ("LD_LIBRARY_PATH".to_string(), "/usr/local/lib".to_string()),
+        ];
+        let output = extension
+            .environment_report(&host, Os::Linux, &shell_env)
+            .unwrap();
+        let labels: Vec<&str> = output
+            .sections
+            .iter()
+            .map(|section| section.label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                "SDK",
+                "Language Server",
+                "Build, Run and Test",
+                "Debug Adapter",
+                "Shell Environment"
+            ]
+        );
+        assert!(output.text.contains("Working Directory: /work"));
+        assert!(output.text.contains("CANGJIE_HOME=/opt/cj\n  (not set in the shell)"));
+        assert!(output.text.contains("LD_LIBRARY_PATH: prepended\n  + /opt/cj/lib"));
+        assert!(output.text.contains("CANGJIE_HOME is not set"));
+    }
+
+    #[test]
+    fn test_builtin_tasks_replay() {
+        use serde_json::json;
+
+        // 测试内置任务模板注入 SDK 环境，并在 PATH 前追加 SDK 的 bin 目录
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("worktree_root", json!("/work")),
+            ("setting cangjie.sdkPath", json!("/opt/cj")),
+            ("is_dir /opt/cj", json!(true)),
+            ("is_dir /opt/cj/bin", json!(true)),
+            ("env PATH", json!("/usr/bin")),
+            (
+                "read_worktree_file cjpm.toml",
+                json!({ "ok": "[scripts]\nlint = \"cjlint -f src\"\n" }),
+            ),
+        ]);
+        let templates = extension.builtin_tasks(&host, Os::Linux).unwrap();
+        assert_eq!(templates.len(), 13);
+        assert_eq!(templates[1]["label"], "cjpm build (debug)");
+        assert_eq!(templates[12]["label"], "cjpm script: lint");
+        assert_eq!(templates[12]["env"]["CANGJIE_HOME"], "/opt/cj");
+        assert_eq!(templates[0]["command"], "cjpm");
+        assert_eq!(templates[0]["env"]["CANGJIE_HOME"], "/opt/cj");
+        assert_eq!(templates[0]["env"]["LD_LIBRARY_PATH"], "/opt/cj/lib");
+        assert_eq!(templates[0]["env"]["PATH"], "/opt/cj/bin:/usr/bin");
+
+        // 测试调试定位器的构建任务使用缓存的 SDK 环境，任务自身的变量优先
+        let task = TaskTemplate {
+            label: "test".to_string(),
+            command: "cjpm".to_string(),
+            args: vec!["test".to_string()],
+            env: vec![("CANGJIE_HOME".to_string(), "/custom".to_string())],
+            cwd: Some("/work/pkg".to_string()),
+        };
+        let scenario = extension
+            .debug_scenario_for_task(
+                Os::Linux,
+                "cangjie".to_string(),
+                task,
+                "Test".to_string(),
+                DEBUG_ADAPTER_NAME.to_string(),
+            )
+            .unwrap();
+        let Some(zed::BuildTaskDefinition::Template(build)) = scenario.build else {
+            panic!("expected a build template");
+        };
+        let env: HashMap<String, String> = build.template.env.into_iter().collect();
+        assert_eq!(env["CANGJIE_HOME"], "/custom");
+        assert_eq!(env["PATH"], "/opt/cj/bin:/usr/bin");
+
+        // 测试交叉编译目标生成构建变体，配置错误时报错
+        let host = replay(&[
+            ("setting cangjie.build.targets", json!(["aarch64-linux-ohos"])),
+            ("setting cangjie.build.defaultProfile", json!("release")),
+        ]);
+        let templates = extension.builtin_tasks(&host, Os::Linux).unwrap();
+        assert_eq!(templates[0]["args"], json!(["build", "-O2"]));
+        assert_eq!(templates[3]["label"], "cjpm build (aarch64-linux-ohos)");
+        let host = replay(&[("setting cangjie.build.defaultProfile", json!("fast"))]);
+        assert!(extension.builtin_tasks(&host, Os::Linux).is_err());
+
+        // 测试 OHOS 工具链的可执行包生成 hdc 部署任务
+        let host = replay(&[
+            ("worktree_root", json!("/work/hello")),
+            ("setting cangjie.sdkPath", json!("/opt/cj")),
+            ("setting cangjie.sdk.flavor", json!("ohos")),
+            ("setting cangjie.ohos.device", json!("FMR0223")),
+            ("is_dir /opt/cj", json!(true)),
+            ("is_dir /opt/cj/bin", json!(true)),
+            ("read_worktree_file cjpm.toml", json!({ "ok": "[package]\n" })),
+        ]);
+        let templates = extension.builtin_tasks(&host, Os::Linux).unwrap();
+        let deploy = templates
+            .iter()
+            .find(|template| template["label"] == "hdc deploy hello")
+            .unwrap();
+        assert!(
+            deploy["command"]
+                .as_str()
+                .unwrap()
+                .contains("file send target/aarch64-linux-ohos/debug/bin/hello /data/local/tmp/")
+        );
+        assert_eq!(deploy["env"]["CANGJIE_HOME"], "/opt/cj");
+    }
+
+    #[test]
+    fn test_smoke_command_replay() {
+        use serde_json::json;
+
+        // 测试 /cangjie-smoke 逐阶段报告，失败后跳过后续阶段
+        const PROJECT: &str = "/ext/smoke-test";
+        let run_key = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            let (program, args) =
+                run_config::wrap_command(Os::Linux, "/sdk/bin/cjpm", &args, PROJECT, None);
+            format!("run {} {}", program, args.join(" "))
+        };
+        let passed = json!({ "ok": { "status": 0, "stdout": "", "stderr": "" } });
+        let host = replay(&[
+            ("current_dir", json!({ "ok": "/ext" })),
+            (&run_key(&["init", "--name", smoke::PACKAGE_NAME]), passed.clone()),
+            (&run_key(&["build"]), passed),
+            (
+                &run_key(&["run"]),
+                json!({ "ok": { "status": 0, "stdout": smoke::EXPECTED_OUTPUT, "stderr": "" } }),
+            ),
+            (
+                &run_key(&["test"]),
+                json!({ "ok": {
+                    "status": 1,
+                    "stdout": "FAILED addition\n  at src/smoke_test.cj:9:9",
+                    "stderr": ""
+                } }),
+            ),
+        ]);
+        let extension = CangjieExtension::new();
+        let lsp = Some(Err("cangjie-lsp not found".to_string()));
+        let output = extension.run_smoke_test(&host, Os::Linux, "/sdk/bin/cjpm", lsp, &[]);
+        assert!(output.text.starts_with("❌ Smoke test failed at Unit Test"));
+        assert!(output.text.contains("| Build | ✅ passed |"));
+        assert!(output.text.contains("| Unit Test | ❌ failed |"));
+        assert!(output.text.contains("| Language Server | ⚠️ skipped | |"));
+        let labels: Vec<_> = output.sections.iter().map(|s| s.label.as_str()).collect();
+        assert!(labels.contains(&"/ext/smoke-test/src/smoke_test.cj:9:9"));
+        let written = host.read_text_file(Path::new("/ext/smoke-test/src/main.cj"));
+        assert_eq!(written.unwrap(), smoke::main_source());
+    }
+
+    #[test]
+    fn test_lsp_health_replay() {
+        use serde_json::json;
+
+        // 测试 /cangjie-lsp-health 在服务器缺失时给出提示
+        let extension = CangjieExtension::new();
+        let host = replay(&[("current_dir", json!({ "ok": "/ext" }))]);
+        let output = extension.lsp_health(&host, Os::Linux);
+        assert!(output.text.contains("❌ cangjie-lsp is missing"));
+
+        // 测试服务器响应 initialize 时列出启用的能力
+        const SERVER: &str = "/opt/lsp/cangjie-lsp";
+        let session = format!("/ext/{}", smoke::HEALTH_SESSION_FILE);
+        let args = vec![LSP_DEFAULT_TRANSPORT.to_string()];
+        let (program, args) =
+            run_config::wrap_command(Os::Linux, SERVER, &args, "/work", Some(&session));
+        let run_key = format!("run {} {}", program, args.join(" "));
+        let response = json!({ "jsonrpc": "2.0", "id": 1, "result": { "capabilities": {
+            "hoverProvider": true,
+            "definitionProvider": {},
+            "renameProvider": false,
+        } } })
+        .to_string();
+        let host = replay(&[
+            ("worktree_root", json!("/work")),
+            ("current_dir", json!({ "ok": "/ext" })),
+            ("lsp_binary_override", json!(SERVER)),
+            (&format!("is_file {}", SERVER), json!(true)),
+            (
+                &format!("run {} --version", SERVER),
+                json!({ "ok": { "status": 0, "stdout": "cangjie-lsp 1.0.0", "stderr": "" } }),
+            ),
+            (
+                &run_key,
+                json!({ "ok": {
+                    "status": 0,
+                    "stdout": format!("Content-Length: {}\r\n\r\n{}", response.len(), response),
+                    "stderr": ""
+                } }),
+            ),
+        ]);
+        let output = extension.lsp_health(&host, Os::Linux);
+        assert!(output.text.contains("✅ Version: 1.0.0"));
+        assert!(output.text.contains("✅ Answered `initialize` on `/work`"));
+        assert!(output.text.contains("- definitionProvider\n- hoverProvider"));
+        assert!(!output.text.contains("renameProvider"));
+        let written = host.read_text_file(Path::new(&session));
+        assert_eq!(written.unwrap(), smoke::lsp_session("/work"));
+
+        // 测试服务器启动即退出时报告退出状态与标准错误
+        let host = replay(&[
+            ("worktree_root", json!("/work")),
+            ("current_dir", json!({ "ok": "/ext" })),
+            ("lsp_binary_override", json!(SERVER)),
+            (&format!("is_file {}", SERVER), json!(true)),
+            (
+                &run_key,
+                json!({ "ok": {
+                    "status": 1,
+                    "stdout": "",
+                    "stderr": "libcangjie-ast.so: not found"
+                } }),
+            ),
+        ]);
+        let output = extension.lsp_health(&host, Os::Linux);
+        assert!(output.text.contains("❌ `--version` failed"));
+        assert!(output.text.contains("did not answer `initialize` (exit status 1)"));
+        assert!(output.text.contains("libcangjie-ast.so: not found"));
+    }
+
+    #[test]
+    fn test_release_metadata_cache() {
+        use serde_json::json;
+
+        // 测试发布元数据在有效期内被复用，过期后重新获取
+        const LATEST: &str = "release cangjie-lang/cangjie latest pre_release=false";
+        let extension = CangjieExtension::new();
+        let release = json!({ "ok": { "version": "v1.0.0", "assets": [] } });
+        let offline = json!({ "err": "API rate limit exceeded" });
+        let host = replay(&[
+            ("unix_time", json!(1_000)),
+            (LATEST, release),
+            (LATEST, offline.clone()),
+        ]);
+        let channel = ToolchainChannel::Release;
+        let first = extension.fetch_language_server_release(&host, channel, None);
+        assert_eq!(first.unwrap().version, "v1.0.0");
+        let cached = extension.fetch_language_server_release(&host, channel, None);
+        assert_eq!(cached.unwrap().version, "v1.0.0");
+
+        let expired = json!(1_000 + RELEASE_CACHE_TTL_SECS);
+        let host = replay(&[("unix_time", expired), (LATEST, offline)]);
+        assert!(extension.fetch_language_server_release(&host, channel, None).is_err());
+        let pinned = extension.fetch_language_server_release(&host, channel, Some("1.0.0"));
+        assert!(pinned.is_err());
+
+        // 测试 cangjie.lsp.preRelease 让 release 通道包含预发布版本，且不复用稳定版缓存
+        let host = replay(&[
+            ("unix_time", json!(1_000)),
+            ("setting cangjie.lsp.preRelease", json!(true)),
+            (
+                "release cangjie-lang/cangjie latest pre_release=true",
+                json!({ "ok": { "version": "v1.1.0-beta.1", "assets": [] } }),
+            ),
+        ]);
+        let extension = CangjieExtension::new();
+        extension.fetch_language_server_release(&host, channel, None).unwrap();
+        let pre_release = extension.fetch_language_server_release(&host, channel, None);
+        assert_eq!(pre_release.unwrap().version, "v1.1.0-beta.1");
+        assert!(host.misses().is_empty());
+    }
+
+    #[test]
+    fn test_completion_index_replay() {
+        use serde_json::json;
+
+        // 测试补全索引在请求补全时才建立，有效期内复用，过期后重新索引
+        let extension = CangjieExtension::new();
+        let empty = extension.completion_index(&replay(&[]), Os::Linux);
+        assert_eq!(empty, completion::CompletionIndex::default());
+
+        let source = json!({ "root": "/work", "configs": ["smoke"] }).to_string();
+        extension
+            .in_memory_state
+            .lock()
+            .unwrap()
+            .insert(STATE_COMPLETION_WORKTREE_KEY.to_string(), source);
+        let (program, args) = completion::list_command(Os::Linux);
+        let (program, args) = run_config::wrap_command(Os::Linux, &program, &args, "/work", None);
+        let listing =
+            |stdout: &str| json!({ "ok": { "status": 0, "stdout": stdout, "stderr": "" } });
+        let list_key = format!("run {} {}", program, args.join(" "));
+        let host = replay(&[
+            ("unix_time", json!(1_000)),
+            ("unix_time", json!(1_010)),
+            ("unix_time", json!(1_000 + COMPLETION_INDEX_TTL_SECS)),
+            (&list_key, listing("./src/main.cj\n")),
+            (&list_key, listing("./src/main.cj\n./src/calc_test.cj\n")),
+            (
+                "read_text_file /work/src/calc_test.cj",
+                json!({ "ok": "@Test\nclass CalcTest {\n    @TestCase\n    func add() {}\n}\n" }),
+            ),
+            (
+                "read_text_file /work/cjpm.toml",
+                json!({ "ok": "[package]\nname = \"demo\"\n" }),
+            ),
+        ]);
+        let index = extension.completion_index(&host, Os::Linux);
+        assert_eq!(index.files, vec!["src/main.cj"]);
+        assert_eq!(index.targets, vec!["demo"]);
+        assert_eq!(index.configs, vec!["smoke"]);
+        let cached = extension.completion_index(&host, Os::Linux);
+        assert_eq!(cached, index);
+        let refreshed = extension.completion_index(&host, Os::Linux);
+        assert_eq!(refreshed.files, vec!["src/calc_test.cj", "src/main.cj"]);
+        assert_eq!(refreshed.tests, vec!["CalcTest.*", "CalcTest.add"]);
+    }
+
+    #[test]
+    fn test_tool_version_cache() {
+        use serde_json::json;
+
+        // 测试未变化的二进制只运行一次 --version，修改后重新查询
+        const SERVER: &str = "/opt/lsp/cangjie-lsp";
+        let version =
+            |output: &str| json!({ "ok": { "status": 0, "stdout": output, "stderr": "" } });
+        let run_key = format!("run {} --version", SERVER);
+        let modified_key = format!("modified_time {}", SERVER);
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            (&modified_key, json!(100)),
+            (&run_key, version("cangjie-lsp 1.0.0")),
+            (&run_key, version("cangjie-lsp 1.1.0")),
+        ]);
+        let first = extension.cached_tool_version(&host, SERVER).unwrap();
+        assert_eq!(first.to_string(), "1.0.0");
+        let cached = extension.cached_tool_version(&host, SERVER).unwrap();
+        assert_eq!(cached.to_string(), "1.0.0");
+
+        let host = replay(&[
+            (&modified_key, json!(200)),
+            (&run_key, version("cangjie-lsp 1.1.0")),
+        ]);
+        let updated = extension.cached_tool_version(&host, SERVER).unwrap();
+        assert_eq!(updated.to_string(), "1.1.0");
+
+        // 测试没有修改时间的二进制每次都重新查询
+        let host = replay(&[
+            ("run cjc --version", version("cjc 1.0.0")),
+            ("run cjc --version", version("cjc 1.1.0")),
+        ]);
+        assert_eq!(extension.cached_tool_version(&host, "cjc").unwrap().to_string(), "1.0.0");
+        assert_eq!(extension.cached_tool_version(&host, "cjc").unwrap().to_string(), "1.1.0");
+    }
+
+    #[test]
+    fn test_command_output_replay() {
+        use serde_json::json;
+
+        // 测试 /cangjie-refresh-sdk 的输出格式
+        let extension = CangjieExtension::new();
+        let host = replay(&[
+            ("worktree_root", json!("/work/seven")),
+            ("env CANGJIE_HOME", json!("/opt/cj")),
+            ("is_dir /opt/cj", json!(true)),
+            ("is_dir /opt/cj/bin", json!(true)),
+        ]);
+        let output = extension.refresh_sdk(&host).unwrap();
+        let labels: Vec<_> = output.sections.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, vec!["Resolution Steps", "Result"]);
+        assert!(output.text.contains("1. No project-local SDK"));
+        assert!(output.text.contains("✅ SDK Root: `/opt/cj`"));
+        let output = extension.refresh_sdk(&host).unwrap();
+        assert!(output.text.contains("Unchanged"));
+
+        // 测试 /cangjie-verify-assets 使用录制的发布列表
+        let host = replay(&[(
+            "read_worktree_file release.json",
+            json!({ "ok": r#"["cangjie-lsp-x86_64-unknown-linux-gnu.tar.gz"]"# }),
+        )]);
+        let args = vec!["--fixture".to_string(), "release.json".to_string()];
+        let output = extension.handle_verify_assets_command(&host, &args).unwrap();
+        assert!(output.text.contains(
+            "| linux-x86_64 | `cangjie-lsp-x86_64-unknown-linux-gnu` | \
+             ✅ `cangjie-lsp-x86_64-unknown-linux-gnu.tar.gz` |"
+        ));
+        assert!(output.text.contains("❌ Asset naming drift"));
+    }
+
+    #[test]
+    fn test_toolchain_channel_from_setting() {
+        // 测试工具链通道的解析
+        assert_eq!(ToolchainChannel::from_setting("release"), Some(ToolchainChannel::Release));
+        assert_eq!(ToolchainChannel::from_setting("Stable"), Some(ToolchainChannel::Release));
+        assert_eq!(ToolchainChannel::from_setting(" LTS "), Some(ToolchainChannel::Lts));
+        assert_eq!(ToolchainChannel::from_setting("nightly"), Some(ToolchainChannel::Nightly));
+        assert_eq!(ToolchainChannel::from_setting("beta"), None);
+        assert_eq!(ToolchainChannel::default(), ToolchainChannel::Release);
+    }
+
+    #[test]
+    fn test_toolchain_channel_sdk_dir_suffix() {
+        // 测试通道对应的 SDK 目录后缀
+        assert_eq!(ToolchainChannel::Release.sdk_dir_suffix(), None);
+        assert_eq!(ToolchainChannel::Lts.sdk_dir_suffix(), Some("lts"));
+        assert_eq!(ToolchainChannel::Nightly.sdk_dir_suffix(), Some("nightly"));
+    }
+
+    #[test]
+    fn test_merge_language_server_args() {
+        // 测试自定义参数保留默认的 --stdio 传输方式
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let (merged, warning) = merge_language_server_args(&args(&["--log-level=debug"]));
+        assert_eq!(merged, args(&["--stdio", "--log-level=debug"]));
+        assert!(warning.unwrap().contains("--stdio was added"));
+
+        // 测试已指定传输方式时原样使用
+        for transport in [&["--stdio", "-v"][..], &["--socket=9257"], &["--port", "9257"]] {
+            let (merged, warning) = merge_language_server_args(&args(transport));
+            assert_eq!(merged, args(transport));
+            assert!(warning.is_none());
+        }
+        let (merged, _) = merge_language_server_args(&args(&["--stdio-log"]));
+        assert_eq!(merged[0], "--stdio");
+        assert_eq!(merge_language_server_args(&[]).0, args(&["--stdio"]));
+    }
+
+    #[test]
+    fn test_language_server_log_args_replay() {
+        use serde_json::json;
+
+        // 测试日志级别与日志文件转换为服务器参数，相对路径基于工作树根目录
+        let extension = CangjieExtension::new();
+        let args = |list: &[&str]| list.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let host = replay(&[
+            ("worktree_root", json!("/work")),
+            ("setting cangjie.lsp.logLevel", json!("Debug")),
+            ("setting cangjie.lsp.logFile", json!("logs/lsp.log")),
+        ]);
+        assert_eq!(
+            extension.language_server_log_args(&host, &args(&["--stdio"])),
+            args(&["--log-level", "debug", "--log-path", "/work/logs/lsp.log"])
+        );
+
+        // 测试 binary.arguments 已指定的参数优先，无效的级别与 .. 路径被忽略
+        assert_eq!(
+            extension.language_server_log_args(&host, &args(&["--stdio", "--log-level=info"])),
+            args(&["--log-path", "/work/logs/lsp.log"])
+        );
+        let host = replay(&[
+            ("setting cangjie.lsp.logLevel", json!("verbose")),
+            ("setting cangjie.lsp.logFile", json!("../lsp.log")),
+        ]);
+        assert!(extension.language_server_log_args(&host, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_lint_server_args_replay() {
+        use serde_json::json;
+
+        // 测试 cjlint 语言服务器默认关闭，启用后使用默认或自定义参数
+        let extension = CangjieExtension::new();
+        let error = extension.lint_server_args(&replay(&[])).unwrap_err();
+        assert!(error.contains(CONFIG_LINT_ENABLED_KEY));
+        let enabled = ("setting cangjie.lint.enabled", json!(true));
+        let host = replay(std::slice::from_ref(&enabled));
+        assert_eq!(extension.lint_server_args(&host).unwrap(), ["--lsp"]);
+        let host = replay(&[enabled.clone(), ("setting cangjie.lint.args", json!(["serve"]))]);
+        assert_eq!(extension.lint_server_args(&host).unwrap(), ["serve"]);
+        let host = replay(&[enabled, ("setting cangjie.lint.args", json!("serve"))]);
+        assert!(extension.lint_server_args(&host).is_err());
+    }
+
+    #[test]
+    fn test_expand_path_variables() {
+        // 测试 ~ 和环境变量展开
+        let lookup = |name: &str| match name {
+            "HOME" => Some("/home/dev".to_string()),
+            "SDKS" => Some("$HOME/sdks".to_string()),
+            "VERSION" => Some("1.0.4".to_string()),
+            _ => None,
+        };
+        assert_eq!(expand_path_variables("~/sdks/cangjie", lookup), "/home/dev/sdks/cangjie");
+        assert_eq!(expand_path_variables("$HOME/cangjie", lookup), "/home/dev/cangjie");
+        assert_eq!(
+            expand_path_variables("${SDKS}/cangjie-%VERSION%", lookup),
+            "/home/dev/sdks/cangjie-1.0.4"
+        );
+        // 未知变量保持原样
+        assert_eq!(expand_path_variables("$MISSING/sdk", lookup), "$MISSING/sdk");
+        assert_eq!(expand_path_variables("/opt/cangjie", lookup), "/opt/cangjie");
+    }
+
     #[test]
     fn test_get_binary_name_unknown() {
         // 测试未知二进制名称的处理
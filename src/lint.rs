@@ -0,0 +1,219 @@
+// src/lint.rs
+//! Running cjlint over a worktree for `/cangjie-lint`.
+//!
+//! cjlint writes its findings as a JSON report, one object per finding with the file,
+//! position, level, rule ID (`defectType`) and description. The report is grouped by level
+//! and file for display, and the rule IDs are counted so noisy rules are easy to find.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::output::SourceLocation;
+
+/// Report file cjlint writes, in the extension's working directory
+pub const REPORT_FILE: &str = "cjlint-report.json";
+
+/// Rule list of a project-specific cjlint configuration, relative to the directory passed
+/// with `-c`
+pub const RULE_LIST_FILE: &str = "config/cjlint_rule_list.json";
+
+/// Level of a lint finding, from most to least severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintLevel {
+    Mandatory,
+    Suggestion,
+    Info,
+}
+
+impl LintLevel {
+    fn parse(value: &str) -> Self {
+        match value.to_ascii_uppercase().as_str() {
+            "MANDATORY" | "ERROR" => LintLevel::Mandatory,
+            "SUGGESTIONS" | "SUGGESTION" | "WARNING" => LintLevel::Suggestion,
+            _ => LintLevel::Info,
+        }
+    }
+
+    /// Returns the status marker and name of the level
+    pub fn label(&self) -> (&'static str, &'static str) {
+        match self {
+            LintLevel::Mandatory => ("❌", "mandatory"),
+            LintLevel::Suggestion => ("⚠️", "suggestion"),
+            LintLevel::Info => ("💡", "info"),
+        }
+    }
+}
+
+/// A cjlint finding
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub level: LintLevel,
+    /// Rule ID, e.g. `G.FUN.01`
+    pub rule: String,
+    pub message: String,
+    pub location: SourceLocation,
+}
+
+/// Returns the cjlint arguments linting a source directory into a JSON report.
+///
+/// # Arguments
+/// * `source_dir` - The directory to lint
+/// * `config_dir` - The directory holding the project's `config` directory, if any
+/// * `report` - The report file to write
+///
+/// # Returns
+/// * `Vec<String>` - The arguments after `cjlint`
+pub fn lint_args(source_dir: &str, config_dir: Option<&str>, report: &str) -> Vec<String> {
+    let mut args = vec![
+        "-f".to_string(),
+        source_dir.to_string(),
+        "-r".to_string(),
+        "json".to_string(),
+        "-o".to_string(),
+        report.to_string(),
+    ];
+    if let Some(config_dir) = config_dir {
+        args.extend(["-c".to_string(), config_dir.to_string()]);
+    }
+    args
+}
+
+/// Parses a cjlint JSON report.
+///
+/// # Arguments
+/// * `report` - The report contents: an array of findings
+/// * `root` - The worktree root, stripped from absolute paths
+///
+/// # Returns
+/// * `Result<Vec<Finding>, String>` - The findings, or an error if the report is malformed
+pub fn parse_report(report: &str, root: &str) -> Result<Vec<Finding>, String> {
+    let value: Value =
+        serde_json::from_str(report).map_err(|e| format!("Invalid cjlint report: {}", e))?;
+    let items = value
+        .as_array()
+        .ok_or_else(|| "Invalid cjlint report: expected a list of findings".to_string())?;
+    let root = format!("{}/", root.replace('\\', "/").trim_end_matches('/'));
+    let text = |item: &Value, key: &str| item[key].as_str().unwrap_or_default().to_string();
+    let number = |item: &Value, key: &str| item[key].as_u64().unwrap_or(1).max(1) as u32;
+    Ok(items
+        .iter()
+        .map(|item| {
+            let file = text(item, "file").replace('\\', "/");
+            let file = file.strip_prefix(&root).unwrap_or(&file).to_string();
+            Finding {
+                level: LintLevel::parse(&text(item, "defectLevel")),
+                rule: text(item, "defectType"),
+                message: text(item, "description").trim().to_string(),
+                location: SourceLocation::new(file, number(item, "line"), number(item, "column")),
+            }
+        })
+        .collect())
+}
+
+/// Groups findings by level and file, most severe level first.
+///
+/// # Arguments
+/// * `findings` - The findings
+///
+/// # Returns
+/// * `BTreeMap<(LintLevel, String), Vec<&Finding>>` - The findings of each level and file,
+///   ordered by position
+pub fn group(findings: &[Finding]) -> BTreeMap<(LintLevel, String), Vec<&Finding>> {
+    let mut groups: BTreeMap<(LintLevel, String), Vec<&Finding>> = BTreeMap::new();
+    for finding in findings {
+        groups
+            .entry((finding.level, finding.location.path.clone()))
+            .or_default()
+            .push(finding);
+    }
+    for findings in groups.values_mut() {
+        findings.sort_by_key(|finding| (finding.location.line, finding.location.column));
+    }
+    groups
+}
+
+/// Counts the findings of each rule, most frequent first
+pub fn rule_counts(findings: &[Finding]) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for finding in findings {
+        *counts.entry(finding.rule.as_str()).or_default() += 1;
+    }
+    let mut counts: Vec<(String, usize)> = counts
+        .into_iter()
+        .map(|(rule, count)| (rule.to_string(), count))
+        .collect();
+    counts.sort_by_key(|(_, count)| Reverse(*count));
+    counts
+}
+
+/// Counts the findings of each level, e.g. `2 mandatory, 1 suggestion`
+pub fn summary(findings: &[Finding]) -> String {
+    [LintLevel::Mandatory, LintLevel::Suggestion, LintLevel::Info]
+        .iter()
+        .map(|level| {
+            let count = findings.iter().filter(|f| f.level == *level).count();
+            (count, level.label().1)
+        })
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, name)| format!("{} {}", count, name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_report() {
+        // 测试解析 cjlint 报告并按级别与文件分组
+        let report = r#"[
+            {"file": "/work/src/main.cj", "line": 7, "column": 2, "defectLevel": "MANDATORY",
+             "defectType": "G.VAR.01", "description": "Prefer let over var "},
+            {"file": "/work/src/main.cj", "line": 3, "column": 5, "defectLevel": "MANDATORY",
+             "defectType": "G.VAR.01", "description": "Prefer let over var"},
+            {"file": "/work/src/util.cj", "line": 1, "defectLevel": "SUGGESTIONS",
+             "defectType": "G.FMT.02", "description": "Line too long"}
+        ]"#;
+        let findings = parse_report(report, "/work").unwrap();
+        assert_eq!(findings.len(), 3);
+        assert_eq!(
+            findings[2].location,
+            SourceLocation::new("src/util.cj", 1, 1)
+        );
+        assert_eq!(findings[0].message, "Prefer let over var");
+
+        let groups = group(&findings);
+        let keys: Vec<_> = groups.keys().cloned().collect();
+        assert_eq!(
+            keys,
+            vec![
+                (LintLevel::Mandatory, "src/main.cj".to_string()),
+                (LintLevel::Suggestion, "src/util.cj".to_string()),
+            ]
+        );
+        assert_eq!(groups[&keys[0]][0].location.line, 3);
+        assert_eq!(
+            rule_counts(&findings),
+            vec![("G.VAR.01".to_string(), 2), ("G.FMT.02".to_string(), 1)]
+        );
+        assert_eq!(summary(&findings), "2 mandatory, 1 suggestion");
+        assert!(parse_report("{}", "/work").is_err());
+
+        assert_eq!(
+            lint_args("src", Some("/work"), "/ext/cjlint-report.json"),
+            vec![
+                "-f",
+                "src",
+                "-r",
+                "json",
+                "-o",
+                "/ext/cjlint-report.json",
+                "-c",
+                "/work"
+            ]
+        );
+    }
+}
@@ -0,0 +1,114 @@
+// src/lsp_config.rs
+//! Translation of the extension's flat `cangjie.*` settings into the configuration schema of
+//! `cangjie-lsp`.
+//!
+//! The server asks for its workspace configuration as a nested object under the `cangjie`
+//! section, with its own key names. Settings it knows are mapped through [`KEY_MAP`];
+//! `cangjie.server.<path>` passes any other server option through unchanged. Settings only
+//! the extension uses, such as `cangjie.lsp.version` or `cangjie.output.style`, are not sent.
+
+use serde_json::{Map, Value};
+
+/// Section the server reads its configuration from
+pub const SECTION: &str = "cangjie";
+
+/// Prefix of settings passed to the server under their own dotted path
+pub const PASSTHROUGH_PREFIX: &str = "cangjie.server.";
+
+/// Extension settings and the dotted server keys they map to
+pub const KEY_MAP: &[(&str, &str)] = &[
+    (
+        "cangjie.enableSemanticHighlighting",
+        "semanticHighlighting.enabled",
+    ),
+    ("cangjie.maxFileSize", "files.maxSize"),
+    ("cangjie.files.watch", "files.watch"),
+    ("cangjie.checkOnSave", "diagnostics.onSave"),
+    ("cangjie.targetBackend", "build.targetBackend"),
+    ("cangjie.modulePath", "paths.modules"),
+    ("cangjie.stdlibPath", "paths.stdlib"),
+];
+
+/// Sets a value at a dotted path, creating intermediate objects.
+///
+/// A value already at an intermediate key that is not an object is replaced.
+fn insert_path(target: &mut Map<String, Value>, path: &str, value: Value) {
+    match path.split_once('.') {
+        None => {
+            target.insert(path.to_string(), value);
+        }
+        Some((head, rest)) => {
+            let child = target
+                .entry(head.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            if !child.is_object() {
+                *child = Value::Object(Map::new());
+            }
+            if let Value::Object(child) = child {
+                insert_path(child, rest, value);
+            }
+        }
+    }
+}
+
+/// Translates the extension settings into the server's workspace configuration.
+///
+/// # Arguments
+/// * `settings` - The flat `cangjie.*` settings
+///
+/// # Returns
+/// * `Value` - The configuration object, `{"cangjie": {...}}`
+pub fn translate(settings: &Map<String, Value>) -> Value {
+    let mut server = Map::new();
+    for (key, server_key) in KEY_MAP {
+        if let Some(value) = settings.get(*key) {
+            insert_path(&mut server, server_key, value.clone());
+        }
+    }
+    // Passed-through options come last, so they can override a mapped setting
+    let mut passthrough: Vec<(&String, &Value)> = settings
+        .iter()
+        .filter(|(key, _)| key.starts_with(PASSTHROUGH_PREFIX))
+        .collect();
+    passthrough.sort_by_key(|(key, _)| key.as_str());
+    for (key, value) in passthrough {
+        let path = &key[PASSTHROUGH_PREFIX.len()..];
+        if !path.is_empty() {
+            insert_path(&mut server, path, value.clone());
+        }
+    }
+    let mut configuration = Map::new();
+    configuration.insert(SECTION.to_string(), Value::Object(server));
+    Value::Object(configuration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_translate_settings() {
+        // 测试扁平的 cangjie.* 设置转换为服务器的嵌套配置
+        let settings = json!({
+            "cangjie.enableSemanticHighlighting": false,
+            "cangjie.targetBackend": "cjnative",
+            "cangjie.lsp.version": "1.0.0",
+            "cangjie.output.style": "plain",
+            "cangjie.server.diagnostics.onSave": true,
+            "cangjie.server.completion.snippets": false,
+            "cangjie.server.": 1,
+        });
+        let translated = translate(settings.as_object().unwrap());
+        assert_eq!(
+            translated,
+            json!({ "cangjie": {
+                "semanticHighlighting": { "enabled": false },
+                "build": { "targetBackend": "cjnative" },
+                "diagnostics": { "onSave": true },
+                "completion": { "snippets": false },
+            } })
+        );
+        assert_eq!(translate(&Map::new()), json!({ "cangjie": {} }));
+    }
+}
@@ -0,0 +1,531 @@
+// src/output.rs
+//! Building slash command output with navigable source locations.
+//!
+//! Every path the extension prints with a position uses the `path:line:col` form, and
+//! locations found in tool output additionally get a section labeled with that form, so
+//! Zed shows each one as its own entry that leads to the file.
+//!
+//! With `cangjie.output.style` set to `plain`, status emoji are replaced with words and
+//! markdown emphasis is flattened, which reads better in screen readers and terminals.
+
+use std::fmt;
+use std::path::Path;
+use zed_extension_api as zed;
+
+/// Most location sections added for a single tool output
+const MAX_LOCATION_SECTIONS: usize = 20;
+
+/// A position in a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub path: String,
+    /// 1-based line
+    pub line: u32,
+    /// 1-based column
+    pub column: u32,
+}
+
+impl SourceLocation {
+    pub fn new(path: impl Into<String>, line: u32, column: u32) -> Self {
+        Self {
+            path: path.into(),
+            line,
+            column,
+        }
+    }
+
+    /// Returns the start of a file
+    pub fn file(path: impl Into<String>) -> Self {
+        Self::new(path, 1, 1)
+    }
+
+    /// Makes a path relative to the worktree absolute
+    pub fn resolve(mut self, root: &str) -> Self {
+        if !crate::run_config::is_absolute(&self.path) {
+            self.path = Path::new(root)
+                .join(&self.path)
+                .to_string_lossy()
+                .to_string();
+        }
+        self
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.path, self.line, self.column)
+    }
+}
+
+/// Returns true if `path` names a file the toolchain reports positions in
+fn is_source_path(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.len() > 3 && (path.ends_with(".cj") || path.ends_with(".toml"))
+}
+
+/// Parses a `path:line[:col]` token as printed by cjc, cjpm and the unittest runner.
+///
+/// Surrounding punctuation such as `(…)`, quotes and a trailing `:` is ignored.
+///
+/// # Arguments
+/// * `token` - A whitespace-separated word of tool output
+///
+/// # Returns
+/// * `Option<SourceLocation>` - The location, if the token is one
+pub fn parse_location(token: &str) -> Option<SourceLocation> {
+    let token = token
+        .trim_start_matches(['(', '[', '"', '\'', '<'])
+        .trim_end_matches([')', ']', '"', '\'', '>', ':', ',', ';', '.']);
+    let (head, last) = token.rsplit_once(':')?;
+    let last: u32 = last.parse().ok()?;
+    if let Some((path, line)) = head.rsplit_once(':')
+        && let Ok(line) = line.parse::<u32>()
+        && is_source_path(path)
+    {
+        return Some(SourceLocation::new(path, line, last.max(1)));
+    }
+    is_source_path(head).then(|| SourceLocation::new(head, last, 1))
+}
+
+/// Finds the source locations mentioned in tool output, with the line mentioning each.
+///
+/// # Arguments
+/// * `output` - The tool's stdout and stderr
+///
+/// # Returns
+/// * `Vec<(SourceLocation, String)>` - Each distinct location and its first output line
+pub fn find_locations(output: &str) -> Vec<(SourceLocation, String)> {
+    let mut found: Vec<(SourceLocation, String)> = Vec::new();
+    for line in output.lines() {
+        for location in line.split_whitespace().filter_map(parse_location) {
+            if !found.iter().any(|(known, _)| *known == location) {
+                found.push((location, line.trim().to_string()));
+            }
+        }
+    }
+    found
+}
+
+/// Appends a labeled section to slash command output
+///
+/// # Arguments
+/// * `text` - The output text being built
+/// * `sections` - The output sections being built
+/// * `label` - Label of the new section
+/// * `content` - Content of the new section
+pub fn push_output_section(
+    text: &mut String,
+    sections: &mut Vec<zed::SlashCommandOutputSection>,
+    label: &str,
+    content: &str,
+) {
+    let start = text.len() as u32;
+    text.push_str(content);
+    if !content.ends_with('\n') {
+        text.push('\n');
+    }
+    sections.push(zed::SlashCommandOutputSection {
+        label: label.to_string(),
+        range: zed::Range {
+            start,
+            end: text.len() as u32,
+        },
+    });
+    text.push('\n');
+}
+
+/// How urgent a section is; sections are ordered from errors to successes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+    Success,
+}
+
+impl Severity {
+    /// Infers the severity from the status emoji the content starts a line with
+    pub fn of(content: &str) -> Self {
+        let starts = |marker: &str| {
+            content
+                .lines()
+                .any(|line| line.trim_start().starts_with(marker))
+        };
+        if starts("❌") {
+            Severity::Error
+        } else if starts("⚠️") {
+            Severity::Warning
+        } else if starts("✅") {
+            Severity::Success
+        } else {
+            Severity::Info
+        }
+    }
+}
+
+/// Status emoji and the words they read as in plain output
+const STATUS_WORDS: &[(&str, &str)] = &[
+    ("✅", "OK:"),
+    ("❌", "Error:"),
+    ("⚠️", "Warning:"),
+    ("⚠", "Warning:"),
+    ("💡", "Tip:"),
+    ("🔒", ""),
+];
+
+/// How slash command output is decorated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputStyle {
+    /// Status emoji and markdown emphasis
+    #[default]
+    Rich,
+    /// Words instead of emoji, no markdown emphasis, headings or code fences
+    Plain,
+}
+
+impl OutputStyle {
+    /// Parses the value of `cangjie.output.style`
+    pub fn from_setting(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "rich" => Some(OutputStyle::Rich),
+            "plain" => Some(OutputStyle::Plain),
+            _ => None,
+        }
+    }
+
+    /// Renders text in this style
+    pub fn render(&self, text: &str) -> String {
+        match self {
+            OutputStyle::Rich => text.to_string(),
+            OutputStyle::Plain => plain_text(text),
+        }
+    }
+
+    /// Renders the text and section labels of an output, keeping the sections aligned.
+    ///
+    /// # Arguments
+    /// * `output` - Output built in the rich style
+    ///
+    /// # Returns
+    /// * `zed::SlashCommandOutput` - The output in this style
+    pub fn apply(&self, output: zed::SlashCommandOutput) -> zed::SlashCommandOutput {
+        if *self == OutputStyle::Rich {
+            return output;
+        }
+        // Render the pieces between section boundaries separately so ranges can be mapped
+        let mut bounds: Vec<usize> = output
+            .sections
+            .iter()
+            .flat_map(|section| [section.range.start as usize, section.range.end as usize])
+            .filter(|&bound| bound <= output.text.len() && output.text.is_char_boundary(bound))
+            .chain([0, output.text.len()])
+            .collect();
+        bounds.sort_unstable();
+        bounds.dedup();
+        let mut text = String::new();
+        let mut mapped = vec![(0, 0)];
+        for pair in bounds.windows(2) {
+            text.push_str(&self.render(&output.text[pair[0]..pair[1]]));
+            mapped.push((pair[1], text.len()));
+        }
+        let map = |offset: u32| {
+            mapped
+                .iter()
+                .find(|(old, _)| *old >= offset as usize)
+                .map_or(text.len(), |(_, new)| *new) as u32
+        };
+        let sections = output
+            .sections
+            .iter()
+            .map(|section| zed::SlashCommandOutputSection {
+                label: self.render(&section.label),
+                range: zed::Range {
+                    start: map(section.range.start),
+                    end: map(section.range.end),
+                },
+            })
+            .collect();
+        zed::SlashCommandOutput { text, sections }
+    }
+}
+
+/// Replaces status emoji with words and removes markdown emphasis, headings and fences
+fn plain_text(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let (content, ending) = match line.strip_suffix('\n') {
+            Some(content) => (content, "\n"),
+            None => (line, ""),
+        };
+        if content.trim_start().starts_with("```") {
+            continue;
+        }
+        let indent = &content[..content.len() - content.trim_start().len()];
+        let mut content = content.trim_start();
+        let heading = content.trim_start_matches('#');
+        if heading.len() < content.len() && heading.starts_with(' ') {
+            content = heading.trim_start();
+        }
+        let mut content = content.replace("**", "").replace('`', "");
+        for (emoji, word) in STATUS_WORDS {
+            content = content.replace(emoji, word);
+        }
+        let content = content.replace('\u{fe0f}', "");
+        plain.push_str(indent);
+        plain.push_str(content.trim_start());
+        plain.push_str(ending);
+    }
+    plain
+}
+
+/// A section waiting to be rendered
+#[derive(Debug)]
+struct Entry {
+    label: String,
+    content: String,
+    severity: Severity,
+}
+
+/// Builds a [`zed::SlashCommandOutput`] whose sections are ordered by severity.
+///
+/// Errors and warnings come first; multi-line info and success sections get a label with
+/// their line count so they can stay collapsed. Sections longer than the line limit are
+/// truncated with a marker.
+#[derive(Debug, Default)]
+pub struct OutputBuilder {
+    /// Worktree root that relative locations are resolved against
+    root: Option<String>,
+    /// Most lines shown per section, or `None` for no limit
+    max_lines: Option<usize>,
+    style: OutputStyle,
+    entries: Vec<Entry>,
+}
+
+impl OutputBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolves relative locations against the worktree root
+    pub fn with_root(root: impl Into<String>) -> Self {
+        Self {
+            root: Some(root.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Resolves relative locations against another directory, e.g. a generated project
+    pub fn root(mut self, root: impl Into<String>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Limits the lines shown per section; `0` disables the limit
+    pub fn max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = (max_lines > 0).then_some(max_lines);
+        self
+    }
+
+    /// Renders the output in the given style
+    pub fn style(mut self, style: OutputStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Returns a location in the form printed by this output
+    pub fn link(&self, location: SourceLocation) -> SourceLocation {
+        match &self.root {
+            Some(root) => location.resolve(root),
+            None => location,
+        }
+    }
+
+    /// Appends a labeled section, inferring its severity from the content
+    pub fn section(&mut self, label: &str, content: &str) -> &mut Self {
+        self.section_with(Severity::of(content), label, content)
+    }
+
+    /// Appends a labeled section with an explicit severity
+    pub fn section_with(&mut self, severity: Severity, label: &str, content: &str) -> &mut Self {
+        self.entries.push(Entry {
+            label: label.to_string(),
+            content: content.to_string(),
+            severity,
+        });
+        self
+    }
+
+    /// Appends a section labeled with a location, e.g. a test or snapshot file
+    pub fn location(&mut self, location: SourceLocation, content: &str) -> &mut Self {
+        let label = self.link(location).to_string();
+        self.section(&label, content)
+    }
+
+    /// Appends one error section per source location mentioned in failed tool output
+    pub fn locations(&mut self, output: &str) -> &mut Self {
+        let found = find_locations(output);
+        let omitted = found.len().saturating_sub(MAX_LOCATION_SECTIONS);
+        for (location, line) in found.into_iter().take(MAX_LOCATION_SECTIONS) {
+            let location = self.link(location);
+            let content = format!("{}\n{}", location, line);
+            self.section_with(Severity::Error, &location.to_string(), &content);
+        }
+        if omitted > 0 {
+            self.section_with(
+                Severity::Error,
+                "More Locations",
+                &format!("… {} more location(s) in the output", omitted),
+            );
+        }
+        self
+    }
+
+    /// Appends the sections of another command's output
+    pub fn append(&mut self, output: zed::SlashCommandOutput) -> &mut Self {
+        for section in output.sections {
+            let range = section.range.start as usize..section.range.end as usize;
+            let content = output.text.get(range).unwrap_or_default();
+            self.section(&section.label, content);
+        }
+        self
+    }
+
+    /// Renders the sections, most severe first
+    pub fn build(mut self) -> zed::SlashCommandOutput {
+        self.entries.sort_by_key(|entry| entry.severity);
+        let mut text = String::new();
+        let mut sections = Vec::new();
+        for entry in &self.entries {
+            let content = entry.content.trim_end_matches('\n');
+            let line_count = content.lines().count();
+            let label = match entry.severity {
+                Severity::Info | Severity::Success if line_count > 1 => {
+                    format!("{} ({} lines)", entry.label, line_count)
+                }
+                _ => entry.label.clone(),
+            };
+            let content = match self.max_lines {
+                Some(max) if line_count > max => {
+                    let mut shown: Vec<&str> = content.lines().take(max).collect();
+                    let marker = format!("…truncated ({} more lines)", line_count - max);
+                    shown.push(&marker);
+                    shown.join("\n")
+                }
+                _ => content.to_string(),
+            };
+            push_output_section(&mut text, &mut sections, &label, &content);
+        }
+        self.style.apply(zed::SlashCommandOutput { text, sections })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_locations() {
+        // 测试从工具输出中提取 path:line:col 位置并生成可导航的分节
+        assert_eq!(
+            parse_location("src/main.cj:12:5:"),
+            Some(SourceLocation::new("src/main.cj", 12, 5))
+        );
+        assert_eq!(
+            parse_location("(src/a_test.cj:7)"),
+            Some(SourceLocation::new("src/a_test.cj", 7, 1))
+        );
+        assert_eq!(
+            parse_location(r"C:\work\src\main.cj:3"),
+            Some(SourceLocation::new(r"C:\work\src\main.cj", 3, 1))
+        );
+        assert_eq!(parse_location("12:30:00"), None);
+        assert_eq!(parse_location("src/main.cj"), None);
+
+        let output = "error: undeclared identifier 'x'\n ==> src/main.cj:3:9:\n\
+                      warning: unused variable\n ==> src/main.cj:3:9:\n";
+        let found = find_locations(output);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].1, "==> src/main.cj:3:9:");
+
+        let mut builder = OutputBuilder::with_root("/work");
+        builder
+            .section("Result", "❌ Build failed")
+            .locations(output);
+        let mut other = OutputBuilder::new();
+        other.section("Appended", "done");
+        builder.append(other.build());
+        let output = builder.build();
+        let labels: Vec<_> = output.sections.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, vec!["Result", "/work/src/main.cj:3:9", "Appended"]);
+        let appended = &output.sections[2].range;
+        assert_eq!(
+            &output.text[appended.start as usize..appended.end as usize],
+            "done\n"
+        );
+    }
+
+    #[test]
+    fn test_section_ordering() {
+        // 测试按严重程度排序、信息分节的行数标签与超长分节截断
+        let mut builder = OutputBuilder::new().max_lines(2);
+        builder
+            .section("Standard Output", "a\nb\nc\nd")
+            .section("Result", "✅ Tests passed")
+            .section("Warnings", "⚠️ deprecated flag")
+            .section("Failure", "❌ Tests failed\nat x\nat y");
+        let output = builder.build();
+        let labels: Vec<_> = output.sections.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec!["Failure", "Warnings", "Standard Output (4 lines)", "Result"]
+        );
+        assert!(
+            output
+                .text
+                .starts_with("❌ Tests failed\nat x\n…truncated (1 more lines)\n")
+        );
+        assert!(output.text.contains("a\nb\n…truncated (2 more lines)\n"));
+        assert_eq!(Severity::of("ok\n  ❌ failed"), Severity::Error);
+    }
+
+    #[test]
+    fn test_plain_style() {
+        // 测试纯文本样式将状态表情替换为文字并去除 markdown 标记
+        let mut builder = OutputBuilder::new().style(OutputStyle::Plain);
+        builder
+            .section("Summary", "## Tests\n✅ **3** passed in `src`")
+            .section("Failure", "❌ Tests failed\n```\nat x\n```\n  ⚠️ slow");
+        let output = builder.build();
+        assert_eq!(
+            output.text,
+            "Error: Tests failed\nat x\n  Warning: slow\n\nTests\nOK: 3 passed in src\n\n"
+        );
+        let ranges: Vec<_> = output
+            .sections
+            .iter()
+            .map(|s| &output.text[s.range.start as usize..s.range.end as usize])
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                "Error: Tests failed\nat x\n  Warning: slow\n",
+                "Tests\nOK: 3 passed in src\n"
+            ]
+        );
+        assert_eq!(output.sections[1].label, "Summary (2 lines)");
+
+        let rich = zed::SlashCommandOutput {
+            text: "**Info:**\n\n🔒 Network isolation enforced\n".to_string(),
+            sections: vec![zed::SlashCommandOutputSection {
+                label: "Isolation".to_string(),
+                range: zed::Range { start: 11, end: 43 },
+            }],
+        };
+        let plain = OutputStyle::Plain.apply(rich);
+        assert_eq!(plain.text, "Info:\n\nNetwork isolation enforced\n");
+        assert_eq!(plain.sections[0].range.start, 7);
+        assert_eq!(plain.sections[0].range.end as usize, plain.text.len());
+        assert_eq!(OutputStyle::from_setting("Plain"), Some(OutputStyle::Plain));
+        assert_eq!(OutputStyle::from_setting("fancy"), None);
+    }
+}
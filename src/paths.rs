@@ -0,0 +1,190 @@
+// src/paths.rs
+//! Safety checks for paths that reach spawned commands.
+//!
+//! Commands are spawned with discrete arguments, so paths with spaces or CJK characters need
+//! no quoting. The one exception is the PowerShell script `run_config::wrap_command` runs on
+//! Windows to apply a working directory and stdin: paths in it are [`powershell_literal`]s,
+//! the program's command line is quoted with [`quote_windows_arg`], and the script is passed
+//! with [`encoded_powershell`], so no shell re-parses any of it. Paths taken from settings
+//! must not climb out of the directory they are resolved against; [`check_setting_path`]
+//! rejects `..`.
+
+/// Returns true if any component of the path is `..`, with either separator.
+pub fn has_traversal(path: &str) -> bool {
+    path.split(['/', '\\']).any(|component| component == "..")
+}
+
+/// Rejects a path from the settings that contains a `..` component.
+///
+/// # Arguments
+/// * `key` - The settings key the path was read from
+/// * `path` - The configured path
+///
+/// # Returns
+/// * `Result<(), String>` - An error naming the setting if the path climbs with `..`
+pub fn check_setting_path(key: &str, path: &str) -> Result<(), String> {
+    if has_traversal(path) {
+        Err(format!(
+            "'{}' must not contain '..' components: {}",
+            key, path
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Quotes one argument of a Windows command line.
+///
+/// The argument is quoted the way the C runtime and `CommandLineToArgvW` split command lines:
+/// backslashes are doubled before a quote and before the closing quote, and embedded quotes
+/// are escaped. The command line is handed to `CreateProcess` without a shell, so no other
+/// character is special.
+///
+/// # Arguments
+/// * `arg` - The argument
+///
+/// # Returns
+/// * `String` - The quoted argument
+pub fn quote_windows_arg(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                quoted.push('"');
+                backslashes = 0;
+            }
+            _ => {
+                quoted.push_str(&"\\".repeat(backslashes));
+                quoted.push(c);
+                backslashes = 0;
+            }
+        }
+    }
+    quoted.push_str(&"\\".repeat(backslashes * 2));
+    quoted.push('"');
+    quoted
+}
+
+/// Quotes one word of a POSIX shell command, leaving words of safe characters as they are.
+pub fn quote_posix_arg(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if safe {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// Returns a PowerShell single-quoted string literal, in which nothing is expanded.
+///
+/// PowerShell also accepts the typographic single quotes as delimiters, so those are doubled
+/// along with `'`.
+pub fn powershell_literal(value: &str) -> String {
+    let mut literal = String::from("'");
+    for c in value.chars() {
+        if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+            literal.push(c);
+        }
+        literal.push(c);
+    }
+    literal.push('\'');
+    literal
+}
+
+/// Returns the `powershell` arguments running a script passed with `-EncodedCommand`.
+///
+/// The script travels as Base64 of its UTF-16LE bytes, so it reaches PowerShell unchanged
+/// whatever quotes or metacharacters it contains.
+///
+/// # Arguments
+/// * `script` - The PowerShell script
+///
+/// # Returns
+/// * `Vec<String>` - The arguments after `powershell`
+pub fn encoded_powershell(script: &str) -> Vec<String> {
+    let bytes: Vec<u8> = script.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    vec![
+        "-NoProfile".to_string(),
+        "-NonInteractive".to_string(),
+        "-EncodedCommand".to_string(),
+        base64(&bytes),
+    ]
+}
+
+/// Encodes bytes as standard Base64 with padding
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | u32::from(*byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_safety() {
+        // 测试 .. 路径穿越检测以及 Windows 命令行参数对空格、中文、UNC 路径的引用
+        assert!(has_traversal("../sdk"));
+        assert!(has_traversal(r"C:\tools\..\sdk"));
+        assert!(!has_traversal("/opt/cangjie..1/bin"));
+        assert!(check_setting_path("cangjie.sdkPath", "/opt/../etc").is_err());
+        assert!(check_setting_path("cangjie.sdkPath", "/opt/仓颉 SDK").is_ok());
+
+        assert_eq!(
+            quote_windows_arg(r"C:\Program Files\仓颉\bin\cjpm.exe"),
+            r#""C:\Program Files\仓颉\bin\cjpm.exe""#
+        );
+        assert_eq!(
+            quote_windows_arg(r"\\server\share\my project\"),
+            r#""\\server\share\my project\\""#
+        );
+        assert_eq!(
+            quote_windows_arg(r#"say \"hi" & echo %PATH%"#),
+            r#""say \\\"hi\" & echo %PATH%""#
+        );
+        assert_eq!(quote_windows_arg(""), r#""""#);
+
+        assert_eq!(quote_posix_arg("--run-args=-v"), "--run-args=-v");
+        assert_eq!(quote_posix_arg("$HOME/in.txt"), "'$HOME/in.txt'");
+        assert_eq!(quote_posix_arg("my file's"), r"'my file'\''s'");
+        assert_eq!(quote_posix_arg(""), "''");
+
+        // 测试 PowerShell 字面量与 -EncodedCommand 编码
+        assert_eq!(
+            powershell_literal(r"C:\it's ‘here’ $HOME"),
+            r"'C:\it''s ‘‘here’’ $HOME'"
+        );
+        assert_eq!(
+            encoded_powershell("dir"),
+            vec![
+                "-NoProfile",
+                "-NonInteractive",
+                "-EncodedCommand",
+                "ZABpAHIA"
+            ]
+        );
+        assert_eq!(base64(b"Ma"), "TWE=");
+        assert_eq!(base64(b"M"), "TQ==");
+        assert_eq!(base64(b"Man"), "TWFu");
+    }
+}
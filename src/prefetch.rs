@@ -0,0 +1,106 @@
+// src/prefetch.rs
+//! Background prefetching of language server releases for `/cangjie-upgrade-lsp`.
+//!
+//! With `cangjie.lsp.autoUpdate` disabled the downloaded server is only replaced on request.
+//! `cangjie.lsp.prefetch` downloads and verifies a newer release into [`PREFETCH_DIR`] ahead
+//! of time, where the language server lookup does not see it, so the upgrade itself is a
+//! rename. Each release is attempted at most once, even if the download failed or the
+//! prefetched copy was deleted, so a broken release does not cost bandwidth on every start.
+
+use serde_json::{Value, json};
+
+/// Directory in the extension's working directory holding prefetched releases
+pub const PREFETCH_DIR: &str = "prefetch";
+
+/// File in the extension's working directory listing the releases already attempted
+pub const STATE_FILE: &str = "prefetch-state.json";
+
+/// How much network use is acceptable for work nobody asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkPolicy {
+    /// Update checks and background downloads
+    Online,
+    /// Update checks only; releases are downloaded on request
+    Metered,
+    /// No background network access
+    Offline,
+}
+
+impl NetworkPolicy {
+    /// Derives the policy from `cangjie.network.offline` and `cangjie.network.metered`
+    pub fn from_settings(offline: bool, metered: bool) -> Self {
+        if offline {
+            NetworkPolicy::Offline
+        } else if metered {
+            NetworkPolicy::Metered
+        } else {
+            NetworkPolicy::Online
+        }
+    }
+
+    /// Returns true if looking for newer releases is allowed
+    pub fn allows_check(&self) -> bool {
+        *self != NetworkPolicy::Offline
+    }
+
+    /// Returns true if releases may be downloaded in the background
+    pub fn allows_download(&self) -> bool {
+        *self == NetworkPolicy::Online
+    }
+}
+
+/// Returns true if a release was prefetched, or attempted, before.
+///
+/// # Arguments
+/// * `state` - Contents of [`STATE_FILE`]
+/// * `version` - The release version
+///
+/// # Returns
+/// * `bool` - Whether the release must not be prefetched again
+pub fn attempted(state: &str, version: &str) -> bool {
+    serde_json::from_str::<Value>(state)
+        .ok()
+        .and_then(|value| value.get("attempted")?.as_array().cloned())
+        .is_some_and(|versions| versions.iter().any(|v| v.as_str() == Some(version)))
+}
+
+/// Records a prefetch attempt.
+///
+/// # Arguments
+/// * `state` - Current contents of [`STATE_FILE`], if any
+/// * `version` - The release version
+///
+/// # Returns
+/// * `String` - The new contents of [`STATE_FILE`]
+pub fn record_attempt(state: Option<&str>, version: &str) -> String {
+    let mut versions = state
+        .and_then(|state| serde_json::from_str::<Value>(state).ok())
+        .and_then(|value| value.get("attempted")?.as_array().cloned())
+        .unwrap_or_default();
+    if !versions.iter().any(|v| v.as_str() == Some(version)) {
+        versions.push(json!(version));
+    }
+    json!({ "attempted": versions }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefetch_state() {
+        // 测试每个版本只预取一次以及网络策略
+        let state = record_attempt(None, "v1.1.0");
+        let state = record_attempt(Some(&state), "v1.1.0");
+        assert!(attempted(&state, "v1.1.0"));
+        assert!(!attempted(&state, "v1.2.0"));
+        assert!(!attempted("not json", "v1.1.0"));
+        assert_eq!(state, r#"{"attempted":["v1.1.0"]}"#);
+
+        let policy = NetworkPolicy::from_settings(false, true);
+        assert_eq!(policy, NetworkPolicy::Metered);
+        assert!(policy.allows_check() && !policy.allows_download());
+        assert!(!NetworkPolicy::from_settings(true, true).allows_check());
+        assert!(NetworkPolicy::from_settings(false, false).allows_download());
+    }
+}
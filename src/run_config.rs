@@ -0,0 +1,531 @@
+// src/run_config.rs
+//! Named run configurations (`cangjie.run.configs.{name}`) used by `/cangjie-run` and the
+//! generated run task variants.
+
+use serde_json::{Map, Value, json};
+use zed_extension_api::Os;
+
+use crate::collate::Collation;
+use crate::paths::{
+    check_setting_path, encoded_powershell, powershell_literal, quote_posix_arg, quote_windows_arg,
+};
+
+/// Settings key holding the run configurations, either as an object keyed by name or as
+/// flat `cangjie.run.configs.<name>` keys
+pub const RUN_CONFIGS_KEY: &str = "cangjie.run.configs";
+
+/// A named run configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunConfig {
+    pub name: String,
+    /// Program arguments
+    pub args: Vec<String>,
+    /// Extra environment variables
+    pub env: Vec<(String, String)>,
+    /// Working directory, relative to the worktree root unless absolute
+    pub cwd: Option<String>,
+    /// File fed to the program's standard input, relative to the worktree root unless absolute
+    pub stdin: Option<String>,
+}
+
+/// Arguments accepted by `/cangjie-run`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RunArgs {
+    /// `--config <name>`
+    pub config: Option<String>,
+    /// `--tasks`: print the task template variants instead of running
+    pub list_tasks: bool,
+    /// `--name <target>`: the executable `cjpm run` builds and runs
+    pub target: Option<String>,
+    /// Arguments passed on to the program, after the configuration's own arguments
+    pub program_args: Vec<String>,
+}
+
+/// Parses `/cangjie-run` arguments.
+///
+/// Arguments other than `--config`, `--name` and `--tasks`, and everything after `--`, are
+/// program arguments.
+///
+/// # Arguments
+/// * `args` - The slash command arguments
+///
+/// # Returns
+/// * `Result<RunArgs, String>` - The parsed arguments or an error message
+pub fn parse_run_args(args: &[String]) -> Result<RunArgs, String> {
+    let mut parsed = RunArgs::default();
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--config" => {
+                let name = it
+                    .next()
+                    .ok_or("`--config` requires a configuration name")?;
+                parsed.config = Some(name.clone());
+            }
+            "--name" => {
+                let name = it.next().ok_or("`--name` requires an executable name")?;
+                parsed.target = Some(name.clone());
+            }
+            "--tasks" => parsed.list_tasks = true,
+            "--" => parsed.program_args.extend(it.by_ref().cloned()),
+            other => match other.strip_prefix("--config=") {
+                Some(name) => parsed.config = Some(name.to_string()),
+                None => parsed.program_args.push(other.to_string()),
+            },
+        }
+    }
+    Ok(parsed)
+}
+
+/// Collects run configurations from the extension settings, sorted by name.
+///
+/// # Arguments
+/// * `settings` - The `cangjie` settings blob
+/// * `collation` - The order of configuration names
+///
+/// # Returns
+/// * `Result<Vec<RunConfig>, String>` - The configurations or an error for malformed entries
+pub fn parse_run_configs(
+    settings: &Map<String, Value>,
+    collation: Collation,
+) -> Result<Vec<RunConfig>, String> {
+    let mut entries: Vec<(String, &Value)> = Vec::new();
+    if let Some(Value::Object(configs)) = settings.get(RUN_CONFIGS_KEY) {
+        entries.extend(configs.iter().map(|(name, value)| (name.clone(), value)));
+    }
+    let flat_prefix = format!("{}.", RUN_CONFIGS_KEY);
+    for (key, value) in settings {
+        if let Some(name) = key.strip_prefix(&flat_prefix) {
+            entries.push((name.to_string(), value));
+        }
+    }
+
+    let mut configs = entries
+        .into_iter()
+        .map(|(name, value)| parse_run_config(&name, value))
+        .collect::<Result<Vec<_>, _>>()?;
+    collation.sort_by_name(&mut configs, |config| &config.name);
+    configs.dedup_by(|a, b| a.name == b.name);
+    Ok(configs)
+}
+
+fn parse_run_config(name: &str, value: &Value) -> Result<RunConfig, String> {
+    let object = value
+        .as_object()
+        .ok_or_else(|| format!("Run configuration '{}' must be an object", name))?;
+
+    let args = match object.get("args") {
+        None => Vec::new(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => Ok(s.clone()),
+                Value::Number(n) => Ok(n.to_string()),
+                _ => Err(format!(
+                    "Run configuration '{}': args must be strings",
+                    name
+                )),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(_) => {
+            return Err(format!(
+                "Run configuration '{}': args must be an array",
+                name
+            ));
+        }
+    };
+
+    let env = match object.get("env") {
+        None => Vec::new(),
+        Some(Value::Object(vars)) => {
+            let mut env: Vec<(String, String)> = vars
+                .iter()
+                .map(|(key, value)| {
+                    let value = value
+                        .as_str()
+                        .map(str::to_string)
+                        .unwrap_or_else(|| value.to_string());
+                    (key.clone(), value)
+                })
+                .collect();
+            env.sort();
+            env
+        }
+        Some(_) => {
+            return Err(format!(
+                "Run configuration '{}': env must be an object",
+                name
+            ));
+        }
+    };
+
+    let string_field = |field: &str| {
+        object
+            .get(field)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+
+    let cwd = string_field("cwd");
+    let stdin = string_field("stdin");
+    for (field, path) in [("cwd", &cwd), ("stdin", &stdin)] {
+        if let Some(path) = path {
+            check_setting_path(&format!("{}.{}.{}", RUN_CONFIGS_KEY, name, field), path)?;
+        }
+    }
+
+    Ok(RunConfig {
+        name: name.to_string(),
+        args,
+        env,
+        cwd,
+        stdin,
+    })
+}
+
+impl RunConfig {
+    /// Builds the `.zed/tasks.json` entry for this configuration.
+    ///
+    /// Zed quotes task arguments, so a stdin file cannot be passed as `<` and a path. With a
+    /// stdin file, the task is a shell command line with a real redirection instead; on
+    /// Windows, whose default task shell has no `<`, it runs the PowerShell script of
+    /// [`wrap_command`], which copies the file into the program's standard input.
+    ///
+    /// # Arguments
+    /// * `cjpm` - The cjpm command to invoke
+    /// * `os` - The platform the task runs on
+    ///
+    /// # Returns
+    /// * `Value` - The task template
+    pub fn task_template(&self, cjpm: &str, os: Os) -> Value {
+        let mut args = vec!["run".to_string()];
+        if !self.args.is_empty() {
+            args.push("--run-args".to_string());
+            args.push(join_run_args(&self.args));
+        }
+        let stdin = self.stdin.as_ref().map(|stdin| {
+            if is_absolute(stdin) {
+                stdin.clone()
+            } else {
+                format!("$ZED_WORKTREE_ROOT/{}", stdin)
+            }
+        });
+        let (command, args) = match stdin {
+            None => (cjpm.to_string(), args),
+            Some(stdin) if os == Os::Windows => (
+                "powershell".to_string(),
+                vec![
+                    "-NoProfile".to_string(),
+                    "-NonInteractive".to_string(),
+                    "-Command".to_string(),
+                    windows_script(cjpm, &args, None, Some(&stdin)),
+                ],
+            ),
+            Some(stdin) => {
+                let line = std::iter::once(cjpm)
+                    .chain(args.iter().map(String::as_str))
+                    .map(quote_posix_arg)
+                    .chain(["<".to_string(), quote_posix_arg(&stdin)])
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                (line, Vec::new())
+            }
+        };
+        let env: Map<String, Value> = self
+            .env
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect();
+        let cwd = match &self.cwd {
+            Some(cwd) if is_absolute(cwd) => cwd.clone(),
+            Some(cwd) => format!("$ZED_WORKTREE_ROOT/{}", cwd),
+            None => "$ZED_WORKTREE_ROOT".to_string(),
+        };
+        json!({
+            "label": format!("cjpm run ({})", self.name),
+            "command": command,
+            "args": args,
+            "env": env,
+            "cwd": cwd,
+            "tags": ["cangjie-run"],
+        })
+    }
+}
+
+/// Joins program arguments into the single `--run-args` value understood by cjpm,
+/// quoting arguments that contain whitespace or quotes.
+///
+/// # Arguments
+/// * `args` - The program arguments
+///
+/// # Returns
+/// * `String` - The joined argument string
+pub fn join_run_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            if arg.is_empty()
+                || arg
+                    .chars()
+                    .any(|c| c.is_whitespace() || c == '"' || c == '\'')
+            {
+                format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+            } else {
+                arg.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Wraps a command so that it runs in `cwd` with `stdin` redirected from a file.
+///
+/// The extension process API has no working directory or stdin support, so the command is
+/// run through a wrapper. On Unix, `sh` gets the paths as separate arguments rather than
+/// interpolated into its script. On Windows, no shell parses the command: a PowerShell script
+/// (see [`windows_script`]) starts the program through `ProcessStartInfo`, which takes the
+/// working directory, UNC shares included, as a path and the program's arguments as a command
+/// line quoted with [`quote_windows_arg`].
+///
+/// # Arguments
+/// * `os` - The current platform
+/// * `program` - The program to run
+/// * `args` - The program arguments
+/// * `cwd` - Absolute working directory
+/// * `stdin` - Absolute path of the stdin fixture, if any
+///
+/// # Returns
+/// * `(String, Vec<String>)` - The wrapper program and its arguments
+pub fn wrap_command(
+    os: Os,
+    program: &str,
+    args: &[String],
+    cwd: &str,
+    stdin: Option<&str>,
+) -> (String, Vec<String>) {
+    match os {
+        Os::Windows => (
+            "powershell".to_string(),
+            encoded_powershell(&windows_script(program, args, Some(cwd), stdin)),
+        ),
+        _ => {
+            const SCRIPT: &str = r#"cd "$1" || exit 125; input="$2"; shift 2; if [ -n "$input" ]; then exec "$@" < "$input"; else exec "$@"; fi"#;
+            let mut wrapped = vec![
+                "-c".to_string(),
+                SCRIPT.to_string(),
+                "cangjie-run".to_string(),
+                cwd.to_string(),
+                stdin.unwrap_or_default().to_string(),
+                program.to_string(),
+            ];
+            wrapped.extend(args.iter().cloned());
+            ("sh".to_string(), wrapped)
+        }
+    }
+}
+
+/// Returns the PowerShell script behind [`wrap_command`] on Windows.
+///
+/// The program is started with `UseShellExecute` off, so it inherits the script's standard
+/// output and error; the stdin fixture, if any, is copied byte for byte into its standard
+/// input. Without a working directory, the program starts in the script's. The script exits
+/// with the program's exit code.
+fn windows_script(
+    program: &str,
+    args: &[String],
+    cwd: Option<&str>,
+    stdin: Option<&str>,
+) -> String {
+    let command_line = args
+        .iter()
+        .map(|arg| quote_windows_arg(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut script = vec![
+        "$ErrorActionPreference = 'Stop'".to_string(),
+        "$info = New-Object System.Diagnostics.ProcessStartInfo".to_string(),
+        format!("$info.FileName = {}", powershell_literal(program)),
+        format!("$info.Arguments = {}", powershell_literal(&command_line)),
+        "$info.UseShellExecute = $false".to_string(),
+    ];
+    if let Some(cwd) = cwd {
+        script.push(format!(
+            "$info.WorkingDirectory = {}",
+            powershell_literal(cwd)
+        ));
+    }
+    if stdin.is_some() {
+        script.push("$info.RedirectStandardInput = $true".to_string());
+    }
+    script.push("$process = [System.Diagnostics.Process]::Start($info)".to_string());
+    if let Some(stdin) = stdin {
+        script.extend([
+            format!(
+                "$file = [System.IO.File]::OpenRead({})",
+                powershell_literal(stdin)
+            ),
+            "$file.CopyTo($process.StandardInput.BaseStream)".to_string(),
+            "$file.Close()".to_string(),
+            "$process.StandardInput.Close()".to_string(),
+        ]);
+    }
+    script.extend([
+        "$process.WaitForExit()".to_string(),
+        "exit $process.ExitCode".to_string(),
+    ]);
+    script.join("; ")
+}
+
+/// Returns true for absolute Unix or Windows paths
+pub fn is_absolute(path: &str) -> bool {
+    path.starts_with('/')
+        || path.starts_with('\\')
+        || (path.len() > 2
+            && path.as_bytes()[1] == b':'
+            && matches!(path.as_bytes()[2], b'/' | b'\\'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_run_configs() {
+        // 测试嵌套和扁平两种运行配置写法
+        let settings = json!({
+            "cangjie.run.configs": {
+                "smoke": { "args": ["--input", "data file.txt", 3], "stdin": "fixtures/in.txt" }
+            },
+            "cangjie.run.configs.bench": { "env": { "RUST_LOG": "debug" }, "cwd": "bench" },
+            "cangjie.sdkPath": "/opt/cangjie",
+        });
+        let configs =
+            parse_run_configs(settings.as_object().unwrap(), Collation::default()).unwrap();
+        assert_eq!(configs.len(), 2);
+        assert_eq!(configs[0].name, "bench");
+        assert_eq!(
+            configs[0].env,
+            vec![("RUST_LOG".to_string(), "debug".to_string())]
+        );
+        assert_eq!(configs[1].args, vec!["--input", "data file.txt", "3"]);
+        assert_eq!(configs[1].stdin.as_deref(), Some("fixtures/in.txt"));
+
+        // 测试带标准输入的任务通过 shell 重定向，Windows 下由 PowerShell 复制输入
+        let task = configs[1].task_template("cjpm", Os::Linux);
+        assert_eq!(task["label"], "cjpm run (smoke)");
+        assert_eq!(
+            task["command"],
+            r#"cjpm run --run-args '--input "data file.txt" 3' < '$ZED_WORKTREE_ROOT/fixtures/in.txt'"#
+        );
+        assert_eq!(task["args"], json!([]));
+        let task = configs[1].task_template("cjpm", Os::Windows);
+        assert_eq!(task["command"], "powershell");
+        let script = task["args"][3].as_str().unwrap();
+        assert!(
+            script.contains(
+                r#"$info.Arguments = '"run" "--run-args" "--input \"data file.txt\" 3"'"#
+            )
+        );
+        assert!(script.contains("OpenRead('$ZED_WORKTREE_ROOT/fixtures/in.txt')"));
+        let absolute = RunConfig {
+            stdin: Some("/data/in.txt".to_string()),
+            ..RunConfig::default()
+        };
+        assert_eq!(
+            absolute.task_template("cjpm", Os::Mac)["command"],
+            "cjpm run < /data/in.txt"
+        );
+        assert_eq!(
+            configs[0].task_template("cjpm", Os::Linux)["args"],
+            json!(["run"])
+        );
+
+        let invalid = json!({ "cangjie.run.configs.bad": { "args": "oops" } });
+        assert!(parse_run_configs(invalid.as_object().unwrap(), Collation::default()).is_err());
+        let escaping = json!({ "cangjie.run.configs.bad": { "stdin": "../../secrets.txt" } });
+        let error = parse_run_configs(escaping.as_object().unwrap(), Collation::default());
+        assert!(error.unwrap_err().contains("cangjie.run.configs.bad.stdin"));
+    }
+
+    #[test]
+    fn test_parse_run_args() {
+        // 测试 /cangjie-run 参数解析
+        let args = vec!["--config".to_string(), "smoke".to_string()];
+        assert_eq!(
+            parse_run_args(&args).unwrap().config.as_deref(),
+            Some("smoke")
+        );
+        let args = vec!["--config=smoke".to_string(), "--tasks".to_string()];
+        let parsed = parse_run_args(&args).unwrap();
+        assert!(parsed.list_tasks);
+        assert_eq!(parsed.config.as_deref(), Some("smoke"));
+        assert!(parse_run_args(&["--config".to_string()]).is_err());
+
+        // 测试其余参数与 -- 之后的参数传给程序
+        let args: Vec<String> = ["input.txt", "--config", "smoke", "-v", "--", "--tasks"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let parsed = parse_run_args(&args).unwrap();
+        assert_eq!(parsed.program_args, vec!["input.txt", "-v", "--tasks"]);
+        let args = vec!["--name".to_string(), "server".to_string()];
+        assert_eq!(
+            parse_run_args(&args).unwrap().target.as_deref(),
+            Some("server")
+        );
+        assert!(!parsed.list_tasks);
+    }
+
+    #[test]
+    fn test_wrap_command() {
+        // 测试 Unix 下通过 sh 包装工作目录和标准输入
+        let (program, args) = wrap_command(
+            Os::Linux,
+            "/opt/cangjie/bin/cjpm",
+            &["run".to_string()],
+            "/work/my project",
+            Some("/work/my project/in.txt"),
+        );
+        assert_eq!(program, "sh");
+        assert_eq!(
+            &args[3..],
+            [
+                "/work/my project",
+                "/work/my project/in.txt",
+                "/opt/cangjie/bin/cjpm",
+                "run"
+            ]
+        );
+
+        // 测试 Windows 下不经 cmd，由 PowerShell 启动程序并复制标准输入
+        let args = vec![
+            "run".to_string(),
+            "a&b".to_string(),
+            "it's \"x\"".to_string(),
+        ];
+        let script = windows_script(
+            r"C:\仓颉 SDK\bin\cjpm.exe",
+            &args,
+            Some(r"\\server\share\my project"),
+            Some(r"\\server\share\my project\in.txt"),
+        );
+        let lines: Vec<&str> = script.split("; ").collect();
+        assert_eq!(lines[2], r"$info.FileName = 'C:\仓颉 SDK\bin\cjpm.exe'");
+        assert_eq!(lines[3], r#"$info.Arguments = '"run" "a&b" "it''s \"x\""'"#);
+        assert_eq!(
+            lines[5],
+            r"$info.WorkingDirectory = '\\server\share\my project'"
+        );
+        assert!(
+            script.contains(
+                r"$file = [System.IO.File]::OpenRead('\\server\share\my project\in.txt')"
+            )
+        );
+        assert!(script.ends_with("exit $process.ExitCode"));
+        assert!(!windows_script("cjpm", &args, None, None).contains("StandardInput"));
+        let (program, wrapped) = wrap_command(Os::Windows, "cjpm", &args, r"C:\work", None);
+        assert_eq!(program, "powershell");
+        assert_eq!(wrapped[2], "-EncodedCommand");
+        assert!(is_absolute("C:\\work"));
+        assert!(!is_absolute("fixtures/in.txt"));
+    }
+}
@@ -0,0 +1,399 @@
+// src/sdk_layout.rs
+//! Normalizes the directory layouts used by different Cangjie SDK releases.
+//!
+//! Newer SDKs split binaries between `bin` (compiler) and `tools/bin` (cjpm, cjfmt, cjdb,
+//! the language server, ...) and ship their runtime in `runtime/lib/<platform>`. Older and
+//! repackaged SDKs put everything under plain `bin` and `lib`.
+//!
+//! The HarmonyOS (OHOS) flavor of the toolchain additionally ships runtime libraries for OHOS
+//! devices (`*_ohos_*` platform directories) and an LLVM cross toolchain in
+//! `third_party/llvm/bin`.
+
+use std::path::{Path, PathBuf};
+
+use zed_extension_api::Os;
+
+/// The directory layout of an SDK installation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutKind {
+    /// `bin` + `tools/bin`, libraries in `runtime/lib`
+    Split,
+    /// Everything in `bin` and `lib`
+    Flat,
+}
+
+/// LLDB scripts with the Cangjie data formatters, relative to the SDK root
+const LLDB_FORMATTER_SCRIPTS: &[&str] = &[
+    "tools/lldb/cangjie_formatters.py",
+    "lib/lldb/cangjie_formatters.py",
+    "third_party/llvm/lldb/cangjie_formatters.py",
+];
+
+/// Target triples supported by the OHOS toolchain flavor
+const OHOS_TARGETS: &[&str] = &["aarch64-linux-ohos", "x86_64-linux-ohos", "arm-linux-ohos"];
+
+/// The toolchain flavor of an SDK installation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SdkFlavor {
+    /// The standard desktop toolchain
+    #[default]
+    Standard,
+    /// The HarmonyOS / OpenHarmony toolchain with OHOS cross-compilers
+    Ohos,
+}
+
+impl SdkFlavor {
+    /// Parses the `cangjie.sdk.flavor` setting.
+    ///
+    /// # Arguments
+    /// * `value` - The setting value
+    ///
+    /// # Returns
+    /// * `Result<Option<SdkFlavor>, String>` - The flavor, `None` for `auto` detection, or an
+    ///   error for unknown values
+    pub fn from_setting(value: &str) -> Result<Option<Self>, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "auto" => Ok(None),
+            "standard" => Ok(Some(Self::Standard)),
+            "ohos" | "harmonyos" | "openharmony" => Ok(Some(Self::Ohos)),
+            other => Err(format!("Unknown SDK flavor: {}", other)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::Ohos => "ohos",
+        }
+    }
+
+    /// Detects the flavor from the SDK root and the names of its runtime platform directories.
+    ///
+    /// # Arguments
+    /// * `root` - The SDK root directory
+    /// * `platform_dirs` - Directory names below `runtime/lib` and `lib`
+    ///
+    /// # Returns
+    /// * `SdkFlavor` - The detected flavor
+    pub fn detect(root: &Path, platform_dirs: &[String]) -> Self {
+        let in_ohos_tree = root.components().any(|c| {
+            let name = c.as_os_str().to_string_lossy().to_ascii_lowercase();
+            name == "openharmony" || name == "harmonyos"
+        });
+        if in_ohos_tree || platform_dirs.iter().any(|dir| is_ohos_platform_dir(dir)) {
+            Self::Ohos
+        } else {
+            Self::Standard
+        }
+    }
+
+    /// Returns the cross-compilation target triples of the flavor
+    pub fn target_triples(&self) -> &'static [&'static str] {
+        match self {
+            Self::Standard => &[],
+            Self::Ohos => OHOS_TARGETS,
+        }
+    }
+}
+
+/// Returns true for runtime directories holding OHOS device libraries
+fn is_ohos_platform_dir(name: &str) -> bool {
+    name.to_ascii_lowercase().contains("ohos")
+}
+
+/// A resolved SDK root and the directory its symbolic links lead to.
+///
+/// SDKs managed by symlink farms (`current -> 1.0.3`) are reported by the link users
+/// configured, while tools are run from the target, which is checked again on each use so a
+/// repointed link takes effect without a restart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdkRoot {
+    /// The path as configured or found, shown in reports
+    pub path: PathBuf,
+    /// The canonical directory tools are run from
+    pub target: PathBuf,
+}
+
+impl SdkRoot {
+    /// Returns true if the path is, or goes through, a symbolic link
+    pub fn is_link(&self) -> bool {
+        self.path != self.target
+    }
+}
+
+/// A probed SDK installation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SdkLayout {
+    pub root: PathBuf,
+    pub kind: LayoutKind,
+    pub flavor: SdkFlavor,
+}
+
+impl SdkLayout {
+    /// Probes the layout using a custom directory check.
+    ///
+    /// # Arguments
+    /// * `root` - The SDK root directory
+    /// * `is_dir` - Returns true if a path is an existing directory
+    /// * `platform_dirs` - Directory names below `runtime/lib` and `lib`
+    ///
+    /// # Returns
+    /// * `SdkLayout` - The detected layout
+    pub fn probe_with(
+        root: &Path,
+        is_dir: impl Fn(&Path) -> bool,
+        platform_dirs: &[String],
+    ) -> Self {
+        let kind = if is_dir(&root.join("tools").join("bin"))
+            || is_dir(&root.join("runtime").join("lib"))
+        {
+            LayoutKind::Split
+        } else {
+            LayoutKind::Flat
+        };
+        Self {
+            root: root.to_path_buf(),
+            kind,
+            flavor: SdkFlavor::detect(root, platform_dirs),
+        }
+    }
+
+    /// Returns true if the directory contains an SDK in either layout
+    pub fn is_sdk_root_with(root: &Path, is_dir: impl Fn(&Path) -> bool) -> bool {
+        is_dir(&root.join("bin")) || is_dir(&root.join("tools").join("bin"))
+    }
+
+    /// Returns the binary directories in search order
+    pub fn bin_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = match self.kind {
+            LayoutKind::Split => vec![self.root.join("bin"), self.root.join("tools").join("bin")],
+            LayoutKind::Flat => vec![self.root.join("bin")],
+        };
+        if self.flavor == SdkFlavor::Ohos {
+            dirs.push(self.root.join("third_party").join("llvm").join("bin"));
+        }
+        dirs
+    }
+
+    /// Returns the runtime library directories in search order.
+    ///
+    /// For the split layout the platform directories below `runtime/lib` (e.g.
+    /// `linux_x86_64_llvm`) are listed when present. OHOS device libraries are skipped as
+    /// they cannot be loaded by host processes.
+    pub fn lib_dirs(&self) -> Vec<PathBuf> {
+        match self.kind {
+            LayoutKind::Split => {
+                let runtime = self.root.join("runtime").join("lib");
+                let mut dirs: Vec<PathBuf> = std::fs::read_dir(&runtime)
+                    .map(|entries| {
+                        entries
+                            .flatten()
+                            .map(|entry| entry.path())
+                            .filter(|path| path.is_dir())
+                            .filter(|path| {
+                                !path.file_name().is_some_and(|name| {
+                                    is_ohos_platform_dir(&name.to_string_lossy())
+                                })
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                dirs.sort();
+                if dirs.is_empty() {
+                    dirs.push(runtime);
+                }
+                dirs.push(self.root.join("lib"));
+                dirs
+            }
+            LayoutKind::Flat => vec![self.root.join("lib")],
+        }
+    }
+
+    /// Finds a tool binary in the SDK.
+    ///
+    /// # Arguments
+    /// * `file_name` - The binary's file name, including any platform extension
+    /// * `exists` - Returns true if a path is an existing binary
+    ///
+    /// # Returns
+    /// * `Option<PathBuf>` - The binary path, if found
+    pub fn find_tool(&self, file_name: &str, exists: impl Fn(&Path) -> bool) -> Option<PathBuf> {
+        self.bin_dirs()
+            .into_iter()
+            .map(|dir| dir.join(file_name))
+            .find(|path| exists(path))
+    }
+
+    /// Finds the LLDB scripts with the Cangjie data formatters, for debugging with a plain
+    /// LLDB instead of cjdb.
+    ///
+    /// # Arguments
+    /// * `exists` - Returns true if a path is an existing file
+    ///
+    /// # Returns
+    /// * `Vec<PathBuf>` - The scripts the SDK ships
+    pub fn lldb_formatter_scripts(&self, exists: impl Fn(&Path) -> bool) -> Vec<PathBuf> {
+        LLDB_FORMATTER_SCRIPTS
+            .iter()
+            .map(|script| self.root.join(script))
+            .filter(|path| exists(path))
+            .collect()
+    }
+
+    /// Builds the environment variable that lets SDK binaries find the runtime libraries.
+    ///
+    /// # Arguments
+    /// * `os` - The current platform
+    /// * `existing` - The current value of the variable, if any
+    ///
+    /// # Returns
+    /// * `(String, String)` - The variable name and its new value
+    pub fn library_path_env(&self, os: Os, existing: Option<&str>) -> (String, String) {
+        let name = library_path_var(os);
+        let separator = if os == Os::Windows { ";" } else { ":" };
+        let mut entries: Vec<String> = self
+            .lib_dirs()
+            .iter()
+            .map(|dir| dir.to_string_lossy().to_string())
+            .collect();
+        if os == Os::Windows {
+            entries.extend(
+                self.bin_dirs()
+                    .iter()
+                    .map(|dir| dir.to_string_lossy().to_string()),
+            );
+        }
+        if let Some(existing) = existing.filter(|e| !e.is_empty()) {
+            entries.push(existing.to_string());
+        }
+        (name.to_string(), entries.join(separator))
+    }
+
+    /// Returns the environment that tasks and commands need to run the SDK's tools.
+    ///
+    /// `CANGJIE_HOME` points at the SDK (the OHOS toolchain also locates its cross-compilation
+    /// sysroots through it), the runtime libraries go in front of the loader path and the
+    /// binary directories in front of `PATH`, so that cjpm runs the SDK's cjc.
+    ///
+    /// # Arguments
+    /// * `os` - The current platform
+    /// * `existing` - Looks up the current value of a variable
+    ///
+    /// # Returns
+    /// * `Vec<(String, String)>` - The environment variables
+    pub fn task_env(
+        &self,
+        os: Os,
+        existing: impl Fn(&str) -> Option<String>,
+    ) -> Vec<(String, String)> {
+        let mut env = vec![
+            (
+                "CANGJIE_HOME".to_string(),
+                self.root.to_string_lossy().to_string(),
+            ),
+            self.library_path_env(os, existing(library_path_var(os)).as_deref()),
+        ];
+        // On Windows the library path is PATH, which already has the binary directories
+        if os != Os::Windows {
+            let mut entries: Vec<String> = self
+                .bin_dirs()
+                .iter()
+                .map(|dir| dir.to_string_lossy().to_string())
+                .collect();
+            entries.extend(existing("PATH").filter(|path| !path.is_empty()));
+            env.push(("PATH".to_string(), entries.join(":")));
+        }
+        env
+    }
+}
+
+/// Returns the variable the dynamic loader searches for shared libraries on a platform
+pub fn library_path_var(os: Os) -> &'static str {
+    match os {
+        Os::Windows => "PATH",
+        Os::Mac => "DYLD_LIBRARY_PATH",
+        Os::Linux => "LD_LIBRARY_PATH",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sdk_layout() {
+        // 测试新旧两种 SDK 目录布局的探测与工具查找
+        let root = Path::new("/opt/cangjie");
+        let split = SdkLayout::probe_with(root, |p| p.ends_with("tools/bin"), &[]);
+        assert_eq!(split.kind, LayoutKind::Split);
+        let cjpm = split.find_tool("cjpm", |p| p.ends_with("tools/bin/cjpm"));
+        assert_eq!(cjpm, Some(PathBuf::from("/opt/cangjie/tools/bin/cjpm")));
+        assert_eq!(
+            split.find_tool("cjc", |p| p.ends_with("bin/cjc")),
+            Some(PathBuf::from("/opt/cangjie/bin/cjc"))
+        );
+
+        let flat = SdkLayout::probe_with(root, |p| p == root.join("bin"), &[]);
+        assert_eq!(flat.kind, LayoutKind::Flat);
+        assert_eq!(
+            flat.find_tool("cjpm", |p| p.ends_with("tools/bin/cjpm")),
+            None
+        );
+        assert_eq!(
+            flat.library_path_env(Os::Linux, Some("/usr/lib")),
+            (
+                "LD_LIBRARY_PATH".to_string(),
+                "/opt/cangjie/lib:/usr/lib".to_string()
+            )
+        );
+        assert_eq!(flat.flavor, SdkFlavor::Standard);
+        assert_eq!(
+            flat.task_env(Os::Linux, |name| (name == "PATH")
+                .then(|| "/usr/bin".to_string())),
+            vec![
+                ("CANGJIE_HOME".to_string(), "/opt/cangjie".to_string()),
+                (
+                    "LD_LIBRARY_PATH".to_string(),
+                    "/opt/cangjie/lib".to_string()
+                ),
+                ("PATH".to_string(), "/opt/cangjie/bin:/usr/bin".to_string()),
+            ]
+        );
+        assert_eq!(flat.task_env(Os::Windows, |_| None).len(), 2);
+
+        // 测试查找 SDK 附带的 LLDB 仓颉格式化脚本
+        assert_eq!(
+            split.lldb_formatter_scripts(|p| p.ends_with("tools/lldb/cangjie_formatters.py")),
+            vec![PathBuf::from(
+                "/opt/cangjie/tools/lldb/cangjie_formatters.py"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_sdk_flavor() {
+        // 测试鸿蒙 (OHOS) SDK 变体的识别
+        let platforms = vec![
+            "linux_x86_64_llvm".to_string(),
+            "linux_ohos_aarch64_llvm".to_string(),
+        ];
+        let root = Path::new("/opt/cangjie");
+        let ohos = SdkLayout::probe_with(root, |p| p.ends_with("tools/bin"), &platforms);
+        assert_eq!(ohos.flavor, SdkFlavor::Ohos);
+        assert!(
+            ohos.bin_dirs()
+                .ends_with(&[PathBuf::from("/opt/cangjie/third_party/llvm/bin")])
+        );
+        assert!(ohos.flavor.target_triples().contains(&"aarch64-linux-ohos"));
+        assert_eq!(ohos.task_env(Os::Linux, |_| None)[0].0, "CANGJIE_HOME");
+
+        let deveco = Path::new("/home/dev/sdk/default/openharmony/cangjie");
+        assert_eq!(SdkFlavor::detect(deveco, &[]), SdkFlavor::Ohos);
+        assert_eq!(SdkFlavor::from_setting("Auto"), Ok(None));
+        assert_eq!(
+            SdkFlavor::from_setting("HarmonyOS"),
+            Ok(Some(SdkFlavor::Ohos))
+        );
+        assert!(SdkFlavor::from_setting("embedded").is_err());
+    }
+}
@@ -0,0 +1,190 @@
+// src/smoke.rs
+//! The hello-world project and language server session used by `/cangjie-smoke`.
+//!
+//! The smoke test exercises the whole toolchain the way a new project would: `cjpm init`,
+//! build, run, a unit test and optionally a language server `initialize` round trip. Each
+//! stage depends on the previous one, so the first failure marks the rest as skipped.
+//!
+//! `/cangjie-lsp-health` replays the same language server session against the worktree.
+
+use serde_json::json;
+
+/// Directory in the extension's working directory holding the smoke test project
+pub const PROJECT_DIR: &str = "smoke-test";
+
+/// Package name passed to `cjpm init`
+pub const PACKAGE_NAME: &str = "cangjie_smoke";
+
+/// Line the smoke test program prints
+pub const EXPECTED_OUTPUT: &str = "cangjie smoke test ok";
+
+/// File holding the language server session, fed to the server's standard input
+pub const LSP_SESSION_FILE: &str = "lsp-session.txt";
+
+/// File in the extension's working directory holding the `/cangjie-lsp-health` session
+pub const HEALTH_SESSION_FILE: &str = "lsp-health-session.txt";
+
+/// `src/main.cj` of the smoke test project
+pub fn main_source() -> String {
+    format!(
+        "package {}\n\nmain(): Int64 {{\n    println(\"{}\")\n    return 0\n}}\n",
+        PACKAGE_NAME, EXPECTED_OUTPUT
+    )
+}
+
+/// `src/smoke_test.cj` of the smoke test project
+pub fn test_source() -> String {
+    format!(
+        "package {}\n\nimport std.unittest.*\nimport std.unittest.testmacro.*\n\n\
+         @Test\nclass SmokeTest {{\n    @TestCase\n    func addition(): Unit {{\n        \
+         @Expect(1 + 1, 2)\n    }}\n}}\n",
+        PACKAGE_NAME
+    )
+}
+
+/// A stage of the smoke test, in execution order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Create,
+    Build,
+    Run,
+    Test,
+    LanguageServer,
+}
+
+impl Stage {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Stage::Create => "Create Project",
+            Stage::Build => "Build",
+            Stage::Run => "Run",
+            Stage::Test => "Unit Test",
+            Stage::LanguageServer => "Language Server",
+        }
+    }
+}
+
+/// Returns the `file://` URI of an absolute path, percent-encoding spaces and non-ASCII
+/// characters
+pub fn file_uri(path: &str) -> String {
+    let path: String = path
+        .replace('\\', "/")
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        format!("file:///{}", path)
+    }
+}
+
+/// Frames a JSON-RPC message with its `Content-Length` header
+fn frame(message: serde_json::Value) -> String {
+    let body = message.to_string();
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+/// Builds a language server session: `initialize`, `initialized`, `shutdown` and `exit`.
+///
+/// # Arguments
+/// * `root` - Absolute path of the project the server is initialized for
+///
+/// # Returns
+/// * `String` - The framed messages, to be written to the server's standard input
+pub fn lsp_session(root: &str) -> String {
+    let uri = file_uri(root);
+    [
+        json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "processId": null,
+                "rootUri": uri,
+                "capabilities": {},
+                "workspaceFolders": [{ "uri": uri, "name": PACKAGE_NAME }],
+            },
+        }),
+        json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ]
+    .into_iter()
+    .map(frame)
+    .collect()
+}
+
+/// Returns the capabilities of the `initialize` response in server output, if any
+pub fn lsp_capabilities(stdout: &str) -> Option<serde_json::Map<String, serde_json::Value>> {
+    stdout
+        .split("Content-Length:")
+        .filter_map(|message| message.find('{').map(|start| &message[start..]))
+        .filter_map(|body| serde_json::from_str::<serde_json::Value>(body.trim()).ok())
+        .filter(|message| message["id"] == 1)
+        .find_map(|message| message["result"]["capabilities"].as_object().cloned())
+}
+
+/// Returns true if server output contains the `initialize` response with capabilities
+pub fn lsp_initialized(stdout: &str) -> bool {
+    lsp_capabilities(stdout).is_some()
+}
+
+/// Lists the capabilities a server enabled, sorted, leaving out `false` and `null` entries
+pub fn enabled_capabilities(
+    capabilities: &serde_json::Map<String, serde_json::Value>,
+) -> Vec<String> {
+    let mut names: Vec<String> = capabilities
+        .iter()
+        .filter(|(_, value)| {
+            !matches!(
+                value,
+                serde_json::Value::Null | serde_json::Value::Bool(false)
+            )
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lsp_session() {
+        // 测试语言服务器会话的消息帧与 initialize 响应识别
+        let session = lsp_session("/tmp/smoke");
+        assert!(session.starts_with("Content-Length: "));
+        assert!(session.contains(r#""rootUri":"file:///tmp/smoke""#));
+        assert_eq!(session.matches("Content-Length").count(), 4);
+        assert_eq!(file_uri(r"C:\smoke"), "file:///C:/smoke");
+        assert_eq!(
+            file_uri("/tmp/仓颉 smoke"),
+            "file:///tmp/%E4%BB%93%E9%A2%89%20smoke"
+        );
+
+        let response =
+            r#"{"jsonrpc":"2.0","id":1,"result":{"capabilities":{"hoverProvider":true}}}"#;
+        let stdout = format!(
+            "Content-Length: {}\r\n\r\n{}Content-Length: 38\r\n\r\n{{\"jsonrpc\":\"2.0\",\"id\":2,\"result\":null}}",
+            response.len(),
+            response
+        );
+        assert!(lsp_initialized(&stdout));
+        let capabilities =
+            json!({ "hoverProvider": true, "renameProvider": false, "textDocumentSync": 1 });
+        assert_eq!(
+            enabled_capabilities(capabilities.as_object().unwrap()),
+            ["hoverProvider", "textDocumentSync"]
+        );
+        assert!(!lsp_initialized("error: failed to load stdlib"));
+        assert!(main_source().contains(EXPECTED_OUTPUT));
+    }
+}
@@ -0,0 +1,249 @@
+// src/snapshot.rs
+//! Snapshot (golden file) review for `/cangjie-test --update-snapshots`.
+//!
+//! Tests run with [`UPDATE_ENV`] set write changed snapshots next to the accepted ones with a
+//! [`PENDING_SUFFIX`] suffix. The extension diffs the pending files against the accepted
+//! versions and replaces them only when the user runs `/cangjie-test --accept-snapshots`.
+
+use zed_extension_api::Os;
+
+use crate::paths::{encoded_powershell, powershell_literal};
+
+/// Directory holding the snapshot files, relative to the worktree root
+pub const SNAPSHOT_DIR: &str = "tests/__snapshots__";
+
+/// Environment variable telling the snapshot helper library to write pending snapshots
+pub const UPDATE_ENV: &str = "CANGJIE_UPDATE_SNAPSHOTS";
+
+/// Environment variable carrying the absolute snapshot directory
+pub const DIR_ENV: &str = "CANGJIE_SNAPSHOT_DIR";
+
+/// Suffix of snapshot files waiting for review
+pub const PENDING_SUFFIX: &str = ".new";
+
+/// Maximum number of diff lines shown per snapshot
+const MAX_DIFF_LINES: usize = 200;
+
+/// A snapshot written by a test run but not yet accepted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingSnapshot {
+    /// Path of the pending file, relative to the worktree root
+    pub pending: String,
+    /// Path of the accepted snapshot it replaces, relative to the worktree root
+    pub target: String,
+}
+
+/// Returns the environment passed to tests when updating snapshots.
+///
+/// # Arguments
+/// * `root` - The worktree root
+pub fn update_env(root: &str) -> Vec<(String, String)> {
+    vec![
+        (UPDATE_ENV.to_string(), "1".to_string()),
+        (DIR_ENV.to_string(), format!("{}/{}", root, SNAPSHOT_DIR)),
+    ]
+}
+
+/// Returns the command listing the pending snapshot files, run in the worktree root.
+///
+/// # Arguments
+/// * `os` - The current platform
+///
+/// # Returns
+/// * `(String, Vec<String>)` - The program and its arguments
+pub fn list_command(os: Os) -> (String, Vec<String>) {
+    match os {
+        Os::Windows => (
+            "cmd".to_string(),
+            vec![
+                "/S".to_string(),
+                "/C".to_string(),
+                format!(
+                    "dir /s /b \"{}\\*{}\"",
+                    SNAPSHOT_DIR.replace('/', "\\"),
+                    PENDING_SUFFIX
+                ),
+            ],
+        ),
+        _ => (
+            "find".to_string(),
+            vec![
+                SNAPSHOT_DIR.to_string(),
+                "-type".to_string(),
+                "f".to_string(),
+                "-name".to_string(),
+                format!("*{}", PENDING_SUFFIX),
+            ],
+        ),
+    }
+}
+
+/// Parses the output of [`list_command`].
+///
+/// # Arguments
+/// * `listing` - The command output, one path per line
+/// * `root` - The worktree root, stripped from absolute paths
+///
+/// # Returns
+/// * `Vec<PendingSnapshot>` - The pending snapshots, sorted by path
+pub fn parse_pending(listing: &str, root: &str) -> Vec<PendingSnapshot> {
+    let mut pending: Vec<PendingSnapshot> = listing
+        .lines()
+        .map(|line| line.trim().replace('\\', "/"))
+        .filter(|line| line.ends_with(PENDING_SUFFIX))
+        .map(|line| {
+            let root = root.replace('\\', "/");
+            let relative = line
+                .strip_prefix(&format!("{}/", root.trim_end_matches('/')))
+                .map(str::to_string)
+                .unwrap_or(line.clone());
+            let relative = relative.trim_start_matches("./").to_string();
+            PendingSnapshot {
+                target: relative[..relative.len() - PENDING_SUFFIX.len()].to_string(),
+                pending: relative,
+            }
+        })
+        .collect();
+    pending.sort_by(|a, b| a.pending.cmp(&b.pending));
+    pending.dedup();
+    pending
+}
+
+/// Returns the command replacing the accepted snapshots with the pending ones.
+///
+/// # Arguments
+/// * `os` - The current platform
+/// * `snapshots` - The snapshots to accept
+///
+/// # Returns
+/// * `(String, Vec<String>)` - The program and its arguments, run in the worktree root
+pub fn accept_command(os: Os, snapshots: &[PendingSnapshot]) -> (String, Vec<String>) {
+    match os {
+        Os::Windows => {
+            // Paths are PowerShell literals in an encoded script, so no shell re-parses them
+            let mut script = vec!["$ErrorActionPreference = 'Stop'".to_string()];
+            script.extend(snapshots.iter().map(|s| {
+                format!(
+                    "Move-Item -LiteralPath {} -Destination {} -Force",
+                    powershell_literal(&s.pending.replace('/', "\\")),
+                    powershell_literal(&s.target.replace('/', "\\"))
+                )
+            }));
+            (
+                "powershell".to_string(),
+                encoded_powershell(&script.join("\n")),
+            )
+        }
+        _ => {
+            // Each pair is passed as positional arguments so paths are never re-parsed
+            const SCRIPT: &str =
+                r#"while [ "$#" -gt 1 ]; do mv -f -- "$1" "$2" || exit 1; shift 2; done"#;
+            let mut args = vec![
+                "-c".to_string(),
+                SCRIPT.to_string(),
+                "cangjie-snapshots".to_string(),
+            ];
+            for s in snapshots {
+                args.push(s.pending.clone());
+                args.push(s.target.clone());
+            }
+            ("sh".to_string(), args)
+        }
+    }
+}
+
+/// Produces a line diff between the accepted and the pending snapshot.
+///
+/// Lines only in `old` are prefixed with `-`, lines only in `new` with `+` and common lines
+/// with a space. Long diffs are truncated.
+///
+/// # Arguments
+/// * `old` - The accepted snapshot (empty for new snapshots)
+/// * `new` - The pending snapshot
+///
+/// # Returns
+/// * `String` - The diff
+pub fn line_diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Longest common subsequence table, filled from the end
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(format!(" {}", old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(format!("-{}", old[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+{}", new[j]));
+            j += 1;
+        }
+    }
+
+    if lines.len() > MAX_DIFF_LINES {
+        let omitted = lines.len() - MAX_DIFF_LINES;
+        lines.truncate(MAX_DIFF_LINES);
+        lines.push(format!("... {} more lines", omitted));
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pending() {
+        // 测试解析待确认的快照文件列表
+        let listing = "tests/__snapshots__/calc.snap.new\n\
+                       /work/app/tests/__snapshots__/io/read.snap.new\n\
+                       tests/__snapshots__/calc.snap\n";
+        let pending = parse_pending(listing, "/work/app");
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].target, "tests/__snapshots__/calc.snap");
+        assert_eq!(pending[1].pending, "tests/__snapshots__/io/read.snap.new");
+
+        let (program, args) = accept_command(Os::Linux, &pending);
+        assert_eq!(program, "sh");
+        assert_eq!(
+            &args[3..5],
+            [
+                "tests/__snapshots__/calc.snap.new",
+                "tests/__snapshots__/calc.snap"
+            ]
+        );
+        let (program, args) = accept_command(Os::Windows, &pending[..1]);
+        assert_eq!(program, "powershell");
+        assert_eq!(
+            args,
+            encoded_powershell(
+                "$ErrorActionPreference = 'Stop'\n\
+                 Move-Item -LiteralPath 'tests\\__snapshots__\\calc.snap.new' \
+                 -Destination 'tests\\__snapshots__\\calc.snap' -Force"
+            )
+        );
+    }
+
+    #[test]
+    fn test_line_diff() {
+        // 测试快照内容的行级差异
+        let diff = line_diff("a\nb\nc\n", "a\nB\nc\nd\n");
+        assert_eq!(diff, " a\n-b\n+B\n c\n+d");
+        assert_eq!(line_diff("", "x"), "+x");
+    }
+}
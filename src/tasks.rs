@@ -0,0 +1,462 @@
+// src/tasks.rs
+//! The task templates shipped with the extension.
+//!
+//! Zed offers the templates in `languages/cangjie/tasks.json` in every Cangjie worktree. They
+//! run `cjpm` and `cjc` from the shell's `PATH`; `/cangjie-tasks` prints the same templates
+//! with the resolved SDK's environment for projects whose shell does not set it up, plus one
+//! task per entry of the package's `[scripts]`.
+
+use serde_json::{Value, json};
+use zed_extension_api::Os;
+
+use crate::build::{BuildProfile, CrossTarget};
+use crate::paths::{powershell_literal, quote_posix_arg};
+
+/// The task templates Zed offers for Cangjie files
+pub const TEMPLATES: &str = include_str!("../languages/cangjie/tasks.json");
+
+/// Returns the built-in task templates
+pub fn builtin_templates() -> Vec<Value> {
+    serde_json::from_str::<Vec<Value>>(TEMPLATES).unwrap_or_default()
+}
+
+/// Adapts the templates to a platform: on Windows, templates chaining commands with `&&` run
+/// in cmd.
+///
+/// # Arguments
+/// * `templates` - The task templates
+/// * `os` - The current platform
+///
+/// # Returns
+/// * `Vec<Value>` - The templates for the platform
+pub fn for_platform(mut templates: Vec<Value>, os: Os) -> Vec<Value> {
+    if os != Os::Windows {
+        return templates;
+    }
+    for template in &mut templates {
+        let chained = template["command"]
+            .as_str()
+            .is_some_and(|command| command.contains(" && "));
+        if chained {
+            template["shell"] = json!({ "with_arguments": { "program": "cmd", "args": ["/C"] } });
+        }
+    }
+    templates
+}
+
+/// Returns the template compiling the current file next to it and running the executable, for
+/// scratch files and exercises outside a package.
+///
+/// No command line chains two commands in every shell, so the template is not shipped in
+/// `tasks.json` but written for the platform's default task shell: POSIX shells on macOS and
+/// Linux, PowerShell on Windows.
+///
+/// # Arguments
+/// * `os` - The current platform
+///
+/// # Returns
+/// * `Value` - The task template
+pub fn single_file_run_template(os: Os) -> Value {
+    let command = match os {
+        Os::Windows => "cjc \"$ZED_FILE\" -o \"$ZED_STEM.exe\"; if ($?) { & \".\\$ZED_STEM.exe\" }",
+        _ => "cjc \"$ZED_FILE\" -o \"$ZED_STEM\" && \"./$ZED_STEM\"",
+    };
+    json!({
+        "label": "cjc run $ZED_FILENAME",
+        "command": command,
+        "cwd": "$ZED_DIRNAME",
+    })
+}
+
+/// Tags of the templates that get a variant per build profile
+const PROFILE_TAGS: &[&str] = &["cangjie-build", "cangjie-run", "cangjie-test"];
+
+/// Adds a debug and a release variant after each build, run and test template.
+///
+/// The variants are labeled with the profile, e.g. `cjpm build (release)`, and keep the
+/// template's tags. The templates themselves build with the default profile.
+///
+/// # Arguments
+/// * `templates` - The task templates
+/// * `default_profile` - The profile from `cangjie.build.defaultProfile`
+///
+/// # Returns
+/// * `Vec<Value>` - The templates with their variants
+pub fn profile_variants(templates: Vec<Value>, default_profile: BuildProfile) -> Vec<Value> {
+    let mut result = Vec::new();
+    for template in templates {
+        let has_profiles = template["tags"]
+            .as_array()
+            .is_some_and(|tags| tags.iter().any(|tag| PROFILE_TAGS.iter().any(|t| tag == t)));
+        if !has_profiles {
+            result.push(template);
+            continue;
+        }
+        let label = template["label"].as_str().unwrap_or_default().to_string();
+        let variants: Vec<Value> = BuildProfile::ALL
+            .iter()
+            .map(|profile| {
+                let mut variant = with_args(&template, profile.flags());
+                variant["label"] = Value::String(format!("{} ({})", label, profile.name()));
+                variant
+            })
+            .collect();
+        if default_profile == BuildProfile::Release {
+            result.push(with_args(&template, default_profile.flags()));
+        } else {
+            result.push(template);
+        }
+        result.extend(variants);
+    }
+    result
+}
+
+/// Returns a copy of a template with arguments appended
+fn with_args(template: &Value, args: Vec<String>) -> Value {
+    let mut template = template.clone();
+    if let Some(template_args) = template["args"].as_array_mut() {
+        template_args.extend(args.into_iter().map(Value::String));
+    }
+    template
+}
+
+/// Tag of the build tasks for cross-compilation targets
+pub const CROSS_BUILD_TAG: &str = "cangjie-cross-build";
+
+/// Adds a variant per cross-compilation target after the `cjpm build` template and the
+/// single-file cjc template.
+///
+/// The variants are labeled with the target triple, e.g. `cjpm build (aarch64-linux-ohos)`,
+/// and tagged [`CROSS_BUILD_TAG`] only, so they get no profile variants of their own.
+///
+/// # Arguments
+/// * `templates` - The task templates
+/// * `targets` - The targets from `cangjie.build.targets`
+///
+/// # Returns
+/// * `Vec<Value>` - The templates with their variants
+pub fn target_variants(templates: Vec<Value>, targets: &[CrossTarget]) -> Vec<Value> {
+    let mut result = Vec::new();
+    for template in templates {
+        let is_build = template["tags"]
+            .as_array()
+            .is_some_and(|tags| tags.iter().any(|tag| tag == "cangjie-build"));
+        let target_args: fn(&CrossTarget) -> Vec<String> = match template["command"].as_str() {
+            Some("cjpm") if is_build => CrossTarget::cjpm_args,
+            Some("cjc") => CrossTarget::cjc_args,
+            _ => {
+                result.push(template);
+                continue;
+            }
+        };
+        let label = template["label"].as_str().unwrap_or_default().to_string();
+        let variants: Vec<Value> = targets
+            .iter()
+            .map(|target| {
+                let mut variant = with_args(&template, target_args(target));
+                variant["label"] = Value::String(format!("{} ({})", label, target.triple));
+                variant["tags"] = json!([CROSS_BUILD_TAG]);
+                variant
+            })
+            .collect();
+        result.push(template);
+        result.extend(variants);
+    }
+    result
+}
+
+/// Tag of the tasks deploying a program to an OHOS device
+pub const OHOS_DEPLOY_TAG: &str = "cangjie-ohos-deploy";
+
+/// Directory on the device that programs are pushed to
+const DEVICE_DIR: &str = "/data/local/tmp";
+
+/// Returns task templates deploying a program to an OHOS device with hdc: one building it
+/// for the target and pushing it to the device, and one also launching it there.
+///
+/// The commands are written for the platform's default task shell, like
+/// [`single_file_run_template`]: POSIX shells chain the steps with `&&`, PowerShell with
+/// `if ($?)`. Every word is quoted for that shell, and the remote path is quoted once more
+/// for the device's shell, which `hdc shell` hands it to.
+///
+/// # Arguments
+/// * `program` - The executable cjpm builds, relative to the worktree root
+/// * `triple` - The target to build for, e.g. `aarch64-linux-ohos`
+/// * `build_flags` - Further `cjpm build` flags, e.g. of the default profile
+/// * `device` - The device serial from `cangjie.ohos.device`; without one, hdc uses the only
+///   connected device
+/// * `os` - The current platform
+///
+/// # Returns
+/// * `Vec<Value>` - The templates, tagged [`OHOS_DEPLOY_TAG`]
+pub fn hdc_templates(
+    program: &str,
+    triple: &str,
+    build_flags: &[String],
+    device: Option<&str>,
+    os: Os,
+) -> Vec<Value> {
+    let command = |words: &[&str]| {
+        let words: Vec<String> = words.iter().map(|word| quote_word(os, word)).collect();
+        words.join(" ")
+    };
+    let hdc = |words: &[&str]| match device {
+        Some(device) => command(&[&["hdc", "-t", device], words].concat()),
+        None => command(&[&["hdc"], words].concat()),
+    };
+    let name = program.rsplit('/').next().unwrap_or(program);
+    let remote = format!("{}/{}", DEVICE_DIR, name);
+    let device_remote = quote_posix_arg(&remote);
+    let mut build = vec!["cjpm", "build", "--target", triple];
+    build.extend(build_flags.iter().map(String::as_str));
+    let deploy = [
+        command(&build),
+        hdc(&["file", "send", program, &remote]),
+        hdc(&["shell", "chmod", "+x", &device_remote]),
+    ];
+    let run = [deploy.as_slice(), &[hdc(&["shell", &device_remote])]].concat();
+    [("deploy", chain(os, &deploy)), ("run", chain(os, &run))]
+        .into_iter()
+        .map(|(action, command)| {
+            json!({
+                "label": format!("hdc {} {}", action, name),
+                "command": command,
+                "cwd": "$ZED_WORKTREE_ROOT",
+                "tags": [OHOS_DEPLOY_TAG],
+            })
+        })
+        .collect()
+}
+
+/// Quotes one word of a task command for the platform's default task shell
+fn quote_word(os: Os, word: &str) -> String {
+    let bare = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=+".contains(c));
+    match os {
+        Os::Windows if bare => word.to_string(),
+        Os::Windows => powershell_literal(word),
+        _ => quote_posix_arg(word),
+    }
+}
+
+/// Chains commands so each runs only if the previous one succeeded, in the platform's
+/// default task shell
+fn chain(os: Os, commands: &[String]) -> String {
+    match os {
+        Os::Windows => commands.iter().rev().fold(String::new(), |rest, command| {
+            if rest.is_empty() {
+                command.clone()
+            } else {
+                format!("{}; if ($?) {{ {} }}", command, rest)
+            }
+        }),
+        _ => commands.join(" && "),
+    }
+}
+
+/// Tag of the tasks running the scripts of a package manifest
+pub const SCRIPT_TAG: &str = "cangjie-script";
+
+/// Returns task templates running the `[scripts]` of a package manifest in the worktree root.
+///
+/// # Arguments
+/// * `scripts` - Script names and their shell commands
+///
+/// # Returns
+/// * `Vec<Value>` - One template per script, labeled `cjpm script: <name>`
+pub fn script_templates(scripts: &[(String, String)]) -> Vec<Value> {
+    scripts
+        .iter()
+        .map(|(name, command)| {
+            json!({
+                "label": format!("cjpm script: {}", name),
+                "command": command,
+                "cwd": "$ZED_WORKTREE_ROOT",
+                "tags": [SCRIPT_TAG],
+            })
+        })
+        .collect()
+}
+
+/// Adds environment variables to task templates, keeping variables a template sets itself.
+///
+/// # Arguments
+/// * `templates` - The task templates
+/// * `env` - The variables to add
+///
+/// # Returns
+/// * `Vec<Value>` - The templates with an `env` object
+pub fn with_env(templates: Vec<Value>, env: &[(String, String)]) -> Vec<Value> {
+    templates
+        .into_iter()
+        .map(|mut template| {
+            if !template["env"].is_object() {
+                template["env"] = Value::Object(Default::default());
+            }
+            if let Some(template_env) = template["env"].as_object_mut() {
+                for (key, value) in env {
+                    template_env
+                        .entry(key.clone())
+                        .or_insert_with(|| Value::String(value.clone()));
+                }
+            }
+            template
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_templates() {
+        // 测试内置任务模板覆盖构建、运行、测试、清理与单文件编译
+        let templates = builtin_templates();
+        let labels: Vec<&str> = templates
+            .iter()
+            .filter_map(|template| template["label"].as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                "cjpm build",
+                "cjpm run",
+                "cjpm test",
+                "cjpm clean",
+                "cjc $ZED_FILENAME"
+            ]
+        );
+        assert!(templates.iter().all(|template| {
+            template["command"]
+                .as_str()
+                .is_some_and(|command| command == "cjpm" || command.starts_with("cjc"))
+        }));
+
+        let env = vec![("CANGJIE_HOME".to_string(), "/opt/cj".to_string())];
+        let templates = with_env(templates, &env);
+        assert_eq!(templates[0]["env"]["CANGJIE_HOME"], "/opt/cj");
+        assert_eq!(templates[4]["cwd"], "$ZED_DIRNAME");
+
+        // 测试构建、运行与测试模板各生成 debug 与 release 变体
+        let templates = profile_variants(builtin_templates(), BuildProfile::Release);
+        assert_eq!(templates.len(), 11);
+        assert_eq!(templates[0]["args"], json!(["build", "-O2"]));
+        assert_eq!(templates[1]["label"], "cjpm build (debug)");
+        assert_eq!(templates[1]["args"], json!(["build", "-g"]));
+        assert_eq!(templates[2]["label"], "cjpm build (release)");
+        assert_eq!(templates[5]["tags"], json!(["cangjie-run"]));
+        let templates = profile_variants(builtin_templates(), BuildProfile::Debug);
+        assert_eq!(templates[0]["args"], json!(["build"]));
+
+        // 测试交叉编译目标为 cjpm build 与单文件 cjc 模板生成变体
+        let targets = vec![CrossTarget {
+            triple: "aarch64-linux-ohos".to_string(),
+            sysroot: Some("/opt/sysroot".to_string()),
+        }];
+        let templates = target_variants(builtin_templates(), &targets);
+        assert_eq!(templates.len(), 7);
+        assert_eq!(templates[1]["label"], "cjpm build (aarch64-linux-ohos)");
+        assert_eq!(
+            templates[1]["args"],
+            json!(["build", "--target", "aarch64-linux-ohos"])
+        );
+        assert_eq!(templates[1]["tags"], json!([CROSS_BUILD_TAG]));
+        assert_eq!(templates[6]["args"][3], "--target");
+        assert_eq!(templates[6]["args"][6], "/opt/sysroot");
+        let templates = profile_variants(templates, BuildProfile::Debug);
+        assert_eq!(templates.len(), 13);
+        assert_eq!(templates[3]["label"], "cjpm build (aarch64-linux-ohos)");
+
+        // 测试单文件运行任务在文件旁编译，并按平台默认 shell 串联运行
+        let template = single_file_run_template(Os::Linux);
+        assert_eq!(
+            template["command"],
+            "cjc \"$ZED_FILE\" -o \"$ZED_STEM\" && \"./$ZED_STEM\""
+        );
+        assert_eq!(template["cwd"], "$ZED_DIRNAME");
+        let template = single_file_run_template(Os::Windows);
+        assert!(
+            template["command"]
+                .as_str()
+                .unwrap()
+                .ends_with("; if ($?) { & \".\\$ZED_STEM.exe\" }")
+        );
+        assert_eq!(
+            for_platform(vec![template.clone()], Os::Windows),
+            vec![template]
+        );
+        assert_eq!(
+            for_platform(builtin_templates(), Os::Mac),
+            builtin_templates()
+        );
+
+        // 测试通过 hdc 部署到 OHOS 设备的任务
+        let templates = hdc_templates(
+            "target/aarch64-linux-ohos/debug/bin/hello",
+            "aarch64-linux-ohos",
+            &[],
+            Some("FMR0223"),
+            Os::Linux,
+        );
+        assert_eq!(templates[0]["label"], "hdc deploy hello");
+        assert_eq!(
+            templates[0]["command"],
+            "cjpm build --target aarch64-linux-ohos && hdc -t FMR0223 file send \
+             target/aarch64-linux-ohos/debug/bin/hello /data/local/tmp/hello && \
+             hdc -t FMR0223 shell chmod +x /data/local/tmp/hello"
+        );
+        assert!(
+            templates[1]["command"]
+                .as_str()
+                .unwrap()
+                .ends_with("&& hdc -t FMR0223 shell /data/local/tmp/hello")
+        );
+        // 测试设备序列号、程序路径与远程路径中的空格和特殊字符被引用
+        let templates = hdc_templates(
+            "out dir/bin/my app",
+            "aarch64-linux-ohos",
+            &[],
+            Some("192.168.0.2:5555 $(id)"),
+            Os::Mac,
+        );
+        assert_eq!(
+            templates[1]["command"],
+            concat!(
+                "cjpm build --target aarch64-linux-ohos && ",
+                "hdc -t '192.168.0.2:5555 $(id)' file send 'out dir/bin/my app' ",
+                "'/data/local/tmp/my app' && ",
+                "hdc -t '192.168.0.2:5555 $(id)' shell chmod +x ",
+                r"''\''/data/local/tmp/my app'\''' && ",
+                r"hdc -t '192.168.0.2:5555 $(id)' shell ''\''/data/local/tmp/my app'\'''"
+            )
+        );
+        // 测试 Windows 下以 PowerShell 串联并引用
+        let templates = for_platform(
+            hdc_templates(
+                "my app",
+                "x86_64-linux-ohos",
+                &["-O2".to_string()],
+                None,
+                Os::Windows,
+            ),
+            Os::Windows,
+        );
+        assert_eq!(
+            templates[0]["command"],
+            "cjpm build --target x86_64-linux-ohos -O2; if ($?) { \
+             hdc file send 'my app' '/data/local/tmp/my app'; if ($?) { \
+             hdc shell chmod +x '''/data/local/tmp/my app''' } }"
+        );
+        assert!(templates[1].get("shell").is_none());
+
+        // 测试 cjpm.toml 中的脚本生成任务
+        let scripts = vec![("lint".to_string(), "cjlint -f src".to_string())];
+        let templates = script_templates(&scripts);
+        assert_eq!(templates[0]["label"], "cjpm script: lint");
+        assert_eq!(templates[0]["command"], "cjlint -f src");
+        assert_eq!(templates[0]["tags"][0], SCRIPT_TAG);
+    }
+}
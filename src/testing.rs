@@ -0,0 +1,1245 @@
+// src/testing.rs
+//! Helpers for running Cangjie unit tests from the editor.
+
+use std::collections::BTreeMap;
+
+use serde_json::{Value, json};
+use zed_extension_api::Os;
+
+/// Address used to black-hole proxied traffic (discard port on loopback)
+const BLACKHOLE_PROXY: &str = "http://127.0.0.1:9";
+
+/// macOS sandbox profile denying all outbound traffic to remote IP addresses
+const SANDBOX_PROFILE: &str = "(version 1)(allow default)(deny network-outbound (remote ip))";
+
+/// Test binary produced by `cjpm test --no-run`, relative to the package root
+pub const TEST_BINARY_PATH: &str = "target/debug/unittest_bin/main";
+
+/// cjc option compiling the unit tests of the given sources into a test binary
+pub const CJC_TEST_FLAG: &str = "--test";
+
+/// Test binary `cjc --test` writes without `-o`, relative to its working directory
+pub const CJC_TEST_OUTPUT: &str = "main";
+
+/// Number of test runs kept in the seed history
+pub const SEED_HISTORY_LIMIT: usize = 20;
+
+/// Most runs `--repeat` accepts, since every run is one `cjpm test` invocation
+const MAX_REPEAT: u32 = 100;
+
+/// Arguments accepted by `/cangjie-test`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestArgs {
+    /// Number of times to run the tests (`--repeat N`)
+    pub repeat: u32,
+    /// Stop repeating after the first failing run (`--until-failure`)
+    pub until_failure: bool,
+    /// Test to run, forwarded as `--filter`
+    pub filter: Option<String>,
+    /// Shuffle seed (`--seed N`); a fresh seed is chosen for every run when absent
+    pub seed: Option<u64>,
+    /// Show the seed history instead of running the tests (`--history`)
+    pub show_history: bool,
+    /// Let tests write changed snapshots for review (`--update-snapshots`)
+    pub update_snapshots: bool,
+    /// Accept the pending snapshots instead of running the tests (`--accept-snapshots`)
+    pub accept_snapshots: bool,
+    /// Value forwarded as `--parallel`, from `cangjie.test.parallelism`
+    pub parallel: Option<String>,
+    /// Remaining arguments, forwarded to `cjpm test` unchanged
+    pub passthrough: Vec<String>,
+}
+
+impl Default for TestArgs {
+    fn default() -> Self {
+        Self {
+            repeat: 1,
+            until_failure: false,
+            filter: None,
+            seed: None,
+            show_history: false,
+            update_snapshots: false,
+            accept_snapshots: false,
+            parallel: None,
+            passthrough: Vec::new(),
+        }
+    }
+}
+
+impl TestArgs {
+    /// Builds the `cjpm` arguments for one test run
+    ///
+    /// # Arguments
+    /// * `seed` - The shuffle seed for this run
+    pub fn cjpm_args(&self, seed: Option<u64>) -> Vec<String> {
+        let mut args = vec!["test".to_string()];
+        args.extend(self.passthrough.iter().cloned());
+        if let Some(filter) = &self.filter {
+            args.push(format!("--filter={}", filter));
+        }
+        if let Some(parallel) = &self.parallel {
+            args.push(format!("--parallel={}", parallel));
+        }
+        if let Some(seed) = seed {
+            args.push(format!("--random-seed={}", seed));
+        }
+        args
+    }
+
+    /// Builds the arguments of a test binary compiled with `cjc --test`, for one test run.
+    ///
+    /// Options meant for `cjpm test` are not passed on.
+    ///
+    /// # Arguments
+    /// * `seed` - The shuffle seed for this run
+    pub fn binary_args(&self, seed: Option<u64>) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(filter) = &self.filter {
+            args.push(format!("--filter={}", filter));
+        }
+        if let Some(seed) = seed {
+            args.push(format!("--random-seed={}", seed));
+        }
+        args
+    }
+
+    /// Returns the options only `cjpm test` understands, as given, so a run of a test binary
+    /// compiled with `cjc --test` can report that it ignores them.
+    pub fn cjpm_only_options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        if self.repeat > 1 {
+            options.push(format!("--repeat {}", self.repeat));
+        }
+        if self.until_failure {
+            options.push("--until-failure".to_string());
+        }
+        if let Some(parallel) = &self.parallel {
+            options.push(format!("--parallel={}", parallel));
+        }
+        options.extend(self.passthrough.iter().cloned());
+        options
+    }
+
+    /// Returns the `/cangjie-test` invocation that replays a run with the given seed
+    pub fn replay_command(&self, seed: u64) -> String {
+        replay_command(self.filter.as_deref(), seed)
+    }
+}
+
+/// Returns the `/cangjie-test` invocation that replays a run
+///
+/// # Arguments
+/// * `filter` - The test filter of the run, if any
+/// * `seed` - The shuffle seed of the run
+pub fn replay_command(filter: Option<&str>, seed: u64) -> String {
+    match filter {
+        Some(filter) => format!("/cangjie-test {} --seed {}", filter, seed),
+        None => format!("/cangjie-test --seed {}", seed),
+    }
+}
+
+/// Derives a shuffle seed for one run from a time-based entropy value (SplitMix64)
+///
+/// # Arguments
+/// * `entropy` - Entropy for the session, e.g. the current time in nanoseconds
+/// * `run` - The 1-based run number within the session
+pub fn derive_seed(entropy: u64, run: u32) -> u64 {
+    let mut z = entropy.wrapping_add(u64::from(run).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    // Keep seeds short enough to type back into `--seed`
+    (z ^ (z >> 31)) % 1_000_000_000
+}
+
+/// Parses `/cangjie-test` arguments.
+///
+/// A bare argument names the test (`Suite.case`, wildcards allowed) to run; options other
+/// than the ones handled here are forwarded to `cjpm test`.
+///
+/// # Arguments
+/// * `args` - The slash command arguments
+///
+/// # Returns
+/// * `Result<TestArgs, String>` - The parsed arguments or an error message
+pub fn parse_test_args(args: &[String]) -> Result<TestArgs, String> {
+    let mut parsed = TestArgs::default();
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--repeat" => {
+                let count = it.next().ok_or("`--repeat` requires a run count")?;
+                parsed.repeat = parse_repeat(count)?;
+            }
+            "--until-failure" => parsed.until_failure = true,
+            "--history" => parsed.show_history = true,
+            "--update-snapshots" => parsed.update_snapshots = true,
+            "--accept-snapshots" => parsed.accept_snapshots = true,
+            "--seed" => {
+                let seed = it.next().ok_or("`--seed` requires a seed value")?;
+                parsed.seed = Some(parse_seed_arg(seed)?);
+            }
+            other if other.starts_with("--repeat=") => {
+                parsed.repeat = parse_repeat(&other["--repeat=".len()..])?;
+            }
+            other if other.starts_with("--seed=") => {
+                parsed.seed = Some(parse_seed_arg(&other["--seed=".len()..])?);
+            }
+            other if other.starts_with('-') => parsed.passthrough.push(other.to_string()),
+            other => {
+                if parsed.filter.replace(other.to_string()).is_some() {
+                    return Err("Only one test name can be given".to_string());
+                }
+            }
+        }
+    }
+    Ok(parsed)
+}
+
+/// Parses a `--repeat` count, which must be between 1 and [`MAX_REPEAT`]
+fn parse_repeat(count: &str) -> Result<u32, String> {
+    match count.parse::<u32>() {
+        Ok(n) if n > MAX_REPEAT => Err(format!(
+            "Repeat count {} is too large; at most {} runs are allowed",
+            n, MAX_REPEAT
+        )),
+        Ok(n) if n >= 1 => Ok(n),
+        _ => Err(format!("Invalid repeat count: {}", count)),
+    }
+}
+
+/// Parallelism requested via `cangjie.test.parallelism`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parallelism {
+    /// A fixed number of workers
+    Workers(u32),
+    /// One worker per CPU core
+    Auto,
+}
+
+impl Parallelism {
+    /// Parses the setting value: a worker count, or `"auto"` for one worker per core.
+    ///
+    /// # Arguments
+    /// * `value` - The setting value
+    ///
+    /// # Returns
+    /// * `Result<Option<Parallelism>, String>` - `None` for serial execution, or an error for
+    ///   invalid values
+    pub fn from_setting(value: &Value) -> Result<Option<Self>, String> {
+        match value {
+            Value::Null | Value::Bool(false) => Ok(None),
+            Value::Bool(true) => Ok(Some(Self::Auto)),
+            Value::Number(n) => match n.as_u64() {
+                Some(0 | 1) => Ok(None),
+                Some(n) => Ok(Some(Self::Workers(n.min(u64::from(u32::MAX)) as u32))),
+                None => Err(format!("Invalid test parallelism: {}", n)),
+            },
+            Value::String(s) if s.eq_ignore_ascii_case("auto") => Ok(Some(Self::Auto)),
+            Value::String(s) => match s.trim().parse::<u64>() {
+                Ok(n) => Self::from_setting(&Value::from(n)),
+                Err(_) => Err(format!("Invalid test parallelism: {}", s)),
+            },
+            other => Err(format!("Invalid test parallelism: {}", other)),
+        }
+    }
+
+    /// Returns the value passed to `cjpm test --parallel`
+    pub fn flag_value(&self) -> String {
+        match self {
+            Self::Workers(n) => n.to_string(),
+            Self::Auto => "nCores".to_string(),
+        }
+    }
+}
+
+/// Returns true if `cjpm test --help` output advertises a `--parallel` option
+pub fn supports_parallel_flag(help: &str) -> bool {
+    help.contains("--parallel")
+}
+
+fn parse_seed_arg(seed: &str) -> Result<u64, String> {
+    seed.parse::<u64>()
+        .map_err(|_| format!("Invalid seed: {}", seed))
+}
+
+/// A test run recorded in the seed history
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeedRecord {
+    pub seed: u64,
+    pub passed: bool,
+    /// Test filter of the run, if any
+    pub filter: Option<String>,
+}
+
+impl SeedRecord {
+    /// Returns the `/cangjie-test` invocation that replays this run
+    pub fn replay_command(&self) -> String {
+        replay_command(self.filter.as_deref(), self.seed)
+    }
+}
+
+/// Parses the seed history, stored one `seed<TAB>passed<TAB>filter` record per line,
+/// oldest first.
+///
+/// # Arguments
+/// * `history` - The stored history
+///
+/// # Returns
+/// * `Vec<SeedRecord>` - The records; malformed lines are skipped
+pub fn parse_seed_history(history: &str) -> Vec<SeedRecord> {
+    history
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let seed = fields.next()?.parse().ok()?;
+            let passed = fields.next()? == "1";
+            let filter = fields.next().filter(|f| !f.is_empty()).map(str::to_string);
+            Some(SeedRecord {
+                seed,
+                passed,
+                filter,
+            })
+        })
+        .collect()
+}
+
+/// Serializes the seed history, keeping only the newest [`SEED_HISTORY_LIMIT`] records.
+///
+/// # Arguments
+/// * `records` - The records, oldest first
+///
+/// # Returns
+/// * `String` - The stored form of the history
+pub fn format_seed_history(records: &[SeedRecord]) -> String {
+    let skip = records.len().saturating_sub(SEED_HISTORY_LIMIT);
+    records[skip..]
+        .iter()
+        .map(|record| {
+            format!(
+                "{}\t{}\t{}",
+                record.seed,
+                if record.passed { 1 } else { 0 },
+                record.filter.as_deref().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Outcome of a single test case
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaseStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// Result of a single test case in a unittest report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseResult {
+    /// Test class (`TCS`) the case belongs to
+    pub suite: String,
+    /// Test case name
+    pub case: String,
+    pub status: CaseStatus,
+}
+
+impl CaseResult {
+    /// Returns the `Suite.case` name accepted by `--filter`
+    pub fn full_name(&self) -> String {
+        format!("{}.{}", self.suite, self.case)
+    }
+}
+
+/// Parses the per-case lines of a Cangjie unittest report.
+///
+/// Recognizes `TCS: <suite>, ...` headers followed by `[ PASSED ] CASE: <name>` lines
+/// (and their `FAILED` / `ERROR` / `SKIPPED` variants).
+///
+/// # Arguments
+/// * `output` - The test run's stdout
+///
+/// # Returns
+/// * `Vec<CaseResult>` - The parsed case results, in report order
+pub fn parse_test_report(output: &str) -> Vec<CaseResult> {
+    let mut results = Vec::new();
+    let mut suite = String::new();
+    for line in output.lines().map(str::trim) {
+        if let Some(rest) = line.strip_prefix("TCS:") {
+            suite = rest
+                .split(',')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            continue;
+        }
+        let Some(rest) = line.strip_prefix('[') else {
+            continue;
+        };
+        let Some((status, rest)) = rest.split_once(']') else {
+            continue;
+        };
+        let status = match status.trim() {
+            "PASSED" | "OK" => CaseStatus::Passed,
+            "FAILED" | "ERROR" => CaseStatus::Failed,
+            "SKIPPED" => CaseStatus::Skipped,
+            _ => continue,
+        };
+        let Some(case) = rest.trim().strip_prefix("CASE:") else {
+            continue;
+        };
+        let case = case
+            .split(['(', ' '])
+            .find(|s| !s.is_empty())
+            .unwrap_or_default();
+        results.push(CaseResult {
+            suite: suite.clone(),
+            case: case.to_string(),
+            status,
+        });
+    }
+    results
+}
+
+/// The output of one test suite in a unittest report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuiteReport {
+    /// Test class (`TCS`) name
+    pub suite: String,
+    /// The suite's lines of the report
+    pub output: String,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+impl SuiteReport {
+    /// Returns the section label, e.g. `CalcTest: 1 passed, 1 failed`
+    pub fn label(&self) -> String {
+        let mut counts = vec![format!("{} passed", self.passed)];
+        if self.failed > 0 {
+            counts.push(format!("{} failed", self.failed));
+        }
+        if self.skipped > 0 {
+            counts.push(format!("{} skipped", self.skipped));
+        }
+        format!("{}: {}", self.suite, counts.join(", "))
+    }
+}
+
+/// Splits a unittest report into the output of each test suite.
+///
+/// A suite's output runs from its `TCS:` header to the next header, or to the `Summary:`
+/// or `TP:` line after it.
+///
+/// # Arguments
+/// * `output` - The test run's stdout
+///
+/// # Returns
+/// * `(Vec<SuiteReport>, String)` - The suites in report order, and the lines outside them
+pub fn split_report(output: &str) -> (Vec<SuiteReport>, String) {
+    let mut suites: Vec<SuiteReport> = Vec::new();
+    let mut rest = String::new();
+    let mut in_suite = false;
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("TCS:") && !trimmed.contains("CASE:") {
+            let name = trimmed["TCS:".len()..]
+                .split(',')
+                .next()
+                .unwrap_or_default();
+            suites.push(SuiteReport {
+                suite: name.trim().to_string(),
+                output: String::new(),
+                passed: 0,
+                failed: 0,
+                skipped: 0,
+            });
+            in_suite = true;
+        } else if trimmed.starts_with("Summary:") || trimmed.starts_with("TP:") {
+            in_suite = false;
+        }
+        match suites.last_mut() {
+            Some(suite) if in_suite => {
+                suite.output.push_str(line);
+                suite.output.push('\n');
+            }
+            _ => {
+                rest.push_str(line);
+                rest.push('\n');
+            }
+        }
+    }
+    for suite in &mut suites {
+        for result in parse_test_report(&suite.output) {
+            match result.status {
+                CaseStatus::Passed => suite.passed += 1,
+                CaseStatus::Failed => suite.failed += 1,
+                CaseStatus::Skipped => suite.skipped += 1,
+            }
+        }
+    }
+    (suites, rest)
+}
+
+/// Extracts the random seed a test run reports (e.g. `random seed: 42`), if any.
+///
+/// # Arguments
+/// * `output` - The test run's output
+///
+/// # Returns
+/// * `Option<String>` - The seed value
+pub fn parse_seed(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let lower = line.to_ascii_lowercase();
+        let pos = lower.find("seed")?;
+        line[pos + 4..]
+            .split(|c: char| !c.is_ascii_digit())
+            .find(|token| !token.is_empty())
+            .map(str::to_string)
+    })
+}
+
+/// Summary of one run within a repeated test session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunRecord {
+    /// 1-based run number
+    pub index: u32,
+    pub passed: bool,
+    /// Seed reported by the run, if any
+    pub seed: Option<String>,
+}
+
+/// Pass/fail counts of one test case across repeated runs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CaseStats {
+    pub passed: u32,
+    pub failed: u32,
+    /// Runs in which the case failed
+    pub failed_runs: Vec<u32>,
+}
+
+impl CaseStats {
+    /// Fraction of runs in which the case failed
+    pub fn failure_rate(&self) -> f64 {
+        let total = self.passed + self.failed;
+        if total == 0 {
+            0.0
+        } else {
+            f64::from(self.failed) / f64::from(total)
+        }
+    }
+}
+
+/// Aggregated results of running the tests repeatedly
+#[derive(Debug, Clone, Default)]
+pub struct RepeatStats {
+    pub runs: Vec<RunRecord>,
+    /// Per-case statistics keyed by `Suite.case`
+    pub cases: BTreeMap<String, CaseStats>,
+}
+
+impl RepeatStats {
+    /// Records the outcome of one run.
+    ///
+    /// # Arguments
+    /// * `seed` - The shuffle seed the run was started with, if known
+    /// * `exit_code` - The run's exit code
+    /// * `output` - The run's stdout and stderr
+    pub fn record(&mut self, seed: Option<u64>, exit_code: Option<i32>, output: &str) {
+        let index = self.runs.len() as u32 + 1;
+        self.runs.push(RunRecord {
+            index,
+            passed: exit_code == Some(0),
+            seed: seed.map(|s| s.to_string()).or_else(|| parse_seed(output)),
+        });
+        for result in parse_test_report(output) {
+            let stats = self.cases.entry(result.full_name()).or_default();
+            match result.status {
+                CaseStatus::Passed => stats.passed += 1,
+                CaseStatus::Failed => {
+                    stats.failed += 1;
+                    stats.failed_runs.push(index);
+                }
+                CaseStatus::Skipped => {}
+            }
+        }
+    }
+
+    /// Returns the cases that both passed and failed, most unstable first
+    pub fn flaky_cases(&self) -> Vec<(&String, &CaseStats)> {
+        let mut flaky: Vec<_> = self
+            .cases
+            .iter()
+            .filter(|(_, stats)| stats.passed > 0 && stats.failed > 0)
+            .collect();
+        flaky.sort_by(|a, b| b.1.failure_rate().total_cmp(&a.1.failure_rate()));
+        flaky
+    }
+
+    /// Returns the seed reported by the given run
+    pub fn seed_of(&self, index: u32) -> Option<&str> {
+        self.runs
+            .iter()
+            .find(|run| run.index == index)
+            .and_then(|run| run.seed.as_deref())
+    }
+}
+
+/// A `@Test` class or function, or a `@TestCase` inside one, found in a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestItem {
+    /// Test class (or top-level test function) name
+    pub suite: String,
+    /// Test case name, `None` for the class itself
+    pub case: Option<String>,
+    /// 1-based line of the first annotation
+    pub start_line: u32,
+    /// 1-based line of the closing brace
+    pub end_line: u32,
+}
+
+impl TestItem {
+    /// Returns the `--filter` value selecting this item
+    pub fn filter(&self) -> String {
+        match &self.case {
+            Some(case) => format!("{}.{}", self.suite, case),
+            None => format!("{}.*", self.suite),
+        }
+    }
+}
+
+/// Finds the `@Test` classes and functions and their `@TestCase` functions in a source file.
+///
+/// This is a lightweight scanner rather than a full parse: it tracks annotations,
+/// `class`/`func` declarations and brace nesting, skipping comments and string literals.
+///
+/// # Arguments
+/// * `source` - The Cangjie source text
+///
+/// # Returns
+/// * `Vec<TestItem>` - The test items in source order
+pub fn find_test_items(source: &str) -> Vec<TestItem> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Annotation {
+        Test,
+        TestCase,
+    }
+
+    let mut items: Vec<TestItem> = Vec::new();
+    // (index into `items`, brace depth inside the item's body)
+    let mut stack: Vec<(usize, i32)> = Vec::new();
+    let mut pending: Option<(Annotation, u32)> = None;
+    let mut awaiting_body: Option<TestItem> = None;
+    let mut depth = 0;
+    let mut in_block_comment = false;
+
+    for (index, raw) in source.lines().enumerate() {
+        let line_no = index as u32 + 1;
+        let code = strip_comments_and_strings(raw, &mut in_block_comment);
+
+        if code.contains("@TestCase") {
+            pending.get_or_insert((Annotation::TestCase, line_no)).0 = Annotation::TestCase;
+        } else if code.contains("@Test") {
+            pending.get_or_insert((Annotation::Test, line_no)).0 = Annotation::Test;
+        }
+
+        let suite = stack.last().map(|(i, _)| items[*i].suite.clone());
+        if let Some((annotation, start)) = pending {
+            let declared = match (
+                annotation,
+                ident_after(&code, "class"),
+                ident_after(&code, "func"),
+            ) {
+                (Annotation::Test, Some(class), _) => Some((class, None)),
+                (Annotation::Test, None, Some(func)) if stack.is_empty() => Some((func, None)),
+                (Annotation::TestCase, _, Some(func)) => {
+                    suite.as_ref().map(|suite| (suite.clone(), Some(func)))
+                }
+                _ => None,
+            };
+            if let Some((suite, case)) = declared {
+                awaiting_body = Some(TestItem {
+                    suite,
+                    case,
+                    start_line: start,
+                    end_line: line_no,
+                });
+                pending = None;
+            }
+        }
+
+        for c in code.chars() {
+            match c {
+                '{' => {
+                    depth += 1;
+                    if let Some(item) = awaiting_body.take() {
+                        items.push(item);
+                        stack.push((items.len() - 1, depth));
+                    }
+                }
+                '}' => {
+                    if let Some((i, item_depth)) = stack.last()
+                        && *item_depth == depth
+                    {
+                        items[*i].end_line = line_no;
+                        stack.pop();
+                    }
+                    depth -= 1;
+                }
+                _ => {}
+            }
+        }
+    }
+    items
+}
+
+/// Returns the innermost test item enclosing a line.
+///
+/// # Arguments
+/// * `source` - The Cangjie source text
+/// * `line` - The 1-based cursor line
+///
+/// # Returns
+/// * `Option<TestItem>` - The enclosing test case, or its class
+pub fn test_at_line(source: &str, line: u32) -> Option<TestItem> {
+    find_test_items(source)
+        .into_iter()
+        .filter(|item| item.start_line <= line && line <= item.end_line)
+        .min_by_key(|item| item.end_line - item.start_line)
+}
+
+/// Parses a `path[:line]` location argument.
+///
+/// # Arguments
+/// * `location` - The location, e.g. `src/calc_test.cj:42`
+///
+/// # Returns
+/// * `Result<(String, Option<u32>), String>` - The path and optional 1-based line
+pub fn parse_location(location: &str) -> Result<(String, Option<u32>), String> {
+    match location.rsplit_once(':') {
+        Some((path, line)) if !path.is_empty() && line.bytes().all(|b| b.is_ascii_digit()) => {
+            let line = line
+                .parse::<u32>()
+                .ok()
+                .filter(|l| *l > 0)
+                .ok_or_else(|| format!("Invalid line number in '{}'", location))?;
+            Ok((path.to_string(), Some(line)))
+        }
+        _ => Ok((location.to_string(), None)),
+    }
+}
+
+/// Builds a `.zed/debug.json` scenario that compiles the tests and debugs one test.
+///
+/// # Arguments
+/// * `filter` - The `--filter` value of the test
+/// * `debug_adapter` - Name of the debug adapter
+///
+/// # Returns
+/// * `Value` - The debug scenario
+pub fn debug_scenario(filter: &str, debug_adapter: &str) -> Value {
+    json!({
+        "label": format!("Debug test {}", filter),
+        "adapter": debug_adapter,
+        "request": "launch",
+        "program": format!("$ZED_WORKTREE_ROOT/{}", TEST_BINARY_PATH),
+        "args": [format!("--filter={}", filter)],
+        "cwd": "$ZED_WORKTREE_ROOT",
+        "build": {
+            "command": "cjpm",
+            "args": ["test", "--no-run", "-g"],
+            "cwd": "$ZED_WORKTREE_ROOT",
+        },
+    })
+}
+
+/// Builds the `cjc` arguments compiling the unit tests of a package into a test binary,
+/// for projects tested without cjpm.
+///
+/// # Arguments
+/// * `source_dir` - The package's source directory
+/// * `output` - The test binary to write
+pub fn cjc_test_args(source_dir: &str, output: &str) -> Vec<String> {
+    vec![
+        CJC_TEST_FLAG.to_string(),
+        "-p".to_string(),
+        source_dir.to_string(),
+        "-o".to_string(),
+        output.to_string(),
+    ]
+}
+
+/// Splits the `--filter` options off a `cjpm test` or `cjc --test` command line.
+///
+/// The test binary takes the filter when it runs; the build command must not see it.
+///
+/// # Arguments
+/// * `args` - The command arguments
+///
+/// # Returns
+/// * `(Vec<String>, Vec<String>)` - The build arguments and the test binary's arguments
+pub fn split_filter_args(args: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut build_args = Vec::new();
+    let mut binary_args = Vec::new();
+    let mut args_it = args.iter();
+    while let Some(arg) = args_it.next() {
+        if arg == "--filter" {
+            binary_args.extend(args_it.next().map(|filter| format!("--filter={}", filter)));
+        } else if arg.starts_with("--filter=") {
+            binary_args.push(arg.clone());
+        } else {
+            build_args.push(arg.clone());
+        }
+    }
+    (build_args, binary_args)
+}
+
+/// Returns the test binary a `cjc --test` command writes, relative to its working directory
+pub fn cjc_test_output(args: &[String]) -> String {
+    let mut output = CJC_TEST_OUTPUT;
+    for (i, arg) in args.iter().enumerate() {
+        let value = match arg.as_str() {
+            "-o" | "--output" => args.get(i + 1).map(String::as_str),
+            _ => arg.strip_prefix("--output="),
+        };
+        output = value.unwrap_or(output);
+    }
+    output.to_string()
+}
+
+/// Returns the identifier following a keyword, e.g. the class name after `class`
+fn ident_after(code: &str, keyword: &str) -> Option<String> {
+    let mut tokens = code
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|t| !t.is_empty());
+    tokens.find(|t| *t == keyword)?;
+    tokens.next().map(str::to_string)
+}
+
+/// Blanks out comments and string literal contents so braces inside them are ignored
+fn strip_comments_and_strings(line: &str, in_block_comment: &mut bool) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    let mut quote: Option<char> = None;
+    while let Some(c) = chars.next() {
+        if *in_block_comment {
+            if c == '*' && chars.peek() == Some(&'/') {
+                chars.next();
+                *in_block_comment = false;
+            }
+            continue;
+        }
+        if let Some(q) = quote {
+            if c == '\\' {
+                chars.next();
+            } else if c == q {
+                quote = None;
+                out.push(c);
+            }
+            continue;
+        }
+        match c {
+            '/' if chars.peek() == Some(&'/') => break,
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                *in_block_comment = true;
+            }
+            '"' | '\'' => {
+                quote = Some(c);
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// How a test command is run with network access blocked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkIsolation {
+    /// Program to run (possibly a sandbox wrapper)
+    pub program: String,
+    /// Arguments for the program
+    pub args: Vec<String>,
+    /// Environment variables that route HTTP(S) traffic to a dead proxy
+    pub env: Vec<(String, String)>,
+    /// Whether isolation is enforced by the OS rather than only by proxy variables
+    pub enforced: bool,
+    /// Explanation of the mechanism used and any limitations
+    pub notes: Vec<String>,
+}
+
+/// Wraps a command so that it runs without network access.
+///
+/// Linux uses `unshare --net` (a fresh network namespace with only loopback), macOS uses
+/// `sandbox-exec` with a profile denying remote connections. Where neither is available,
+/// the command runs with proxy variables pointing at a dead address and the notes explain
+/// how to block the network with a firewall instead.
+///
+/// # Arguments
+/// * `os` - The current platform
+/// * `program` - The program to run
+/// * `args` - The program arguments
+/// * `has_unshare` - Whether `unshare` is on the PATH
+/// * `has_sandbox_exec` - Whether `sandbox-exec` is on the PATH
+///
+/// # Returns
+/// * `NetworkIsolation` - The wrapped command
+pub fn isolate_network(
+    os: Os,
+    program: &str,
+    args: &[String],
+    has_unshare: bool,
+    has_sandbox_exec: bool,
+) -> NetworkIsolation {
+    let env = [
+        "http_proxy",
+        "https_proxy",
+        "all_proxy",
+        "HTTP_PROXY",
+        "HTTPS_PROXY",
+        "ALL_PROXY",
+    ]
+    .iter()
+    .map(|key| (key.to_string(), BLACKHOLE_PROXY.to_string()))
+    .chain([
+        ("no_proxy".to_string(), String::new()),
+        ("NO_PROXY".to_string(), String::new()),
+    ])
+    .collect();
+
+    let mut isolation = NetworkIsolation {
+        program: program.to_string(),
+        args: args.to_vec(),
+        env,
+        enforced: false,
+        notes: Vec::new(),
+    };
+
+    match os {
+        Os::Linux if has_unshare => {
+            let mut wrapped = vec![
+                "--user".to_string(),
+                "--map-root-user".to_string(),
+                "--net".to_string(),
+                "--".to_string(),
+                program.to_string(),
+            ];
+            wrapped.extend(args.iter().cloned());
+            isolation.program = "unshare".to_string();
+            isolation.args = wrapped;
+            isolation.enforced = true;
+            isolation
+                .notes
+                .push("Running in a private network namespace (`unshare --net`).".to_string());
+        }
+        Os::Mac if has_sandbox_exec => {
+            let mut wrapped = vec![
+                "-p".to_string(),
+                SANDBOX_PROFILE.to_string(),
+                program.to_string(),
+            ];
+            wrapped.extend(args.iter().cloned());
+            isolation.program = "sandbox-exec".to_string();
+            isolation.args = wrapped;
+            isolation.enforced = true;
+            isolation.notes.push(
+                "Running under `sandbox-exec` with remote network connections denied.".to_string(),
+            );
+        }
+        Os::Linux => isolation.notes.push(
+            "`unshare` is not available; only HTTP(S) proxy variables are blocked. \
+             Install util-linux or enable unprivileged user namespaces for full isolation."
+                .to_string(),
+        ),
+        Os::Mac => isolation.notes.push(
+            "`sandbox-exec` is not available; only HTTP(S) proxy variables are blocked."
+                .to_string(),
+        ),
+        Os::Windows => isolation.notes.push(format!(
+            "Windows has no per-process network sandbox; only HTTP(S) proxy variables are \
+             blocked. For full isolation add an outbound firewall rule, e.g. \
+             `New-NetFirewallRule -DisplayName cangjie-tests -Direction Outbound -Action Block -Program \"{}\"`.",
+            program
+        )),
+    }
+
+    isolation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPORT: &str = "\
+TP: default, time elapsed: 120 ns, RESULT:
+    TCS: CalcTest, time elapsed: 100 ns, RESULT:
+    [ PASSED ] CASE: testAdd (10 ns)
+    [ FAILED ] CASE: testDiv (20 ns)
+    Assert Failed: `(a / b == 2)`
+    TCS: IoTest, time elapsed: 20 ns, RESULT:
+    [ SKIPPED ] CASE: testRead (0 ns)
+Summary: TOTAL: 3
+random seed: 1234
+";
+
+    #[test]
+    fn test_parse_test_args() {
+        // 测试 /cangjie-test 参数解析
+        let args: Vec<String> = [
+            "--repeat",
+            "5",
+            "--until-failure",
+            "CalcTest.*",
+            "--no-color",
+            "--seed=42",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let parsed = parse_test_args(&args).unwrap();
+        assert_eq!(parsed.repeat, 5);
+        assert!(parsed.until_failure);
+        assert_eq!(parsed.seed, Some(42));
+        assert_eq!(
+            parsed.cjpm_args(parsed.seed),
+            vec![
+                "test",
+                "--no-color",
+                "--filter=CalcTest.*",
+                "--random-seed=42"
+            ]
+        );
+        assert_eq!(
+            parsed.replay_command(42),
+            "/cangjie-test CalcTest.* --seed 42"
+        );
+        // 测试不经 cjpm 运行时需要报告被忽略的选项
+        assert_eq!(
+            parsed.cjpm_only_options(),
+            vec!["--repeat 5", "--until-failure", "--no-color"]
+        );
+        assert!(TestArgs::default().cjpm_only_options().is_empty());
+        assert!(parse_test_args(&["--repeat=0".to_string()]).is_err());
+        // 测试重复次数上限
+        assert_eq!(
+            parse_test_args(&["--repeat=100".to_string()])
+                .unwrap()
+                .repeat,
+            100
+        );
+        let error = parse_test_args(&["--repeat=101".to_string()]).unwrap_err();
+        assert!(error.contains("at most 100"));
+        assert!(
+            parse_test_args(&["--update-snapshots".to_string()])
+                .unwrap()
+                .update_snapshots
+        );
+        assert!(parse_test_args(&["--seed".to_string(), "abc".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_parse_test_report() {
+        // 测试解析单元测试报告
+        let results = parse_test_report(REPORT);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].full_name(), "CalcTest.testAdd");
+        assert_eq!(results[1].status, CaseStatus::Failed);
+        assert_eq!(results[2].full_name(), "IoTest.testRead");
+        assert_eq!(parse_seed(REPORT), Some("1234".to_string()));
+    }
+
+    #[test]
+    fn test_repeat_stats_flaky_cases() {
+        // 测试重复运行时的不稳定测试检测
+        let mut stats = RepeatStats::default();
+        stats.record(None, Some(1), REPORT);
+        stats.record(
+            Some(99),
+            Some(0),
+            &REPORT.replace("FAILED", "PASSED").replace("1234", "99"),
+        );
+        let flaky = stats.flaky_cases();
+        assert_eq!(flaky.len(), 1);
+        assert_eq!(flaky[0].0, "CalcTest.testDiv");
+        assert_eq!(flaky[0].1.failed_runs, vec![1]);
+        assert_eq!(flaky[0].1.failure_rate(), 0.5);
+        assert_eq!(stats.seed_of(1), Some("1234"));
+        assert_eq!(stats.seed_of(2), Some("99"));
+    }
+
+    #[test]
+    fn test_parallelism() {
+        // 测试并行度设置解析与 --parallel 支持检测
+        assert_eq!(Parallelism::from_setting(&Value::from(1)), Ok(None));
+        assert_eq!(
+            Parallelism::from_setting(&Value::from("8")),
+            Ok(Some(Parallelism::Workers(8)))
+        );
+        let auto = Parallelism::from_setting(&Value::from("auto"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(auto.flag_value(), "nCores");
+        assert!(Parallelism::from_setting(&Value::from("many")).is_err());
+
+        assert!(supports_parallel_flag(
+            "  --parallel <N>  run tests in parallel"
+        ));
+    }
+
+    #[test]
+    fn test_find_test_items() {
+        // 测试根据光标行定位 @Test 类和 @TestCase 用例
+        let source = r#"package calc
+
+@Test
+class CalcTest {
+    @TestCase
+    func testAdd(): Unit {
+        @Expect(add(1, 2), 3) // }
+    }
+
+    @TestCase[x in [1, 2]]
+    func testDiv(x: Int64): Unit {
+        let s = "{"
+        @Expect(div(x, x), 1)
+    }
+}
+
+@Test
+func testStandalone(): Unit {
+}
+"#;
+        let items = find_test_items(source);
+        assert_eq!(items.len(), 4);
+        assert_eq!(
+            test_at_line(source, 7).unwrap().filter(),
+            "CalcTest.testAdd"
+        );
+        assert_eq!(
+            test_at_line(source, 10).unwrap().filter(),
+            "CalcTest.testDiv"
+        );
+        assert_eq!(test_at_line(source, 9).unwrap().filter(), "CalcTest.*");
+        assert_eq!(
+            test_at_line(source, 19).unwrap().filter(),
+            "testStandalone.*"
+        );
+        assert_eq!(test_at_line(source, 1), None);
+
+        assert_eq!(
+            parse_location("src/calc_test.cj:12"),
+            Ok(("src/calc_test.cj".to_string(), Some(12)))
+        );
+        assert_eq!(
+            parse_location("C:\\work\\a.cj"),
+            Ok(("C:\\work\\a.cj".to_string(), None))
+        );
+        let scenario = debug_scenario("CalcTest.testAdd", "Cangjie");
+        assert_eq!(scenario["args"], json!(["--filter=CalcTest.testAdd"]));
+    }
+
+    #[test]
+    fn test_seed_history() {
+        // 测试随机种子历史的读写与截断
+        let mut records: Vec<SeedRecord> = (0..25)
+            .map(|seed| SeedRecord {
+                seed,
+                passed: seed % 2 == 0,
+                filter: None,
+            })
+            .collect();
+        records.push(SeedRecord {
+            seed: 7,
+            passed: false,
+            filter: Some("CalcTest.testDiv".to_string()),
+        });
+        let history = parse_seed_history(&format_seed_history(&records));
+        assert_eq!(history.len(), SEED_HISTORY_LIMIT);
+        assert_eq!(history[0].seed, 6);
+        assert_eq!(
+            history.last().unwrap().replay_command(),
+            "/cangjie-test CalcTest.testDiv --seed 7"
+        );
+        assert_ne!(derive_seed(1, 1), derive_seed(1, 2));
+    }
+
+    #[test]
+    fn test_isolate_network() {
+        // 测试 Linux 下使用 unshare 隔离网络
+        let args = vec!["test".to_string()];
+        let isolation = isolate_network(Os::Linux, "/sdk/bin/cjpm", &args, true, false);
+        assert_eq!(isolation.program, "unshare");
+        assert_eq!(&isolation.args[3..], ["--", "/sdk/bin/cjpm", "test"]);
+        assert!(isolation.enforced);
+
+        // 测试 macOS 下使用 sandbox-exec
+        let isolation = isolate_network(Os::Mac, "/sdk/bin/cjpm", &args, false, true);
+        assert_eq!(isolation.program, "sandbox-exec");
+        assert!(isolation.enforced);
+
+        // 测试没有沙箱工具时仅设置代理环境变量
+        let isolation = isolate_network(Os::Windows, "cjpm.exe", &args, false, false);
+        assert_eq!(isolation.program, "cjpm.exe");
+        assert!(!isolation.enforced);
+        assert!(
+            isolation
+                .env
+                .contains(&("HTTPS_PROXY".to_string(), BLACKHOLE_PROXY.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_debug_test_args() {
+        // 测试调试单个测试时把 --filter 交给测试程序，其余参数用于构建
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        let (build, binary) =
+            split_filter_args(&args(&["test", "--filter", "CalcTest.*", "src/calc"]));
+        assert_eq!(build, vec!["test", "src/calc"]);
+        assert_eq!(binary, vec!["--filter=CalcTest.*"]);
+        let (_, binary) = split_filter_args(&args(&["--test", "--filter=A.b"]));
+        assert_eq!(binary, vec!["--filter=A.b"]);
+        let test_args = TestArgs {
+            filter: Some("CalcTest.*".to_string()),
+            passthrough: vec!["--no-color".to_string()],
+            ..TestArgs::default()
+        };
+        assert_eq!(
+            test_args.binary_args(Some(7)),
+            vec!["--filter=CalcTest.*", "--random-seed=7"]
+        );
+        assert_eq!(
+            cjc_test_args("src", "target/main"),
+            vec!["--test", "-p", "src", "-o", "target/main"]
+        );
+
+        // 测试 cjc --test 输出的测试程序名
+        assert_eq!(
+            cjc_test_output(&args(&["--test", "calc.cj"])),
+            CJC_TEST_OUTPUT
+        );
+        assert_eq!(
+            cjc_test_output(&args(&["--test", "-o", "calc_test"])),
+            "calc_test"
+        );
+        assert_eq!(cjc_test_output(&args(&["--test", "--output=t"])), "t");
+    }
+
+    #[test]
+    fn test_split_report() {
+        // 测试按测试套件拆分单元测试报告并统计通过与失败数
+        let (suites, rest) = split_report(REPORT);
+        assert_eq!(suites.len(), 2);
+        assert_eq!(suites[0].label(), "CalcTest: 1 passed, 1 failed");
+        assert!(suites[0].output.contains("Assert Failed"));
+        assert_eq!(suites[1].label(), "IoTest: 0 passed, 1 skipped");
+        assert!(rest.starts_with("TP: default"));
+        assert!(rest.contains("Summary: TOTAL: 3"));
+        assert!(!rest.contains("CASE:"));
+        let (suites, rest) = split_report("error: no tests\n");
+        assert!(suites.is_empty());
+        assert_eq!(rest, "error: no tests\n");
+    }
+}
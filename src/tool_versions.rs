@@ -0,0 +1,101 @@
+// src/tool_versions.rs
+//! Support for `.tool-versions` files written by asdf and mise.
+
+use std::path::PathBuf;
+
+/// Name of the version file in the worktree root
+pub const TOOL_VERSIONS_FILE: &str = ".tool-versions";
+
+/// Plugin name of the Cangjie toolchain in asdf/mise
+pub const CANGJIE_PLUGIN: &str = "cangjie";
+
+/// Returns the versions listed for a tool, in order of preference.
+///
+/// Comments (`# ...`) are ignored; a tool may list several fallback versions on one line.
+///
+/// # Arguments
+/// * `contents` - The contents of `.tool-versions`
+/// * `tool` - The tool (plugin) name
+///
+/// # Returns
+/// * `Vec<String>` - The listed versions, empty if the tool is not listed
+pub fn versions_for(contents: &str, tool: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .find_map(|line| {
+            let mut fields = line.split_whitespace();
+            (fields.next() == Some(tool)).then(|| fields.map(str::to_string).collect())
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the directories a version may be installed in.
+///
+/// `path:<dir>` versions point at a directory directly; `system` has no install directory.
+/// Other versions map to `<data dir>/installs/<tool>/<version>` for asdf (`ASDF_DATA_DIR`,
+/// default `~/.asdf`) and mise (`MISE_DATA_DIR`, default `~/.local/share/mise`).
+///
+/// # Arguments
+/// * `tool` - The tool (plugin) name
+/// * `version` - A version from `.tool-versions`
+/// * `lookup` - Resolves environment variables
+///
+/// # Returns
+/// * `Vec<PathBuf>` - The candidate install directories
+pub fn install_dirs(
+    tool: &str,
+    version: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Vec<PathBuf> {
+    if let Some(path) = version.strip_prefix("path:") {
+        return vec![PathBuf::from(path)];
+    }
+    if version == "system" {
+        return Vec::new();
+    }
+    let home = lookup("HOME").or_else(|| lookup("USERPROFILE"));
+    let data_dirs = [
+        lookup("ASDF_DATA_DIR").or_else(|| home.as_ref().map(|h| format!("{}/.asdf", h))),
+        lookup("MISE_DATA_DIR").or_else(|| {
+            lookup("XDG_DATA_HOME")
+                .map(|d| format!("{}/mise", d))
+                .or_else(|| home.as_ref().map(|h| format!("{}/.local/share/mise", h)))
+        }),
+    ];
+    data_dirs
+        .into_iter()
+        .flatten()
+        .map(|dir| PathBuf::from(dir).join("installs").join(tool).join(version))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_versions() {
+        // 测试解析 .tool-versions 并映射到安装目录
+        let contents = "# toolchains\nnodejs 20.1.0\ncangjie 0.59.6 path:/opt/cj # pinned\n";
+        assert_eq!(
+            versions_for(contents, CANGJIE_PLUGIN),
+            vec!["0.59.6", "path:/opt/cj"]
+        );
+        assert!(versions_for(contents, "python").is_empty());
+
+        let lookup = |name: &str| (name == "HOME").then(|| "/home/dev".to_string());
+        assert_eq!(
+            install_dirs(CANGJIE_PLUGIN, "0.59.6", lookup),
+            vec![
+                PathBuf::from("/home/dev/.asdf/installs/cangjie/0.59.6"),
+                PathBuf::from("/home/dev/.local/share/mise/installs/cangjie/0.59.6"),
+            ]
+        );
+        assert_eq!(
+            install_dirs(CANGJIE_PLUGIN, "path:/opt/cj", lookup),
+            vec![PathBuf::from("/opt/cj")]
+        );
+        assert!(install_dirs(CANGJIE_PLUGIN, "system", lookup).is_empty());
+    }
+}
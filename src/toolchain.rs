@@ -0,0 +1,137 @@
+// src/toolchain.rs
+//! The toolchain version matrix reported by `/cangjie-version`.
+//!
+//! Besides the sections per component, the report ends with a compact Markdown table of all
+//! versions, meant to be pasted into bug reports as is.
+
+use zed_extension_api::{Architecture, Os};
+
+/// The extension manifest the extension was built with
+pub const EXTENSION_MANIFEST: &str = include_str!("../extension.toml");
+
+/// Name of the grammar in the extension manifest
+pub const GRAMMAR_NAME: &str = "cangjie";
+
+/// Where the grammar is built from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarSource {
+    pub repository: String,
+    /// Git revision: a commit, tag or branch
+    pub rev: String,
+}
+
+/// Reads a grammar's source from the `[grammars.<name>]` table of an extension manifest.
+///
+/// # Arguments
+/// * `manifest` - The contents of `extension.toml`
+/// * `name` - The grammar name
+///
+/// # Returns
+/// * `Option<GrammarSource>` - The repository and revision, `None` if either is missing
+pub fn grammar_source(manifest: &str, name: &str) -> Option<GrammarSource> {
+    let header = format!("[grammars.{}]", name);
+    let mut repository = None;
+    let mut rev = None;
+    let mut in_table = false;
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_table = line == header;
+            continue;
+        }
+        if !in_table {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"').to_string();
+        match key.trim() {
+            "repository" => repository = Some(value),
+            "rev" | "commit" => rev = Some(value),
+            _ => {}
+        }
+    }
+    Some(GrammarSource {
+        repository: repository?,
+        rev: rev?,
+    })
+}
+
+/// Returns the platform name shown in the report, e.g. `linux-x86_64`
+pub fn platform_name(os: Os, arch: Architecture) -> String {
+    let os = match os {
+        Os::Mac => "macos",
+        Os::Linux => "linux",
+        Os::Windows => "windows",
+    };
+    let arch = match arch {
+        Architecture::Aarch64 => "aarch64",
+        Architecture::X86 => "x86",
+        Architecture::X8664 => "x86_64",
+    };
+    format!("{}-{}", os, arch)
+}
+
+/// Formats the version matrix as a Markdown table.
+///
+/// # Arguments
+/// * `rows` - Component names and their versions, in display order
+///
+/// # Returns
+/// * `String` - The table, one row per component
+pub fn version_table(rows: &[(String, String)]) -> String {
+    let mut table = "| Component | Version |\n| --- | --- |\n".to_string();
+    for (component, version) in rows {
+        table.push_str(&format!(
+            "| {} | {} |\n",
+            component,
+            version.replace('|', "\\|")
+        ));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_matrix() {
+        // 测试读取语法来源并生成版本表
+        let manifest = "\
+id = \"cangjie\"
+
+[grammars.cangjie]
+repository = \"https://github.com/SmiteWindows/tree-sitter-cangjie\"
+rev = \"main\"
+
+[language_servers.cangjie-lsp]
+name = \"Cangjie LSP\"
+";
+        assert_eq!(
+            grammar_source(manifest, GRAMMAR_NAME),
+            Some(GrammarSource {
+                repository: "https://github.com/SmiteWindows/tree-sitter-cangjie".to_string(),
+                rev: "main".to_string(),
+            })
+        );
+        assert_eq!(grammar_source(manifest, "other"), None);
+        assert!(grammar_source(EXTENSION_MANIFEST, GRAMMAR_NAME).is_some());
+
+        assert_eq!(
+            platform_name(Os::Mac, Architecture::Aarch64),
+            "macos-aarch64"
+        );
+
+        let rows = vec![
+            ("Extension".to_string(), "0.1.0".to_string()),
+            ("cjfmt".to_string(), "not found".to_string()),
+            ("cjc".to_string(), "a|b".to_string()),
+        ];
+        assert_eq!(
+            version_table(&rows),
+            "| Component | Version |\n| --- | --- |\n| Extension | 0.1.0 |\n\
+             | cjfmt | not found |\n| cjc | a\\|b |\n"
+        );
+    }
+}
@@ -0,0 +1,151 @@
+// src/updates.rs
+//! Language server update checks for `/cangjie-check-updates`.
+//!
+//! The result of the last check is kept in [`STATE_FILE`] in the extension's working
+//! directory, so repeated checks within [`CHECK_INTERVAL_SECS`] are answered without a
+//! network request, also across restarts. A check made for another repository, toolchain
+//! channel or pre-release setting is not reused.
+
+use serde_json::{Value, json};
+
+/// File in the extension's working directory holding the last update check
+pub const STATE_FILE: &str = "update-check.json";
+
+/// How long the result of an update check is reused, in seconds
+pub const CHECK_INTERVAL_SECS: u64 = 3600;
+
+/// Most changelog lines shown
+const CHANGELOG_LINES: usize = 15;
+
+/// The result of an update check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateCheck {
+    /// Seconds since the Unix epoch
+    pub checked_at: u64,
+    /// GitHub repository the release was looked up in
+    pub repo: String,
+    /// Toolchain channel the release was selected for
+    pub channel: String,
+    /// Whether pre-releases were considered
+    pub pre_release: bool,
+    /// Tag of the latest release
+    pub latest: String,
+    /// Release notes of the latest release, if they could be fetched
+    pub changelog: Option<String>,
+}
+
+impl UpdateCheck {
+    pub fn to_json(&self) -> String {
+        let mut value = json!({
+            "checked_at": self.checked_at,
+            "repo": self.repo,
+            "channel": self.channel,
+            "pre_release": self.pre_release,
+            "latest": self.latest,
+        });
+        if let Some(changelog) = &self.changelog {
+            value["changelog"] = json!(changelog);
+        }
+        value.to_string()
+    }
+
+    pub fn from_json(json: &str) -> Option<Self> {
+        let value: Value = serde_json::from_str(json).ok()?;
+        Some(UpdateCheck {
+            checked_at: value.get("checked_at")?.as_u64()?,
+            repo: value.get("repo")?.as_str()?.to_string(),
+            channel: value.get("channel")?.as_str()?.to_string(),
+            pre_release: value.get("pre_release")?.as_bool()?,
+            latest: value.get("latest")?.as_str()?.to_string(),
+            changelog: value
+                .get("changelog")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        })
+    }
+
+    /// Returns true if the check is recent enough to reuse and was made for the same
+    /// repository, channel and pre-release setting
+    pub fn is_fresh(&self, now: u64, repo: &str, channel: &str, pre_release: bool) -> bool {
+        now.saturating_sub(self.checked_at) < CHECK_INTERVAL_SECS
+            && self.repo == repo
+            && self.channel == channel
+            && self.pre_release == pre_release
+    }
+}
+
+/// Returns the GitHub API URL of a release, whose `body` holds the release notes
+pub fn release_api_url(repo: &str, tag: &str) -> String {
+    format!(
+        "https://api.github.com/repos/{}/releases/tags/{}",
+        repo, tag
+    )
+}
+
+/// Returns the first lines of the release notes in a GitHub API release response.
+///
+/// # Arguments
+/// * `release_json` - The response of [`release_api_url`]
+///
+/// # Returns
+/// * `Option<String>` - The excerpt, `None` if the release has no notes
+pub fn changelog_excerpt(release_json: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(release_json).ok()?;
+    let body = value.get("body")?.as_str()?.trim();
+    if body.is_empty() {
+        return None;
+    }
+    let lines: Vec<&str> = body.lines().map(str::trim_end).collect();
+    let mut excerpt = lines
+        .iter()
+        .take(CHANGELOG_LINES)
+        .copied()
+        .collect::<Vec<_>>()
+        .join("\n");
+    if lines.len() > CHANGELOG_LINES {
+        excerpt.push_str(&format!("\n… {} more lines", lines.len() - CHANGELOG_LINES));
+    }
+    Some(excerpt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_check() {
+        // 测试检查结果的持久化与有效期
+        let check = UpdateCheck {
+            checked_at: 1_000,
+            repo: "cangjie-lang/cangjie".to_string(),
+            channel: "release".to_string(),
+            pre_release: false,
+            latest: "v1.1.0".to_string(),
+            changelog: Some("- Faster completion".to_string()),
+        };
+        assert_eq!(
+            UpdateCheck::from_json(&check.to_json()),
+            Some(check.clone())
+        );
+        const REPO: &str = "cangjie-lang/cangjie";
+        assert!(check.is_fresh(1_000 + CHECK_INTERVAL_SECS - 1, REPO, "release", false));
+        assert!(!check.is_fresh(1_000 + CHECK_INTERVAL_SECS, REPO, "release", false));
+        assert_eq!(UpdateCheck::from_json("{}"), None);
+
+        // 测试仓库、通道或预发布设置变化后不复用检查结果
+        assert!(!check.is_fresh(1_000, "fork/cangjie", "release", false));
+        assert!(!check.is_fresh(1_000, REPO, "nightly", false));
+        assert!(!check.is_fresh(1_000, REPO, "release", true));
+        let legacy = r#"{"checked_at": 1000, "latest": "v1.1.0"}"#;
+        assert_eq!(UpdateCheck::from_json(legacy), None);
+
+        // 测试截取发布说明
+        let body: Vec<String> = (1..=20).map(|i| format!("- change {}", i)).collect();
+        let release = json!({ "tag_name": "v1.1.0", "body": body.join("\r\n") }).to_string();
+        let excerpt = changelog_excerpt(&release).unwrap();
+        assert!(excerpt.starts_with("- change 1\n- change 2\n"));
+        assert!(excerpt.ends_with("- change 15\n… 5 more lines"));
+        assert_eq!(changelog_excerpt(r#"{"body": " "}"#), None);
+        assert_eq!(changelog_excerpt(r#"{"body": null}"#), None);
+    }
+}
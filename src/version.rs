@@ -0,0 +1,233 @@
+// src/version.rs
+//! Parsing of `--version` output reported by Cangjie toolchain binaries.
+
+/// Version information reported by a toolchain binary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolVersion {
+    /// Version number, e.g. `0.53.13` or `1.0.0-beta.2`
+    pub version: String,
+    /// Build date in `YYYY-MM-DD` form, if the tool reports one
+    pub build_date: Option<String>,
+}
+
+/// A `MAJOR.MINOR.PATCH[-PRE]` version number that can be ordered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// Pre-release identifier; versions with one sort before the plain release
+    pub pre: Option<String>,
+}
+
+impl Version {
+    /// Parses a version number, ignoring a leading `v` and any build metadata
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = version_token(text)?;
+        let text = text.split('+').next()?;
+        let (core, pre) = match text.split_once('-') {
+            Some((core, pre)) => (core, Some(pre.to_string())),
+            None => (text, None),
+        };
+        let mut parts = core.split('.').map(|p| p.parse::<u64>().ok());
+        Some(Self {
+            major: parts.next()??,
+            minor: parts.next()??,
+            patch: parts.next().unwrap_or(Some(0))?,
+            pre,
+        })
+    }
+}
+
+impl Ord for Version {
+    /// Orders versions the semver way (a pre-release sorts before its release)
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => std::cmp::Ordering::Equal,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (Some(a), Some(b)) => a.cmp(b),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(pre) = &self.pre {
+            write!(f, "-{}", pre)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks whether a language server version can be used with a compiler version.
+///
+/// The server is considered incompatible when its `MAJOR.MINOR` is newer than the
+/// compiler's, because it then reports diagnostics for language features the compiler
+/// does not know about yet.
+///
+/// # Arguments
+/// * `lsp` - Version of `cangjie-lsp`
+/// * `cjc` - Version of `cjc`
+///
+/// # Returns
+/// * `Result<(), String>` - An explanation if the versions are incompatible
+pub fn check_lsp_compatibility(lsp: &Version, cjc: &Version) -> Result<(), String> {
+    if (lsp.major, lsp.minor) > (cjc.major, cjc.minor) {
+        return Err(format!(
+            "cangjie-lsp {} is newer than cjc {}; diagnostics may disagree with the compiler. \
+             Install a matching language server or SDK.",
+            lsp, cjc
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the release tags a pinned version may be published under.
+///
+/// Releases are tagged both with and without a `v` prefix, so `1.0.0` and `v1.0.0` are
+/// tried in the order given by the user.
+///
+/// # Arguments
+/// * `pin` - The pinned version or tag
+///
+/// # Returns
+/// * `Vec<String>` - The tags to try, empty for a blank pin
+pub fn release_tag_candidates(pin: &str) -> Vec<String> {
+    let pin = pin.trim();
+    if pin.is_empty() {
+        return Vec::new();
+    }
+    let mut tags = vec![pin.to_string()];
+    match pin.strip_prefix('v') {
+        Some(bare) if Version::parse(bare).is_some() => tags.push(bare.to_string()),
+        None if Version::parse(pin).is_some() => tags.push(format!("v{}", pin)),
+        _ => {}
+    }
+    tags
+}
+
+/// Extracts the version number and build date from `--version` output.
+///
+/// # Arguments
+/// * `output` - Combined stdout/stderr of the tool
+///
+/// # Returns
+/// * `Option<ToolVersion>` - The parsed version, or `None` if no version number was found
+pub fn parse_tool_version(output: &str) -> Option<ToolVersion> {
+    let version = output.split_whitespace().find_map(version_token)?;
+    let build_date = output
+        .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | ',' | ';'))
+        .find(|token| is_date(token))
+        .map(str::to_string);
+    Some(ToolVersion {
+        version,
+        build_date,
+    })
+}
+
+/// Returns the token as a version number if it looks like `[v]MAJOR.MINOR[.PATCH][-PRE]`
+fn version_token(token: &str) -> Option<String> {
+    let token = token.trim_matches(|c: char| matches!(c, '(' | ')' | ',' | ':' | ';'));
+    let token = token.strip_prefix('v').unwrap_or(token);
+    let (core, pre) = match token.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (token, None),
+    };
+    let parts: Vec<&str> = core.split('.').collect();
+    if parts.len() < 2
+        || parts.len() > 4
+        || parts
+            .iter()
+            .any(|p| p.is_empty() || !p.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return None;
+    }
+    if let Some(pre) = pre
+        && (pre.is_empty() || !pre.chars().all(|c| c.is_ascii_alphanumeric() || c == '.'))
+    {
+        return None;
+    }
+    Some(token.to_string())
+}
+
+/// Returns true if the token is a `YYYY-MM-DD` date
+fn is_date(token: &str) -> bool {
+    let bytes = token.as_bytes();
+    bytes.len() == 10
+        && bytes[4] == b'-'
+        && bytes[7] == b'-'
+        && bytes
+            .iter()
+            .enumerate()
+            .all(|(i, b)| i == 4 || i == 7 || b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_parse_and_compare() {
+        // 测试版本号解析与比较
+        let release = Version::parse("v1.0.0").unwrap();
+        let beta = Version::parse("1.0.0-beta.2").unwrap();
+        let old = Version::parse("0.53.13").unwrap();
+        assert_eq!(Version::parse("0.59").unwrap().to_string(), "0.59.0");
+        assert!(beta < release);
+        assert!(old < beta);
+        assert_eq!(Version::parse("latest"), None);
+    }
+
+    #[test]
+    fn test_check_lsp_compatibility() {
+        // 测试 LSP 与 cjc 版本兼容性检查
+        let cjc = Version::parse("0.59.4").unwrap();
+        assert!(check_lsp_compatibility(&Version::parse("0.59.9").unwrap(), &cjc).is_ok());
+        assert!(check_lsp_compatibility(&Version::parse("0.53.0").unwrap(), &cjc).is_ok());
+        assert!(check_lsp_compatibility(&Version::parse("1.0.0").unwrap(), &cjc).is_err());
+    }
+
+    #[test]
+    fn test_release_tag_candidates() {
+        // 测试固定版本号对应的发布标签
+        assert_eq!(release_tag_candidates("1.0.0"), vec!["1.0.0", "v1.0.0"]);
+        assert_eq!(release_tag_candidates(" v0.59.6 "), vec!["v0.59.6", "0.59.6"]);
+        assert_eq!(release_tag_candidates("lsp-nightly"), vec!["lsp-nightly"]);
+        assert!(release_tag_candidates("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_tool_version() {
+        // 测试解析 cjc 的版本输出
+        let output = "Cangjie Compiler: 0.53.13 (cjnative)\nTarget: x86_64-unknown-linux-gnu\n";
+        assert_eq!(
+            parse_tool_version(output),
+            Some(ToolVersion {
+                version: "0.53.13".to_string(),
+                build_date: None,
+            })
+        );
+
+        // 测试带构建日期和预发布标识的输出
+        let output = "cangjie-lsp v1.0.0-beta.2 (built 2025-06-30)";
+        assert_eq!(
+            parse_tool_version(output),
+            Some(ToolVersion {
+                version: "1.0.0-beta.2".to_string(),
+                build_date: Some("2025-06-30".to_string()),
+            })
+        );
+
+        assert_eq!(parse_tool_version("unknown option --version"), None);
+    }
+}